@@ -0,0 +1,635 @@
+//! Object-safe calendar backend trait.
+//!
+//! [`CalendarBackend`] is the shared API as a trait: every per-backend
+//! client ([`VdirClient`], [`WebdavClientStd`], [`MemoryClient`])
+//! implements it, and [`CalendarClientStd`] can hold any other
+//! implementation behind a `Box<dyn CalendarBackend>` (an in-house
+//! store, a test double).
+//!
+//! Errors cross the trait boundary as a [`CalendarBackendError`], a
+//! boxed error that still downcasts to the implementor's concrete
+//! error type:
+//!
+//! ```rust,ignore
+//! use io_calendar::{backend::CalendarBackend, vdir::client::{VdirClient, VdirClientError}};
+//!
+//! let mut backend: Box<dyn CalendarBackend> = Box::new(VdirClient::new("/path/to/vdir"));
+//! if let Err(err) = backend.list_calendars() {
+//!     if let Some(err) = err.downcast_ref::<VdirClientError>() {
+//!         // handle the vdir error
+//!     }
+//! }
+//! ```
+//!
+//! [`VdirClient`]: crate::vdir::client::VdirClient
+//! [`WebdavClientStd`]: crate::webdav::client::WebdavClientStd
+//! [`MemoryClient`]: crate::memory::client::MemoryClient
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{error::Error, fmt::Debug};
+
+#[cfg(feature = "memory")]
+use crate::memory::client::MemoryClient;
+#[cfg(feature = "vdir")]
+use crate::vdir::client::VdirClient;
+#[cfg(feature = "webdav")]
+use crate::webdav::client::WebdavClientStd;
+use crate::{
+    calendar::{Calendar, CalendarDiff},
+    client::{CalendarClientStd, CalendarClientStdError},
+    item::{CalendarItem, CalendarItemKind, CalendarItemReceipt, ItemFilter, TimeRange},
+};
+
+/// Boxed error surfaced by [`CalendarBackend`] methods.
+///
+/// Implementors box their own error type, so callers can
+/// [`downcast_ref`](Box::downcast_ref) it back.
+pub type CalendarBackendError = Box<dyn Error + Send + Sync>;
+
+/// Shared calendar API, implemented by every backend client.
+///
+/// The trait is object-safe: methods take `&mut self` (the WebDAV
+/// backend mutates its connection and discovery cache) and only
+/// borrowed or owned shared types.
+pub trait CalendarBackend: Debug {
+    /// Lists every calendar available to the account.
+    fn list_calendars(&mut self) -> Result<Vec<Calendar>, CalendarBackendError>;
+
+    /// Creates calendar `id` (display name `name`), optionally carrying
+    /// a description and a color.
+    fn create_calendar(
+        &mut self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<(), CalendarBackendError>;
+
+    /// Applies a partial update to calendar `id`.
+    fn update_calendar(
+        &mut self,
+        id: &str,
+        patch: CalendarDiff,
+    ) -> Result<(), CalendarBackendError>;
+
+    /// Deletes calendar `id` and every item it contains.
+    fn delete_calendar(&mut self, id: &str) -> Result<(), CalendarBackendError>;
+
+    /// Lists items inside `calendar_id`, applying 1-indexed pagination
+    /// and the optional time-range, kind and property filters (an empty
+    /// `kinds` slice lists every kind).
+    fn list_items(
+        &mut self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<Vec<CalendarItem>, CalendarBackendError>;
+
+    /// Fetches item `item_id` from `calendar_id`.
+    fn get_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<CalendarItem, CalendarBackendError>;
+
+    /// Appends a raw iCalendar item to `calendar_id`. Returns the
+    /// identifier the backend assigned to the stored item along with
+    /// its new entity tag, when the backend reports one.
+    fn create_item(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError>;
+
+    /// Replaces the bytes of `item_id` inside `calendar_id`, gating on
+    /// `if_match` when the backend supports entity tags. Returns the
+    /// new entity tag, when the backend reports one.
+    fn update_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError>;
+
+    /// Permanently deletes `item_id` from `calendar_id`, gating on
+    /// `if_match` when the backend supports entity tags.
+    fn delete_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), CalendarBackendError>;
+}
+
+impl CalendarBackend for CalendarClientStd {
+    fn list_calendars(&mut self) -> Result<Vec<Calendar>, CalendarBackendError> {
+        CalendarClientStd::list_calendars(self).map_err(into_backend_error)
+    }
+
+    fn create_calendar(
+        &mut self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<(), CalendarBackendError> {
+        CalendarClientStd::create_calendar(self, id, name, description, color)
+            .map_err(into_backend_error)
+    }
+
+    fn update_calendar(
+        &mut self,
+        id: &str,
+        patch: CalendarDiff,
+    ) -> Result<(), CalendarBackendError> {
+        CalendarClientStd::update_calendar(self, id, patch).map_err(into_backend_error)
+    }
+
+    fn delete_calendar(&mut self, id: &str) -> Result<(), CalendarBackendError> {
+        CalendarClientStd::delete_calendar(self, id).map_err(into_backend_error)
+    }
+
+    fn list_items(
+        &mut self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<Vec<CalendarItem>, CalendarBackendError> {
+        CalendarClientStd::list_items(
+            self,
+            calendar_id,
+            page,
+            page_size,
+            time_range,
+            kinds,
+            filter,
+        )
+        .map_err(into_backend_error)
+    }
+
+    fn get_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<CalendarItem, CalendarBackendError> {
+        CalendarClientStd::get_item(self, calendar_id, item_id).map_err(into_backend_error)
+    }
+
+    fn create_item(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError> {
+        CalendarClientStd::create_item(self, calendar_id, contents).map_err(into_backend_error)
+    }
+
+    fn update_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError> {
+        CalendarClientStd::update_item(self, calendar_id, item_id, contents, if_match)
+            .map_err(into_backend_error)
+    }
+
+    fn delete_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), CalendarBackendError> {
+        CalendarClientStd::delete_item(self, calendar_id, item_id, if_match)
+            .map_err(into_backend_error)
+    }
+}
+
+/// Unwraps the backend error carried by a [`CalendarClientStdError`],
+/// so that callers of a [`CalendarClientStd`] used as a trait object
+/// can still downcast to the concrete backend error, lifted variants
+/// included. Lifted variants without a source stay a
+/// `CalendarClientStdError`.
+fn into_backend_error(err: CalendarClientStdError) -> CalendarBackendError {
+    match err {
+        #[cfg(feature = "vdir")]
+        CalendarClientStdError::Vdir(err) => Box::new(err),
+        #[cfg(feature = "webdav")]
+        CalendarClientStdError::Webdav(err) => Box::new(err),
+        #[cfg(feature = "memory")]
+        CalendarClientStdError::Memory(err) => Box::new(err),
+        CalendarClientStdError::AlreadyExists {
+            source: Some(err), ..
+        }
+        | CalendarClientStdError::NotFound {
+            source: Some(err), ..
+        }
+        | CalendarClientStdError::PreconditionFailed {
+            source: Some(err), ..
+        }
+        | CalendarClientStdError::Backend(err) => err,
+        err => Box::new(err),
+    }
+}
+
+#[cfg(feature = "vdir")]
+impl CalendarBackend for VdirClient {
+    fn list_calendars(&mut self) -> Result<Vec<Calendar>, CalendarBackendError> {
+        Ok(VdirClient::list_calendars(self)?)
+    }
+
+    fn create_calendar(
+        &mut self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(VdirClient::create_calendar(
+            self,
+            id,
+            name,
+            description,
+            color,
+        )?)
+    }
+
+    fn update_calendar(
+        &mut self,
+        id: &str,
+        patch: CalendarDiff,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(VdirClient::update_calendar(self, id, patch)?)
+    }
+
+    fn delete_calendar(&mut self, id: &str) -> Result<(), CalendarBackendError> {
+        Ok(VdirClient::delete_calendar(self, id)?)
+    }
+
+    fn list_items(
+        &mut self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<Vec<CalendarItem>, CalendarBackendError> {
+        Ok(VdirClient::list_items(
+            self,
+            calendar_id,
+            page,
+            page_size,
+            time_range,
+            kinds,
+            filter,
+        )?)
+    }
+
+    fn get_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<CalendarItem, CalendarBackendError> {
+        Ok(VdirClient::get_item(self, calendar_id, item_id)?)
+    }
+
+    fn create_item(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError> {
+        Ok(VdirClient::create_item(self, calendar_id, contents)?)
+    }
+
+    fn update_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError> {
+        Ok(VdirClient::update_item(
+            self,
+            calendar_id,
+            item_id,
+            contents,
+            if_match,
+        )?)
+    }
+
+    fn delete_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(VdirClient::delete_item(
+            self,
+            calendar_id,
+            item_id,
+            if_match,
+        )?)
+    }
+}
+
+#[cfg(feature = "webdav")]
+impl CalendarBackend for WebdavClientStd {
+    fn list_calendars(&mut self) -> Result<Vec<Calendar>, CalendarBackendError> {
+        Ok(WebdavClientStd::list_calendars(self)?)
+    }
+
+    fn create_calendar(
+        &mut self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(WebdavClientStd::create_calendar(
+            self,
+            id,
+            name,
+            description,
+            color,
+        )?)
+    }
+
+    fn update_calendar(
+        &mut self,
+        id: &str,
+        patch: CalendarDiff,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(WebdavClientStd::update_calendar(self, id, patch)?)
+    }
+
+    fn delete_calendar(&mut self, id: &str) -> Result<(), CalendarBackendError> {
+        Ok(WebdavClientStd::delete_calendar(self, id)?)
+    }
+
+    fn list_items(
+        &mut self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<Vec<CalendarItem>, CalendarBackendError> {
+        Ok(WebdavClientStd::list_items(
+            self,
+            calendar_id,
+            page,
+            page_size,
+            time_range,
+            kinds,
+            filter,
+        )?)
+    }
+
+    fn get_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<CalendarItem, CalendarBackendError> {
+        Ok(WebdavClientStd::get_item(self, calendar_id, item_id)?)
+    }
+
+    fn create_item(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError> {
+        Ok(WebdavClientStd::create_item(self, calendar_id, contents)?)
+    }
+
+    fn update_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError> {
+        Ok(WebdavClientStd::update_item(
+            self,
+            calendar_id,
+            item_id,
+            contents,
+            if_match,
+        )?)
+    }
+
+    fn delete_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(WebdavClientStd::delete_item(
+            self,
+            calendar_id,
+            item_id,
+            if_match,
+        )?)
+    }
+}
+
+#[cfg(feature = "memory")]
+impl CalendarBackend for MemoryClient {
+    fn list_calendars(&mut self) -> Result<Vec<Calendar>, CalendarBackendError> {
+        Ok(MemoryClient::list_calendars(self)?)
+    }
+
+    fn create_calendar(
+        &mut self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(MemoryClient::create_calendar(
+            self,
+            id,
+            name,
+            description,
+            color,
+        )?)
+    }
+
+    fn update_calendar(
+        &mut self,
+        id: &str,
+        patch: CalendarDiff,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(MemoryClient::update_calendar(self, id, patch)?)
+    }
+
+    fn delete_calendar(&mut self, id: &str) -> Result<(), CalendarBackendError> {
+        Ok(MemoryClient::delete_calendar(self, id)?)
+    }
+
+    fn list_items(
+        &mut self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<Vec<CalendarItem>, CalendarBackendError> {
+        Ok(MemoryClient::list_items(
+            self,
+            calendar_id,
+            page,
+            page_size,
+            time_range,
+            kinds,
+            filter,
+        )?)
+    }
+
+    fn get_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<CalendarItem, CalendarBackendError> {
+        Ok(MemoryClient::get_item(self, calendar_id, item_id)?)
+    }
+
+    fn create_item(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError> {
+        Ok(MemoryClient::create_item(self, calendar_id, contents)?)
+    }
+
+    fn update_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, CalendarBackendError> {
+        Ok(MemoryClient::update_item(
+            self,
+            calendar_id,
+            item_id,
+            contents,
+            if_match,
+        )?)
+    }
+
+    fn delete_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), CalendarBackendError> {
+        Ok(MemoryClient::delete_item(
+            self,
+            calendar_id,
+            item_id,
+            if_match,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[cfg(feature = "vdir")]
+    #[test]
+    fn vdir_lifted_error_downcasts_to_backend_error() {
+        use crate::{
+            item::vdir::update::VdirCalendarItemUpdateError, vdir::client::VdirClientError,
+        };
+
+        let err = CalendarClientStdError::from(VdirClientError::AlreadyExists("a".to_string()));
+        assert!(matches!(err, CalendarClientStdError::AlreadyExists { ref id, .. } if id == "a"));
+        let err = into_backend_error(err);
+        assert!(matches!(
+            err.downcast_ref::<VdirClientError>(),
+            Some(VdirClientError::AlreadyExists(id)) if id == "a"
+        ));
+
+        let err = VdirCalendarItemUpdateError::PreconditionFailed("b".to_string());
+        let err = CalendarClientStdError::from(VdirClientError::ItemUpdate(err));
+        assert!(matches!(
+            err,
+            CalendarClientStdError::PreconditionFailed { .. }
+        ));
+        let err = into_backend_error(err);
+        assert!(matches!(
+            err.downcast_ref::<VdirClientError>(),
+            Some(VdirClientError::ItemUpdate(
+                VdirCalendarItemUpdateError::PreconditionFailed(id)
+            )) if id == "b"
+        ));
+    }
+
+    #[cfg(feature = "webdav")]
+    #[test]
+    fn webdav_lifted_error_downcasts_to_backend_error() {
+        use crate::webdav::client::WebdavClientError;
+
+        let err = CalendarClientStdError::from(WebdavClientError::NotFound("/c/a.ics".to_string()));
+        assert!(matches!(err, CalendarClientStdError::NotFound { ref id, .. } if id == "/c/a.ics"));
+        let err = into_backend_error(err);
+        assert!(matches!(
+            err.downcast_ref::<WebdavClientError>(),
+            Some(WebdavClientError::NotFound(path)) if path == "/c/a.ics"
+        ));
+
+        let err = WebdavClientError::PreconditionFailed("/c/b.ics".to_string());
+        let err = into_backend_error(CalendarClientStdError::from(err));
+        assert!(matches!(
+            err.downcast_ref::<WebdavClientError>(),
+            Some(WebdavClientError::PreconditionFailed(_))
+        ));
+    }
+
+    #[cfg(feature = "memory")]
+    #[test]
+    fn memory_lifted_error_downcasts_to_backend_error() {
+        use crate::memory::client::MemoryClientError;
+
+        let err = CalendarClientStdError::from(MemoryClientError::ItemNotFound("a".to_string()));
+        assert!(err.source().is_some());
+        let err = into_backend_error(err);
+        assert!(matches!(
+            err.downcast_ref::<MemoryClientError>(),
+            Some(MemoryClientError::ItemNotFound(id)) if id == "a"
+        ));
+
+        let err = MemoryClientError::ItemAlreadyExists("b".to_string());
+        let err = into_backend_error(CalendarClientStdError::from(err));
+        assert!(matches!(
+            err.downcast_ref::<MemoryClientError>(),
+            Some(MemoryClientError::ItemAlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn lifted_error_without_source_stays_unified() {
+        let err = CalendarClientStdError::AlreadyExists {
+            id: "a".to_string(),
+            source: None,
+        };
+        let err = into_backend_error(err);
+        assert!(matches!(
+            err.downcast_ref::<CalendarClientStdError>(),
+            Some(CalendarClientStdError::AlreadyExists { source: None, .. })
+        ));
+    }
+}
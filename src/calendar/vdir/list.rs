@@ -34,6 +34,10 @@ pub enum VdirCalendarListError {
 }
 
 /// I/O-free coroutine listing every calendar under a vdir root.
+///
+/// io-vdir exposes no file metadata: the CTags are left unset here and
+/// filled in by the runners from the names, modification times and
+/// sizes of the item files, so no item is read.
 pub struct VdirCalendarList {
     inner: VdirCollectionList,
 }
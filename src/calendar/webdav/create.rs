@@ -18,9 +18,12 @@ use io_webdav::{
 use log::trace;
 use url::Url;
 
+use crate::webdav::xml::percent_decode;
+
 /// I/O-free coroutine creating a WebDAV calendar collection.
 ///
-/// On completion returns the new calendar id (its URL segment).
+/// On completion returns the new calendar id (its URL segment,
+/// percent-decoded).
 pub struct WebdavCalendarCreate {
     id: String,
     inner: CreateCalendar,
@@ -37,7 +40,7 @@ impl WebdavCalendarCreate {
     ) -> Self {
         trace!("prepare webdav calendar create");
         Self {
-            id: calendar.id.clone(),
+            id: percent_decode(&calendar.id),
             inner: CreateCalendar::new(base_url, auth, user_agent, home_set_path, calendar),
         }
     }
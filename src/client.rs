@@ -2,55 +2,173 @@
 //!
 //! [`CalendarClientStd`] is an enum over the single registered backend: a value
 //! is exactly one of the compiled-in per-backend clients ([`VdirClient`],
-//! [`WebdavClientStd`]). Unlike io-email's multi-backend `EmailClientStd`
-//! struct, a calendar account speaks one protocol at a time, so the unified
-//! client is an enum rather than a bag of optional slots; dispatch is a plain
-//! `match` with no priority order.
+//! [`WebdavClientStd`], [`MemoryClient`]) or a caller-provided [`CalendarBackend`] trait object.
+//! Unlike io-email's multi-backend `EmailClientStd` struct, a calendar account
+//! speaks one protocol at a time, so the unified client is an enum rather than
+//! a bag of optional slots; dispatch is a plain `match` with no priority order.
 //!
 //! Build one via the per-backend `From` impls (e.g.
 //! `CalendarClientStd::from(VdirClient::new(root))`) or by naming the variant
-//! directly.
+//! directly. Custom backends go through
+//! `CalendarClientStd::from(Box::new(backend) as Box<dyn CalendarBackend>)`.
 //!
 //! [`VdirClient`]: crate::vdir::client::VdirClient
 //! [`WebdavClientStd`]: crate::webdav::client::WebdavClientStd
+//! [`MemoryClient`]: crate::memory::client::MemoryClient
+//! [`CalendarBackend`]: crate::backend::CalendarBackend
 
-#[cfg(feature = "webdav")]
-use alloc::boxed::Box;
-use alloc::{string::String, vec::Vec};
+use alloc::{boxed::Box, string::String, vec::Vec};
 
 use log::trace;
 use thiserror::Error;
 
+#[cfg(feature = "parser")]
+use crate::item::{SearchHit, UidCheck, UidConflict, check_uid, item_uid, search_items};
 use crate::{
     calendar::{Calendar, CalendarDiff},
-    item::{CalendarItem, TimeRange},
+    item::{
+        CalendarItem, CalendarItemKind, CalendarItemReceipt, CalendarItemRef, CalendarItemSync,
+        ItemFilter, TimeRange,
+    },
 };
 
 /// Errors surfaced by [`CalendarClientStd`].
 ///
-/// Each variant flattens the registered backend's error type via
-/// `#[from]`, so the `?` operator works across the dispatch boundary.
+/// Each variant wraps the registered backend's error type through a
+/// `From` impl, so the `?` operator works across the dispatch boundary.
+/// Failures callers commonly branch on are lifted to shared variants
+/// whatever the backend: a create refused because the item exists
+/// becomes `AlreadyExists`, a missing item `NotFound` and a stale
+/// `if_match` entity tag `PreconditionFailed`. They carry the item id,
+/// or the request path for WebDAV, and keep the backend error as their
+/// [`source`](core::error::Error::source); WebDAV-only failures (403,
+/// 507, `DAV:error` conditions) stay typed under the WebDAV variant.
 #[derive(Debug, Error)]
 pub enum CalendarClientStdError {
     #[cfg(feature = "vdir")]
     #[error(transparent)]
-    Vdir(#[from] crate::vdir::client::VdirClientError),
+    Vdir(crate::vdir::client::VdirClientError),
     #[cfg(feature = "webdav")]
     #[error(transparent)]
-    Webdav(#[from] crate::webdav::client::WebdavClientError),
+    Webdav(crate::webdav::client::WebdavClientError),
+    #[cfg(feature = "memory")]
+    #[error(transparent)]
+    Memory(crate::memory::client::MemoryClientError),
+    #[error("Item `{id}` already exists")]
+    AlreadyExists {
+        id: String,
+        source: Option<crate::backend::CalendarBackendError>,
+    },
+    #[error("Item `{id}` not found")]
+    NotFound {
+        id: String,
+        source: Option<crate::backend::CalendarBackendError>,
+    },
+    #[error("Item `{id}` does not match the expected entity tag")]
+    PreconditionFailed {
+        id: String,
+        source: Option<crate::backend::CalendarBackendError>,
+    },
+    #[error(transparent)]
+    Backend(crate::backend::CalendarBackendError),
+}
+
+#[cfg(feature = "vdir")]
+impl From<crate::vdir::client::VdirClientError> for CalendarClientStdError {
+    fn from(err: crate::vdir::client::VdirClientError) -> Self {
+        use crate::{
+            item::vdir::{
+                delete::VdirCalendarItemDeleteError, update::VdirCalendarItemUpdateError,
+            },
+            vdir::client::VdirClientError,
+        };
+
+        match err {
+            VdirClientError::AlreadyExists(ref id) => Self::AlreadyExists {
+                id: id.clone(),
+                source: Some(Box::new(err)),
+            },
+            VdirClientError::ItemUpdate(VdirCalendarItemUpdateError::PreconditionFailed(
+                ref id,
+            ))
+            | VdirClientError::ItemDelete(VdirCalendarItemDeleteError::PreconditionFailed(
+                ref id,
+            )) => Self::PreconditionFailed {
+                id: id.clone(),
+                source: Some(Box::new(err)),
+            },
+            err => Self::Vdir(err),
+        }
+    }
+}
+
+#[cfg(feature = "webdav")]
+impl From<crate::webdav::client::WebdavClientError> for CalendarClientStdError {
+    fn from(err: crate::webdav::client::WebdavClientError) -> Self {
+        use crate::webdav::client::WebdavClientError;
+
+        match err {
+            WebdavClientError::AlreadyExists(ref id) => Self::AlreadyExists {
+                id: id.clone(),
+                source: Some(Box::new(err)),
+            },
+            WebdavClientError::NotFound(ref path) => Self::NotFound {
+                id: path.clone(),
+                source: Some(Box::new(err)),
+            },
+            WebdavClientError::PreconditionFailed(ref path) => Self::PreconditionFailed {
+                id: path.clone(),
+                source: Some(Box::new(err)),
+            },
+            err => Self::Webdav(err),
+        }
+    }
+}
+
+#[cfg(feature = "memory")]
+impl From<crate::memory::client::MemoryClientError> for CalendarClientStdError {
+    fn from(err: crate::memory::client::MemoryClientError) -> Self {
+        use crate::memory::client::MemoryClientError;
+
+        match err {
+            MemoryClientError::ItemAlreadyExists(ref id) => Self::AlreadyExists {
+                id: id.clone(),
+                source: Some(Box::new(err)),
+            },
+            MemoryClientError::ItemNotFound(ref id) => Self::NotFound {
+                id: id.clone(),
+                source: Some(Box::new(err)),
+            },
+            MemoryClientError::PreconditionFailed(ref id) => Self::PreconditionFailed {
+                id: id.clone(),
+                source: Some(Box::new(err)),
+            },
+            err => Self::Memory(err),
+        }
+    }
 }
 
 /// Std-blocking unified calendar client.
 ///
-/// One variant per compiled-in backend; a value always holds exactly
-/// one. Each shared-API method dispatches to the active backend's
-/// matching method.
+/// One variant per compiled-in backend plus a trait-object variant for
+/// custom [`CalendarBackend`](crate::backend::CalendarBackend)
+/// implementations; a value always holds exactly one. Each shared-API
+/// method dispatches to the active backend's matching method.
 #[derive(Debug)]
 pub enum CalendarClientStd {
     #[cfg(feature = "vdir")]
     Vdir(crate::vdir::client::VdirClient),
     #[cfg(feature = "webdav")]
     Webdav(Box<crate::webdav::client::WebdavClientStd>),
+    #[cfg(feature = "memory")]
+    Memory(crate::memory::client::MemoryClient),
+    Backend(Box<dyn crate::backend::CalendarBackend>),
+}
+
+impl From<Box<dyn crate::backend::CalendarBackend>> for CalendarClientStd {
+    fn from(backend: Box<dyn crate::backend::CalendarBackend>) -> Self {
+        Self::Backend(backend)
+    }
 }
 
 impl CalendarClientStd {
@@ -62,6 +180,11 @@ impl CalendarClientStd {
             Self::Vdir(client) => Ok(client.list_calendars()?),
             #[cfg(feature = "webdav")]
             Self::Webdav(client) => Ok(client.list_calendars()?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.list_calendars()?),
+            Self::Backend(backend) => backend
+                .list_calendars()
+                .map_err(CalendarClientStdError::Backend),
         }
     }
 
@@ -80,6 +203,11 @@ impl CalendarClientStd {
             Self::Vdir(client) => Ok(client.create_calendar(id, name, description, color)?),
             #[cfg(feature = "webdav")]
             Self::Webdav(client) => Ok(client.create_calendar(id, name, description, color)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.create_calendar(id, name, description, color)?),
+            Self::Backend(backend) => backend
+                .create_calendar(id, name, description, color)
+                .map_err(CalendarClientStdError::Backend),
         }
     }
 
@@ -96,6 +224,11 @@ impl CalendarClientStd {
             Self::Vdir(client) => Ok(client.update_calendar(id, patch)?),
             #[cfg(feature = "webdav")]
             Self::Webdav(client) => Ok(client.update_calendar(id, patch)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.update_calendar(id, patch)?),
+            Self::Backend(backend) => backend
+                .update_calendar(id, patch)
+                .map_err(CalendarClientStdError::Backend),
         }
     }
 
@@ -107,31 +240,50 @@ impl CalendarClientStd {
             Self::Vdir(client) => Ok(client.delete_calendar(id)?),
             #[cfg(feature = "webdav")]
             Self::Webdav(client) => Ok(client.delete_calendar(id)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.delete_calendar(id)?),
+            Self::Backend(backend) => backend
+                .delete_calendar(id)
+                .map_err(CalendarClientStdError::Backend),
         }
     }
 
     /// Lists items inside `calendar_id`. `page` is 1-indexed; pass
     /// `None` to default to page 1. `page_size = None` returns the full
-    /// window. When `time_range` is set, only VEVENTs overlapping the
-    /// range are returned (server-side for WebDAV, client-side for
-    /// vdir).
+    /// window. When `time_range` is set, only items overlapping the
+    /// range per RFC 4791 section 9.9 are returned (server-side for
+    /// WebDAV, client-side for vdir). When `kinds` is non-empty, only
+    /// items of those kinds are returned (a `comp-filter` for WebDAV,
+    /// client-side for vdir). A range carrying a
+    /// [`RecurrenceExpansion`](crate::item::RecurrenceExpansion)
+    /// returns recurring items in the same expanded or limited shape
+    /// from every backend.
     pub fn list_items(
         &mut self,
         calendar_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
         time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
     ) -> Result<Vec<CalendarItem>, CalendarClientStdError> {
         trace!("list items");
         match self {
             #[cfg(feature = "vdir")]
             Self::Vdir(client) => {
-                Ok(client.list_items(calendar_id, page, page_size, time_range)?)
+                Ok(client.list_items(calendar_id, page, page_size, time_range, kinds, filter)?)
             }
             #[cfg(feature = "webdav")]
             Self::Webdav(client) => {
-                Ok(client.list_items(calendar_id, page, page_size, time_range)?)
+                Ok(client.list_items(calendar_id, page, page_size, time_range, kinds, filter)?)
+            }
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => {
+                Ok(client.list_items(calendar_id, page, page_size, time_range, kinds, filter)?)
             }
+            Self::Backend(backend) => backend
+                .list_items(calendar_id, page, page_size, time_range, kinds, filter)
+                .map_err(CalendarClientStdError::Backend),
         }
     }
 
@@ -147,37 +299,257 @@ impl CalendarClientStd {
             Self::Vdir(client) => Ok(client.get_item(calendar_id, item_id)?),
             #[cfg(feature = "webdav")]
             Self::Webdav(client) => Ok(client.get_item(calendar_id, item_id)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.get_item(calendar_id, item_id)?),
+            Self::Backend(backend) => backend
+                .get_item(calendar_id, item_id)
+                .map_err(CalendarClientStdError::Backend),
+        }
+    }
+
+    /// Fetches `item_ids` from `calendar_id` in one batch, in the
+    /// requested order; ids unknown to the backend are left out.
+    ///
+    /// WebDAV sends a single `calendar-multiget` REPORT and vdir reads
+    /// every file in one batch. [`Self::Backend`] implementations are
+    /// asked item by item through
+    /// [`CalendarBackend::get_item`](crate::backend::CalendarBackend::get_item),
+    /// so their errors (including a missing item) are returned as is.
+    pub fn get_items(
+        &mut self,
+        calendar_id: &str,
+        item_ids: &[&str],
+    ) -> Result<Vec<CalendarItem>, CalendarClientStdError> {
+        trace!("get items");
+        match self {
+            #[cfg(feature = "vdir")]
+            Self::Vdir(client) => Ok(client.get_items(calendar_id, item_ids)?),
+            #[cfg(feature = "webdav")]
+            Self::Webdav(client) => Ok(client.get_items(calendar_id, item_ids)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.get_items(calendar_id, item_ids)?),
+            Self::Backend(backend) => item_ids
+                .iter()
+                .map(|item_id| backend.get_item(calendar_id, item_id))
+                .collect::<Result<_, _>>()
+                .map_err(CalendarClientStdError::Backend),
+        }
+    }
+
+    /// Finds the item of `calendar_id` carrying the iCalendar `uid`,
+    /// the one with the smallest id when several match.
+    ///
+    /// WebDAV asks the server with a UID `prop-filter` REPORT; vdir,
+    /// memory and [`Self::Backend`] implementations scan the parsed
+    /// items of the calendar.
+    #[cfg(feature = "parser")]
+    pub fn find_item_by_uid(
+        &mut self,
+        calendar_id: &str,
+        uid: &str,
+    ) -> Result<Option<CalendarItem>, CalendarClientStdError> {
+        trace!("find item by uid");
+        match self {
+            #[cfg(feature = "vdir")]
+            Self::Vdir(client) => Ok(client.find_item_by_uid(calendar_id, uid)?),
+            #[cfg(feature = "webdav")]
+            Self::Webdav(client) => Ok(client.find_item_by_uid(calendar_id, uid)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.find_item_by_uid(calendar_id, uid)?),
+            Self::Backend(backend) => {
+                let items = backend
+                    .list_items(calendar_id, None, None, None, &[], None)
+                    .map_err(CalendarClientStdError::Backend)?;
+                Ok(items
+                    .into_iter()
+                    .filter(|item| item.uid().as_deref() == Some(uid))
+                    .min_by(|a, b| a.id.cmp(&b.id)))
+            }
+        }
+    }
+
+    /// Finds the item carrying the iCalendar `uid` in any calendar,
+    /// searching them in [`list_calendars`](Self::list_calendars)
+    /// order. The returned item names the calendar it was found in.
+    #[cfg(feature = "parser")]
+    pub fn find_item_by_uid_in_any_calendar(
+        &mut self,
+        uid: &str,
+    ) -> Result<Option<CalendarItem>, CalendarClientStdError> {
+        for calendar in self.list_calendars()? {
+            if let Some(item) = self.find_item_by_uid(&calendar.id, uid)? {
+                return Ok(Some(item));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Searches `query` in the SUMMARY, DESCRIPTION, LOCATION, ATTENDEE
+    /// and CATEGORIES of the items of `calendar_id`, case-insensitively
+    /// with Unicode case folding. Returns the hits sorted by id.
+    ///
+    /// WebDAV asks the server with `text-match` REPORTs; vdir, memory
+    /// and [`Self::Backend`] implementations scan the parsed items of
+    /// the calendar.
+    #[cfg(feature = "parser")]
+    pub fn search_items(
+        &mut self,
+        calendar_id: &str,
+        query: &str,
+    ) -> Result<Vec<SearchHit>, CalendarClientStdError> {
+        trace!("search items");
+        match self {
+            #[cfg(feature = "vdir")]
+            Self::Vdir(client) => Ok(client.search_items(calendar_id, query)?),
+            #[cfg(feature = "webdav")]
+            Self::Webdav(client) => Ok(client.search_items(calendar_id, query)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.search_items(calendar_id, query)?),
+            Self::Backend(backend) => {
+                let items = backend
+                    .list_items(calendar_id, None, None, None, &[], None)
+                    .map_err(CalendarClientStdError::Backend)?;
+                Ok(search_items(items, query))
+            }
+        }
+    }
+
+    /// Searches `query` in every calendar, in
+    /// [`list_calendars`](Self::list_calendars) order. Each hit names
+    /// the calendar its item was found in, hits of one calendar being
+    /// sorted by id.
+    #[cfg(feature = "parser")]
+    pub fn search(&mut self, query: &str) -> Result<Vec<SearchHit>, CalendarClientStdError> {
+        let mut hits = Vec::new();
+
+        for calendar in self.list_calendars()? {
+            hits.extend(self.search_items(&calendar.id, query)?);
+        }
+
+        Ok(hits)
+    }
+
+    /// Lists the id, ETag, size and modification time of every item of
+    /// `calendar_id`, sorted by id, without their contents.
+    ///
+    /// WebDAV sends a Depth 1 PROPFIND asking for metadata only.
+    /// [`Self::Backend`] implementations have no such operation: their
+    /// items are listed in full through
+    /// [`CalendarBackend::list_items`](crate::backend::CalendarBackend::list_items)
+    /// and reduced to references.
+    pub fn list_item_refs(
+        &mut self,
+        calendar_id: &str,
+    ) -> Result<Vec<CalendarItemRef>, CalendarClientStdError> {
+        trace!("list item refs");
+        match self {
+            #[cfg(feature = "vdir")]
+            Self::Vdir(client) => Ok(client.list_item_refs(calendar_id)?),
+            #[cfg(feature = "webdav")]
+            Self::Webdav(client) => Ok(client.list_item_refs(calendar_id)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.list_item_refs(calendar_id)?),
+            Self::Backend(backend) => {
+                let items = backend
+                    .list_items(calendar_id, None, None, None, &[], None)
+                    .map_err(CalendarClientStdError::Backend)?;
+                let mut refs: Vec<CalendarItemRef> =
+                    items.iter().map(CalendarItemRef::from).collect();
+                refs.sort_by(|a, b| a.id.cmp(&b.id));
+                Ok(refs)
+            }
         }
     }
 
     /// Appends a raw iCalendar item to `calendar_id`. Returns the
-    /// identifier the backend assigned to the stored item.
+    /// identifier the backend assigned to the stored item along with
+    /// its new entity tag, when the backend reports one.
     pub fn create_item(
         &mut self,
         calendar_id: &str,
         contents: Vec<u8>,
-    ) -> Result<String, CalendarClientStdError> {
+    ) -> Result<CalendarItemReceipt, CalendarClientStdError> {
         trace!("create item");
         match self {
             #[cfg(feature = "vdir")]
             Self::Vdir(client) => Ok(client.create_item(calendar_id, contents)?),
             #[cfg(feature = "webdav")]
             Self::Webdav(client) => Ok(client.create_item(calendar_id, contents)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.create_item(calendar_id, contents)?),
+            Self::Backend(backend) => backend
+                .create_item(calendar_id, contents)
+                .map_err(CalendarClientStdError::Backend),
+        }
+    }
+
+    /// Appends a raw iCalendar item to `calendar_id` like
+    /// [`create_item`](Self::create_item), first scanning the calendar
+    /// for an item with the same UID. `on_conflict` then either fails
+    /// with [`CalendarClientStdError::AlreadyExists`], replaces that
+    /// item (gated on its entity tag) or stores the new one under a
+    /// suffixed UID. Items without a UID are created as is.
+    #[cfg(feature = "parser")]
+    pub fn create_item_with(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+        on_conflict: UidConflict,
+    ) -> Result<CalendarItemReceipt, CalendarClientStdError> {
+        trace!("create item with uid conflict {on_conflict:?}");
+        match self {
+            #[cfg(feature = "vdir")]
+            Self::Vdir(client) => {
+                Ok(client.create_item_with(calendar_id, contents, on_conflict)?)
+            }
+            #[cfg(feature = "webdav")]
+            Self::Webdav(client) => {
+                Ok(client.create_item_with(calendar_id, contents, on_conflict)?)
+            }
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => {
+                Ok(client.create_item_with(calendar_id, contents, on_conflict)?)
+            }
+            Self::Backend(backend) => {
+                let Some(uid) = item_uid(&contents) else {
+                    return backend
+                        .create_item(calendar_id, contents)
+                        .map_err(CalendarClientStdError::Backend);
+                };
+                let items = backend
+                    .list_items(calendar_id, None, None, None, &[], None)
+                    .map_err(CalendarClientStdError::Backend)?;
+
+                let receipt = match check_uid(items, &uid, contents, on_conflict) {
+                    UidCheck::Create(contents) => backend.create_item(calendar_id, contents),
+                    UidCheck::Exists(id) => {
+                        return Err(CalendarClientStdError::AlreadyExists { id, source: None });
+                    }
+                    UidCheck::Replace(existing, contents) => {
+                        let etag = existing.etag.as_deref();
+                        backend.update_item(calendar_id, &existing.id, contents, etag)
+                    }
+                };
+                receipt.map_err(CalendarClientStdError::Backend)
+            }
         }
     }
 
     /// Replaces the bytes of `item_id` inside `calendar_id`.
     ///
     /// `if_match` is the backend-specific entity tag to gate the update
-    /// on; pass `None` to overwrite unconditionally. Backends without
-    /// ETag support (vdir) ignore it.
+    /// on; pass `None` to overwrite unconditionally. Vdir compares it
+    /// against the synthetic ETag derived from the file contents. The
+    /// returned receipt carries the new entity tag, ready for the next
+    /// conditional write.
     pub fn update_item(
         &mut self,
         calendar_id: &str,
         item_id: &str,
         contents: Vec<u8>,
         if_match: Option<&str>,
-    ) -> Result<(), CalendarClientStdError> {
+    ) -> Result<CalendarItemReceipt, CalendarClientStdError> {
         trace!("update item");
         match self {
             #[cfg(feature = "vdir")]
@@ -188,21 +560,89 @@ impl CalendarClientStd {
             Self::Webdav(client) => {
                 Ok(client.update_item(calendar_id, item_id, contents, if_match)?)
             }
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => {
+                Ok(client.update_item(calendar_id, item_id, contents, if_match)?)
+            }
+            Self::Backend(backend) => backend
+                .update_item(calendar_id, item_id, contents, if_match)
+                .map_err(CalendarClientStdError::Backend),
         }
     }
 
     /// Permanently deletes `item_id` from `calendar_id`.
+    ///
+    /// `if_match` gates the removal on the entity tag, as for
+    /// [`update_item`](Self::update_item).
     pub fn delete_item(
         &mut self,
         calendar_id: &str,
         item_id: &str,
+        if_match: Option<&str>,
     ) -> Result<(), CalendarClientStdError> {
         trace!("delete item");
         match self {
             #[cfg(feature = "vdir")]
-            Self::Vdir(client) => Ok(client.delete_item(calendar_id, item_id)?),
+            Self::Vdir(client) => Ok(client.delete_item(calendar_id, item_id, if_match)?),
             #[cfg(feature = "webdav")]
-            Self::Webdav(client) => Ok(client.delete_item(calendar_id, item_id)?),
+            Self::Webdav(client) => Ok(client.delete_item(calendar_id, item_id, if_match)?),
+            #[cfg(feature = "memory")]
+            Self::Memory(client) => Ok(client.delete_item(calendar_id, item_id, if_match)?),
+            Self::Backend(backend) => backend
+                .delete_item(calendar_id, item_id, if_match)
+                .map_err(CalendarClientStdError::Backend),
         }
     }
+
+    /// Collects the changes of `calendar_id` since `sync_token`.
+    ///
+    /// WebDAV issues an RFC 6578 `sync-collection` REPORT. Other
+    /// backends, and WebDAV when the server rejects the token, fall
+    /// back to a full listing diffed by ETag against `known` (`(id,
+    /// etag)` pairs the caller already holds), flagged
+    /// [`full`](CalendarItemSync::full). The fallback uses the calendar
+    /// CTag as token and skips the listing while it is unchanged.
+    pub fn sync_items(
+        &mut self,
+        calendar_id: &str,
+        sync_token: Option<&str>,
+        known: &[(&str, &str)],
+    ) -> Result<CalendarItemSync, CalendarClientStdError> {
+        trace!("sync items");
+        match self {
+            #[cfg(feature = "webdav")]
+            Self::Webdav(client) => Ok(client.sync_items(calendar_id, sync_token, known)?),
+            _ => self.sync_items_by_listing(calendar_id, sync_token, known),
+        }
+    }
+
+    /// Listing-based [`Self::sync_items`] for backends without a sync
+    /// protocol.
+    fn sync_items_by_listing(
+        &mut self,
+        calendar_id: &str,
+        sync_token: Option<&str>,
+        known: &[(&str, &str)],
+    ) -> Result<CalendarItemSync, CalendarClientStdError> {
+        let ctag = self
+            .list_calendars()?
+            .into_iter()
+            .find(|calendar| calendar.id == calendar_id)
+            .and_then(|calendar| calendar.ctag);
+
+        if let Some(ctag) = ctag.as_deref().filter(|ctag| Some(*ctag) == sync_token) {
+            trace!("calendar CTag unchanged, nothing to sync");
+            return Ok(CalendarItemSync {
+                sync_token: ctag.into(),
+                ..CalendarItemSync::default()
+            });
+        }
+
+        let items = self.list_items(calendar_id, None, None, None, &[], None)?;
+        Ok(CalendarItemSync::from_listing(
+            items,
+            known,
+            ctag.unwrap_or_default(),
+        ))
+    }
 }
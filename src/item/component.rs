@@ -0,0 +1,1061 @@
+//! Typed views over the `VEVENT`, `VTODO` and `VJOURNAL` components of
+//! a [`CalendarItem`].
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::str::from_utf8;
+
+use calcard::{
+    Entry, Parser,
+    common::{PartialDateTime, Uri},
+    icalendar::{
+        ICalendar, ICalendarComponent, ICalendarComponentType, ICalendarDuration, ICalendarEntry,
+        ICalendarParameter, ICalendarParameterName, ICalendarParameterValue, ICalendarProperty,
+        ICalendarValue, ICalendarValueType,
+    },
+};
+
+use super::{
+    CalendarItem, CalendarItemKind,
+    datetime::{DateTime, Duration, Time, TimeZone},
+};
+
+const PRODID: &str = "-//pimalaya//io-calendar//EN";
+
+/// Status of an item (`STATUS` property, RFC 5545 section 3.8.1.11).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum ItemStatus {
+    Tentative,
+    Confirmed,
+    Cancelled,
+    NeedsAction,
+    Completed,
+    InProcess,
+    Draft,
+    Final,
+    /// Any other (e.g. `X-` prefixed) value, kept verbatim.
+    Other(String),
+}
+
+impl ItemStatus {
+    /// Returns the iCalendar value.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Tentative => "TENTATIVE",
+            Self::Confirmed => "CONFIRMED",
+            Self::Cancelled => "CANCELLED",
+            Self::NeedsAction => "NEEDS-ACTION",
+            Self::Completed => "COMPLETED",
+            Self::InProcess => "IN-PROCESS",
+            Self::Draft => "DRAFT",
+            Self::Final => "FINAL",
+            Self::Other(status) => status,
+        }
+    }
+}
+
+impl From<&str> for ItemStatus {
+    fn from(status: &str) -> Self {
+        match status.trim().to_ascii_uppercase().as_str() {
+            "TENTATIVE" => Self::Tentative,
+            "CONFIRMED" => Self::Confirmed,
+            "CANCELLED" => Self::Cancelled,
+            "NEEDS-ACTION" => Self::NeedsAction,
+            "COMPLETED" => Self::Completed,
+            "IN-PROCESS" => Self::InProcess,
+            "DRAFT" => Self::Draft,
+            "FINAL" => Self::Final,
+            _ => Self::Other(status.trim().to_string()),
+        }
+    }
+}
+
+/// An `ORGANIZER` or `ATTENDEE` (RFC 5545 sections 3.8.4.1 and
+/// 3.8.4.3).
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct CalendarUser {
+    /// Calendar user address, usually a `mailto:` URI.
+    pub address: String,
+    /// `CN` parameter.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub common_name: Option<String>,
+    /// `ROLE` parameter (`REQ-PARTICIPANT`, `CHAIR`, ...).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub role: Option<String>,
+    /// `PARTSTAT` parameter (`ACCEPTED`, `DECLINED`, ...).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub partstat: Option<String>,
+}
+
+impl CalendarUser {
+    /// Builds a user from its address alone.
+    pub fn new(address: impl ToString) -> Self {
+        Self {
+            address: address.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn from_entry(entry: &ICalendarEntry) -> Self {
+        Self {
+            address: entry
+                .values
+                .first()
+                .and_then(value_text)
+                .unwrap_or_default(),
+            common_name: param_text(entry, &ICalendarParameterName::Cn),
+            role: param_text(entry, &ICalendarParameterName::Role),
+            partstat: param_text(entry, &ICalendarParameterName::Partstat),
+        }
+    }
+
+    fn to_entry(&self, name: ICalendarProperty) -> ICalendarEntry {
+        let params = [
+            (ICalendarParameterName::Cn, &self.common_name),
+            (ICalendarParameterName::Role, &self.role),
+            (ICalendarParameterName::Partstat, &self.partstat),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            Some(ICalendarParameter {
+                name,
+                value: ICalendarParameterValue::Text(value.clone()?),
+            })
+        })
+        .collect();
+
+        ICalendarEntry {
+            name,
+            params,
+            values: vec![ICalendarValue::Uri(Uri::Location(self.address.clone()))],
+        }
+    }
+}
+
+/// A `VEVENT` component.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct Event {
+    pub uid: String,
+    /// `DTSTAMP`, in UTC.
+    pub dtstamp: Option<DateTime>,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    /// `DTSTART`.
+    pub start: Option<DateTime>,
+    /// `DTEND`, mutually exclusive with `duration`.
+    pub end: Option<DateTime>,
+    pub duration: Option<Duration>,
+    pub status: Option<ItemStatus>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub categories: Vec<String>,
+    pub organizer: Option<CalendarUser>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attendees: Vec<CalendarUser>,
+    /// Raw `RRULE` value (`FREQ=WEEKLY;BYDAY=MO`).
+    pub rrule: Option<String>,
+    pub sequence: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<Source>,
+}
+
+impl Event {
+    /// Builds an empty event with the given UID, stamped with
+    /// `dtstamp`.
+    ///
+    /// The VCALENDAR wrapper gets `VERSION` and `PRODID`. This crate
+    /// has no clock, so `dtstamp` is the current UTC time as known by
+    /// the caller.
+    pub fn new(uid: impl ToString, dtstamp: DateTime) -> Self {
+        Self {
+            uid: uid.to_string(),
+            dtstamp: Some(dtstamp),
+            ..Default::default()
+        }
+    }
+
+    /// Extracts the first `VEVENT` from raw iCalendar bytes.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let (fields, source) = Source::parse(bytes, ICalendarComponentType::VEvent)?;
+        Some(Self::from_fields(fields, Some(source)))
+    }
+
+    /// Serializes back to iCalendar bytes. Only the properties whose
+    /// typed value changed are rewritten; every other property,
+    /// parameter and component of the source calcard tree is kept.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Source::render(
+            self.source.as_ref(),
+            ICalendarComponentType::VEvent,
+            &self.fields(),
+        )
+    }
+
+    fn from_fields(fields: Fields, source: Option<Source>) -> Self {
+        Self {
+            uid: fields.uid,
+            dtstamp: fields.dtstamp,
+            summary: fields.summary,
+            description: fields.description,
+            location: fields.location,
+            start: fields.start,
+            end: fields.end,
+            duration: fields.duration,
+            status: fields.status,
+            categories: fields.categories,
+            organizer: fields.organizer,
+            attendees: fields.attendees,
+            rrule: fields.rrule,
+            sequence: fields.sequence,
+            source,
+        }
+    }
+
+    fn fields(&self) -> Fields {
+        Fields {
+            uid: self.uid.clone(),
+            dtstamp: self.dtstamp.clone(),
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            location: self.location.clone(),
+            start: self.start.clone(),
+            end: self.end.clone(),
+            due: None,
+            duration: self.duration,
+            status: self.status.clone(),
+            categories: self.categories.clone(),
+            organizer: self.organizer.clone(),
+            attendees: self.attendees.clone(),
+            rrule: self.rrule.clone(),
+            sequence: self.sequence,
+        }
+    }
+}
+
+/// A `VTODO` component.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct Todo {
+    pub uid: String,
+    /// `DTSTAMP`, in UTC.
+    pub dtstamp: Option<DateTime>,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    /// `DTSTART`.
+    pub start: Option<DateTime>,
+    /// `DUE`, mutually exclusive with `duration`.
+    pub due: Option<DateTime>,
+    pub duration: Option<Duration>,
+    pub status: Option<ItemStatus>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub categories: Vec<String>,
+    pub organizer: Option<CalendarUser>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attendees: Vec<CalendarUser>,
+    /// Raw `RRULE` value.
+    pub rrule: Option<String>,
+    pub sequence: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<Source>,
+}
+
+impl Todo {
+    /// Builds an empty todo with the given UID, stamped with
+    /// `dtstamp`, see [`Event::new`].
+    pub fn new(uid: impl ToString, dtstamp: DateTime) -> Self {
+        Self {
+            uid: uid.to_string(),
+            dtstamp: Some(dtstamp),
+            ..Default::default()
+        }
+    }
+
+    /// Extracts the first `VTODO` from raw iCalendar bytes.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let (fields, source) = Source::parse(bytes, ICalendarComponentType::VTodo)?;
+        Some(Self::from_fields(fields, Some(source)))
+    }
+
+    /// Serializes back to iCalendar bytes, see [`Event::to_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Source::render(
+            self.source.as_ref(),
+            ICalendarComponentType::VTodo,
+            &self.fields(),
+        )
+    }
+
+    fn from_fields(fields: Fields, source: Option<Source>) -> Self {
+        Self {
+            uid: fields.uid,
+            dtstamp: fields.dtstamp,
+            summary: fields.summary,
+            description: fields.description,
+            location: fields.location,
+            start: fields.start,
+            due: fields.due,
+            duration: fields.duration,
+            status: fields.status,
+            categories: fields.categories,
+            organizer: fields.organizer,
+            attendees: fields.attendees,
+            rrule: fields.rrule,
+            sequence: fields.sequence,
+            source,
+        }
+    }
+
+    fn fields(&self) -> Fields {
+        Fields {
+            uid: self.uid.clone(),
+            dtstamp: self.dtstamp.clone(),
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            location: self.location.clone(),
+            start: self.start.clone(),
+            end: None,
+            due: self.due.clone(),
+            duration: self.duration,
+            status: self.status.clone(),
+            categories: self.categories.clone(),
+            organizer: self.organizer.clone(),
+            attendees: self.attendees.clone(),
+            rrule: self.rrule.clone(),
+            sequence: self.sequence,
+        }
+    }
+}
+
+/// A `VJOURNAL` component.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct Journal {
+    pub uid: String,
+    /// `DTSTAMP`, in UTC.
+    pub dtstamp: Option<DateTime>,
+    pub summary: Option<String>,
+    /// First `DESCRIPTION`; journals may carry several, the others are
+    /// preserved untouched unless this one changes.
+    pub description: Option<String>,
+    /// `DTSTART`.
+    pub start: Option<DateTime>,
+    pub status: Option<ItemStatus>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub categories: Vec<String>,
+    pub organizer: Option<CalendarUser>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attendees: Vec<CalendarUser>,
+    /// Raw `RRULE` value.
+    pub rrule: Option<String>,
+    pub sequence: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<Source>,
+}
+
+impl Journal {
+    /// Builds an empty journal entry with the given UID, stamped with
+    /// `dtstamp`, see [`Event::new`].
+    pub fn new(uid: impl ToString, dtstamp: DateTime) -> Self {
+        Self {
+            uid: uid.to_string(),
+            dtstamp: Some(dtstamp),
+            ..Default::default()
+        }
+    }
+
+    /// Extracts the first `VJOURNAL` from raw iCalendar bytes.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let (fields, source) = Source::parse(bytes, ICalendarComponentType::VJournal)?;
+        Some(Self::from_fields(fields, Some(source)))
+    }
+
+    /// Serializes back to iCalendar bytes, see [`Event::to_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Source::render(
+            self.source.as_ref(),
+            ICalendarComponentType::VJournal,
+            &self.fields(),
+        )
+    }
+
+    fn from_fields(fields: Fields, source: Option<Source>) -> Self {
+        Self {
+            uid: fields.uid,
+            dtstamp: fields.dtstamp,
+            summary: fields.summary,
+            description: fields.description,
+            start: fields.start,
+            status: fields.status,
+            categories: fields.categories,
+            organizer: fields.organizer,
+            attendees: fields.attendees,
+            rrule: fields.rrule,
+            sequence: fields.sequence,
+            source,
+        }
+    }
+
+    fn fields(&self) -> Fields {
+        Fields {
+            uid: self.uid.clone(),
+            dtstamp: self.dtstamp.clone(),
+            summary: self.summary.clone(),
+            description: self.description.clone(),
+            location: None,
+            start: self.start.clone(),
+            end: None,
+            due: None,
+            duration: None,
+            status: self.status.clone(),
+            categories: self.categories.clone(),
+            organizer: self.organizer.clone(),
+            attendees: self.attendees.clone(),
+            rrule: self.rrule.clone(),
+            sequence: self.sequence,
+        }
+    }
+}
+
+/// Typed view of the main component of a [`CalendarItem`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum CalendarComponent {
+    Event(Event),
+    Todo(Todo),
+    Journal(Journal),
+}
+
+impl CalendarComponent {
+    /// Extracts the first `VEVENT`, `VTODO` or `VJOURNAL` child of the
+    /// VCALENDAR (time zones and other components are skipped).
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let calendar = parse_calendar(bytes)?;
+        let (index, kind) = calendar.components.iter().enumerate().find_map(|(i, c)| {
+            Some((
+                i,
+                CalendarItemKind::from_component_name(c.component_type.as_str())?,
+            ))
+        })?;
+
+        let fields = Fields::read(&calendar.components[index]);
+        let source = Some(Source {
+            calendar,
+            index,
+            original: fields.clone(),
+        });
+
+        Some(match kind {
+            CalendarItemKind::Event => Self::Event(Event::from_fields(fields, source)),
+            CalendarItemKind::Todo => Self::Todo(Todo::from_fields(fields, source)),
+            CalendarItemKind::Journal => Self::Journal(Journal::from_fields(fields, source)),
+        })
+    }
+
+    /// Kind of the component.
+    pub fn kind(&self) -> CalendarItemKind {
+        match self {
+            Self::Event(_) => CalendarItemKind::Event,
+            Self::Todo(_) => CalendarItemKind::Todo,
+            Self::Journal(_) => CalendarItemKind::Journal,
+        }
+    }
+
+    /// UID of the component.
+    pub fn uid(&self) -> &str {
+        match self {
+            Self::Event(event) => &event.uid,
+            Self::Todo(todo) => &todo.uid,
+            Self::Journal(journal) => &journal.uid,
+        }
+    }
+
+    /// Serializes back to iCalendar bytes, see [`Event::to_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Event(event) => event.to_bytes(),
+            Self::Todo(todo) => todo.to_bytes(),
+            Self::Journal(journal) => journal.to_bytes(),
+        }
+    }
+}
+
+impl CalendarItem {
+    /// Parses the item into its typed main component.
+    pub fn component(&self) -> Option<CalendarComponent> {
+        CalendarComponent::parse(&self.contents)
+    }
+}
+
+/// Parsed VCALENDAR a typed component came from, plus the typed values
+/// as first extracted, so serialization only touches what changed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Source {
+    calendar: ICalendar,
+    index: usize,
+    original: Fields,
+}
+
+impl Source {
+    fn parse(bytes: &[u8], component_type: ICalendarComponentType) -> Option<(Fields, Self)> {
+        let calendar = parse_calendar(bytes)?;
+        let index = calendar
+            .components
+            .iter()
+            .position(|c| c.component_type == component_type)?;
+        let fields = Fields::read(&calendar.components[index]);
+
+        let source = Self {
+            calendar,
+            index,
+            original: fields.clone(),
+        };
+
+        Some((fields, source))
+    }
+
+    fn render(
+        source: Option<&Self>,
+        component_type: ICalendarComponentType,
+        fields: &Fields,
+    ) -> Vec<u8> {
+        match source {
+            Some(source) => {
+                let mut calendar = source.calendar.clone();
+                fields.write(&mut calendar.components[source.index], &source.original);
+                calendar.to_string().into_bytes()
+            }
+            None => {
+                let mut component = ICalendarComponent {
+                    component_type,
+                    entries: Vec::new(),
+                    component_ids: Vec::new(),
+                };
+                fields.write(&mut component, &Fields::default());
+
+                let vcalendar = ICalendarComponent {
+                    component_type: ICalendarComponentType::VCalendar,
+                    entries: [
+                        text_entries(ICalendarProperty::Version, Some("2.0")),
+                        text_entries(ICalendarProperty::Prodid, Some(PRODID)),
+                    ]
+                    .concat(),
+                    component_ids: vec![1],
+                };
+
+                let calendar = ICalendar {
+                    components: vec![vcalendar, component],
+                };
+                calendar.to_string().into_bytes()
+            }
+        }
+    }
+}
+
+/// Union of the properties exposed by the typed components.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct Fields {
+    uid: String,
+    dtstamp: Option<DateTime>,
+    summary: Option<String>,
+    description: Option<String>,
+    location: Option<String>,
+    start: Option<DateTime>,
+    end: Option<DateTime>,
+    due: Option<DateTime>,
+    duration: Option<Duration>,
+    status: Option<ItemStatus>,
+    categories: Vec<String>,
+    organizer: Option<CalendarUser>,
+    attendees: Vec<CalendarUser>,
+    rrule: Option<String>,
+    sequence: Option<u32>,
+}
+
+impl Fields {
+    fn read(component: &ICalendarComponent) -> Self {
+        let text = |name| {
+            let entry = entry(component, &name)?;
+            entry.values.first().and_then(value_text)
+        };
+        let datetime = |name| entry(component, &name).and_then(datetime_value);
+
+        Self {
+            uid: text(ICalendarProperty::Uid).unwrap_or_default(),
+            dtstamp: datetime(ICalendarProperty::Dtstamp),
+            summary: text(ICalendarProperty::Summary),
+            description: text(ICalendarProperty::Description),
+            location: text(ICalendarProperty::Location),
+            start: datetime(ICalendarProperty::Dtstart),
+            end: datetime(ICalendarProperty::Dtend),
+            due: datetime(ICalendarProperty::Due),
+            duration: entry(component, &ICalendarProperty::Duration).and_then(duration_value),
+            status: text(ICalendarProperty::Status).map(|status| ItemStatus::from(status.as_str())),
+            categories: entries(component, &ICalendarProperty::Categories)
+                .flat_map(|entry| entry.values.iter().filter_map(value_text))
+                .collect(),
+            organizer: entry(component, &ICalendarProperty::Organizer)
+                .map(CalendarUser::from_entry),
+            attendees: entries(component, &ICalendarProperty::Attendee)
+                .map(CalendarUser::from_entry)
+                .collect(),
+            rrule: entry(component, &ICalendarProperty::Rrule).and_then(rrule_value),
+            sequence: entry(component, &ICalendarProperty::Sequence).and_then(sequence_value),
+        }
+    }
+
+    /// Rewrites in `component` the properties whose value differs from
+    /// `original`.
+    fn write(&self, component: &mut ICalendarComponent, original: &Self) {
+        if self.uid != original.uid {
+            let uid = (!self.uid.is_empty()).then_some(self.uid.as_str());
+            let uid = text_entries(ICalendarProperty::Uid, uid);
+            replace(component, &ICalendarProperty::Uid, uid);
+        }
+
+        if self.dtstamp != original.dtstamp {
+            let dtstamp = datetime_entries(ICalendarProperty::Dtstamp, self.dtstamp.as_ref());
+            replace(component, &ICalendarProperty::Dtstamp, dtstamp);
+        }
+
+        if self.summary != original.summary {
+            let summary = text_entries(ICalendarProperty::Summary, self.summary.as_deref());
+            replace(component, &ICalendarProperty::Summary, summary);
+        }
+
+        if self.description != original.description {
+            // only the first DESCRIPTION is modelled, keep the others
+            let description =
+                text_entries(ICalendarProperty::Description, self.description.as_deref());
+            let others: Vec<_> = entries(component, &ICalendarProperty::Description)
+                .skip(1)
+                .cloned()
+                .collect();
+            let description = [description, others].concat();
+            replace(component, &ICalendarProperty::Description, description);
+        }
+
+        if self.location != original.location {
+            let location = text_entries(ICalendarProperty::Location, self.location.as_deref());
+            replace(component, &ICalendarProperty::Location, location);
+        }
+
+        if self.start != original.start {
+            let start = datetime_entries(ICalendarProperty::Dtstart, self.start.as_ref());
+            replace(component, &ICalendarProperty::Dtstart, start);
+        }
+
+        if self.end != original.end {
+            let end = datetime_entries(ICalendarProperty::Dtend, self.end.as_ref());
+            replace(component, &ICalendarProperty::Dtend, end);
+        }
+
+        if self.due != original.due {
+            let due = datetime_entries(ICalendarProperty::Due, self.due.as_ref());
+            replace(component, &ICalendarProperty::Due, due);
+        }
+
+        if self.duration != original.duration {
+            let duration = self.duration.map(|duration| ICalendarEntry {
+                name: ICalendarProperty::Duration,
+                params: Vec::new(),
+                values: vec![ICalendarValue::Duration(ICalendarDuration {
+                    neg: duration.negative,
+                    weeks: duration.weeks,
+                    days: duration.days,
+                    hours: duration.hours,
+                    minutes: duration.minutes,
+                    seconds: duration.seconds,
+                })],
+            });
+            let duration = duration.into_iter().collect();
+            replace(component, &ICalendarProperty::Duration, duration);
+        }
+
+        if self.status != original.status {
+            let status = self.status.as_ref().map(ItemStatus::as_str);
+            let status = text_entries(ICalendarProperty::Status, status);
+            replace(component, &ICalendarProperty::Status, status);
+        }
+
+        if self.categories != original.categories {
+            let categories = (!self.categories.is_empty()).then(|| ICalendarEntry {
+                name: ICalendarProperty::Categories,
+                params: Vec::new(),
+                values: self
+                    .categories
+                    .iter()
+                    .map(|category| ICalendarValue::Text(category.clone()))
+                    .collect(),
+            });
+            let categories = categories.into_iter().collect();
+            replace(component, &ICalendarProperty::Categories, categories);
+        }
+
+        if self.organizer != original.organizer {
+            let organizer = users_entries(
+                component,
+                ICalendarProperty::Organizer,
+                self.organizer.iter(),
+            );
+            replace(component, &ICalendarProperty::Organizer, organizer);
+        }
+
+        if self.attendees != original.attendees {
+            let attendees = users_entries(
+                component,
+                ICalendarProperty::Attendee,
+                self.attendees.iter(),
+            );
+            replace(component, &ICalendarProperty::Attendee, attendees);
+        }
+
+        if self.rrule != original.rrule {
+            let rrule = rrule_entries(self.rrule.as_deref());
+            replace(component, &ICalendarProperty::Rrule, rrule);
+        }
+
+        if self.sequence != original.sequence {
+            let sequence = self.sequence.map(|n| ICalendarEntry {
+                name: ICalendarProperty::Sequence,
+                params: Vec::new(),
+                values: vec![ICalendarValue::Integer(i64::from(n))],
+            });
+            let sequence = sequence.into_iter().collect();
+            replace(component, &ICalendarProperty::Sequence, sequence);
+        }
+    }
+}
+
+fn parse_calendar(bytes: &[u8]) -> Option<ICalendar> {
+    let text = from_utf8(bytes).ok()?;
+    match Parser::new(text).entry() {
+        Entry::ICalendar(ical) => Some(ical),
+        _ => None,
+    }
+}
+
+fn entries<'a>(
+    component: &'a ICalendarComponent,
+    name: &'a ICalendarProperty,
+) -> impl Iterator<Item = &'a ICalendarEntry> + 'a {
+    component
+        .entries
+        .iter()
+        .filter(move |entry| entry.name == *name)
+}
+
+fn entry<'a>(
+    component: &'a ICalendarComponent,
+    name: &'a ICalendarProperty,
+) -> Option<&'a ICalendarEntry> {
+    entries(component, name).next()
+}
+
+/// Replaces every `name` entry by `entries`, inserted where the first
+/// one was (or at the end).
+fn replace(
+    component: &mut ICalendarComponent,
+    name: &ICalendarProperty,
+    entries: Vec<ICalendarEntry>,
+) {
+    let position = component.entries.iter().position(|e| e.name == *name);
+    component.entries.retain(|e| e.name != *name);
+    let position = position.unwrap_or(component.entries.len());
+    component.entries.splice(position..position, entries);
+}
+
+/// Text of a value; URIs (`ORGANIZER`, `ATTENDEE`) and enumerated
+/// values (`STATUS`) are rendered as written.
+fn value_text(value: &ICalendarValue) -> Option<String> {
+    match value {
+        ICalendarValue::Text(text) => Some(text.clone()),
+        ICalendarValue::Uri(Uri::Location(uri)) => Some(uri.clone()),
+        ICalendarValue::Status(status) => Some(status.as_str().to_string()),
+        _ => None,
+    }
+}
+
+fn param_text(entry: &ICalendarEntry, name: &ICalendarParameterName) -> Option<String> {
+    entry
+        .params
+        .iter()
+        .filter(|param| param.name == *name)
+        .find_map(|param| match &param.value {
+            ICalendarParameterValue::Text(text) => Some(text.clone()),
+            ICalendarParameterValue::Role(role) => Some(role.as_str().to_string()),
+            ICalendarParameterValue::Partstat(partstat) => Some(partstat.as_str().to_string()),
+            _ => None,
+        })
+}
+
+/// Reads a `DATE` or `DATE-TIME` entry; the zone comes from the `Z`
+/// suffix, then from the `TZID` parameter.
+fn datetime_value(entry: &ICalendarEntry) -> Option<DateTime> {
+    let ICalendarValue::PartialDateTime(value) = entry.values.first()? else {
+        return None;
+    };
+
+    let mut datetime = DateTime::date(value.year?, value.month?, value.day?);
+
+    if let Some(hour) = value.hour {
+        datetime.time = Some(Time {
+            hour,
+            minute: value.minute.unwrap_or_default(),
+            second: value.second.unwrap_or_default(),
+        });
+        datetime.tz = match value.tz_hour {
+            Some(_) => TimeZone::Utc,
+            None => param_text(entry, &ICalendarParameterName::Tzid)
+                .map_or(TimeZone::Floating, TimeZone::Id),
+        };
+    }
+
+    Some(datetime)
+}
+
+fn duration_value(entry: &ICalendarEntry) -> Option<Duration> {
+    let ICalendarValue::Duration(duration) = entry.values.first()? else {
+        return None;
+    };
+
+    Some(Duration {
+        negative: duration.neg,
+        weeks: duration.weeks,
+        days: duration.days,
+        hours: duration.hours,
+        minutes: duration.minutes,
+        seconds: duration.seconds,
+    })
+}
+
+/// Raw text of a recurrence rule (`FREQ=WEEKLY;BYDAY=MO`).
+fn rrule_value(entry: &ICalendarEntry) -> Option<String> {
+    match entry.values.first()? {
+        ICalendarValue::RecurrenceRule(rrule) => Some(rrule.to_string()),
+        value => value_text(value),
+    }
+}
+
+fn sequence_value(entry: &ICalendarEntry) -> Option<u32> {
+    match entry.values.first()? {
+        ICalendarValue::Integer(n) => u32::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+fn text_entries(name: ICalendarProperty, value: Option<&str>) -> Vec<ICalendarEntry> {
+    value
+        .map(|value| ICalendarEntry {
+            name,
+            params: Vec::new(),
+            values: vec![ICalendarValue::Text(value.to_string())],
+        })
+        .into_iter()
+        .collect()
+}
+
+fn datetime_entries(name: ICalendarProperty, value: Option<&DateTime>) -> Vec<ICalendarEntry> {
+    let Some(datetime) = value else {
+        return Vec::new();
+    };
+
+    let mut params = Vec::new();
+    if datetime.is_date() {
+        params.push(ICalendarParameter {
+            name: ICalendarParameterName::Value,
+            value: ICalendarParameterValue::Value(ICalendarValueType::Date),
+        });
+    } else if let TimeZone::Id(tzid) = &datetime.tz {
+        params.push(ICalendarParameter {
+            name: ICalendarParameterName::Tzid,
+            value: ICalendarParameterValue::Text(tzid.clone()),
+        });
+    }
+
+    let utc = !datetime.is_date() && datetime.tz == TimeZone::Utc;
+    let value = PartialDateTime {
+        year: Some(datetime.year),
+        month: Some(datetime.month),
+        day: Some(datetime.day),
+        hour: datetime.time.map(|time| time.hour),
+        minute: datetime.time.map(|time| time.minute),
+        second: datetime.time.map(|time| time.second),
+        tz_hour: utc.then_some(0),
+        tz_minute: utc.then_some(0),
+        tz_minus: false,
+    };
+
+    vec![ICalendarEntry {
+        name,
+        params,
+        values: vec![ICalendarValue::PartialDateTime(Box::new(value))],
+    }]
+}
+
+/// Builds the `RRULE` entry through the calcard parser, which is the
+/// only way it exposes to read a recurrence rule from text. An invalid
+/// rule is dropped.
+fn rrule_entries(rrule: Option<&str>) -> Vec<ICalendarEntry> {
+    let Some(rrule) = rrule else {
+        return Vec::new();
+    };
+
+    let text = format!(
+        "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nRRULE:{rrule}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+    );
+
+    parse_calendar(text.as_bytes())
+        .into_iter()
+        .flat_map(|calendar| calendar.components)
+        .flat_map(|component| component.entries)
+        .filter(|entry| entry.name == ICalendarProperty::Rrule)
+        .collect()
+}
+
+/// Renders `users`, reusing the existing entry of an unchanged user so
+/// its extra parameters (`RSVP`, `CUTYPE`, ...) survive.
+fn users_entries<'a>(
+    component: &ICalendarComponent,
+    name: ICalendarProperty,
+    users: impl Iterator<Item = &'a CalendarUser>,
+) -> Vec<ICalendarEntry> {
+    users
+        .map(|user| {
+            entries(component, &name)
+                .find(|entry| CalendarUser::from_entry(entry) == *user)
+                .cloned()
+                .unwrap_or_else(|| user.to_entry(name.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+
+    const EVENT: &str = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        PRODID:-//test//EN\r\n\
+        BEGIN:VTIMEZONE\r\n\
+        TZID:Europe/Paris\r\n\
+        BEGIN:STANDARD\r\n\
+        DTSTART:19701025T030000\r\n\
+        TZOFFSETFROM:+0200\r\n\
+        TZOFFSETTO:+0100\r\n\
+        END:STANDARD\r\n\
+        END:VTIMEZONE\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:event-1\r\n\
+        DTSTAMP:20250101T080000Z\r\n\
+        DTSTART;TZID=Europe/Paris:20250310T090000\r\n\
+        DURATION:PT1H\r\n\
+        SUMMARY:Standup\r\n\
+        CATEGORIES:WORK,DAILY\r\n\
+        ATTENDEE;CN=Alice;RSVP=TRUE:mailto:alice@example.com\r\n\
+        RRULE:FREQ=DAILY;COUNT=5\r\n\
+        SEQUENCE:2\r\n\
+        X-CUSTOM;X-PARAM=kept:custom value\r\n\
+        BEGIN:VALARM\r\n\
+        ACTION:DISPLAY\r\n\
+        TRIGGER:-PT15M\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    fn as_event(component: CalendarComponent) -> Event {
+        match component {
+            CalendarComponent::Event(event) => event,
+            component => panic!("expected an event, got {component:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_typed_fields() {
+        let event = as_event(CalendarComponent::parse(EVENT.as_bytes()).unwrap());
+
+        assert_eq!(event.uid, "event-1");
+        assert_eq!(event.dtstamp, Some(DateTime::utc(2025, 1, 1, 8, 0, 0)));
+        assert_eq!(event.summary.as_deref(), Some("Standup"));
+        assert_eq!(
+            event.start.as_ref().map(|start| &start.tz),
+            Some(&TimeZone::Id("Europe/Paris".into()))
+        );
+        assert_eq!(event.duration.map(|d| d.as_seconds()), Some(3_600));
+        assert_eq!(event.categories, ["WORK", "DAILY"]);
+        assert_eq!(event.attendees.len(), 1);
+        assert_eq!(event.attendees[0].address, "mailto:alice@example.com");
+        assert_eq!(event.attendees[0].common_name.as_deref(), Some("Alice"));
+        assert_eq!(event.sequence, Some(2));
+        assert!(event.rrule.as_deref().unwrap().contains("COUNT=5"));
+    }
+
+    #[test]
+    fn round_trips_edits_and_keeps_unknown_properties() {
+        let mut event = as_event(CalendarComponent::parse(EVENT.as_bytes()).unwrap());
+
+        event.summary = Some("Standup, moved".into());
+        event.start = Some(DateTime::utc(2025, 3, 10, 10, 0, 0));
+        event.sequence = Some(3);
+        event
+            .attendees
+            .push(CalendarUser::new("mailto:bob@example.com"));
+
+        let bytes = event.to_bytes();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+
+        // untouched properties, parameters and components survive
+        assert!(text.contains("X-CUSTOM"));
+        assert!(text.contains("custom value"));
+        assert!(text.contains("RSVP=TRUE"));
+        assert!(text.contains("BEGIN:VALARM"));
+        assert!(text.contains("BEGIN:VTIMEZONE"));
+
+        let reparsed = as_event(CalendarComponent::parse(&bytes).unwrap());
+        assert_eq!(reparsed, Event::parse(&bytes).unwrap());
+        assert_eq!(reparsed.summary.as_deref(), Some("Standup, moved"));
+        assert_eq!(reparsed.start, Some(DateTime::utc(2025, 3, 10, 10, 0, 0)));
+        assert_eq!(reparsed.sequence, Some(3));
+        assert_eq!(reparsed.dtstamp, event.dtstamp);
+        assert_eq!(reparsed.categories, event.categories);
+        assert_eq!(reparsed.rrule, event.rrule);
+        assert_eq!(reparsed.attendees, event.attendees);
+    }
+
+    #[test]
+    fn new_components_are_stamped() {
+        let dtstamp = DateTime::utc(2025, 6, 1, 12, 30, 0);
+
+        let mut todo = Todo::new("todo-1", dtstamp.clone());
+        todo.due = Some(DateTime::date(2025, 6, 2));
+        let bytes = todo.to_bytes();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+
+        assert!(text.contains("VERSION:2.0"));
+        assert!(text.contains(PRODID));
+
+        let todo = Todo::parse(&bytes).unwrap();
+        assert_eq!(todo.uid, "todo-1");
+        assert_eq!(todo.dtstamp, Some(dtstamp.clone()));
+        assert_eq!(todo.due, Some(DateTime::date(2025, 6, 2)));
+
+        let journal = Journal::parse(&Journal::new("journal-1", dtstamp.clone()).to_bytes());
+        assert_eq!(journal.unwrap().dtstamp, Some(dtstamp));
+    }
+}
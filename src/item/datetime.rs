@@ -0,0 +1,333 @@
+//! iCalendar date, date-time and duration values (RFC 5545 sections
+//! 3.3.4, 3.3.5 and 3.3.6).
+
+use alloc::string::String;
+use core::fmt;
+
+/// Time zone a [`DateTime`] is expressed in.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum TimeZone {
+    /// Floating time, bound to whatever zone the reader is in (no `Z`
+    /// suffix, no `TZID` parameter).
+    #[default]
+    Floating,
+    /// UTC time (`Z` suffix).
+    Utc,
+    /// Local time in the zone named by the `TZID` parameter.
+    Id(String),
+}
+
+/// Wall-clock time of a [`DateTime`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+/// An iCalendar `DATE` or `DATE-TIME` value.
+///
+/// `time = None` is a `DATE` value (an all-day item); the zone is then
+/// always [`TimeZone::Floating`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub time: Option<Time>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tz: TimeZone,
+}
+
+impl DateTime {
+    /// Builds a `DATE` value.
+    pub fn date(year: u16, month: u8, day: u8) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            time: None,
+            tz: TimeZone::Floating,
+        }
+    }
+
+    /// Builds a UTC `DATE-TIME` value.
+    pub fn utc(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            time: Some(Time {
+                hour,
+                minute,
+                second,
+            }),
+            tz: TimeZone::Utc,
+        }
+    }
+
+    /// Whether this is a `DATE` value (no time of day).
+    pub fn is_date(&self) -> bool {
+        self.time.is_none()
+    }
+
+    /// Builds a value from a wall-clock timestamp (see
+    /// [`Self::wall_clock_timestamp`]), as a `DATE` when `date` is set.
+    pub(crate) fn from_wall_clock(wall: i64, date: bool, tz: TimeZone) -> Self {
+        let (year, month, day) = civil_from_days(wall.div_euclid(86_400));
+        let seconds = wall.rem_euclid(86_400);
+
+        let time = (!date).then_some(Time {
+            hour: (seconds / 3_600) as u8,
+            minute: (seconds % 3_600 / 60) as u8,
+            second: (seconds % 60) as u8,
+        });
+
+        Self {
+            year: year as u16,
+            month,
+            day,
+            time,
+            tz: if date { TimeZone::Floating } else { tz },
+        }
+    }
+
+    /// Seconds since the Unix epoch of the wall-clock value, read as if
+    /// it were UTC (a `DATE` is its midnight). The zone is not applied.
+    pub(crate) fn wall_clock_timestamp(&self) -> i64 {
+        let days = days_from_civil(i64::from(self.year), self.month, self.day);
+        let time = self.time.unwrap_or_default();
+
+        days * 86_400
+            + i64::from(time.hour) * 3_600
+            + i64::from(time.minute) * 60
+            + i64::from(time.second)
+    }
+
+    /// Parses `YYYYMMDD` or `YYYYMMDDTHHMMSS[Z]`, with `tzid` taken
+    /// from the `TZID` parameter. A trailing `Z` wins over `tzid`.
+    pub fn parse(value: &str, tzid: Option<&str>) -> Option<Self> {
+        let value = value.trim();
+        let bytes = value.as_bytes();
+
+        if bytes.len() < 8 || !bytes[..8].iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+
+        let year = value[0..4].parse().ok()?;
+        let month = value[4..6].parse().ok()?;
+        let day = value[6..8].parse().ok()?;
+
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+
+        if bytes.len() == 8 {
+            return Some(Self::date(year, month, day));
+        }
+
+        let (clock, utc) = match value[8..].strip_suffix('Z') {
+            Some(clock) => (clock, true),
+            None => (&value[8..], false),
+        };
+
+        let clock = clock.strip_prefix('T')?;
+        if clock.len() != 6 || !clock.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let time = Time {
+            hour: clock[0..2].parse().ok()?,
+            minute: clock[2..4].parse().ok()?,
+            second: clock[4..6].parse().ok()?,
+        };
+
+        if time.hour > 23 || time.minute > 59 || time.second > 60 {
+            return None;
+        }
+
+        let tz = match (utc, tzid) {
+            (true, _) => TimeZone::Utc,
+            (false, Some(tzid)) => TimeZone::Id(tzid.into()),
+            (false, None) => TimeZone::Floating,
+        };
+
+        Some(Self {
+            year,
+            month,
+            day,
+            time: Some(time),
+            tz,
+        })
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (Howard
+/// Hinnant's `days_from_civil`).
+pub(crate) fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian `(year, month, day)` of a day count since
+/// 1970-01-01, the inverse of [`days_from_civil`].
+pub(crate) fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Number of days in `month` of `year`.
+pub(crate) fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        4 | 6 | 9 | 11 => 30,
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        _ => 31,
+    }
+}
+
+/// Renders the iCalendar value (without the `TZID` parameter).
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}{:02}{:02}", self.year, self.month, self.day)?;
+
+        if let Some(time) = self.time {
+            write!(f, "T{:02}{:02}{:02}", time.hour, time.minute, time.second)?;
+            if self.tz == TimeZone::Utc {
+                f.write_str("Z")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An iCalendar `DURATION` value.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Duration {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub negative: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub weeks: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub days: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hours: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub minutes: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seconds: u32,
+}
+
+impl Duration {
+    /// Parses `[+-]P[nW][nD][T[nH][nM][nS]]`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (negative, value) = match value.as_bytes().first()? {
+            b'-' => (true, &value[1..]),
+            b'+' => (false, &value[1..]),
+            _ => (false, value),
+        };
+
+        let mut rest = value.strip_prefix('P')?;
+        let mut duration = Self {
+            negative,
+            ..Self::default()
+        };
+        let mut in_time = false;
+        let mut seen = false;
+
+        while !rest.is_empty() {
+            if let Some(tail) = rest.strip_prefix('T') {
+                in_time = true;
+                rest = tail;
+                continue;
+            }
+
+            let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return None;
+            }
+
+            let n: u32 = rest[..digits].parse().ok()?;
+            let unit = rest.as_bytes().get(digits)?;
+            rest = &rest[digits + 1..];
+            seen = true;
+
+            match (in_time, unit) {
+                (false, b'W') => duration.weeks = n,
+                (false, b'D') => duration.days = n,
+                (true, b'H') => duration.hours = n,
+                (true, b'M') => duration.minutes = n,
+                (true, b'S') => duration.seconds = n,
+                _ => return None,
+            }
+        }
+
+        seen.then_some(duration)
+    }
+
+    /// Total length in seconds, negative for negative durations.
+    pub fn as_seconds(&self) -> i64 {
+        let seconds = i64::from(self.weeks) * 7 * 86_400
+            + i64::from(self.days) * 86_400
+            + i64::from(self.hours) * 3_600
+            + i64::from(self.minutes) * 60
+            + i64::from(self.seconds);
+
+        if self.negative { -seconds } else { seconds }
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            f.write_str("-")?;
+        }
+        f.write_str("P")?;
+
+        if self.weeks > 0 {
+            write!(f, "{}W", self.weeks)?;
+        }
+        if self.days > 0 {
+            write!(f, "{}D", self.days)?;
+        }
+
+        if self.hours > 0 || self.minutes > 0 || self.seconds > 0 {
+            f.write_str("T")?;
+            if self.hours > 0 {
+                write!(f, "{}H", self.hours)?;
+            }
+            if self.minutes > 0 {
+                write!(f, "{}M", self.minutes)?;
+            }
+            if self.seconds > 0 {
+                write!(f, "{}S", self.seconds)?;
+            }
+        } else if self.weeks == 0 && self.days == 0 {
+            f.write_str("T0S")?;
+        }
+
+        Ok(())
+    }
+}
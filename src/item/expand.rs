@@ -0,0 +1,153 @@
+//! Client-side equivalents of the CalDAV `expand` and
+//! `limit-recurrence-set` `calendar-data` options (RFC 4791 sections
+//! 9.6.5 and 9.6.6), so every backend returns recurring items in the
+//! same shape as a CalDAV server.
+
+use alloc::{string::ToString, vec, vec::Vec};
+
+use crate::item::{
+    CalendarItem, DateTime, TimeRange, TimeZone,
+    ical::{Component, Property},
+    recurrence::{Bounds, Instance, instances, is_recurring},
+    timezone::TimeZones,
+};
+
+impl CalendarItem {
+    /// Expands the item into one item per occurrence overlapping
+    /// `range`, sorted by start, like the CalDAV `expand` option.
+    ///
+    /// Each returned item keeps the id and ETag of this one and holds a
+    /// single component: recurrence properties are dropped, a
+    /// `RECURRENCE-ID` is added to occurrences of recurring components
+    /// and date-times are converted to UTC (time zones are omitted).
+    pub fn expand(&self, range: &TimeRange) -> Vec<CalendarItem> {
+        let Some(kind) = self.kind() else {
+            return Vec::new();
+        };
+        let Some(calendar) = Component::parse(&self.contents) else {
+            return Vec::new();
+        };
+
+        let zones = TimeZones::new(&calendar);
+
+        instances(&calendar, kind, &Bounds::from(range))
+            .iter()
+            .map(|instance| {
+                let component = occurrence(&calendar, instance, &zones);
+                self.with_components(&calendar, vec![component])
+            })
+            .collect()
+    }
+
+    /// Drops the overrides whose occurrence does not overlap `range`,
+    /// like the CalDAV `limit-recurrence-set` option. Masters, time
+    /// zones and non-recurring components are kept as they are.
+    pub fn limit_recurrence_set(&self, range: &TimeRange) -> CalendarItem {
+        let Some(kind) = self.kind() else {
+            return self.clone();
+        };
+        let Some(calendar) = Component::parse(&self.contents) else {
+            return self.clone();
+        };
+
+        let kept: Vec<usize> = instances(&calendar, kind, &Bounds::from(range))
+            .into_iter()
+            .filter(|instance| instance.occurrence.overridden)
+            .map(|instance| instance.component)
+            .collect();
+
+        let components = calendar
+            .components
+            .iter()
+            .enumerate()
+            .filter(|(index, component)| {
+                component.name != kind.component_name()
+                    || component.property("RECURRENCE-ID").is_none()
+                    || kept.contains(index)
+            })
+            .map(|(_, component)| component.clone())
+            .collect();
+
+        self.with_components(&calendar, components)
+    }
+
+    /// Splits an item returned expanded by a CalDAV server (several
+    /// instances in one VCALENDAR) into one item per instance.
+    #[cfg(feature = "webdav")]
+    pub(crate) fn split_instances(self) -> Vec<CalendarItem> {
+        let Some(kind) = self.kind() else {
+            return vec![self];
+        };
+        let Some(calendar) = Component::parse(&self.contents) else {
+            return vec![self];
+        };
+
+        let instances: Vec<&Component> = calendar
+            .components
+            .iter()
+            .filter(|component| component.name == kind.component_name())
+            .collect();
+
+        if instances.len() < 2 {
+            return vec![self];
+        }
+
+        instances
+            .into_iter()
+            .map(|component| self.with_components(&calendar, vec![component.clone()]))
+            .collect()
+    }
+
+    /// Copy of this item whose VCALENDAR keeps the properties of
+    /// `calendar` but holds `components` only.
+    fn with_components(&self, calendar: &Component, components: Vec<Component>) -> CalendarItem {
+        let mut root = Component::new(&calendar.name);
+        root.properties = calendar.properties.clone();
+        root.components = components;
+
+        CalendarItem {
+            contents: root.to_bytes(),
+            ..self.clone()
+        }
+    }
+}
+
+/// The component of one expanded instance: its source with recurrence
+/// properties dropped and start, end and `RECURRENCE-ID` set in UTC.
+fn occurrence(calendar: &Component, instance: &Instance, zones: &TimeZones) -> Component {
+    let mut component = calendar.components[instance.component].clone();
+    let recurring = instance.occurrence.overridden || is_recurring(&component);
+
+    for name in ["RRULE", "RDATE", "EXDATE", "EXRULE"] {
+        component.replace(name, Vec::new());
+    }
+
+    let occurrence = &instance.occurrence;
+    component.replace("DTSTART", vec![utc("DTSTART", &occurrence.start, zones)]);
+
+    if let Some(end) = &occurrence.end {
+        for name in ["DTEND", "DUE"] {
+            if component.property(name).is_some() {
+                component.replace(name, vec![utc(name, end, zones)]);
+            }
+        }
+    }
+
+    if recurring {
+        let rid = utc("RECURRENCE-ID", &occurrence.recurrence_id, zones);
+        component.replace("RECURRENCE-ID", vec![rid]);
+    }
+
+    component
+}
+
+/// `name` property holding `value` converted to UTC; dates stay dates.
+fn utc(name: &str, value: &DateTime, zones: &TimeZones) -> Property {
+    if value.is_date() {
+        let params = vec![("VALUE".into(), vec!["DATE".into()])];
+        return Property::new(name, params, value.to_string());
+    }
+
+    let utc = DateTime::from_wall_clock(zones.timestamp(value), false, TimeZone::Utc);
+    Property::new(name, Vec::new(), utc.to_string())
+}
@@ -0,0 +1,514 @@
+//! Property filters for `list_items`, modelled on the CalDAV
+//! `prop-filter`, `param-filter` and `text-match` elements (RFC 4791
+//! section 9.7).
+//!
+//! The WebDAV backend serializes an [`ItemFilter`] into the
+//! `calendar-query` body; the vdir and memory backends evaluate it
+//! client-side with the same rules, which needs the `parser` feature.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(all(not(feature = "parser"), any(feature = "vdir", feature = "memory")))]
+use log::trace;
+
+#[cfg(any(feature = "parser", feature = "vdir", feature = "memory"))]
+use crate::item::CalendarItem;
+#[cfg(feature = "parser")]
+use crate::item::{
+    CalendarItemKind,
+    ical::{Component, Property},
+};
+
+/// Property conditions passed as a `list_items` option, on top of the
+/// kinds and the [`TimeRange`](crate::item::TimeRange).
+///
+/// An item matches when one of its `VEVENT`, `VTODO` or `VJOURNAL`
+/// components satisfies every [`PropFilter`]: CalDAV ANDs sibling
+/// filters. An empty filter matches every item.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use io_calendar::item::{ItemFilter, PropFilter, TextMatch};
+///
+/// // STATUS not COMPLETED
+/// let open = ItemFilter::new()
+///     .prop(PropFilter::text("STATUS", TextMatch::new("COMPLETED").negate()));
+///
+/// // CATEGORIES containing `ops`
+/// let ops = ItemFilter::new().prop(PropFilter::text("CATEGORIES", TextMatch::new("ops")));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct ItemFilter {
+    /// Property filters, all of which must match the same component.
+    pub props: Vec<PropFilter>,
+}
+
+impl ItemFilter {
+    /// Builds an empty filter, matching every item.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a property filter.
+    pub fn prop(mut self, filter: PropFilter) -> Self {
+        self.props.push(filter);
+        self
+    }
+
+    /// Whether the filter holds no condition.
+    pub fn is_empty(&self) -> bool {
+        self.props.is_empty()
+    }
+
+    /// Whether `item` matches: one of its `VEVENT`, `VTODO` or
+    /// `VJOURNAL` components satisfies every property filter. Contents
+    /// that do not parse only match an empty filter.
+    #[cfg(feature = "parser")]
+    pub fn matches(&self, item: &CalendarItem) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let Some(calendar) = Component::parse(&item.contents) else {
+            return false;
+        };
+
+        calendar
+            .components
+            .iter()
+            .filter(|c| CalendarItemKind::from_component_name(&c.name).is_some())
+            .any(|component| self.props.iter().all(|filter| filter.matches(component)))
+    }
+}
+
+/// What a [`PropFilter`] or [`ParamFilter`] checks on its target.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum FilterTest {
+    /// The target is present (an empty CalDAV filter element).
+    Defined,
+    /// The target is absent (`is-not-defined`).
+    NotDefined,
+    /// The target is present and its value passes the
+    /// [`TextMatch`].
+    Text(TextMatch),
+}
+
+/// Condition on one property of a component (`prop-filter`, RFC 4791
+/// section 9.7.2).
+///
+/// Unless the test is [`FilterTest::NotDefined`], the filter matches
+/// when some property named `name` passes the test and every
+/// [`ParamFilter`]. A missing property never matches a text test, even
+/// a negated one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct PropFilter {
+    /// Upper-cased property name (`STATUS`, `CATEGORIES`, ...).
+    pub name: String,
+
+    /// Check run on the property.
+    pub test: FilterTest,
+
+    /// Parameter filters, all of which must match the same property.
+    /// Ignored by [`FilterTest::NotDefined`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub params: Vec<ParamFilter>,
+}
+
+impl PropFilter {
+    /// Builds a filter matching components carrying property `name`.
+    pub fn defined(name: impl ToString) -> Self {
+        Self::with_test(name, FilterTest::Defined)
+    }
+
+    /// Builds a filter matching components without property `name`.
+    pub fn not_defined(name: impl ToString) -> Self {
+        Self::with_test(name, FilterTest::NotDefined)
+    }
+
+    /// Builds a filter matching components carrying property `name`
+    /// with a value passing `text_match`.
+    pub fn text(name: impl ToString, text_match: TextMatch) -> Self {
+        Self::with_test(name, FilterTest::Text(text_match))
+    }
+
+    /// Adds a parameter filter.
+    pub fn param(mut self, filter: ParamFilter) -> Self {
+        self.params.push(filter);
+        self
+    }
+
+    fn with_test(name: impl ToString, test: FilterTest) -> Self {
+        Self {
+            name: name.to_string().to_ascii_uppercase(),
+            test,
+            params: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "parser")]
+    fn matches(&self, component: &Component) -> bool {
+        let name = self.name.to_ascii_uppercase();
+        let mut properties = component.properties(&name);
+        let params = |property: &Property| self.params.iter().all(|f| f.matches(property));
+
+        match &self.test {
+            FilterTest::NotDefined => properties.next().is_none(),
+            FilterTest::Defined => properties.any(params),
+            FilterTest::Text(text_match) => {
+                properties.any(|property| text_match.matches(&property.text()) && params(property))
+            }
+        }
+    }
+}
+
+/// Condition on one parameter of a property (`param-filter`, RFC 4791
+/// section 9.7.3).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct ParamFilter {
+    /// Upper-cased parameter name (`PARTSTAT`, `ROLE`, ...).
+    pub name: String,
+
+    /// Check run on the parameter.
+    pub test: FilterTest,
+}
+
+impl ParamFilter {
+    /// Builds a filter matching properties carrying parameter `name`.
+    pub fn defined(name: impl ToString) -> Self {
+        Self::with_test(name, FilterTest::Defined)
+    }
+
+    /// Builds a filter matching properties without parameter `name`.
+    pub fn not_defined(name: impl ToString) -> Self {
+        Self::with_test(name, FilterTest::NotDefined)
+    }
+
+    /// Builds a filter matching properties carrying parameter `name`
+    /// with a value passing `text_match`.
+    pub fn text(name: impl ToString, text_match: TextMatch) -> Self {
+        Self::with_test(name, FilterTest::Text(text_match))
+    }
+
+    fn with_test(name: impl ToString, test: FilterTest) -> Self {
+        Self {
+            name: name.to_string().to_ascii_uppercase(),
+            test,
+        }
+    }
+
+    #[cfg(feature = "parser")]
+    fn matches(&self, property: &Property) -> bool {
+        let mut values = property
+            .params
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(&self.name))
+            .flat_map(|(_, values)| values)
+            .peekable();
+
+        match &self.test {
+            FilterTest::NotDefined => values.peek().is_none(),
+            FilterTest::Defined => values.peek().is_some(),
+            FilterTest::Text(text_match) => values.any(|value| text_match.matches(value)),
+        }
+    }
+}
+
+/// Substring match on a property or parameter value (`text-match`, RFC
+/// 4791 section 9.7.5).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct TextMatch {
+    /// Text looked for inside the value.
+    pub text: String,
+
+    /// How characters are compared.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub collation: Collation,
+
+    /// Whether the match is inverted (`negate-condition="yes"`): the
+    /// value must not contain the text.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub negate: bool,
+}
+
+impl TextMatch {
+    /// Builds a case-insensitive match on `text`.
+    pub fn new(text: impl ToString) -> Self {
+        Self {
+            text: text.to_string(),
+            collation: Collation::default(),
+            negate: false,
+        }
+    }
+
+    /// Sets the collation.
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    /// Inverts the match.
+    pub fn negate(mut self) -> Self {
+        self.negate = true;
+        self
+    }
+
+    /// Whether `value` passes the match.
+    #[cfg(feature = "parser")]
+    fn matches(&self, value: &str) -> bool {
+        let found = match self.collation {
+            Collation::Octet => value.contains(self.text.as_str()),
+            Collation::AsciiCasemap => value
+                .to_ascii_lowercase()
+                .contains(&self.text.to_ascii_lowercase()),
+            Collation::UnicodeCasemap => fold(value).contains(&fold(&self.text)),
+        };
+
+        found != self.negate
+    }
+}
+
+/// Collation of a [`TextMatch`] (RFC 4790). Every CalDAV server
+/// supports `i;octet` and `i;ascii-casemap`; `i;unicode-casemap` is
+/// optional and refused by some servers with a `supported-collation`
+/// precondition error.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Collation {
+    /// `i;octet`: byte-wise comparison.
+    Octet,
+    /// `i;ascii-casemap`: ASCII letters compare case-insensitively.
+    #[default]
+    AsciiCasemap,
+    /// `i;unicode-casemap` (RFC 5051): characters compare after Unicode
+    /// case folding.
+    UnicodeCasemap,
+}
+
+impl Collation {
+    /// Collation identifier sent to the server.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Octet => "i;octet",
+            Self::AsciiCasemap => "i;ascii-casemap",
+            Self::UnicodeCasemap => "i;unicode-casemap",
+        }
+    }
+}
+
+/// Case-folds `value` for caseless comparison: every character is
+/// lower-cased, then the few full foldings that lower-casing misses are
+/// applied (`ß` to `ss`, final sigma to `σ`). Decomposition is not
+/// performed, so precomposed and combining forms stay distinct.
+#[cfg(feature = "parser")]
+pub(crate) fn fold(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+
+    for c in value.chars().flat_map(char::to_lowercase) {
+        match c {
+            'ß' => out.push_str("ss"),
+            'ς' => out.push('σ'),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Keeps only the items matching `filter`, when set.
+#[cfg(all(feature = "parser", any(feature = "vdir", feature = "memory")))]
+pub(crate) fn filter_props(
+    items: Vec<CalendarItem>,
+    filter: Option<&ItemFilter>,
+) -> Vec<CalendarItem> {
+    let Some(filter) = filter else {
+        return items;
+    };
+
+    items
+        .into_iter()
+        .filter(|item| filter.matches(item))
+        .collect()
+}
+
+/// Without the `parser` feature the items cannot be inspected, so the
+/// filter is ignored and every fetched item is returned.
+#[cfg(all(not(feature = "parser"), any(feature = "vdir", feature = "memory")))]
+pub(crate) fn filter_props(
+    items: Vec<CalendarItem>,
+    filter: Option<&ItemFilter>,
+) -> Vec<CalendarItem> {
+    if filter.is_some_and(|filter| !filter.is_empty()) {
+        trace!("property filter ignored: parser feature is disabled");
+    }
+    items
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use super::*;
+    use crate::item::fixture;
+
+    fn check(table: &[(PropFilter, &str, bool)]) {
+        for (filter, lines, expected) in table {
+            let matched = ItemFilter::new()
+                .prop(filter.clone())
+                .matches(&fixture::events("1", &[lines]));
+            assert_eq!(matched, *expected, "{filter:?} on {lines}");
+        }
+    }
+
+    #[test]
+    fn matches_defined_and_not_defined() {
+        let attendee = || PropFilter::defined("attendee");
+
+        check(&[
+            (PropFilter::defined("STATUS"), "STATUS:CONFIRMED", true),
+            (PropFilter::defined("STATUS"), "SUMMARY:a", false),
+            (PropFilter::not_defined("STATUS"), "SUMMARY:a", true),
+            (PropFilter::not_defined("STATUS"), "STATUS:CONFIRMED", false),
+            (
+                PropFilter::not_defined("STATUS").param(ParamFilter::defined("X")),
+                "SUMMARY:a",
+                true,
+            ),
+            (
+                attendee().param(ParamFilter::not_defined("PARTSTAT")),
+                "ATTENDEE:mailto:a@example.com",
+                true,
+            ),
+            (
+                attendee().param(ParamFilter::not_defined("PARTSTAT")),
+                "ATTENDEE;PARTSTAT=ACCEPTED:mailto:a@example.com",
+                false,
+            ),
+            (
+                attendee().param(ParamFilter::defined("partstat")),
+                "ATTENDEE;PARTSTAT=ACCEPTED:mailto:a@example.com",
+                true,
+            ),
+        ]);
+    }
+
+    #[test]
+    fn negates_text_matches() {
+        let open = || PropFilter::text("STATUS", TextMatch::new("COMPLETED").negate());
+        let declined = TextMatch::new("DECLINED").negate();
+        let attendee = || PropFilter::defined("ATTENDEE");
+
+        check(&[
+            (open(), "STATUS:NEEDS-ACTION", true),
+            (open(), "STATUS:COMPLETED", false),
+            // a missing property never passes a text test
+            (open(), "SUMMARY:a", false),
+            (
+                attendee().param(ParamFilter::text("PARTSTAT", declined.clone())),
+                "ATTENDEE;PARTSTAT=ACCEPTED:mailto:a@example.com",
+                true,
+            ),
+            (
+                attendee().param(ParamFilter::text("PARTSTAT", declined)),
+                "ATTENDEE;PARTSTAT=DECLINED:mailto:a@example.com",
+                false,
+            ),
+        ]);
+    }
+
+    #[test]
+    fn compares_with_the_collation() {
+        let summary = |text: &str, collation| {
+            PropFilter::text("SUMMARY", TextMatch::new(text).collation(collation))
+        };
+
+        check(&[
+            (
+                summary("meeting", Collation::AsciiCasemap),
+                "SUMMARY:Team MEETING",
+                true,
+            ),
+            (
+                summary("meeting", Collation::Octet),
+                "SUMMARY:Team MEETING",
+                false,
+            ),
+            (
+                summary("MEETING", Collation::Octet),
+                "SUMMARY:Team MEETING",
+                true,
+            ),
+            (
+                summary("été", Collation::AsciiCasemap),
+                "SUMMARY:ÉTÉ",
+                false,
+            ),
+            (
+                summary("été", Collation::UnicodeCasemap),
+                "SUMMARY:ÉTÉ",
+                true,
+            ),
+            (
+                summary("STRASSE", Collation::UnicodeCasemap),
+                "SUMMARY:straße",
+                true,
+            ),
+        ]);
+        assert_eq!(TextMatch::new("a").collation, Collation::AsciiCasemap);
+    }
+
+    #[test]
+    fn matches_any_value_of_multi_valued_properties() {
+        let ops = || PropFilter::text("CATEGORIES", TextMatch::new("ops"));
+        let not_ops = || PropFilter::text("CATEGORIES", TextMatch::new("ops").negate());
+        let delegated = |text| {
+            let param = ParamFilter::text("DELEGATED-TO", TextMatch::new(text));
+            PropFilter::defined("ATTENDEE").param(param)
+        };
+        let attendee = "ATTENDEE;DELEGATED-TO=\"mailto:b@example.com\",\"mailto:c@example.com\":\
+                        mailto:a@example.com";
+
+        check(&[
+            (ops(), "CATEGORIES:work,ops", true),
+            (ops(), "CATEGORIES:work|CATEGORIES:OPS", true),
+            (ops(), "CATEGORIES:work,home", false),
+            (not_ops(), "CATEGORIES:work,ops", false),
+            (not_ops(), "CATEGORIES:work,home", true),
+            (delegated("c@example.com"), attendee, true),
+            (delegated("d@example.com"), attendee, false),
+        ]);
+    }
+
+    #[test]
+    fn requires_every_filter_on_one_component() {
+        let filter = ItemFilter::new()
+            .prop(PropFilter::text("STATUS", TextMatch::new("CANCELLED")))
+            .prop(PropFilter::defined("LOCATION"));
+
+        assert!(filter.matches(&fixture::events("1", &["STATUS:CANCELLED|LOCATION:here"])));
+        assert!(!filter.matches(&fixture::events(
+            "1",
+            &["STATUS:CANCELLED", "LOCATION:here"]
+        )));
+        assert!(ItemFilter::new().matches(&fixture::events("1", &[])));
+
+        let garbage = CalendarItem {
+            contents: b"not a calendar".to_vec(),
+            ..CalendarItem::default()
+        };
+        assert!(ItemFilter::new().matches(&garbage));
+        assert!(!filter.matches(&garbage));
+    }
+}
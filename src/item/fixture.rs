@@ -0,0 +1,44 @@
+//! Calendar items built from content lines, shared by unit tests.
+
+use alloc::string::String;
+#[cfg(feature = "parser")]
+use alloc::{format, vec::Vec};
+
+use crate::item::CalendarItem;
+
+/// The `Europe/Paris` time zone, content lines separated by `|`.
+#[cfg(feature = "parser")]
+pub(crate) const PARIS: &str = "BEGIN:VTIMEZONE|TZID:Europe/Paris\
+    |BEGIN:DAYLIGHT|DTSTART:19810329T020000|TZOFFSETFROM:+0100\
+    |TZOFFSETTO:+0200|RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU|END:DAYLIGHT\
+    |BEGIN:STANDARD|DTSTART:19961027T030000|TZOFFSETFROM:+0200\
+    |TZOFFSETTO:+0100|RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU|END:STANDARD\
+    |END:VTIMEZONE";
+
+/// An item `id` whose VCALENDAR holds `components`, each made of
+/// content lines separated by `|`.
+pub(crate) fn item(id: &str, components: &[&str]) -> CalendarItem {
+    let mut contents = String::from("BEGIN:VCALENDAR\r\n");
+    for line in components.iter().flat_map(|lines| lines.split('|')) {
+        contents.push_str(line);
+        contents.push_str("\r\n");
+    }
+    contents.push_str("END:VCALENDAR\r\n");
+
+    CalendarItem {
+        id: String::from(id),
+        contents: contents.into_bytes(),
+        ..CalendarItem::default()
+    }
+}
+
+/// Like [`item`], with each of `events` wrapped into a `VEVENT`.
+#[cfg(feature = "parser")]
+pub(crate) fn events(id: &str, events: &[&str]) -> CalendarItem {
+    let events: Vec<String> = events
+        .iter()
+        .map(|lines| format!("BEGIN:VEVENT|{lines}|END:VEVENT"))
+        .collect();
+    let events: Vec<&str> = events.iter().map(String::as_str).collect();
+    item(id, &events)
+}
@@ -0,0 +1,315 @@
+//! Minimal iCalendar (RFC 5545) content-line reader and writer.
+//!
+//! Works on unfolded content lines rather than on the calcard tree, for
+//! the code reading or rewriting raw property values.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str::from_utf8;
+
+/// A parsed content line (`NAME;PARAM=VALUE:value`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Property {
+    /// Upper-cased property name.
+    pub name: String,
+    /// Parameters in source order: upper-cased name plus unquoted
+    /// values.
+    pub params: Vec<(String, Vec<String>)>,
+    /// Raw (still escaped) value.
+    pub value: String,
+    /// Unfolded source line, re-emitted verbatim when the property is
+    /// left untouched.
+    raw: String,
+}
+
+impl Property {
+    /// Builds a property from its parts, rendering its raw line.
+    pub fn new(name: &str, params: Vec<(String, Vec<String>)>, value: String) -> Self {
+        let name = name.to_ascii_uppercase();
+        let mut raw = name.clone();
+
+        for (key, values) in &params {
+            raw.push(';');
+            raw.push_str(key);
+            raw.push('=');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    raw.push(',');
+                }
+                if value.contains([':', ';', ',']) {
+                    raw.push('"');
+                    raw.push_str(value);
+                    raw.push('"');
+                } else {
+                    raw.push_str(value);
+                }
+            }
+        }
+
+        raw.push(':');
+        raw.push_str(&value);
+
+        Self {
+            name,
+            params,
+            value,
+            raw,
+        }
+    }
+
+    /// Parses one unfolded content line. Returns `None` when the line
+    /// has no `:` separator outside quoted parameter values.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut in_quotes = false;
+        let mut colon = None;
+
+        for (i, c) in line.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ':' if !in_quotes => {
+                    colon = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let colon = colon?;
+        let head = &line[..colon];
+        let value = line[colon + 1..].to_string();
+
+        let mut parts = split_unquoted(head, ';').into_iter();
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut params = Vec::new();
+        for part in parts {
+            let Some((key, values)) = part.split_once('=') else {
+                continue;
+            };
+            let values = split_unquoted(values, ',')
+                .into_iter()
+                .map(|value| value.trim_matches('"').to_string())
+                .collect();
+            params.push((key.trim().to_ascii_uppercase(), values));
+        }
+
+        Some(Self {
+            name,
+            params,
+            value,
+            raw: line.to_string(),
+        })
+    }
+
+    /// First value of parameter `name` (case-insensitive), when set.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, values)| values.first())
+            .map(String::as_str)
+    }
+
+    /// Unescaped TEXT value (RFC 5545 section 3.3.11).
+    pub fn text(&self) -> String {
+        unescape_text(&self.value)
+    }
+}
+
+/// A component (`BEGIN:NAME` ... `END:NAME`) with its properties and
+/// nested components.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Component {
+    /// Upper-cased component name (`VCALENDAR`, `VEVENT`, ...).
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    /// Builds an empty component named `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_ascii_uppercase(),
+            ..Default::default()
+        }
+    }
+
+    /// Parses raw iCalendar bytes into the root component (usually
+    /// `VCALENDAR`). Returns `None` on invalid UTF-8 or unbalanced
+    /// `BEGIN` / `END` lines.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let text = from_utf8(bytes).ok()?;
+        let mut stack: Vec<Component> = Vec::new();
+        let mut root = None;
+
+        for line in unfold(text) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let Some(property) = Property::parse(&line) else {
+                continue;
+            };
+
+            match property.name.as_str() {
+                "BEGIN" => stack.push(Component::new(property.value.trim())),
+                "END" => {
+                    let component = stack.pop()?;
+                    if !component.name.eq_ignore_ascii_case(property.value.trim()) {
+                        return None;
+                    }
+                    match stack.last_mut() {
+                        Some(parent) => parent.components.push(component),
+                        None => {
+                            root = Some(component);
+                            break;
+                        }
+                    }
+                }
+                _ => stack.last_mut()?.properties.push(property),
+            }
+        }
+
+        root
+    }
+
+    /// First property named `name`, when present.
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Every property named `name`, in source order.
+    pub fn properties<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> + 'a {
+        self.properties.iter().filter(move |p| p.name == name)
+    }
+
+    /// Replaces every property named `name` with `properties`, keeping
+    /// the position of the first replaced one (or appending when there
+    /// was none).
+    pub fn replace(&mut self, name: &str, properties: Vec<Property>) {
+        let position = self.properties.iter().position(|p| p.name == name);
+        self.properties.retain(|p| p.name != name);
+        let position = position.unwrap_or(self.properties.len());
+
+        for (offset, property) in properties.into_iter().enumerate() {
+            self.properties.insert(position + offset, property);
+        }
+    }
+
+    /// Serializes the component, folding lines longer than 75 octets
+    /// (RFC 5545 section 3.1).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = String::new();
+        self.write(&mut out);
+        out.into_bytes()
+    }
+
+    fn write(&self, out: &mut String) {
+        fold_into(out, &["BEGIN:", &self.name].concat());
+        for property in &self.properties {
+            fold_into(out, &property.raw);
+        }
+        for component in &self.components {
+            component.write(out);
+        }
+        fold_into(out, &["END:", &self.name].concat());
+    }
+}
+
+/// Unfolds content lines: a line break followed by a space or a tab
+/// continues the previous line.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Appends `line` to `out`, folded at 75 octets on UTF-8 boundaries.
+fn fold_into(out: &mut String, line: &str) {
+    let mut width = 0;
+
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += len;
+    }
+
+    out.push_str("\r\n");
+}
+
+/// Splits `input` on `separator`, ignoring separators inside double
+/// quotes.
+fn split_unquoted(input: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in input.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == separator && !in_quotes {
+            parts.push(&input[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+
+    parts.push(&input[start..]);
+    parts
+}
+
+/// Unescapes a TEXT value (`\\`, `\;`, `\,`, `\n` / `\N`).
+pub(crate) fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+/// Escapes a TEXT value, the inverse of [`unescape_text`].
+#[cfg(feature = "client")]
+pub(crate) fn escape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
@@ -0,0 +1,62 @@
+//! Ids minted for new items: the item's UID when it makes a safe
+//! resource name, otherwise a random RFC 4122 v4 UUID. Shared by the
+//! vdir, WebDAV and memory backends.
+
+use alloc::{format, string::String};
+
+/// Formats 16 random bytes as an RFC 4122 v4 item id.
+pub(crate) fn uuid_v4(mut bytes: [u8; 16]) -> String {
+    // NOTE: RFC 4122 4.4 stamps version 4 and variant 10xx.
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let mut out = String::with_capacity(36);
+    for (i, byte) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            out.push('-');
+        }
+        out.push_str(&format!("{byte:02x}"));
+    }
+
+    out
+}
+
+/// Generates a fresh item id from the system entropy source.
+#[cfg(any(feature = "memory", all(feature = "client", feature = "webdav")))]
+pub(crate) fn fresh_item_id() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes)?;
+    Ok(uuid_v4(bytes))
+}
+
+/// Picks the id of new item `contents`: with the `parser` feature its
+/// UID when it makes a safe id, so importing the same item twice
+/// targets the existing one; otherwise a fresh one from
+/// [`fresh_item_id`].
+#[cfg(any(feature = "memory", all(feature = "client", feature = "webdav")))]
+pub(crate) fn new_item_id(contents: &[u8]) -> Result<String, getrandom::Error> {
+    #[cfg(feature = "parser")]
+    if let Some(id) = super::item_uid(contents)
+        .as_deref()
+        .and_then(super::id_from_uid)
+    {
+        return Ok(id);
+    }
+    #[cfg(not(feature = "parser"))]
+    let _ = contents;
+
+    fresh_item_id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::uuid_v4;
+
+    #[test]
+    fn uuid_v4_stamps_version_and_variant() {
+        let id = uuid_v4([0xff; 16]);
+
+        assert_eq!(id, "ffffffff-ffff-4fff-bfff-ffffffffffff");
+        assert_eq!(uuid_v4([0; 16]), "00000000-0000-4000-8000-000000000000");
+    }
+}
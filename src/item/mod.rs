@@ -6,7 +6,90 @@ pub mod vdir;
 #[cfg(feature = "webdav")]
 pub mod webdav;
 
+#[cfg(feature = "parser")]
+mod component;
+#[cfg(feature = "parser")]
+mod datetime;
+#[cfg(feature = "parser")]
+mod expand;
+mod filter;
+#[cfg(all(test, any(feature = "client", feature = "parser")))]
+pub(crate) mod fixture;
+#[cfg(feature = "parser")]
+mod ical;
+#[cfg(any(
+    feature = "vdir",
+    feature = "memory",
+    all(feature = "client", feature = "webdav")
+))]
+mod id;
+#[cfg(any(feature = "vdir", feature = "webdav", feature = "memory"))]
+mod page;
+#[cfg(any(feature = "vdir", feature = "memory"))]
+mod range;
+#[cfg(feature = "parser")]
+mod recurrence;
+#[cfg(feature = "parser")]
+mod search;
+#[cfg(any(feature = "vdir", feature = "client"))]
+mod tag;
+#[cfg(feature = "parser")]
+mod timezone;
 mod types;
+#[cfg(feature = "parser")]
+mod uid;
 
+#[cfg(feature = "parser")]
+#[doc(inline)]
+pub use component::*;
+#[cfg(feature = "parser")]
+#[doc(inline)]
+pub use datetime::*;
+#[doc(inline)]
+pub use filter::*;
+#[cfg(feature = "parser")]
+#[doc(inline)]
+pub use recurrence::Occurrence;
+#[cfg(feature = "parser")]
+#[doc(inline)]
+pub use search::{SearchField, SearchHit};
 #[doc(inline)]
 pub use types::*;
+#[cfg(feature = "parser")]
+#[doc(inline)]
+pub use uid::UidConflict;
+
+#[cfg(any(feature = "vdir", feature = "memory"))]
+pub(crate) use filter::filter_props;
+#[cfg(any(feature = "memory", all(feature = "client", feature = "webdav")))]
+pub(crate) use id::new_item_id;
+#[cfg(feature = "vdir")]
+pub(crate) use id::uuid_v4;
+#[cfg(any(feature = "vdir", feature = "webdav", feature = "memory"))]
+pub(crate) use page::paginate;
+#[cfg(any(feature = "vdir", feature = "memory"))]
+pub(crate) use range::{expand_recurrences, filter_time_range};
+#[cfg(all(feature = "parser", feature = "vdir", feature = "client"))]
+pub(crate) use range::{item_span, span_overlaps};
+#[cfg(all(
+    feature = "parser",
+    any(feature = "vdir", feature = "webdav", feature = "client")
+))]
+pub(crate) use search::search_items;
+#[cfg(any(feature = "vdir", feature = "client"))]
+pub(crate) use tag::content_tag;
+#[cfg(feature = "vdir")]
+pub(crate) use tag::digest;
+#[cfg(all(
+    feature = "parser",
+    any(
+        feature = "vdir",
+        all(feature = "client", feature = "webdav"),
+        feature = "memory"
+    )
+))]
+pub(crate) use uid::id_from_uid;
+#[cfg(all(feature = "parser", any(feature = "vdir", feature = "client")))]
+pub(crate) use uid::item_uid;
+#[cfg(all(feature = "parser", feature = "client"))]
+pub(crate) use uid::{UidCheck, check_uid};
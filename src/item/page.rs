@@ -0,0 +1,52 @@
+//! Pagination shared by the backends that page an in-memory listing.
+
+use alloc::vec::Vec;
+
+/// 1-indexed pagination on an in-memory list. `page_size = None`
+/// returns the full slice; `page_size = 0` or a page past the end
+/// returns an empty vector.
+pub(crate) fn paginate<T>(items: Vec<T>, page: Option<u32>, page_size: Option<u32>) -> Vec<T> {
+    let Some(size) = page_size else {
+        return items;
+    };
+
+    if size == 0 {
+        return Vec::new();
+    }
+
+    let page = page.unwrap_or(1).max(1);
+    let skip = ((page - 1) as usize).saturating_mul(size as usize);
+
+    if skip >= items.len() {
+        return Vec::new();
+    }
+
+    items.into_iter().skip(skip).take(size as usize).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn paginates_within_bounds() {
+        let items = vec![1, 2, 3, 4, 5];
+        assert_eq!(paginate(items.clone(), None, None), items);
+        assert_eq!(paginate(items.clone(), Some(3), None), items);
+        assert_eq!(paginate(items.clone(), None, Some(2)), [1, 2]);
+        assert_eq!(paginate(items.clone(), Some(0), Some(2)), [1, 2]);
+        assert_eq!(paginate(items.clone(), Some(2), Some(2)), [3, 4]);
+        assert_eq!(paginate(items.clone(), Some(3), Some(2)), [5]);
+    }
+
+    #[test]
+    fn paginates_out_of_bounds_to_nothing() {
+        let items = vec![1, 2, 3];
+        assert!(paginate(items.clone(), Some(1), Some(0)).is_empty());
+        assert!(paginate(items.clone(), Some(2), Some(3)).is_empty());
+        assert!(paginate(items.clone(), Some(u32::MAX), Some(u32::MAX)).is_empty());
+        assert!(paginate(Vec::<u8>::new(), Some(1), Some(10)).is_empty());
+    }
+}
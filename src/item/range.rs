@@ -0,0 +1,631 @@
+//! Client-side [`TimeRange`] filtering shared by the backends that
+//! have no server-side query (vdir, memory).
+//!
+//! Follows the per-component overlap rules of RFC 4791 section 9.9 so
+//! the result matches what a CalDAV server returns for the same range:
+//! values carrying a `TZID` are resolved through the embedded
+//! `VTIMEZONE`, floating times and all-day dates are read as UTC. The
+//! range's [`RecurrenceExpansion`] is then applied the same way.
+
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "parser"))]
+use log::trace;
+
+#[cfg(all(feature = "parser", feature = "vdir", feature = "client"))]
+use crate::item::recurrence::is_unbounded;
+use crate::item::{CalendarItem, TimeRange};
+#[cfg(feature = "parser")]
+use crate::item::{
+    CalendarItemKind, DateTime, Duration, RecurrenceExpansion,
+    ical::{Component, Property},
+    recurrence::{Bounds, instances, is_recurring},
+    timezone::TimeZones,
+};
+
+/// Keeps only the items matching `time_range`, when set: items with at
+/// least one component of their kind, or one occurrence of a recurring
+/// one, overlapping the range.
+#[cfg(feature = "parser")]
+pub(crate) fn filter_time_range(
+    items: Vec<CalendarItem>,
+    time_range: Option<&TimeRange>,
+) -> Vec<CalendarItem> {
+    let Some(range) = time_range else {
+        return items;
+    };
+
+    let bounds = Bounds::from(range);
+
+    items
+        .into_iter()
+        .filter(|item| item_in_range(item, &bounds))
+        .collect()
+}
+
+/// Without the `parser` feature the items cannot be inspected, so the
+/// range is ignored and every fetched item is returned.
+#[cfg(not(feature = "parser"))]
+pub(crate) fn filter_time_range(
+    items: Vec<CalendarItem>,
+    time_range: Option<&TimeRange>,
+) -> Vec<CalendarItem> {
+    if time_range.is_some() {
+        trace!("time-range filter ignored: parser feature is disabled");
+    }
+    items
+}
+
+/// Applies the [`RecurrenceExpansion`] of `time_range`, when set, to
+/// items already filtered by it.
+#[cfg(feature = "parser")]
+pub(crate) fn expand_recurrences(
+    items: Vec<CalendarItem>,
+    time_range: Option<&TimeRange>,
+) -> Vec<CalendarItem> {
+    let Some(range) = time_range else {
+        return items;
+    };
+
+    match range.expansion() {
+        None => items,
+        Some(RecurrenceExpansion::Expand) => {
+            items.iter().flat_map(|item| item.expand(range)).collect()
+        }
+        Some(RecurrenceExpansion::LimitRecurrenceSet) => items
+            .iter()
+            .map(|item| item.limit_recurrence_set(range))
+            .collect(),
+    }
+}
+
+/// Without the `parser` feature the items cannot be expanded, so they
+/// are returned whole.
+#[cfg(not(feature = "parser"))]
+pub(crate) fn expand_recurrences(
+    items: Vec<CalendarItem>,
+    time_range: Option<&TimeRange>,
+) -> Vec<CalendarItem> {
+    if time_range.is_some_and(|range| range.expansion().is_some()) {
+        trace!("recurrence expansion ignored: parser feature is disabled");
+    }
+    items
+}
+
+/// Whether any component of `item`'s kind overlaps `bounds`. Items that
+/// cannot be parsed are dropped.
+#[cfg(feature = "parser")]
+fn item_in_range(item: &CalendarItem, bounds: &Bounds) -> bool {
+    let Some(kind) = item.kind() else {
+        return false;
+    };
+
+    let Some(calendar) = Component::parse(&item.contents) else {
+        return false;
+    };
+
+    let components = calendar
+        .components
+        .iter()
+        .filter(|component| component.name == kind.component_name());
+
+    if components.clone().any(is_recurring) && !instances(&calendar, kind, bounds).is_empty() {
+        return true;
+    }
+
+    let zones = TimeZones::new(&calendar);
+
+    components
+        .filter(|component| !is_recurring(component))
+        .any(|component| match kind {
+            CalendarItemKind::Event => event_overlaps(component, &zones, bounds),
+            CalendarItemKind::Todo => todo_overlaps(component, &zones, bounds),
+            CalendarItemKind::Journal => journal_overlaps(component, &zones, bounds),
+        })
+}
+
+/// Time bounds of an item, as recorded by the
+/// [`VdirIndex`](crate::vdir::index::VdirIndex).
+#[cfg(all(feature = "parser", feature = "vdir", feature = "client"))]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ItemSpan {
+    /// UTC `DTSTART` of the main component.
+    pub start: Option<i64>,
+    /// UTC `DTEND` (or `DUE`, or `DTSTART` plus `DURATION`) of the main
+    /// component.
+    pub end: Option<i64>,
+    /// Inclusive UTC bounds of every instant a range must reach to
+    /// match the item, recurrences included; `i64::MIN` and `i64::MAX`
+    /// stand for open ends. `None` when no range matches the item.
+    pub bounds: Option<(i64, i64)>,
+}
+
+/// Computes the [`ItemSpan`] of `item`.
+///
+/// The bounds are conservative: [`filter_time_range`] only keeps the
+/// item for ranges reaching them, but may still drop it for some of
+/// those ranges.
+#[cfg(all(feature = "parser", feature = "vdir", feature = "client"))]
+pub(crate) fn item_span(item: &CalendarItem) -> ItemSpan {
+    let Some(kind) = item.kind() else {
+        return ItemSpan::default();
+    };
+    let Some(calendar) = Component::parse(&item.contents) else {
+        return ItemSpan::default();
+    };
+
+    let zones = TimeZones::new(&calendar);
+    let stamp =
+        |component: &Component, name| datetime(component, name).map(|dt| zones.timestamp(&dt));
+    let after = |component: &Component, duration: &Duration| {
+        let start = datetime(component, "DTSTART")?;
+        Some(zones.timestamp_after(&start, duration))
+    };
+
+    let components: Vec<&Component> = calendar
+        .components
+        .iter()
+        .filter(|component| component.name == kind.component_name())
+        .collect();
+
+    let main = components
+        .iter()
+        .find(|component| component.property("RECURRENCE-ID").is_none())
+        .or(components.first());
+
+    let start = main.and_then(|main| stamp(main, "DTSTART"));
+    let end = main.and_then(|main| {
+        stamp(main, "DTEND")
+            .or_else(|| stamp(main, "DUE"))
+            .or_else(|| after(main, &duration(main)?))
+    });
+
+    let mut bounds: Option<(i64, i64)> = None;
+    let mut extend = |lower: i64, upper: i64| {
+        bounds = Some(match bounds {
+            Some((l, u)) => (l.min(lower), u.max(upper)),
+            None => (lower, upper),
+        });
+    };
+
+    let masters: Vec<&Component> = components
+        .iter()
+        .copied()
+        .filter(|component| is_recurring(component))
+        .collect();
+
+    if !masters.is_empty() {
+        let unbounded = masters.iter().any(|master| is_unbounded(master));
+        let first = masters
+            .iter()
+            .filter_map(|master| stamp(master, "DTSTART"))
+            .min();
+
+        // NOTE: an endless rule is only expanded up to its first start,
+        // which still catches RDATEs set before DTSTART.
+        let limit = match (unbounded, first) {
+            (true, Some(first)) => first,
+            _ => i64::MAX,
+        };
+        let all = Bounds {
+            start: i64::MIN,
+            end: limit,
+        };
+
+        for instance in instances(&calendar, kind, &all) {
+            let upper = if unbounded { i64::MAX } else { instance.end };
+            extend(instance.start.min(instance.end), upper);
+        }
+
+        if let (true, Some(first)) = (unbounded, first) {
+            extend(first, i64::MAX);
+        }
+    }
+
+    for component in components.iter().filter(|c| !is_recurring(c)) {
+        let mut stamps = Vec::new();
+
+        match kind {
+            CalendarItemKind::Event | CalendarItemKind::Journal => {
+                let Some(start) = datetime(component, "DTSTART") else {
+                    continue;
+                };
+                stamps.push(zones.timestamp(&start));
+                stamps.extend(stamp(component, "DTEND"));
+                stamps.extend(duration(component).and_then(|d| after(component, &d)));
+                if start.is_date() {
+                    stamps.push(zones.timestamp_after(&start, &ONE_DAY));
+                }
+            }
+            CalendarItemKind::Todo => {
+                let start = stamp(component, "DTSTART");
+                let due = stamp(component, "DUE");
+                let completed = stamp(component, "COMPLETED");
+                let created = stamp(component, "CREATED");
+
+                if start.is_none() && due.is_none() && completed.is_none() {
+                    // only CREATED, or nothing: matches every range
+                    // ending after it
+                    extend(created.unwrap_or(i64::MIN), i64::MAX);
+                    continue;
+                }
+
+                stamps.extend([start, due, completed, created].into_iter().flatten());
+                stamps.extend(duration(component).and_then(|d| after(component, &d)));
+            }
+        }
+
+        if let (Some(lower), Some(upper)) = (stamps.iter().min(), stamps.iter().max()) {
+            extend(*lower, *upper);
+        }
+    }
+
+    ItemSpan { start, end, bounds }
+}
+
+/// Whether inclusive item `bounds` reach `time_range`, see
+/// [`ItemSpan::bounds`].
+#[cfg(all(feature = "parser", feature = "vdir", feature = "client"))]
+pub(crate) fn span_overlaps((lower, upper): (i64, i64), time_range: &TimeRange) -> bool {
+    let range = Bounds::from(time_range);
+    range.start <= upper && range.end >= lower
+}
+
+/// VEVENT rules of the RFC 4791 section 9.9 table.
+#[cfg(feature = "parser")]
+fn event_overlaps(component: &Component, zones: &TimeZones, bounds: &Bounds) -> bool {
+    let Some(start) = datetime(component, "DTSTART") else {
+        return false;
+    };
+    let dtstart = zones.timestamp(&start);
+
+    if let Some(end) = datetime(component, "DTEND") {
+        return bounds.start < zones.timestamp(&end) && bounds.end > dtstart;
+    }
+
+    if let Some(duration) = duration(component) {
+        if duration.as_seconds() > 0 {
+            let until = zones.timestamp_after(&start, &duration);
+            return bounds.start < until && bounds.end > dtstart;
+        }
+        return bounds.start <= dtstart && bounds.end > dtstart;
+    }
+
+    if start.is_date() {
+        let until = zones.timestamp_after(&start, &ONE_DAY);
+        return bounds.start < until && bounds.end > dtstart;
+    }
+
+    bounds.start <= dtstart && bounds.end > dtstart
+}
+
+/// VTODO rules of the RFC 4791 section 9.9 table.
+#[cfg(feature = "parser")]
+fn todo_overlaps(component: &Component, zones: &TimeZones, bounds: &Bounds) -> bool {
+    let start = datetime(component, "DTSTART");
+    let stamp = |name| datetime(component, name).map(|dt| zones.timestamp(&dt));
+    let (lower, upper) = (bounds.start, bounds.end);
+
+    let due = stamp("DUE");
+    let completed = stamp("COMPLETED");
+    let created = stamp("CREATED");
+
+    match (start, due, duration(component)) {
+        (Some(start), None, Some(duration)) => {
+            let dtstart = zones.timestamp(&start);
+            let until = zones.timestamp_after(&start, &duration);
+            lower <= until && (upper > dtstart || upper >= until)
+        }
+        (Some(start), Some(due), _) => {
+            let dtstart = zones.timestamp(&start);
+            (lower < due || lower <= dtstart) && (upper > dtstart || upper >= due)
+        }
+        (Some(start), None, None) => {
+            let dtstart = zones.timestamp(&start);
+            lower <= dtstart && upper > dtstart
+        }
+        (None, Some(due), _) => lower < due && upper >= due,
+        (None, None, _) => match (completed, created) {
+            (Some(completed), Some(created)) => {
+                (lower <= created || lower <= completed) && (upper >= created || upper >= completed)
+            }
+            (Some(completed), None) => lower <= completed && upper >= completed,
+            (None, Some(created)) => upper > created,
+            (None, None) => true,
+        },
+    }
+}
+
+/// VJOURNAL rules of the RFC 4791 section 9.9 table.
+#[cfg(feature = "parser")]
+fn journal_overlaps(component: &Component, zones: &TimeZones, bounds: &Bounds) -> bool {
+    let Some(start) = datetime(component, "DTSTART") else {
+        return false;
+    };
+    let dtstart = zones.timestamp(&start);
+
+    if start.is_date() {
+        let until = zones.timestamp_after(&start, &ONE_DAY);
+        return bounds.start < until && bounds.end > dtstart;
+    }
+
+    bounds.start <= dtstart && bounds.end > dtstart
+}
+
+#[cfg(feature = "parser")]
+const ONE_DAY: Duration = Duration {
+    negative: false,
+    weeks: 0,
+    days: 1,
+    hours: 0,
+    minutes: 0,
+    seconds: 0,
+};
+
+#[cfg(feature = "parser")]
+fn datetime(component: &Component, name: &str) -> Option<DateTime> {
+    let property = component.property(name)?;
+    DateTime::parse(&property.value, property.param("TZID"))
+}
+
+#[cfg(feature = "parser")]
+fn duration(component: &Component) -> Option<Duration> {
+    component
+        .property("DURATION")
+        .map(Property::text)
+        .and_then(|value| Duration::parse(&value))
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use alloc::{format, vec};
+
+    use super::*;
+    use crate::item::fixture;
+
+    /// An item with one `kind` component holding `properties`
+    /// (content lines separated by `|`), plus the Paris time zone.
+    fn item(kind: &str, properties: &str) -> CalendarItem {
+        let component = format!("BEGIN:{kind}|UID:item|{properties}|END:{kind}");
+        fixture::item("item", &[fixture::PARIS, &component])
+    }
+
+    /// Runs `(properties, range start, range end, expected)` rows
+    /// against `kind` components.
+    fn check(kind: &str, table: &[(&str, &str, &str, bool)]) {
+        for (properties, start, end, expected) in table {
+            let range = TimeRange::new(Some(start), Some(end)).unwrap();
+            let items = filter_time_range(vec![item(kind, properties)], Some(&range));
+            assert_eq!(
+                !items.is_empty(),
+                *expected,
+                "{kind} {properties} in [{start}, {end})"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_vevent_rows() {
+        let dtend = "DTSTART:20250110T100000Z|DTEND:20250110T110000Z";
+        let duration = "DTSTART:20250110T100000Z|DURATION:PT1H";
+        let instant = "DTSTART:20250110T100000Z|DURATION:PT0S";
+        let date = "DTSTART;VALUE=DATE:20250110";
+        let start = "DTSTART:20250110T100000Z";
+
+        check(
+            "VEVENT",
+            &[
+                // DTEND: start < DTEND and end > DTSTART
+                (dtend, "20250110T090000Z", "20250110T100000Z", false),
+                (dtend, "20250110T090000Z", "20250110T100001Z", true),
+                (dtend, "20250110T105959Z", "20250110T120000Z", true),
+                (dtend, "20250110T110000Z", "20250110T120000Z", false),
+                // DURATION > 0: start < DTSTART + DURATION and end > DTSTART
+                (duration, "20250110T090000Z", "20250110T100000Z", false),
+                (duration, "20250110T103000Z", "20250110T103001Z", true),
+                (duration, "20250110T110000Z", "20250110T120000Z", false),
+                // DURATION = 0: start <= DTSTART and end > DTSTART
+                (instant, "20250110T100000Z", "20250110T100001Z", true),
+                (instant, "20250110T090000Z", "20250110T100000Z", false),
+                (instant, "20250110T100001Z", "20250110T110000Z", false),
+                // DATE DTSTART: start < DTSTART + P1D and end > DTSTART
+                (date, "20250110T235959Z", "20250111T120000Z", true),
+                (date, "20250109T120000Z", "20250110T000001Z", true),
+                (date, "20250111T000000Z", "20250111T120000Z", false),
+                (date, "20250109T000000Z", "20250110T000000Z", false),
+                // DATE-TIME DTSTART alone: start <= DTSTART and end > DTSTART
+                (start, "20250110T100000Z", "20250110T100001Z", true),
+                (start, "20250110T090000Z", "20250110T100000Z", false),
+                (start, "20250110T100001Z", "20250110T110000Z", false),
+                // no DTSTART never matches
+                (
+                    "SUMMARY:undated",
+                    "19700101T000000Z",
+                    "29991231T000000Z",
+                    false,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn matches_vtodo_rows() {
+        let duration = "DTSTART:20250110T100000Z|DURATION:PT1H";
+        let due = "DTSTART:20250110T100000Z|DUE:20250110T110000Z";
+        let start = "DTSTART:20250110T100000Z";
+        let only_due = "DUE:20250110T110000Z";
+        let both = "COMPLETED:20250110T110000Z|CREATED:20250110T100000Z";
+        let completed = "COMPLETED:20250110T110000Z";
+        let created = "CREATED:20250110T100000Z";
+
+        check(
+            "VTODO",
+            &[
+                // DTSTART + DURATION: start <= DTSTART + DURATION and
+                // (end > DTSTART or end >= DTSTART + DURATION)
+                (duration, "20250110T110000Z", "20250110T120000Z", true),
+                (duration, "20250110T110001Z", "20250110T120000Z", false),
+                (duration, "20250110T090000Z", "20250110T100001Z", true),
+                (duration, "20250110T090000Z", "20250110T100000Z", false),
+                // DTSTART + DUE: (start < DUE or start <= DTSTART) and
+                // (end > DTSTART or end >= DUE)
+                (due, "20250110T105959Z", "20250110T120000Z", true),
+                (due, "20250110T110000Z", "20250110T120000Z", false),
+                (due, "20250110T090000Z", "20250110T100001Z", true),
+                (due, "20250110T090000Z", "20250110T100000Z", false),
+                // DTSTART alone: start <= DTSTART and end > DTSTART
+                (start, "20250110T100000Z", "20250110T100001Z", true),
+                (start, "20250110T090000Z", "20250110T100000Z", false),
+                (start, "20250110T100001Z", "20250110T120000Z", false),
+                // DUE alone: start < DUE and end >= DUE
+                (only_due, "20250110T090000Z", "20250110T110000Z", true),
+                (only_due, "20250110T090000Z", "20250110T105959Z", false),
+                (only_due, "20250110T110000Z", "20250110T120000Z", false),
+                // COMPLETED + CREATED: (start <= CREATED or start <=
+                // COMPLETED) and (end >= CREATED or end >= COMPLETED)
+                (both, "20250110T110000Z", "20250110T120000Z", true),
+                (both, "20250110T110001Z", "20250110T120000Z", false),
+                (both, "20250110T090000Z", "20250110T100000Z", true),
+                (both, "20250110T080000Z", "20250110T095959Z", false),
+                // COMPLETED alone: start <= COMPLETED and end >= COMPLETED
+                (completed, "20250110T110000Z", "20250110T120000Z", true),
+                (completed, "20250110T090000Z", "20250110T110000Z", true),
+                (completed, "20250110T110001Z", "20250110T120000Z", false),
+                (completed, "20250110T090000Z", "20250110T105959Z", false),
+                // CREATED alone: end > CREATED
+                (created, "20250110T090000Z", "20250110T100001Z", true),
+                (created, "20250110T090000Z", "20250110T100000Z", false),
+                (created, "20300101T000000Z", "20300102T000000Z", true),
+                // nothing: every range matches
+                (
+                    "SUMMARY:undated",
+                    "20250110T090000Z",
+                    "20250110T100000Z",
+                    true,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn matches_vjournal_rows() {
+        let start = "DTSTART:20250110T100000Z";
+        let date = "DTSTART;VALUE=DATE:20250110";
+
+        check(
+            "VJOURNAL",
+            &[
+                // DATE-TIME DTSTART: start <= DTSTART and end > DTSTART
+                (start, "20250110T100000Z", "20250110T100001Z", true),
+                (start, "20250110T090000Z", "20250110T100000Z", false),
+                (start, "20250110T100001Z", "20250110T120000Z", false),
+                // DATE DTSTART: start < DTSTART + P1D and end > DTSTART
+                (date, "20250110T235959Z", "20250111T000000Z", true),
+                (date, "20250111T000000Z", "20250111T120000Z", false),
+                (date, "20250109T000000Z", "20250110T000000Z", false),
+                // no DTSTART never matches
+                (
+                    "SUMMARY:undated",
+                    "19700101T000000Z",
+                    "29991231T000000Z",
+                    false,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn resolves_floating_utc_and_tzid_times() {
+        check(
+            "VEVENT",
+            &[
+                // UTC
+                (
+                    "DTSTART:20250110T100000Z",
+                    "20250110T100000Z",
+                    "20250110T103000Z",
+                    true,
+                ),
+                (
+                    "DTSTART:20250110T100000Z",
+                    "20250110T090000Z",
+                    "20250110T093000Z",
+                    false,
+                ),
+                // floating times are read as UTC
+                (
+                    "DTSTART:20250110T100000",
+                    "20250110T100000Z",
+                    "20250110T103000Z",
+                    true,
+                ),
+                (
+                    "DTSTART:20250110T100000",
+                    "20250110T090000Z",
+                    "20250110T093000Z",
+                    false,
+                ),
+                // TZID resolved through the VTIMEZONE: 10:00 Paris is 09:00 UTC
+                (
+                    "DTSTART;TZID=Europe/Paris:20250110T100000",
+                    "20250110T090000Z",
+                    "20250110T093000Z",
+                    true,
+                ),
+                (
+                    "DTSTART;TZID=Europe/Paris:20250110T100000",
+                    "20250110T100000Z",
+                    "20250110T103000Z",
+                    false,
+                ),
+                // in summer, 10:00 Paris is 08:00 UTC
+                (
+                    "DTSTART;TZID=Europe/Paris:20250710T100000",
+                    "20250710T080000Z",
+                    "20250710T083000Z",
+                    true,
+                ),
+                (
+                    "DTSTART;TZID=Europe/Paris:20250710T100000",
+                    "20250710T090000Z",
+                    "20250710T093000Z",
+                    false,
+                ),
+                // unknown TZID: read as UTC
+                (
+                    "DTSTART;TZID=Mars/Olympus:20250110T100000",
+                    "20250110T100000Z",
+                    "20250110T103000Z",
+                    true,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn spans_dst_transitions_in_wall_clock_time() {
+        // 2025-03-30 00:00 to 2025-03-31 00:00 Paris is 23 hours long:
+        // 2025-03-29 23:00 UTC to 2025-03-30 22:00 UTC
+        let day = "DTSTART;TZID=Europe/Paris:20250330T000000|DURATION:P1D";
+        let dtend =
+            "DTSTART;TZID=Europe/Paris:20250330T000000|DTEND;TZID=Europe/Paris:20250331T000000";
+
+        check(
+            "VEVENT",
+            &[
+                (day, "20250330T215959Z", "20250331T000000Z", true),
+                (day, "20250330T220000Z", "20250331T000000Z", false),
+                (day, "20250329T220000Z", "20250329T230001Z", true),
+                (day, "20250329T220000Z", "20250329T230000Z", false),
+                (dtend, "20250330T215959Z", "20250331T000000Z", true),
+                (dtend, "20250330T220000Z", "20250331T000000Z", false),
+            ],
+        );
+    }
+
+    #[test]
+    fn keeps_every_item_without_range() {
+        let items = vec![item("VEVENT", "DTSTART:20250110T100000Z")];
+        assert_eq!(filter_time_range(items.clone(), None), items);
+    }
+}
@@ -0,0 +1,1022 @@
+//! Recurrence expansion (RFC 5545 sections 3.3.10 and 3.8.5).
+//!
+//! Expands the `RRULE`, `RDATE` and `EXDATE` of a master component,
+//! then applies the `RECURRENCE-ID` overrides sharing its UID. Rules are
+//! evaluated in the wall-clock time of `DTSTART`, so daily and weekly
+//! instances keep their local time across DST changes.
+//!
+//! `BYWEEKNO` and `BYYEARDAY` are not supported: a rule using them only
+//! yields its `DTSTART`. `RANGE=THISANDFUTURE` overrides only replace
+//! the instance they name.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use log::trace;
+
+use crate::item::{
+    CalendarItem, CalendarItemKind, DateTime, Duration, TimeRange, TimeZone,
+    datetime::{civil_from_days, days_from_civil, days_in_month},
+    ical::{Component, Property},
+    timezone::{TimeZones, weekday_index},
+};
+
+/// Upper bound on the recurrence periods walked for one rule, so an
+/// unbounded rule cannot loop forever.
+const MAX_PERIODS: i64 = 100_000;
+
+/// Upper bound on the instances generated for one rule, counted from
+/// the start of the expanded range.
+const MAX_INSTANCES: usize = 10_000;
+
+/// A concrete occurrence of a (possibly recurring) item.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct Occurrence {
+    /// UID of the recurring component.
+    pub uid: String,
+    /// Start generated by the recurrence set, i.e. the `RECURRENCE-ID`
+    /// identifying this occurrence.
+    pub recurrence_id: DateTime,
+    /// Actual start (differs from `recurrence_id` when overridden).
+    pub start: DateTime,
+    /// Actual end, `None` for components without `DTEND`, `DUE` or
+    /// `DURATION` (which last zero seconds, or one day for dates).
+    pub end: Option<DateTime>,
+    /// Whether a `RECURRENCE-ID` component replaced the generated
+    /// occurrence.
+    pub overridden: bool,
+}
+
+impl CalendarItem {
+    /// Expands the item into its occurrences overlapping `range`,
+    /// sorted by start.
+    ///
+    /// Non-recurring items yield at most one occurrence. Unbounded
+    /// rules stop after about 10 000 instances from the start of
+    /// `range` (from `DTSTART` when the range has no start).
+    pub fn occurrences(&self, range: &TimeRange) -> Vec<Occurrence> {
+        let Some(kind) = self.kind() else {
+            return Vec::new();
+        };
+        let Some(calendar) = Component::parse(&self.contents) else {
+            return Vec::new();
+        };
+
+        instances(&calendar, kind, &Bounds::from(range))
+            .into_iter()
+            .map(|instance| instance.occurrence)
+            .collect()
+    }
+}
+
+/// Range bounds as UTC timestamps; a missing bound is open-ended.
+pub(crate) struct Bounds {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Bounds {
+    /// Whether `[start, end)` overlaps the bounds; a zero-length
+    /// instance overlaps when `start` lies inside them.
+    pub fn overlaps(&self, start: i64, end: i64) -> bool {
+        if end > start {
+            self.start < end && self.end > start
+        } else {
+            self.start <= start && self.end > start
+        }
+    }
+}
+
+impl From<&TimeRange> for Bounds {
+    fn from(range: &TimeRange) -> Self {
+        let stamp = |value: &str| DateTime::parse(value, None).map(|dt| dt.wall_clock_timestamp());
+
+        Self {
+            start: range.start().and_then(stamp).unwrap_or(i64::MIN),
+            end: range.end().and_then(stamp).unwrap_or(i64::MAX),
+        }
+    }
+}
+
+/// An expanded occurrence plus its UTC bounds and the index, in the
+/// VCALENDAR children, of the component it was built from.
+pub(crate) struct Instance {
+    pub occurrence: Occurrence,
+    pub start: i64,
+    pub end: i64,
+    pub component: usize,
+}
+
+/// Whether `component` carries recurrence properties.
+pub(crate) fn is_recurring(component: &Component) -> bool {
+    component.property("RRULE").is_some() || component.property("RDATE").is_some()
+}
+
+/// Whether the `RRULE` of `component` repeats forever, having neither
+/// `COUNT` nor `UNTIL`. Unsupported rules only yield `DTSTART`, so they
+/// are bounded.
+#[cfg(all(feature = "vdir", feature = "client"))]
+pub(crate) fn is_unbounded(component: &Component) -> bool {
+    component
+        .property("RRULE")
+        .and_then(|property| Rule::parse(&property.value))
+        .is_some_and(|rule| rule.count.is_none() && rule.until.is_none())
+}
+
+/// Expands every master component of `kind` in `calendar` and returns
+/// the instances overlapping `bounds`, sorted by start.
+pub(crate) fn instances(
+    calendar: &Component,
+    kind: CalendarItemKind,
+    bounds: &Bounds,
+) -> Vec<Instance> {
+    let zones = TimeZones::new(calendar);
+    let name = kind.component_name();
+    let uid = |c: &Component| c.property("UID").map(Property::text).unwrap_or_default();
+
+    let mut instances = Vec::new();
+
+    let masters = calendar
+        .components
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.name == name && c.property("RECURRENCE-ID").is_none());
+
+    for (index, master) in masters {
+        let Some(dtstart) = datetime(master, "DTSTART") else {
+            continue;
+        };
+
+        let master_uid = uid(master);
+        let overrides: Vec<(usize, &Component)> = calendar
+            .components
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.name == name && c.property("RECURRENCE-ID").is_some())
+            .filter(|(_, c)| uid(c) == master_uid)
+            .collect();
+
+        // instances starting before `from` end before the range, so
+        // they are not generated; the slack covers time zone offsets
+        let length = span(master, &dtstart, &zones).unwrap_or(0);
+        let from = bounds
+            .start
+            .saturating_sub(length)
+            .saturating_sub(2 * 86_400);
+        let limit = bounds.end.saturating_add(2 * 86_400);
+        let mut starts = recurrence_set(master, &dtstart, &zones, from, limit);
+        let mut used = vec![false; overrides.len()];
+
+        for start in starts.drain(..) {
+            let start_ts = zones.timestamp(&start);
+
+            let overridden = overrides.iter().position(|(_, c)| {
+                datetime(c, "RECURRENCE-ID").is_some_and(|rid| zones.timestamp(&rid) == start_ts)
+            });
+
+            let instance = match overridden {
+                Some(i) => {
+                    used[i] = true;
+                    let (index, component) = overrides[i];
+                    instance(component, index, start, &zones, &master_uid, true)
+                }
+                None => instance(master, index, start, &zones, &master_uid, false),
+            };
+
+            if let Some(instance) = instance {
+                instances.push(instance);
+            }
+        }
+
+        // overrides of instances outside the generated set (or outside
+        // the expanded span) still count
+        for (i, (index, component)) in overrides.iter().enumerate() {
+            if used[i] {
+                continue;
+            }
+            let Some(rid) = datetime(component, "RECURRENCE-ID") else {
+                continue;
+            };
+            if let Some(instance) = instance(component, *index, rid, &zones, &master_uid, true) {
+                instances.push(instance);
+            }
+        }
+    }
+
+    instances.retain(|instance| bounds.overlaps(instance.start, instance.end));
+    instances.sort_by_key(|instance| instance.start);
+    instances
+}
+
+/// Builds the instance identified by `recurrence_id` out of
+/// `component` (child `index` of the VCALENDAR): the component's own
+/// start when it is an override, the generated one otherwise, plus the
+/// component's span.
+fn instance(
+    component: &Component,
+    index: usize,
+    recurrence_id: DateTime,
+    zones: &TimeZones,
+    uid: &str,
+    overridden: bool,
+) -> Option<Instance> {
+    let dtstart = datetime(component, "DTSTART")?;
+    let start = if overridden {
+        dtstart.clone()
+    } else {
+        recurrence_id.clone()
+    };
+
+    let span = span(component, &dtstart, zones);
+    let start_ts = zones.timestamp(&start);
+
+    let (end, end_ts) = match span {
+        Some(seconds) => {
+            let wall = start.wall_clock_timestamp() + seconds;
+            let end = DateTime::from_wall_clock(wall, start.is_date(), start.tz.clone());
+            let end_ts = zones.timestamp(&end);
+            (Some(end), end_ts)
+        }
+        None if start.is_date() => (None, start_ts + 86_400),
+        None => (None, start_ts),
+    };
+
+    Some(Instance {
+        occurrence: Occurrence {
+            uid: uid.to_string(),
+            recurrence_id,
+            start,
+            end,
+            overridden,
+        },
+        start: start_ts,
+        end: end_ts,
+        component: index,
+    })
+}
+
+/// Wall-clock length of `component` in seconds, from `DTEND` / `DUE`
+/// or `DURATION`.
+fn span(component: &Component, dtstart: &DateTime, zones: &TimeZones) -> Option<i64> {
+    if let Some(end) = datetime(component, "DTEND").or_else(|| datetime(component, "DUE")) {
+        // convert across zones when DTEND is expressed in another one
+        let seconds = if end.tz == dtstart.tz {
+            end.wall_clock_timestamp() - dtstart.wall_clock_timestamp()
+        } else {
+            zones.timestamp(&end) - zones.timestamp(dtstart)
+        };
+        return Some(seconds.max(0));
+    }
+
+    let duration = component
+        .property("DURATION")
+        .and_then(|p| Duration::parse(&p.value))?;
+    Some(duration.as_seconds().max(0))
+}
+
+/// Generated starts of `master`: `DTSTART`, `RRULE` and `RDATE`
+/// instances minus the `EXDATE` ones, deduplicated. `RRULE` instances
+/// are only generated between the wall-clock bounds `from` and
+/// `limit`.
+fn recurrence_set(
+    master: &Component,
+    dtstart: &DateTime,
+    zones: &TimeZones,
+    from: i64,
+    limit: i64,
+) -> Vec<DateTime> {
+    let mut starts = match master.property("RRULE").map(|p| Rule::parse(&p.value)) {
+        Some(Some(rule)) => rule
+            .expand(dtstart, zones, from, limit)
+            .into_iter()
+            .map(|wall| DateTime::from_wall_clock(wall, dtstart.is_date(), dtstart.tz.clone()))
+            .collect(),
+        Some(None) => {
+            trace!("unsupported RRULE, keeping DTSTART only");
+            vec![dtstart.clone()]
+        }
+        None => vec![dtstart.clone()],
+    };
+
+    starts.extend(datetimes(master, "RDATE"));
+
+    let excluded: Vec<i64> = datetimes(master, "EXDATE")
+        .iter()
+        .map(|dt| zones.timestamp(dt))
+        .collect();
+
+    let mut starts: Vec<(i64, DateTime)> = starts
+        .into_iter()
+        .map(|dt| (zones.timestamp(&dt), dt))
+        .filter(|(ts, _)| !excluded.contains(ts))
+        .collect();
+
+    starts.sort_by_key(|(ts, _)| *ts);
+    starts.dedup_by_key(|(ts, _)| *ts);
+    starts.into_iter().map(|(_, dt)| dt).collect()
+}
+
+fn datetime(component: &Component, name: &str) -> Option<DateTime> {
+    let property = component.property(name)?;
+    DateTime::parse(&property.value, property.param("TZID"))
+}
+
+/// Every value of every `name` property (`RDATE`, `EXDATE`); `PERIOD`
+/// values contribute their start.
+fn datetimes(component: &Component, name: &str) -> Vec<DateTime> {
+    component
+        .properties(name)
+        .flat_map(|p| {
+            let tzid = p.param("TZID");
+            p.value
+                .split(',')
+                .filter_map(move |value| {
+                    let start = value.split('/').next().unwrap_or(value);
+                    DateTime::parse(start, tzid)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Frequency {
+    Secondly,
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A parsed `RRULE` value.
+#[derive(Debug)]
+struct Rule {
+    frequency: Frequency,
+    interval: i64,
+    count: Option<usize>,
+    until: Option<DateTime>,
+    by_month: Vec<u8>,
+    by_month_day: Vec<i8>,
+    /// `(ordinal, weekday)`, ordinal 0 meaning every such weekday.
+    by_day: Vec<(i8, u8)>,
+    by_hour: Vec<u8>,
+    by_minute: Vec<u8>,
+    by_second: Vec<u8>,
+    by_set_pos: Vec<i32>,
+    week_start: u8,
+}
+
+impl Rule {
+    /// Parses an `RRULE` value. Returns `None` when `FREQ` is missing
+    /// or the rule uses an unsupported part.
+    fn parse(value: &str) -> Option<Self> {
+        let mut rule = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_month: Vec::new(),
+            by_month_day: Vec::new(),
+            by_day: Vec::new(),
+            by_hour: Vec::new(),
+            by_minute: Vec::new(),
+            by_second: Vec::new(),
+            by_set_pos: Vec::new(),
+            week_start: 1,
+        };
+        let mut frequency = None;
+
+        for part in value.trim().split(';') {
+            let (key, value) = part.split_once('=')?;
+            let value = value.trim();
+            let list = || value.split(',').map(str::trim);
+
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "SECONDLY" => Frequency::Secondly,
+                        "MINUTELY" => Frequency::Minutely,
+                        "HOURLY" => Frequency::Hourly,
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return None,
+                    })
+                }
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|n| *n > 0)?,
+                "COUNT" => rule.count = Some(value.parse().ok()?),
+                "UNTIL" => rule.until = Some(DateTime::parse(value, None)?),
+                "BYMONTH" => {
+                    rule.by_month = list().map(|v| v.parse().ok()).collect::<Option<_>>()?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = list().map(|v| v.parse().ok()).collect::<Option<_>>()?
+                }
+                "BYDAY" => rule.by_day = list().map(parse_by_day).collect::<Option<_>>()?,
+                "BYHOUR" => rule.by_hour = list().map(|v| v.parse().ok()).collect::<Option<_>>()?,
+                "BYMINUTE" => {
+                    rule.by_minute = list().map(|v| v.parse().ok()).collect::<Option<_>>()?
+                }
+                "BYSECOND" => {
+                    rule.by_second = list().map(|v| v.parse().ok()).collect::<Option<_>>()?
+                }
+                "BYSETPOS" => {
+                    rule.by_set_pos = list().map(|v| v.parse().ok()).collect::<Option<_>>()?
+                }
+                "WKST" => rule.week_start = weekday_index(value)?,
+                "BYWEEKNO" | "BYYEARDAY" => return None,
+                _ => {}
+            }
+        }
+
+        rule.frequency = frequency?;
+        Some(rule)
+    }
+
+    /// Wall-clock starts of the rule, `DTSTART` first, from `from` up
+    /// to `limit` (wall-clock bounds), `UNTIL` or `COUNT`.
+    ///
+    /// Starts before `from` are skipped without counting towards the
+    /// caps. Without `COUNT`, the periods before `from` are not walked
+    /// at all; with it, every earlier instance has to be counted.
+    fn expand(&self, dtstart: &DateTime, zones: &TimeZones, from: i64, limit: i64) -> Vec<i64> {
+        let first = dtstart.wall_clock_timestamp();
+        let mut out = Vec::new();
+        let mut generated = 1;
+
+        if first >= from {
+            out.push(first);
+        }
+
+        let until = self.until.as_ref().map(|until| match until.tz {
+            TimeZone::Utc => until.wall_clock_timestamp(),
+            // a floating or date UNTIL is read in the zone of DTSTART
+            _ => zones.resolve(until.wall_clock_timestamp(), &dtstart.tz),
+        });
+
+        let skipped = match self.count {
+            Some(_) => 0,
+            None => self.periods_before(first, from),
+        };
+
+        for period in skipped..skipped.saturating_add(MAX_PERIODS) {
+            let (period_start, candidates) = self.period(dtstart, period);
+
+            if period_start > limit {
+                break;
+            }
+
+            for candidate in candidates {
+                if candidate <= first {
+                    continue;
+                }
+                if candidate > limit {
+                    return out;
+                }
+                if let Some(until) = until {
+                    if zones.resolve(candidate, &dtstart.tz) > until {
+                        return out;
+                    }
+                }
+
+                generated += 1;
+                if candidate >= from {
+                    out.push(candidate);
+                }
+
+                if self.count.is_some_and(|count| generated >= count) || out.len() >= MAX_INSTANCES
+                {
+                    return out;
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Index of a period starting no later than the wall-clock `from`,
+    /// so every period before it only holds earlier starts. Periods are
+    /// assumed as long as they can be (31-day months, 366-day years),
+    /// which keeps the estimate below the exact index.
+    fn periods_before(&self, first: i64, from: i64) -> i64 {
+        let elapsed = from.saturating_sub(first);
+        let seconds: i64 = match self.frequency {
+            Frequency::Secondly => 1,
+            Frequency::Minutely => 60,
+            Frequency::Hourly => 3_600,
+            Frequency::Daily => 86_400,
+            Frequency::Weekly => 7 * 86_400,
+            Frequency::Monthly => 31 * 86_400,
+            Frequency::Yearly => 366 * 86_400,
+        };
+
+        (elapsed / seconds.saturating_mul(self.interval) - 1).max(0)
+    }
+
+    /// First wall-clock second of the `n`-th period after the one of
+    /// `dtstart`, plus the sorted candidate starts of that period.
+    fn period(&self, dtstart: &DateTime, n: i64) -> (i64, Vec<i64>) {
+        let first = dtstart.wall_clock_timestamp();
+        let first_day = first.div_euclid(86_400);
+        let step = n.saturating_mul(self.interval);
+
+        let (period_start, days) = match self.frequency {
+            Frequency::Secondly | Frequency::Minutely | Frequency::Hourly => {
+                let unit = match self.frequency {
+                    Frequency::Secondly => 1,
+                    Frequency::Minutely => 60,
+                    _ => 3_600,
+                };
+                let at = first.saturating_add(step.saturating_mul(unit));
+                let candidates = if self.matches_date(at.div_euclid(86_400))
+                    && self.matches_time(at.rem_euclid(86_400))
+                {
+                    vec![at]
+                } else {
+                    Vec::new()
+                };
+                return (at, candidates);
+            }
+            Frequency::Daily => {
+                let day = first_day.saturating_add(step);
+                let days = if self.matches_date(day) {
+                    vec![day]
+                } else {
+                    Vec::new()
+                };
+                (day * 86_400, days)
+            }
+            Frequency::Weekly => {
+                let weekday = weekday_of(first_day);
+                let offset = i64::from((weekday + 7 - self.week_start) % 7);
+                let week = first_day - offset + step.saturating_mul(7);
+                let weekdays: Vec<u8> = if self.by_day.is_empty() {
+                    vec![weekday]
+                } else {
+                    self.by_day.iter().map(|(_, wd)| *wd).collect()
+                };
+                let days = (week..week + 7)
+                    .filter(|day| weekdays.contains(&weekday_of(*day)))
+                    .filter(|day| self.in_by_month(*day))
+                    .collect();
+                (week * 86_400, days)
+            }
+            Frequency::Monthly => {
+                let index = i64::from(dtstart.year) * 12 + i64::from(dtstart.month) - 1 + step;
+                let (year, month) = (index.div_euclid(12), (index.rem_euclid(12) + 1) as u8);
+                let start = days_from_civil(year, month, 1);
+                let days = if self.by_month.is_empty() || self.by_month.contains(&month) {
+                    self.month_days(year, month, dtstart.day)
+                } else {
+                    Vec::new()
+                };
+                (start * 86_400, days)
+            }
+            Frequency::Yearly => {
+                let year = i64::from(dtstart.year) + step;
+                let start = days_from_civil(year, 1, 1);
+                (start * 86_400, self.year_days(year, dtstart))
+            }
+        };
+
+        let times = self.times(first.rem_euclid(86_400));
+        let mut candidates: Vec<i64> = days
+            .into_iter()
+            .flat_map(|day| times.iter().map(move |time| day * 86_400 + time))
+            .collect();
+
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        (period_start, self.apply_set_pos(candidates))
+    }
+
+    /// Days (since the epoch) of a `YEARLY` period.
+    fn year_days(&self, year: i64, dtstart: &DateTime) -> Vec<i64> {
+        // BYDAY without BYMONTH / BYMONTHDAY: ordinals count within the
+        // whole year
+        if self.by_month.is_empty() && self.by_month_day.is_empty() && !self.by_day.is_empty() {
+            let first = days_from_civil(year, 1, 1);
+            let last = days_from_civil(year + 1, 1, 1);
+            return self.select_by_day(first, last);
+        }
+
+        let months: Vec<u8> = if !self.by_month.is_empty() {
+            self.by_month.clone()
+        } else if !self.by_month_day.is_empty() {
+            (1..=12).collect()
+        } else {
+            vec![dtstart.month]
+        };
+
+        months
+            .into_iter()
+            .filter(|month| (1..=12).contains(month))
+            .flat_map(|month| self.month_days(year, month, dtstart.day))
+            .collect()
+    }
+
+    /// Days (since the epoch) of `month` selected by `BYMONTHDAY` /
+    /// `BYDAY`, or day `default` of the month.
+    fn month_days(&self, year: i64, month: u8, default: u8) -> Vec<i64> {
+        let last = days_in_month(year, month);
+        let first = days_from_civil(year, month, 1);
+
+        if !self.by_month_day.is_empty() {
+            return self
+                .by_month_day
+                .iter()
+                .filter_map(|n| match *n {
+                    n if n > 0 && n as u8 <= last => Some(n as u8),
+                    n if n < 0 && n.unsigned_abs() <= last => Some(last + 1 - n.unsigned_abs()),
+                    _ => None,
+                })
+                .map(|day| first + i64::from(day) - 1)
+                .filter(|day| self.by_day.is_empty() || self.in_by_weekday(*day))
+                .collect();
+        }
+
+        if !self.by_day.is_empty() {
+            return self.select_by_day(first, first + i64::from(last));
+        }
+
+        if default > last {
+            // e.g. the 31st in a 30-day month: no instance (RFC 5545)
+            return Vec::new();
+        }
+
+        vec![first + i64::from(default) - 1]
+    }
+
+    /// Days in `[from, to)` matching `BYDAY`, ordinals counted within
+    /// that span.
+    fn select_by_day(&self, from: i64, to: i64) -> Vec<i64> {
+        let mut days = Vec::new();
+
+        for (ordinal, weekday) in &self.by_day {
+            let matching: Vec<i64> = (from..to)
+                .filter(|day| weekday_of(*day) == *weekday)
+                .collect();
+
+            match *ordinal {
+                0 => days.extend(matching),
+                n if n > 0 => days.extend(matching.get(n as usize - 1)),
+                n => {
+                    let back = n.unsigned_abs() as usize;
+                    if back <= matching.len() {
+                        days.push(matching[matching.len() - back]);
+                    }
+                }
+            }
+        }
+
+        days.sort_unstable();
+        days.dedup();
+        days
+    }
+
+    /// Seconds-of-day candidates from `BYHOUR`, `BYMINUTE` and
+    /// `BYSECOND`, defaulting to the `DTSTART` time.
+    fn times(&self, default: i64) -> Vec<i64> {
+        let pick = |by: &Vec<u8>, default: i64| -> Vec<i64> {
+            if by.is_empty() {
+                vec![default]
+            } else {
+                by.iter().map(|v| i64::from(*v)).collect()
+            }
+        };
+
+        let hours = pick(&self.by_hour, default / 3_600);
+        let minutes = pick(&self.by_minute, default % 3_600 / 60);
+        let seconds = pick(&self.by_second, default % 60);
+
+        let mut times = Vec::new();
+        for h in &hours {
+            for m in &minutes {
+                for s in &seconds {
+                    times.push(h * 3_600 + m * 60 + s);
+                }
+            }
+        }
+        times
+    }
+
+    fn apply_set_pos(&self, candidates: Vec<i64>) -> Vec<i64> {
+        if self.by_set_pos.is_empty() {
+            return candidates;
+        }
+
+        let len = candidates.len() as i32;
+        let mut selected: Vec<i64> = self
+            .by_set_pos
+            .iter()
+            .filter_map(|pos| match *pos {
+                pos if pos > 0 && pos <= len => Some(candidates[pos as usize - 1]),
+                pos if pos < 0 && -pos <= len => Some(candidates[(len + pos) as usize]),
+                _ => None,
+            })
+            .collect();
+
+        selected.sort_unstable();
+        selected.dedup();
+        selected
+    }
+
+    /// `BYMONTH`, `BYMONTHDAY` and `BYDAY` used as filters (daily and
+    /// sub-daily rules).
+    fn matches_date(&self, day: i64) -> bool {
+        let (year, month, date) = civil_from_days(day);
+        let last = days_in_month(year, month);
+
+        let month_day = self.by_month_day.is_empty()
+            || self.by_month_day.iter().any(|n| match *n {
+                n if n > 0 => n as u8 == date,
+                n => last + 1 - n.unsigned_abs() == date,
+            });
+
+        self.in_by_month(day) && month_day && (self.by_day.is_empty() || self.in_by_weekday(day))
+    }
+
+    /// `BYHOUR`, `BYMINUTE` and `BYSECOND` used as filters (sub-daily
+    /// rules).
+    fn matches_time(&self, seconds: i64) -> bool {
+        let matches = |by: &Vec<u8>, value: i64| by.is_empty() || by.contains(&(value as u8));
+
+        matches(&self.by_hour, seconds / 3_600)
+            && matches(&self.by_minute, seconds % 3_600 / 60)
+            && matches(&self.by_second, seconds % 60)
+    }
+
+    fn in_by_month(&self, day: i64) -> bool {
+        self.by_month.is_empty() || self.by_month.contains(&civil_from_days(day).1)
+    }
+
+    fn in_by_weekday(&self, day: i64) -> bool {
+        let weekday = weekday_of(day);
+        self.by_day.iter().any(|(_, wd)| *wd == weekday)
+    }
+}
+
+/// Parses a `BYDAY` entry (`MO`, `2TU`, `-1SU`).
+fn parse_by_day(value: &str) -> Option<(i8, u8)> {
+    let split = value.len().checked_sub(2)?;
+    let (ordinal, weekday) = value.split_at(split);
+    let ordinal = match ordinal {
+        "" => 0,
+        ordinal => ordinal.trim_start_matches('+').parse().ok()?,
+    };
+    Some((ordinal, weekday_index(weekday)?))
+}
+
+/// `SU` = 0 ... `SA` = 6 (1970-01-01 was a Thursday).
+fn weekday_of(day: i64) -> u8 {
+    (day + 4).rem_euclid(7) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::String};
+
+    use super::*;
+    use crate::item::fixture;
+
+    /// An item holding one `recurring` event per entry of
+    /// `components`, each made of content lines separated by `|`.
+    fn item(components: &[&str]) -> CalendarItem {
+        let events: Vec<String> = components
+            .iter()
+            .map(|lines| format!("UID:recurring|{lines}"))
+            .collect();
+        let events: Vec<&str> = events.iter().map(String::as_str).collect();
+        fixture::events("recurring", &events)
+    }
+
+    fn expand(item: &CalendarItem, start: &str, end: Option<&str>) -> Vec<Occurrence> {
+        item.occurrences(&TimeRange::new(Some(start), end).unwrap())
+    }
+
+    /// Starts of the occurrences of `component` during January and
+    /// February 2025.
+    fn starts(component: &str) -> Vec<String> {
+        let item = item(&[component]);
+        expand(&item, "20250101T000000Z", Some("20250301T000000Z"))
+            .into_iter()
+            .map(|occurrence| occurrence.start.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn expands_daily_and_weekly_rules_with_count_and_until() {
+        let table: &[(&str, &[&str])] = &[
+            (
+                "DTSTART:20250106T090000Z|RRULE:FREQ=DAILY;COUNT=3",
+                &["20250106T090000Z", "20250107T090000Z", "20250108T090000Z"],
+            ),
+            (
+                "DTSTART:20250106T090000Z|RRULE:FREQ=DAILY;INTERVAL=2;COUNT=3",
+                &["20250106T090000Z", "20250108T090000Z", "20250110T090000Z"],
+            ),
+            // UNTIL is inclusive
+            (
+                "DTSTART:20250106T090000Z|RRULE:FREQ=DAILY;UNTIL=20250108T090000Z",
+                &["20250106T090000Z", "20250107T090000Z", "20250108T090000Z"],
+            ),
+            (
+                "DTSTART:20250106T090000Z|RRULE:FREQ=DAILY;UNTIL=20250108T085959Z",
+                &["20250106T090000Z", "20250107T090000Z"],
+            ),
+            (
+                "DTSTART:20250106T090000Z|RRULE:FREQ=WEEKLY;COUNT=3",
+                &["20250106T090000Z", "20250113T090000Z", "20250120T090000Z"],
+            ),
+            (
+                "DTSTART:20250106T090000Z|RRULE:FREQ=WEEKLY;UNTIL=20250120T090000Z",
+                &["20250106T090000Z", "20250113T090000Z", "20250120T090000Z"],
+            ),
+            (
+                "DTSTART;VALUE=DATE:20250106|RRULE:FREQ=WEEKLY;UNTIL=20250113",
+                &["20250106", "20250113"],
+            ),
+        ];
+
+        for (component, expected) in table {
+            assert_eq!(starts(component), *expected, "{component}");
+        }
+    }
+
+    #[test]
+    fn expands_byday_and_bymonthday() {
+        let table: &[(&str, &[&str])] = &[
+            (
+                "DTSTART:20250106T090000Z|RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4",
+                &[
+                    "20250106T090000Z",
+                    "20250108T090000Z",
+                    "20250113T090000Z",
+                    "20250115T090000Z",
+                ],
+            ),
+            // second Tuesday of the month
+            (
+                "DTSTART:20250114T090000Z|RRULE:FREQ=MONTHLY;BYDAY=2TU;COUNT=2",
+                &["20250114T090000Z", "20250211T090000Z"],
+            ),
+            // last Friday of the month
+            (
+                "DTSTART:20250131T090000Z|RRULE:FREQ=MONTHLY;BYDAY=-1FR;COUNT=2",
+                &["20250131T090000Z", "20250228T090000Z"],
+            ),
+            (
+                "DTSTART:20250115T090000Z|RRULE:FREQ=MONTHLY;BYMONTHDAY=15,-1;COUNT=4",
+                &[
+                    "20250115T090000Z",
+                    "20250131T090000Z",
+                    "20250215T090000Z",
+                    "20250228T090000Z",
+                ],
+            ),
+        ];
+
+        for (component, expected) in table {
+            assert_eq!(starts(component), *expected, "{component}");
+        }
+    }
+
+    #[test]
+    fn removes_exdates_and_adds_rdates() {
+        let starts = starts(
+            "DTSTART:20250106T090000Z|RRULE:FREQ=DAILY;COUNT=4\
+             |EXDATE:20250107T090000Z,20250108T090000Z\
+             |RDATE:20250120T090000Z|RDATE:20250106T090000Z",
+        );
+
+        assert_eq!(
+            starts,
+            ["20250106T090000Z", "20250109T090000Z", "20250120T090000Z"]
+        );
+    }
+
+    #[test]
+    fn applies_recurrence_id_overrides() {
+        let item = item(&[
+            "DTSTART:20250106T090000Z|DURATION:PT1H|RRULE:FREQ=DAILY;COUNT=3",
+            "RECURRENCE-ID:20250107T090000Z|DTSTART:20250107T140000Z\
+             |DTEND:20250107T143000Z|SUMMARY:moved",
+        ]);
+
+        let occurrences = expand(&item, "20250101T000000Z", Some("20250201T000000Z"));
+        assert_eq!(occurrences.len(), 3);
+
+        let moved = &occurrences[1];
+        assert!(moved.overridden);
+        assert_eq!(moved.uid, "recurring");
+        assert_eq!(moved.recurrence_id, DateTime::utc(2025, 1, 7, 9, 0, 0));
+        assert_eq!(moved.start, DateTime::utc(2025, 1, 7, 14, 0, 0));
+        assert_eq!(moved.end, Some(DateTime::utc(2025, 1, 7, 14, 30, 0)));
+
+        assert!(!occurrences[0].overridden);
+        assert_eq!(
+            occurrences[0].end,
+            Some(DateTime::utc(2025, 1, 6, 10, 0, 0))
+        );
+        assert_eq!(occurrences[2].start, DateTime::utc(2025, 1, 8, 9, 0, 0));
+
+        // the override is matched through its own start, not the
+        // generated one
+        let afternoon = expand(&item, "20250107T120000Z", Some("20250107T160000Z"));
+        assert_eq!(afternoon.len(), 1);
+        assert!(afternoon[0].overridden);
+        let morning = expand(&item, "20250107T080000Z", Some("20250107T120000Z"));
+        assert!(morning.is_empty());
+    }
+
+    #[test]
+    fn stops_expanding_at_the_range_end() {
+        let item = item(&["DTSTART:20250106T090000Z|RRULE:FREQ=DAILY"]);
+
+        let days = expand(&item, "20250106T000000Z", Some("20250110T000000Z"));
+        assert_eq!(days.len(), 4);
+        assert_eq!(days[3].start, DateTime::utc(2025, 1, 9, 9, 0, 0));
+
+        let later = expand(&item, "20300301T000000Z", Some("20300303T000000Z"));
+        let later: Vec<_> = later.iter().map(|o| o.start.clone()).collect();
+        assert_eq!(
+            later,
+            [
+                DateTime::utc(2030, 3, 1, 9, 0, 0),
+                DateTime::utc(2030, 3, 2, 9, 0, 0)
+            ]
+        );
+
+        // an open-ended range stops at the instance cap
+        let open = expand(&item, "20250106T000000Z", None);
+        assert_eq!(open.len(), MAX_INSTANCES);
+    }
+
+    #[test]
+    fn expands_long_running_rules_years_later() {
+        let hourly = item(&["DTSTART:20000101T000000Z|RRULE:FREQ=HOURLY"]);
+        let hours = expand(&hourly, "20300301T000000Z", Some("20300301T030000Z"));
+        let hours: Vec<_> = hours.iter().map(|o| o.start.clone()).collect();
+        assert_eq!(
+            hours,
+            [
+                DateTime::utc(2030, 3, 1, 0, 0, 0),
+                DateTime::utc(2030, 3, 1, 1, 0, 0),
+                DateTime::utc(2030, 3, 1, 2, 0, 0)
+            ]
+        );
+
+        let daily = item(&["DTSTART:19700101T090000Z|RRULE:FREQ=DAILY;INTERVAL=2"]);
+        let days = expand(&daily, "20300301T000000Z", Some("20300305T000000Z"));
+        let days: Vec<_> = days.iter().map(|o| o.start.clone()).collect();
+        assert_eq!(
+            days,
+            [
+                DateTime::utc(2030, 3, 1, 9, 0, 0),
+                DateTime::utc(2030, 3, 3, 9, 0, 0)
+            ]
+        );
+
+        // the instance cap counts from the range start, less the two
+        // days of time zone slack generated before it
+        let open = expand(&hourly, "20300301T000000Z", None);
+        assert_eq!(open.len(), MAX_INSTANCES - 48);
+        assert_eq!(open[0].start, DateTime::utc(2030, 3, 1, 0, 0, 0));
+
+        // COUNT still counts from DTSTART
+        let counted = item(&["DTSTART:20000101T000000Z|RRULE:FREQ=DAILY;COUNT=3"]);
+        assert!(expand(&counted, "20300301T000000Z", None).is_empty());
+    }
+
+    #[test]
+    fn keeps_the_local_time_across_dst() {
+        let item = fixture::item(
+            "recurring",
+            &[
+                fixture::PARIS,
+                "BEGIN:VEVENT|UID:recurring|DTSTART;TZID=Europe/Paris:20250329T090000\
+                 |RRULE:FREQ=DAILY;COUNT=2|END:VEVENT",
+            ],
+        );
+
+        let all = expand(&item, "20250301T000000Z", Some("20250401T000000Z"));
+        let starts: Vec<_> = all.iter().map(|o| o.start.to_string()).collect();
+        assert_eq!(starts, ["20250329T090000", "20250330T090000"]);
+
+        // 09:00 in Paris is 08:00 UTC before the change, 07:00 after
+        let before = expand(&item, "20250329T080000Z", Some("20250329T080001Z"));
+        assert_eq!(before.len(), 1);
+        let after = expand(&item, "20250330T070000Z", Some("20250330T070001Z"));
+        assert_eq!(after.len(), 1);
+        assert!(expand(&item, "20250330T080000Z", Some("20250330T080001Z")).is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+//! Full-text search over the human-readable properties of items.
+//!
+//! A query matches a field when the case-folded field value contains
+//! the case-folded query. ATTENDEE matches on both its address and its
+//! `CN` parameter, CATEGORIES on its comma-separated list.
+
+use alloc::vec::Vec;
+
+use super::{CalendarItem, CalendarItemKind, filter::fold, ical::Component};
+
+/// Property a search query is matched against.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SearchField {
+    /// `SUMMARY` property.
+    Summary,
+    /// `DESCRIPTION` property.
+    Description,
+    /// `LOCATION` property.
+    Location,
+    /// `ATTENDEE` property, address or common name.
+    Attendee,
+    /// `CATEGORIES` property.
+    Categories,
+}
+
+impl SearchField {
+    /// Every searched field, in the order they are tried.
+    pub const ALL: [Self; 5] = [
+        Self::Summary,
+        Self::Description,
+        Self::Location,
+        Self::Attendee,
+        Self::Categories,
+    ];
+
+    /// iCalendar property name (`SUMMARY`, `ATTENDEE`, ...).
+    pub fn property_name(&self) -> &'static str {
+        match self {
+            Self::Summary => "SUMMARY",
+            Self::Description => "DESCRIPTION",
+            Self::Location => "LOCATION",
+            Self::Attendee => "ATTENDEE",
+            Self::Categories => "CATEGORIES",
+        }
+    }
+}
+
+/// Item matching a search query, with the field that matched.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct SearchHit {
+    /// The matching item.
+    pub item: CalendarItem,
+
+    /// First field, in [`SearchField::ALL`] order, containing the
+    /// query.
+    pub field: SearchField,
+}
+
+impl CalendarItem {
+    /// First field, in [`SearchField::ALL`] order, of one of the
+    /// `VEVENT`, `VTODO` or `VJOURNAL` components containing `query`
+    /// after Unicode case folding. A blank query matches nothing.
+    pub fn matching_field(&self, query: &str) -> Option<SearchField> {
+        let query = fold(query.trim());
+        if query.is_empty() {
+            return None;
+        }
+
+        let calendar = Component::parse(&self.contents)?;
+        let components: Vec<&Component> = calendar
+            .components
+            .iter()
+            .filter(|c| CalendarItemKind::from_component_name(&c.name).is_some())
+            .collect();
+
+        SearchField::ALL.into_iter().find(|field| {
+            components
+                .iter()
+                .flat_map(|component| component.properties(field.property_name()))
+                .any(|property| {
+                    let common_name = match field {
+                        SearchField::Attendee => property
+                            .params
+                            .iter()
+                            .filter(|(key, _)| key == "CN")
+                            .flat_map(|(_, values)| values)
+                            .any(|value| fold(value).contains(&query)),
+                        _ => false,
+                    };
+                    common_name || fold(&property.text()).contains(&query)
+                })
+        })
+    }
+}
+
+/// Keeps the `items` matching `query`, sorted by id, paired with their
+/// matched field.
+#[cfg(any(feature = "vdir", feature = "webdav", feature = "client"))]
+pub(crate) fn search_items(items: Vec<CalendarItem>, query: &str) -> Vec<SearchHit> {
+    let mut hits: Vec<SearchHit> = items
+        .into_iter()
+        .filter_map(|item| {
+            let field = item.matching_field(query)?;
+            Some(SearchHit { item, field })
+        })
+        .collect();
+
+    hits.sort_by(|a, b| a.item.id.cmp(&b.item.id));
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::fixture;
+
+    #[test]
+    fn finds_the_first_matching_field() {
+        let attendee = "ATTENDEE;CN=Jane Doe:mailto:jdoe@example.com";
+
+        for (lines, query, expected) in [
+            (
+                "SUMMARY:Budget review",
+                "budget",
+                Some(SearchField::Summary),
+            ),
+            (
+                "DESCRIPTION:budget|SUMMARY:Budget review",
+                "BUDGET",
+                Some(SearchField::Summary),
+            ),
+            (
+                "DESCRIPTION:Q3 numbers",
+                "q3",
+                Some(SearchField::Description),
+            ),
+            ("LOCATION:Room 4", "room", Some(SearchField::Location)),
+            (attendee, "jane", Some(SearchField::Attendee)),
+            (attendee, "jdoe@", Some(SearchField::Attendee)),
+            ("CATEGORIES:work,ops", "ops", Some(SearchField::Categories)),
+            (
+                "CATEGORIES:work|CATEGORIES:Ops",
+                "OPS",
+                Some(SearchField::Categories),
+            ),
+            ("SUMMARY:Straße", "STRASSE", Some(SearchField::Summary)),
+            ("SUMMARY:a\\, b", "a, b", Some(SearchField::Summary)),
+            ("UID:budget", "budget", None),
+            ("SUMMARY:Budget review", "  ", None),
+        ] {
+            let item = fixture::events("1", &[lines]);
+            assert_eq!(item.matching_field(query), expected, "{query:?} in {lines}");
+        }
+    }
+
+    #[cfg(any(feature = "vdir", feature = "webdav", feature = "client"))]
+    #[test]
+    fn keeps_matching_items_sorted_by_id() {
+        use alloc::{string::String, vec};
+
+        let items = vec![
+            fixture::events("c", &["SUMMARY:Standup"]),
+            fixture::events("a", &["LOCATION:Standup room"]),
+            fixture::events("b", &["SUMMARY:Lunch"]),
+        ];
+
+        let hits: Vec<_> = search_items(items, "standup")
+            .into_iter()
+            .map(|hit| (hit.item.id, hit.field))
+            .collect();
+        assert_eq!(
+            hits,
+            [
+                (String::from("a"), SearchField::Location),
+                (String::from("c"), SearchField::Summary),
+            ]
+        );
+    }
+}
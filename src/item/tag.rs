@@ -0,0 +1,22 @@
+//! Synthetic entity tags for backends and callers that have none:
+//! FNV-1a 64-bit hashes rendered as 16 hex digits, stable across runs
+//! and machines.
+
+use alloc::{format, string::String};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Tag of `contents`: changes whenever the bytes do.
+pub(crate) fn content_tag(contents: &[u8]) -> String {
+    digest([contents])
+}
+
+/// Tag of the concatenation of `parts`.
+pub(crate) fn digest<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> String {
+    let hash = parts.into_iter().flatten().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    });
+
+    format!("{hash:016x}")
+}
@@ -0,0 +1,423 @@
+//! `TZID` resolution against the `VTIMEZONE` components embedded in a
+//! VCALENDAR (RFC 5545 section 3.6.5).
+//!
+//! Only what real-world time zone definitions use is supported: one-off
+//! observances, `RDATE` lists and yearly `RRULE`s driven by `BYMONTH`
+//! plus either `BYDAY` (`-1SU`, `2SU`) or `BYMONTHDAY`, bounded by
+//! `UNTIL`.
+
+use alloc::vec::Vec;
+
+use crate::item::{
+    DateTime, TimeZone,
+    datetime::{civil_from_days, days_from_civil, days_in_month},
+    ical::Component,
+};
+
+/// Resolves [`DateTime`] values of one VCALENDAR to UTC timestamps.
+///
+/// Floating times and `DATE` values have no zone of their own; they
+/// are read as UTC, which is what CalDAV servers do for a collection
+/// without a `calendar-timezone`. A `TZID` without matching
+/// `VTIMEZONE` falls back to the same rule.
+pub(crate) struct TimeZones<'a> {
+    calendar: &'a Component,
+}
+
+impl<'a> TimeZones<'a> {
+    pub fn new(calendar: &'a Component) -> Self {
+        Self { calendar }
+    }
+
+    /// Seconds since the Unix epoch, in UTC.
+    pub fn timestamp(&self, datetime: &DateTime) -> i64 {
+        self.resolve(datetime.wall_clock_timestamp(), &datetime.tz)
+    }
+
+    /// Timestamp of `datetime + duration`. The duration is added in
+    /// wall-clock time before the zone is applied, so `P1D` stays a
+    /// nominal day across DST changes.
+    #[cfg(any(feature = "vdir", feature = "memory"))]
+    pub fn timestamp_after(&self, datetime: &DateTime, duration: &crate::item::Duration) -> i64 {
+        let wall = datetime.wall_clock_timestamp() + duration.as_seconds();
+        self.resolve(wall, &datetime.tz)
+    }
+
+    /// Timestamp of wall-clock time `wall` read in zone `tz`.
+    pub fn resolve(&self, wall: i64, tz: &TimeZone) -> i64 {
+        let TimeZone::Id(tzid) = tz else {
+            return wall;
+        };
+
+        let vtimezone = self.calendar.components.iter().find(|c| {
+            c.name == "VTIMEZONE"
+                && c.property("TZID")
+                    .is_some_and(|p| p.value.trim() == tzid.as_str())
+        });
+
+        match vtimezone {
+            Some(vtimezone) => wall - utc_offset(vtimezone, wall),
+            None => wall,
+        }
+    }
+}
+
+/// UTC offset in seconds in effect at local wall-clock time `wall`.
+fn utc_offset(vtimezone: &Component, wall: i64) -> i64 {
+    let (year, _, _) = civil_from_days(wall.div_euclid(86_400));
+
+    // (onset in local time, offset before, offset after)
+    let mut onsets: Vec<(i64, i64, i64)> = Vec::new();
+
+    for observance in &vtimezone.components {
+        if observance.name != "STANDARD" && observance.name != "DAYLIGHT" {
+            continue;
+        }
+
+        let Some(start) = observance
+            .property("DTSTART")
+            .and_then(|p| DateTime::parse(&p.value, None))
+        else {
+            continue;
+        };
+        let Some(from) = observance
+            .property("TZOFFSETFROM")
+            .and_then(|p| offset(&p.value))
+        else {
+            continue;
+        };
+        let Some(to) = observance
+            .property("TZOFFSETTO")
+            .and_then(|p| offset(&p.value))
+        else {
+            continue;
+        };
+
+        let first = start.wall_clock_timestamp();
+        onsets.push((first, from, to));
+
+        for rdate in observance.properties("RDATE") {
+            for value in rdate.value.split(',') {
+                if let Some(date) = DateTime::parse(value, None) {
+                    onsets.push((date.wall_clock_timestamp(), from, to));
+                }
+            }
+        }
+
+        if let Some(rrule) = observance.property("RRULE") {
+            for year in [year - 1, year] {
+                let Some(onset) = yearly_onset(&rrule.value, &start, year) else {
+                    continue;
+                };
+                if onset > first && within_until(&rrule.value, onset - from) {
+                    onsets.push((onset, from, to));
+                }
+            }
+        }
+    }
+
+    let current = onsets
+        .iter()
+        .filter(|(onset, _, _)| *onset <= wall)
+        .max_by_key(|(onset, _, _)| *onset);
+
+    match current {
+        Some((_, _, to)) => *to,
+        // before every onset: the offset the earliest one moves away from
+        None => onsets
+            .iter()
+            .min_by_key(|(onset, _, _)| *onset)
+            .map(|(_, from, _)| *from)
+            .unwrap_or_default(),
+    }
+}
+
+/// Onset of a yearly observance rule during `year`, in local time.
+fn yearly_onset(rrule: &str, start: &DateTime, year: i64) -> Option<i64> {
+    let mut freq = None;
+    let mut month = start.month;
+    let mut byday = None;
+    let mut monthday = None;
+
+    for part in rrule.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.trim().to_ascii_uppercase().as_str() {
+            "FREQ" => freq = Some(value.trim().to_ascii_uppercase()),
+            "BYMONTH" => month = value.trim().parse().ok()?,
+            "BYDAY" => byday = Some(value.trim().to_ascii_uppercase()),
+            "BYMONTHDAY" => monthday = Some(value.trim().parse::<i8>().ok()?),
+            _ => {}
+        }
+    }
+
+    if freq.as_deref() != Some("YEARLY") || !(1..=12).contains(&month) {
+        return None;
+    }
+
+    let last = days_in_month(year, month);
+    let day = match (byday, monthday) {
+        (Some(byday), _) => nth_weekday(&byday, year, month)?,
+        (None, Some(n)) if n > 0 => n as u8,
+        (None, Some(n)) => (i16::from(last) + i16::from(n) + 1) as u8,
+        (None, None) => start.day,
+    };
+
+    if day == 0 || day > last {
+        return None;
+    }
+
+    let time = start.time.unwrap_or_default();
+    Some(
+        days_from_civil(year, month, day) * 86_400
+            + i64::from(time.hour) * 3_600
+            + i64::from(time.minute) * 60
+            + i64::from(time.second),
+    )
+}
+
+/// Day of the month of a `BYDAY` entry such as `-1SU` or `2SU`.
+fn nth_weekday(byday: &str, year: i64, month: u8) -> Option<u8> {
+    let byday = byday.split(',').next()?.trim();
+    let split = byday.len().checked_sub(2)?;
+    let (ordinal, weekday) = byday.split_at(split);
+    let weekday = weekday_index(weekday)?;
+    let ordinal: i8 = match ordinal {
+        "" | "+" => 1,
+        ordinal => ordinal.trim_start_matches('+').parse().ok()?,
+    };
+
+    let last = days_in_month(year, month);
+    let first_weekday = (days_from_civil(year, month, 1) + 4).rem_euclid(7) as u8;
+    let first = 1 + (weekday + 7 - first_weekday) % 7;
+
+    if ordinal > 5 {
+        return None;
+    }
+
+    if ordinal > 0 {
+        let day = first + (ordinal as u8 - 1) * 7;
+        (day <= last).then_some(day)
+    } else {
+        let count = (last - first) / 7 + 1;
+        let nth = count.checked_sub(ordinal.unsigned_abs() - 1)?;
+        (nth >= 1).then(|| first + (nth - 1) * 7)
+    }
+}
+
+/// `SU` = 0 ... `SA` = 6, the same numbering as `(days + 4) % 7`.
+pub(crate) fn weekday_index(weekday: &str) -> Option<u8> {
+    ["SU", "MO", "TU", "WE", "TH", "FR", "SA"]
+        .iter()
+        .position(|day| day.eq_ignore_ascii_case(weekday))
+        .map(|i| i as u8)
+}
+
+/// Whether `utc` (an onset converted to UTC) is not past the rule's
+/// `UNTIL`, when any.
+fn within_until(rrule: &str, utc: i64) -> bool {
+    let until = rrule.split(';').find_map(|part| {
+        let (key, value) = part.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("UNTIL")
+            .then(|| DateTime::parse(value, None))?
+    });
+
+    match until {
+        Some(until) => utc <= until.wall_clock_timestamp(),
+        None => true,
+    }
+}
+
+/// Parses a `UTC-OFFSET` value (`+0100`, `-0530`, `+013000`) into
+/// seconds.
+fn offset(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (sign, digits) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+
+    if !(digits.len() == 4 || digits.len() == 6) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let hours: i64 = digits[0..2].parse().ok()?;
+    let minutes: i64 = digits[2..4].parse().ok()?;
+    let seconds: i64 = digits.get(4..6).map_or(Some(0), |s| s.parse().ok())?;
+
+    Some(sign * (hours * 3_600 + minutes * 60 + seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use super::*;
+
+    const PARIS: &[&str] = &[
+        "BEGIN:VTIMEZONE",
+        "TZID:Europe/Paris",
+        "BEGIN:DAYLIGHT",
+        "DTSTART:19810329T020000",
+        "TZOFFSETFROM:+0100",
+        "TZOFFSETTO:+0200",
+        "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+        "END:DAYLIGHT",
+        "BEGIN:STANDARD",
+        "DTSTART:19961027T030000",
+        "TZOFFSETFROM:+0200",
+        "TZOFFSETTO:+0100",
+        "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+        "END:STANDARD",
+        "END:VTIMEZONE",
+    ];
+
+    const NEW_YORK: &[&str] = &[
+        "BEGIN:VTIMEZONE",
+        "TZID:America/New_York",
+        "BEGIN:DAYLIGHT",
+        "DTSTART:20070311T020000",
+        "TZOFFSETFROM:-0500",
+        "TZOFFSETTO:-0400",
+        "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU",
+        "END:DAYLIGHT",
+        "BEGIN:STANDARD",
+        "DTSTART:20071104T020000",
+        "TZOFFSETFROM:-0400",
+        "TZOFFSETTO:-0500",
+        "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU",
+        "END:STANDARD",
+        "END:VTIMEZONE",
+    ];
+
+    /// Daylight saving time abandoned in 2010: the rules stop at their
+    /// `UNTIL`, the last onset stays in effect.
+    const ABANDONED: &[&str] = &[
+        "BEGIN:VTIMEZONE",
+        "TZID:Test/Abandoned",
+        "BEGIN:DAYLIGHT",
+        "DTSTART:19800330T020000",
+        "TZOFFSETFROM:+0100",
+        "TZOFFSETTO:+0200",
+        "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU;UNTIL=20100328T010000Z",
+        "END:DAYLIGHT",
+        "BEGIN:STANDARD",
+        "DTSTART:19801026T030000",
+        "TZOFFSETFROM:+0200",
+        "TZOFFSETTO:+0100",
+        "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU;UNTIL=20101031T010000Z",
+        "END:STANDARD",
+        "END:VTIMEZONE",
+    ];
+
+    /// One-off observances listed through `RDATE`.
+    const RDATES: &[&str] = &[
+        "BEGIN:VTIMEZONE",
+        "TZID:Test/Rdates",
+        "BEGIN:STANDARD",
+        "DTSTART:19700101T000000",
+        "TZOFFSETFROM:+0300",
+        "TZOFFSETTO:+0300",
+        "END:STANDARD",
+        "BEGIN:DAYLIGHT",
+        "DTSTART:20200301T000000",
+        "RDATE:20200301T000000,20210301T000000",
+        "TZOFFSETFROM:+0300",
+        "TZOFFSETTO:+0400",
+        "END:DAYLIGHT",
+        "BEGIN:STANDARD",
+        "DTSTART:20200901T000000",
+        "RDATE:20210901T000000",
+        "TZOFFSETFROM:+0400",
+        "TZOFFSETTO:+0300",
+        "END:STANDARD",
+        "END:VTIMEZONE",
+    ];
+
+    fn calendar(zones: &[&[&str]]) -> Component {
+        let mut lines: Vec<&str> = Vec::from(["BEGIN:VCALENDAR", "VERSION:2.0"]);
+        for zone in zones {
+            lines.extend_from_slice(zone);
+        }
+        lines.push("END:VCALENDAR");
+        Component::parse(lines.join("\r\n").as_bytes()).unwrap()
+    }
+
+    fn stamp(value: &str) -> i64 {
+        DateTime::parse(value, None).unwrap().wall_clock_timestamp()
+    }
+
+    #[test]
+    fn resolves_local_times_across_dst_transitions() {
+        let calendar = calendar(&[PARIS, NEW_YORK, ABANDONED, RDATES]);
+        let zones = TimeZones::new(&calendar);
+
+        // (TZID, local wall-clock time, expected UTC time)
+        let table = [
+            ("Europe/Paris", "20250115T120000", "20250115T110000Z"),
+            ("Europe/Paris", "20250715T120000", "20250715T100000Z"),
+            // spring forward on the last Sunday of March, 02:00 local
+            ("Europe/Paris", "20250330T015959", "20250330T005959Z"),
+            ("Europe/Paris", "20250330T030000", "20250330T010000Z"),
+            // fall back on the last Sunday of October, 03:00 local
+            ("Europe/Paris", "20251026T015959", "20251025T235959Z"),
+            ("Europe/Paris", "20251026T030000", "20251026T020000Z"),
+            // year before the first onset of the rule
+            ("Europe/Paris", "19800615T120000", "19800615T110000Z"),
+            // second Sunday of March, first Sunday of November
+            ("America/New_York", "20250308T120000", "20250308T170000Z"),
+            ("America/New_York", "20250309T120000", "20250309T160000Z"),
+            ("America/New_York", "20251101T120000", "20251101T160000Z"),
+            ("America/New_York", "20251102T120000", "20251102T170000Z"),
+            ("Test/Abandoned", "20090715T120000", "20090715T100000Z"),
+            ("Test/Abandoned", "20250715T120000", "20250715T110000Z"),
+            ("Test/Rdates", "20210615T120000", "20210615T080000Z"),
+            ("Test/Rdates", "20211015T120000", "20211015T090000Z"),
+            ("Test/Rdates", "20220615T120000", "20220615T090000Z"),
+        ];
+
+        for (tzid, local, utc) in table {
+            let tz = TimeZone::Id(String::from(tzid));
+            assert_eq!(
+                zones.resolve(stamp(local), &tz),
+                stamp(utc),
+                "{local} in {tzid}"
+            );
+        }
+    }
+
+    #[test]
+    fn reads_floating_utc_and_unknown_zones_as_utc() {
+        let calendar = calendar(&[PARIS]);
+        let zones = TimeZones::new(&calendar);
+        let wall = stamp("20250715T120000");
+
+        assert_eq!(zones.resolve(wall, &TimeZone::Floating), wall);
+        assert_eq!(zones.resolve(wall, &TimeZone::Utc), wall);
+        assert_eq!(
+            zones.resolve(wall, &TimeZone::Id("Mars/Olympus".into())),
+            wall
+        );
+
+        let date = DateTime::date(2025, 7, 15);
+        assert_eq!(zones.timestamp(&date), stamp("20250715T000000Z"));
+    }
+
+    #[cfg(any(feature = "vdir", feature = "memory"))]
+    #[test]
+    fn adds_durations_in_wall_clock_time() {
+        let calendar = calendar(&[PARIS]);
+        let zones = TimeZones::new(&calendar);
+        let start = DateTime::parse("20250329T120000", Some("Europe/Paris")).unwrap();
+        let one_day = crate::item::Duration {
+            days: 1,
+            ..Default::default()
+        };
+
+        // the day of the spring transition only lasts 23 hours
+        let end = zones.timestamp_after(&start, &one_day);
+        assert_eq!(end - zones.timestamp(&start), 23 * 3_600);
+        assert_eq!(end, stamp("20250330T100000Z"));
+    }
+}
@@ -1,6 +1,5 @@
 //! Calendar item shared across all protocols.
 
-#[cfg(feature = "parser")]
 use core::str::from_utf8;
 
 use alloc::{string::String, vec::Vec};
@@ -24,8 +23,9 @@ pub struct CalendarItem {
     /// Parent calendar identifier.
     pub calendar_id: String,
 
-    /// Entity tag (RFC 9110 section 8.8.3, without surrounding quotes)
-    /// when the backend exposes it; vdir surfaces `None`.
+    /// Entity tag (RFC 9110 section 8.8.3) when the backend exposes
+    /// it, kept verbatim (WebDAV tags keep their quotes and weak `W/`
+    /// marker). Vdir derives it from a hash of the file contents.
     #[cfg_attr(feature = "serde", serde(default))]
     pub etag: Option<String>,
 
@@ -48,6 +48,143 @@ impl CalendarItem {
             _ => None,
         }
     }
+
+    /// Kind of the item, derived from the first `VEVENT`, `VTODO` or
+    /// `VJOURNAL` child of the VCALENDAR (time zones and other
+    /// components are skipped). Returns `None` when there is none.
+    pub fn kind(&self) -> Option<CalendarItemKind> {
+        let text = from_utf8(&self.contents).ok()?;
+
+        text.lines().find_map(|line| {
+            let (key, name) = line.trim_end().split_once(':')?;
+            if !key.eq_ignore_ascii_case("BEGIN") {
+                return None;
+            }
+            CalendarItemKind::from_component_name(name)
+        })
+    }
+
+    /// Whether the item kind is one of `kinds`; an empty slice matches
+    /// every item.
+    #[cfg(any(feature = "vdir", feature = "memory"))]
+    pub(crate) fn matches_kinds(&self, kinds: &[CalendarItemKind]) -> bool {
+        kinds.is_empty() || self.kind().is_some_and(|kind| kinds.contains(&kind))
+    }
+}
+
+/// Changes of a calendar since a previous synchronization, returned by
+/// `sync_items`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct CalendarItemSync {
+    /// Items created or modified since the previous token.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub changed: Vec<CalendarItem>,
+
+    /// Ids of the items deleted since the previous token (for WebDAV,
+    /// the last segment of their href, without `.ics`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub deleted: Vec<String>,
+
+    /// Token to pass to the next `sync_items` call.
+    pub sync_token: String,
+
+    /// Whether the changes were computed from a full listing diffed by
+    /// ETag against the known items, because there was no previous
+    /// token or the backend rejected it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub full: bool,
+}
+
+impl CalendarItemSync {
+    /// Builds the changes from a full listing: items whose ETag is
+    /// missing from `known` (`(id, etag)` pairs) or differs are
+    /// changed, `known` ids absent from `items` are deleted.
+    #[cfg(any(feature = "webdav", feature = "client"))]
+    pub(crate) fn from_listing<K: AsRef<str>>(
+        items: Vec<CalendarItem>,
+        known: &[(K, K)],
+        sync_token: String,
+    ) -> Self {
+        let deleted = known
+            .iter()
+            .map(|(id, _)| id.as_ref())
+            .filter(|id| !items.iter().any(|item| item.id == *id))
+            .map(String::from)
+            .collect();
+
+        let changed = items
+            .into_iter()
+            .filter(|item| {
+                let etag = known.iter().find(|(id, _)| id.as_ref() == item.id);
+                etag.is_none_or(|(_, etag)| item.etag.as_deref() != Some(etag.as_ref()))
+            })
+            .collect();
+
+        Self {
+            changed,
+            deleted,
+            sync_token,
+            full: true,
+        }
+    }
+}
+
+/// Metadata of a calendar item without its contents, returned by
+/// `list_item_refs` for cheap change detection.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct CalendarItemRef {
+    /// Item identifier, as in [`CalendarItem::id`].
+    pub id: String,
+
+    /// Parent calendar identifier.
+    pub calendar_id: String,
+
+    /// Entity tag, as in [`CalendarItem::etag`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub etag: Option<String>,
+
+    /// Size of the contents in bytes, when known.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub size: Option<u64>,
+
+    /// Last modification time in seconds since the Unix epoch, when
+    /// known (WebDAV `getlastmodified`, file modification time for
+    /// vdir).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub last_modified: Option<u64>,
+}
+
+impl From<&CalendarItem> for CalendarItemRef {
+    fn from(item: &CalendarItem) -> Self {
+        Self {
+            id: item.id.clone(),
+            calendar_id: item.calendar_id.clone(),
+            etag: item.etag.clone(),
+            size: Some(item.contents.len() as u64),
+            last_modified: None,
+        }
+    }
+}
+
+/// Receipt of a successful `create_item` or `update_item`, carrying
+/// what a following conditional write needs.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct CalendarItemReceipt {
+    /// Identifier of the written item.
+    pub id: String,
+
+    /// Entity tag of the written contents, as in
+    /// [`CalendarItem::etag`]. `None` when the server reported none,
+    /// which it may do when it altered the stored contents (RFC 4791
+    /// section 5.3.4).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub etag: Option<String>,
 }
 
 /// A CalDAV time-range filter (RFC 4791 section 9.9) passed as a
@@ -57,10 +194,14 @@ impl CalendarItem {
 /// bound. Values are UTC timestamps in iCalendar `YYYYMMDDTHHMMSSZ`
 /// form. The WebDAV backend pushes this to the server as a `time-range`
 /// element; the vdir backend filters fetched items client-side.
+///
+/// A [`RecurrenceExpansion`] can be attached to also shape recurring
+/// items (RFC 4791 section 9.6.5 and 9.6.6) over the same range.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TimeRange {
     start: Option<String>,
     end: Option<String>,
+    expansion: Option<RecurrenceExpansion>,
 }
 
 impl TimeRange {
@@ -91,9 +232,29 @@ impl TimeRange {
         Some(Self {
             start: start.map(String::from),
             end: end.map(String::from),
+            expansion: None,
         })
     }
 
+    /// Asks for every recurring item to be expanded into one item per
+    /// occurrence overlapping the range (CalDAV `expand`).
+    pub fn expand(mut self) -> Self {
+        self.expansion = Some(RecurrenceExpansion::Expand);
+        self
+    }
+
+    /// Asks for recurring items to only keep the overrides affecting
+    /// the range (CalDAV `limit-recurrence-set`).
+    pub fn limit_recurrence_set(mut self) -> Self {
+        self.expansion = Some(RecurrenceExpansion::LimitRecurrenceSet);
+        self
+    }
+
+    /// Recurrence expansion requested on top of the filter, when any.
+    pub fn expansion(&self) -> Option<RecurrenceExpansion> {
+        self.expansion
+    }
+
     /// Inclusive lower bound, when set.
     pub fn start(&self) -> Option<&str> {
         self.start.as_deref()
@@ -135,9 +296,26 @@ impl TimeRange {
     }
 }
 
+/// How recurring items matched by a [`TimeRange`] are returned.
+///
+/// CalDAV requires both bounds for either form; an open bound is sent
+/// as the earliest or latest representable UTC timestamp.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum RecurrenceExpansion {
+    /// One item per occurrence, each holding a single component with a
+    /// `RECURRENCE-ID`, no recurrence properties and UTC times. Items
+    /// keep the id and ETag of the resource they were expanded from.
+    Expand,
+    /// One item per resource, holding the master component plus only
+    /// the overrides whose occurrence overlaps the range.
+    LimitRecurrenceSet,
+}
+
 /// Kind of a calendar item, derived from the first VCALENDAR child
 /// component. Used to filter list output across backends.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum CalendarItemKind {
@@ -148,3 +326,24 @@ pub enum CalendarItemKind {
     /// `VJOURNAL` component.
     Journal,
 }
+
+impl CalendarItemKind {
+    /// Every kind, in RFC 5545 order.
+    pub const ALL: [Self; 3] = [Self::Event, Self::Todo, Self::Journal];
+
+    /// iCalendar component name (`VEVENT`, `VTODO`, `VJOURNAL`).
+    pub fn component_name(&self) -> &'static str {
+        match self {
+            Self::Event => "VEVENT",
+            Self::Todo => "VTODO",
+            Self::Journal => "VJOURNAL",
+        }
+    }
+
+    /// Parses an iCalendar component name, case-insensitively.
+    pub fn from_component_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.component_name().eq_ignore_ascii_case(name.trim()))
+    }
+}
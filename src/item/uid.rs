@@ -0,0 +1,228 @@
+//! UID-derived resource names and UID conflict handling for item
+//! creation.
+
+use alloc::string::{String, ToString};
+#[cfg(feature = "client")]
+use alloc::{format, vec, vec::Vec};
+
+#[cfg(feature = "client")]
+use super::ical::{Property, escape_text};
+use super::{CalendarItem, CalendarItemKind, ical::Component};
+
+/// Longest UID used verbatim as a resource name.
+#[cfg(any(
+    feature = "vdir",
+    all(feature = "client", feature = "webdav"),
+    feature = "memory"
+))]
+const MAX_ID_LEN: usize = 128;
+
+/// Highest suffix tried when renaming a conflicting UID.
+#[cfg(feature = "client")]
+const MAX_UID_SUFFIX: usize = 100;
+
+/// What `create_item_with` does when the target calendar already holds
+/// an item carrying the same UID.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum UidConflict {
+    /// Refuse the write with an `AlreadyExists` error naming the
+    /// existing item.
+    #[default]
+    Fail,
+    /// Overwrite the existing item, gated on its entity tag.
+    Replace,
+    /// Store the new item alongside under a suffixed UID (`-2`, `-3`,
+    /// ...), leaving the existing item untouched. Fails like
+    /// [`Fail`](Self::Fail) when every suffix is taken or the UID
+    /// cannot be rewritten.
+    Rename,
+}
+
+impl CalendarItem {
+    /// UID of the main `VEVENT`, `VTODO` or `VJOURNAL` component, when
+    /// present and not blank.
+    pub fn uid(&self) -> Option<String> {
+        item_uid(&self.contents)
+    }
+}
+
+/// UID of the first `VEVENT`, `VTODO` or `VJOURNAL` child of the
+/// VCALENDAR in `contents`.
+pub(crate) fn item_uid(contents: &[u8]) -> Option<String> {
+    let calendar = Component::parse(contents)?;
+    let component = calendar
+        .components
+        .iter()
+        .find(|c| CalendarItemKind::from_component_name(&c.name).is_some())?;
+
+    let uid = component.property("UID")?.text();
+    let uid = uid.trim();
+    (!uid.is_empty()).then(|| uid.to_string())
+}
+
+/// Resource name derived from `uid`, or `None` when the UID would not
+/// make a safe file stem and URL segment: blank, too long, starting
+/// with a dot, or holding anything but ASCII letters, digits, `-`, `_`,
+/// `.` and `@`.
+#[cfg(any(
+    feature = "vdir",
+    all(feature = "client", feature = "webdav"),
+    feature = "memory"
+))]
+pub(crate) fn id_from_uid(uid: &str) -> Option<String> {
+    let safe = !uid.is_empty()
+        && uid.len() <= MAX_ID_LEN
+        && !uid.starts_with('.')
+        && uid
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'@'));
+
+    safe.then(|| uid.to_string())
+}
+
+/// Rewrites the UID of every `VEVENT`, `VTODO` and `VJOURNAL` child
+/// sharing the main component's UID (recurrence overrides included)
+/// to `uid`. Returns `None` when `contents` carries no UID.
+#[cfg(feature = "client")]
+fn with_uid(contents: &[u8], uid: &str) -> Option<Vec<u8>> {
+    let old = item_uid(contents)?;
+    let mut calendar = Component::parse(contents)?;
+
+    for component in &mut calendar.components {
+        if CalendarItemKind::from_component_name(&component.name).is_none() {
+            continue;
+        }
+        let same = component
+            .property("UID")
+            .is_some_and(|p| p.text().trim() == old);
+        if same {
+            let property = Property::new("UID", Vec::new(), escape_text(uid));
+            component.replace("UID", vec![property]);
+        }
+    }
+
+    Some(calendar.to_bytes())
+}
+
+/// Outcome of checking new item contents against the items of their
+/// target calendar, see [`check_uid`].
+#[cfg(feature = "client")]
+pub(crate) enum UidCheck {
+    /// No conflict left: create the item from these contents.
+    Create(Vec<u8>),
+    /// Refuse the write, the given item already carries the UID.
+    Exists(String),
+    /// Overwrite the existing item with these contents.
+    Replace(CalendarItem, Vec<u8>),
+}
+
+/// Resolves the UID conflict of new `contents` carrying `uid` against
+/// the `items` of the target calendar, following `on_conflict`.
+///
+/// Renaming appends the first free `-2`, `-3`, ... suffix to the UID,
+/// so no randomness is needed and the new UID stays recognizable. When
+/// no suffix up to [`MAX_UID_SUFFIX`] is free or the contents cannot be
+/// rewritten, the conflict is refused as [`UidCheck::Exists`] rather
+/// than stored under the duplicate UID.
+#[cfg(feature = "client")]
+pub(crate) fn check_uid(
+    items: Vec<CalendarItem>,
+    uid: &str,
+    contents: Vec<u8>,
+    on_conflict: UidConflict,
+) -> UidCheck {
+    let uids: Vec<Option<String>> = items.iter().map(CalendarItem::uid).collect();
+    let Some(position) = uids.iter().position(|other| other.as_deref() == Some(uid)) else {
+        return UidCheck::Create(contents);
+    };
+
+    match on_conflict {
+        UidConflict::Fail => UidCheck::Exists(items[position].id.clone()),
+        UidConflict::Replace => {
+            let existing = items.into_iter().nth(position);
+            UidCheck::Replace(existing.expect("position is in bounds"), contents)
+        }
+        UidConflict::Rename => {
+            let renamed = (2..=MAX_UID_SUFFIX)
+                .map(|n| format!("{uid}-{n}"))
+                .find(|candidate| !uids.iter().flatten().any(|other| other == candidate))
+                .and_then(|renamed| with_uid(&contents, &renamed));
+
+            match renamed {
+                Some(contents) => UidCheck::Create(contents),
+                None => UidCheck::Exists(items[position].id.clone()),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+
+    fn event(uid: &str) -> Vec<u8> {
+        format!("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:{uid}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n")
+            .into_bytes()
+    }
+
+    fn items(uids: &[&str]) -> Vec<CalendarItem> {
+        uids.iter()
+            .map(|uid| CalendarItem {
+                id: format!("id-{uid}"),
+                contents: event(uid),
+                ..CalendarItem::default()
+            })
+            .collect()
+    }
+
+    fn check(items: Vec<CalendarItem>, contents: Vec<u8>, on_conflict: UidConflict) -> UidCheck {
+        check_uid(items, "a", contents, on_conflict)
+    }
+
+    #[test]
+    fn follows_the_conflict_policy() {
+        let taken = || items(&["a", "a-2"]);
+
+        assert!(matches!(
+            check(items(&["b"]), event("a"), UidConflict::Fail),
+            UidCheck::Create(contents) if contents == event("a")
+        ));
+        assert!(matches!(
+            check(taken(), event("a"), UidConflict::Fail),
+            UidCheck::Exists(id) if id == "id-a"
+        ));
+        assert!(matches!(
+            check(taken(), event("a"), UidConflict::Replace),
+            UidCheck::Replace(existing, contents) if existing.id == "id-a" && contents == event("a")
+        ));
+
+        let UidCheck::Create(contents) = check(taken(), event("a"), UidConflict::Rename) else {
+            panic!("expected a renamed copy");
+        };
+        assert_eq!(item_uid(&contents).as_deref(), Some("a-3"));
+    }
+
+    #[test]
+    fn refuses_renames_without_a_free_suffix() {
+        let suffixed: Vec<String> = (2..=MAX_UID_SUFFIX).map(|n| format!("a-{n}")).collect();
+        let mut uids = vec!["a"];
+        uids.extend(suffixed.iter().map(String::as_str));
+
+        assert!(matches!(
+            check(items(&uids), event("a"), UidConflict::Rename),
+            UidCheck::Exists(id) if id == "id-a"
+        ));
+    }
+
+    #[test]
+    fn refuses_renames_of_unreadable_contents() {
+        let contents = b"BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n".to_vec();
+
+        assert!(matches!(
+            check(items(&["a"]), contents, UidConflict::Rename),
+            UidCheck::Exists(id) if id == "id-a"
+        ));
+    }
+}
@@ -1,5 +1,11 @@
 //! Vdir item create coroutine wrapping
-//! [`io_vdir::item::store::VdirItemStore`] with a generated id.
+//! [`io_vdir::item::store::VdirItemStore`] with a chosen id.
+//!
+//! The id is chosen here rather than by the store, so the target file
+//! can be checked first: creation never overwrites an existing item.
+//! With the `parser` feature the item's UID is used when it makes a
+//! safe file stem, so importing the same item twice hits the existing
+//! file; otherwise a random id is minted.
 //!
 //! # Example
 //!
@@ -8,10 +14,11 @@
 //!
 //! let client = VdirClient::new("/path/to/vdir");
 //! let path = client.inner.root().join("personal");
-//! let id = client.run(VdirCalendarItemCreate::new(path, ical_bytes)?)?;
+//! let receipt = client.run(VdirCalendarItemCreate::new(path, ical_bytes)?)?;
 //! ```
 
-use alloc::{string::String, vec::Vec};
+use alloc::{collections::BTreeSet, format, string::String, vec::Vec};
+use core::mem;
 
 use io_vdir::{
     coroutine::*,
@@ -24,6 +31,10 @@ use io_vdir::{
 use log::trace;
 use thiserror::Error;
 
+use crate::item::{CalendarItemReceipt, content_tag, uuid_v4};
+#[cfg(feature = "parser")]
+use crate::item::{id_from_uid, item_uid};
+
 /// Errors produced by [`VdirCalendarItemCreate`].
 #[derive(Debug, Error)]
 pub enum VdirCalendarItemCreateError {
@@ -31,18 +42,34 @@ pub enum VdirCalendarItemCreateError {
     Store(#[from] VdirItemStoreError),
     #[error("Empty item body")]
     EmptyBody,
+    #[error("Item `{0}` already exists")]
+    AlreadyExists(String),
+    #[error("Unexpected reply to a vdir item create")]
+    InvalidReply,
+}
+
+enum State {
+    Random,
+    Named(String),
+    Exists(String),
+    Store(VdirItemStore),
 }
 
 /// I/O-free coroutine writing a new iCalendar item under a collection.
 ///
-/// The id is minted by the inner store coroutine; on completion the
-/// generated item id is returned.
+/// The id is the item's UID when safe (with the `parser` feature),
+/// otherwise minted from the runner's randomness; the item is only
+/// stored when no file carries it yet. On completion the id is
+/// returned along with the synthetic ETag of the written contents.
 pub struct VdirCalendarItemCreate {
-    inner: VdirItemStore,
+    path: VdirPath,
+    contents: Vec<u8>,
+    etag: String,
+    state: State,
 }
 
 impl VdirCalendarItemCreate {
-    /// Builds the coroutine storing `contents` as a fresh iCalendar item
+    /// Builds the coroutine storing `contents` as a new iCalendar item
     /// under the calendar at `path`, rejecting empty contents.
     pub fn new(
         path: impl Into<VdirPath>,
@@ -54,27 +81,86 @@ impl VdirCalendarItemCreate {
             return Err(VdirCalendarItemCreateError::EmptyBody);
         }
 
+        #[cfg(feature = "parser")]
+        let state = match item_uid(&contents).as_deref().and_then(id_from_uid) {
+            Some(id) => State::Named(id),
+            None => State::Random,
+        };
+        #[cfg(not(feature = "parser"))]
+        let state = State::Random;
+
         Ok(Self {
-            inner: VdirItemStore::new(
-                path,
-                None,
-                ItemKind::Ical,
-                contents,
-                VdirItemStoreOptions::default(),
-            ),
+            path: path.into(),
+            etag: content_tag(&contents),
+            contents,
+            state,
         })
     }
 }
 
 impl VdirCoroutine for VdirCalendarItemCreate {
     type Yield = VdirYield;
-    type Return = Result<String, VdirCalendarItemCreateError>;
+    type Return = Result<CalendarItemReceipt, VdirCalendarItemCreateError>;
 
     fn resume(&mut self, arg: Option<VdirReply>) -> VdirCoroutineState<Self::Yield, Self::Return> {
-        match self.inner.resume(arg) {
-            VdirCoroutineState::Yielded(y) => VdirCoroutineState::Yielded(y),
-            VdirCoroutineState::Complete(Ok(out)) => VdirCoroutineState::Complete(Ok(out.id)),
-            VdirCoroutineState::Complete(Err(err)) => VdirCoroutineState::Complete(Err(err.into())),
+        match (&mut self.state, arg) {
+            (State::Random, None) => {
+                VdirCoroutineState::Yielded(VdirYield::WantsRandom { len: 16 })
+            }
+            (State::Random, Some(VdirReply::Random(bytes))) => {
+                let Ok(bytes) = <[u8; 16]>::try_from(bytes) else {
+                    return VdirCoroutineState::Complete(Err(
+                        VdirCalendarItemCreateError::InvalidReply,
+                    ));
+                };
+
+                self.state = State::Named(uuid_v4(bytes));
+                self.resume(None)
+            }
+            (State::Named(id), None) => {
+                let id = mem::take(id);
+                let file = item_file(&self.path, &id);
+                self.state = State::Exists(id);
+                VdirCoroutineState::Yielded(VdirYield::WantsFileExists(BTreeSet::from([file])))
+            }
+            (State::Exists(id), Some(VdirReply::FileExists(exists))) => {
+                if exists
+                    .get(&item_file(&self.path, id))
+                    .copied()
+                    .unwrap_or(false)
+                {
+                    let err = VdirCalendarItemCreateError::AlreadyExists(id.clone());
+                    return VdirCoroutineState::Complete(Err(err));
+                }
+
+                let store = VdirItemStore::new(
+                    self.path.clone(),
+                    Some(id.clone()),
+                    ItemKind::Ical,
+                    mem::take(&mut self.contents),
+                    VdirItemStoreOptions::default(),
+                );
+                self.state = State::Store(store);
+                self.resume(None)
+            }
+            (State::Store(store), arg) => match store.resume(arg) {
+                VdirCoroutineState::Yielded(y) => VdirCoroutineState::Yielded(y),
+                VdirCoroutineState::Complete(Ok(out)) => {
+                    VdirCoroutineState::Complete(Ok(CalendarItemReceipt {
+                        id: out.id,
+                        etag: Some(self.etag.clone()),
+                    }))
+                }
+                VdirCoroutineState::Complete(Err(err)) => {
+                    VdirCoroutineState::Complete(Err(err.into()))
+                }
+            },
+            _ => VdirCoroutineState::Complete(Err(VdirCalendarItemCreateError::InvalidReply)),
         }
     }
 }
+
+/// Path of the file of item `id` in the calendar at `path`.
+fn item_file(path: &VdirPath, id: &str) -> VdirPath {
+    path.join(&format!("{id}.ics"))
+}
@@ -1,6 +1,9 @@
 //! Vdir item delete coroutine wrapping
 //! [`io_vdir::item::delete::VdirItemDelete`].
 //!
+//! With an `if_match` entity tag, the item is read first and its
+//! synthetic ETag (a hash of the file contents) compared before removing it.
+//!
 //! # Example
 //!
 //! ```rust,ignore
@@ -8,38 +11,62 @@
 //!
 //! let client = VdirClient::new("/path/to/vdir");
 //! let path = client.inner.root().join("personal");
-//! client.run(VdirCalendarItemDelete::new(path, "event-1"))?;
+//! client.run(VdirCalendarItemDelete::new(path, "event-1", None))?;
 //! ```
 
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 
 use io_vdir::{
     coroutine::*,
-    item::delete::{VdirItemDelete, VdirItemDeleteError, VdirItemDeleteOptions},
+    item::{
+        delete::{VdirItemDelete, VdirItemDeleteError, VdirItemDeleteOptions},
+        get::{VdirItemGet, VdirItemGetError, VdirItemGetOptions},
+    },
     path::VdirPath,
 };
 use log::trace;
 use thiserror::Error;
 
+use crate::item::content_tag;
+
 /// Errors produced by [`VdirCalendarItemDelete`].
 #[derive(Debug, Error)]
 pub enum VdirCalendarItemDeleteError {
     #[error(transparent)]
     Delete(#[from] VdirItemDeleteError),
+    #[error(transparent)]
+    Get(#[from] VdirItemGetError),
+    #[error("Item `{0}` does not match the expected entity tag")]
+    PreconditionFailed(String),
 }
 
 /// I/O-free coroutine locating then removing a Vdir item by its id.
 pub struct VdirCalendarItemDelete {
+    item_id: String,
+    check: Option<(VdirItemGet, String)>,
     inner: VdirItemDelete,
 }
 
 impl VdirCalendarItemDelete {
     /// Builds the coroutine deleting item `item_id` from the calendar at
     /// `path`.
-    pub fn new(path: impl Into<VdirPath>, item_id: impl ToString) -> Self {
+    ///
+    /// When `if_match` is set, the item is only removed if its current
+    /// ETag equals it (`*` matches any).
+    pub fn new(path: impl Into<VdirPath>, item_id: impl ToString, if_match: Option<&str>) -> Self {
         trace!("prepare vdir item delete");
+
+        let path = path.into();
+        let item_id = item_id.to_string();
+        let check = if_match.map(|etag| {
+            let get = VdirItemGet::new(path.clone(), &item_id, VdirItemGetOptions::default());
+            (get, etag.to_string())
+        });
+
         Self {
-            inner: VdirItemDelete::new(path, item_id, VdirItemDeleteOptions::default()),
+            inner: VdirItemDelete::new(path, &item_id, VdirItemDeleteOptions::default()),
+            item_id,
+            check,
         }
     }
 }
@@ -49,6 +76,25 @@ impl VdirCoroutine for VdirCalendarItemDelete {
     type Return = Result<(), VdirCalendarItemDeleteError>;
 
     fn resume(&mut self, arg: Option<VdirReply>) -> VdirCoroutineState<Self::Yield, Self::Return> {
+        let mut arg = arg;
+
+        if let Some((get, expected)) = &mut self.check {
+            match get.resume(arg.take()) {
+                VdirCoroutineState::Yielded(y) => return VdirCoroutineState::Yielded(y),
+                VdirCoroutineState::Complete(Ok(item)) => {
+                    if expected != "*" && content_tag(&item.contents) != *expected {
+                        let err =
+                            VdirCalendarItemDeleteError::PreconditionFailed(self.item_id.clone());
+                        return VdirCoroutineState::Complete(Err(err));
+                    }
+                    self.check = None;
+                }
+                VdirCoroutineState::Complete(Err(err)) => {
+                    return VdirCoroutineState::Complete(Err(err.into()));
+                }
+            }
+        }
+
         match self.inner.resume(arg) {
             VdirCoroutineState::Yielded(y) => VdirCoroutineState::Yielded(y),
             VdirCoroutineState::Complete(r) => VdirCoroutineState::Complete(r.map_err(Into::into)),
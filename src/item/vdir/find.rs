@@ -0,0 +1,82 @@
+//! Vdir item lookup by iCalendar UID, wrapping
+//! [`io_vdir::item::list::VdirItemList`].
+//!
+//! Vdir has no query language: every `.ics` file of the collection is
+//! read in one batch and parsed for its UID.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use io_calendar::{item::vdir::find::VdirCalendarItemFind, vdir::client::VdirClient};
+//!
+//! let client = VdirClient::new("/path/to/vdir");
+//! let path = client.inner.root().join("personal");
+//! let item = client.run(VdirCalendarItemFind::new(path, "personal", "4f1c@example.com"))?;
+//! ```
+
+use alloc::string::{String, ToString};
+
+use io_vdir::{
+    coroutine::*,
+    item::list::{VdirItemList, VdirItemListError, VdirItemListOptions},
+    path::VdirPath,
+};
+use log::trace;
+use thiserror::Error;
+
+use crate::{
+    item::CalendarItem,
+    vdir::convert::{is_calendar_item, item_from},
+};
+
+/// Errors produced by [`VdirCalendarItemFind`].
+#[derive(Debug, Error)]
+pub enum VdirCalendarItemFindError {
+    #[error(transparent)]
+    List(#[from] VdirItemListError),
+}
+
+/// I/O-free coroutine finding the item carrying a given UID in a Vdir
+/// calendar.
+///
+/// On completion returns the matching item with its synthetic ETag, the
+/// one with the smallest id when several match, or `None`.
+pub struct VdirCalendarItemFind {
+    calendar_id: String,
+    uid: String,
+    inner: VdirItemList,
+}
+
+impl VdirCalendarItemFind {
+    /// Builds the coroutine looking for `uid` in calendar `calendar_id`
+    /// located at `path`.
+    pub fn new(path: impl Into<VdirPath>, calendar_id: &str, uid: &str) -> Self {
+        trace!("prepare vdir item find");
+        Self {
+            calendar_id: calendar_id.to_string(),
+            uid: uid.to_string(),
+            inner: VdirItemList::new(path, VdirItemListOptions::default()),
+        }
+    }
+}
+
+impl VdirCoroutine for VdirCalendarItemFind {
+    type Yield = VdirYield;
+    type Return = Result<Option<CalendarItem>, VdirCalendarItemFindError>;
+
+    fn resume(&mut self, arg: Option<VdirReply>) -> VdirCoroutineState<Self::Yield, Self::Return> {
+        match self.inner.resume(arg) {
+            VdirCoroutineState::Yielded(y) => VdirCoroutineState::Yielded(y),
+            VdirCoroutineState::Complete(Ok(items)) => {
+                let found = items
+                    .into_iter()
+                    .filter(|item| is_calendar_item(item.kind))
+                    .filter_map(|item| item_from(item, &self.calendar_id))
+                    .filter(|item| item.uid().as_deref() == Some(self.uid.as_str()))
+                    .min_by(|a, b| a.id.cmp(&b.id));
+                VdirCoroutineState::Complete(Ok(found))
+            }
+            VdirCoroutineState::Complete(Err(err)) => VdirCoroutineState::Complete(Err(err.into())),
+        }
+    }
+}
@@ -1,7 +1,9 @@
 //! Vdir item list coroutine wrapping
 //! [`io_vdir::item::list::VdirItemList`].
 //!
-//! Filters to iCalendar items, sorts by id, then applies 1-indexed
+//! Filters to iCalendar items (optionally of the requested kinds,
+//! overlapping a [`TimeRange`] and matching an [`ItemFilter`]), sorts
+//! by id, applies the range's recurrence expansion, then 1-indexed
 //! pagination.
 //!
 //! # Example
@@ -12,7 +14,7 @@
 //!
 //! let client = VdirClient::new("/path/to/vdir");
 //! let path = client.inner.root().join("personal");
-//! let items = client.run(VdirCalendarItemList::new(path, "personal", None, None))?;
+//! let items = client.run(VdirCalendarItemList::new(path, "personal", None, None, None, &[], None))?;
 //! ```
 
 use alloc::{
@@ -29,8 +31,11 @@ use log::trace;
 use thiserror::Error;
 
 use crate::{
-    item::CalendarItem,
-    vdir::convert::{is_calendar_item, item_from, paginate},
+    item::{
+        CalendarItem, CalendarItemKind, ItemFilter, TimeRange, expand_recurrences, filter_props,
+        filter_time_range, paginate,
+    },
+    vdir::convert::{is_calendar_item, item_from},
 };
 
 /// Errors produced by [`VdirCalendarItemList`].
@@ -42,10 +47,15 @@ pub enum VdirCalendarItemListError {
 
 /// I/O-free coroutine listing every iCalendar item in a Vdir calendar.
 ///
-/// On completion keeps only iCalendar items, maps each to a
-/// [`CalendarItem`], sorts by id, then paginates.
+/// On completion keeps only iCalendar items of the requested kinds
+/// overlapping the requested range and matching the requested filter,
+/// maps each to a [`CalendarItem`], sorts by id, expands recurrences
+/// when asked to, then paginates.
 pub struct VdirCalendarItemList {
     calendar_id: String,
+    time_range: Option<TimeRange>,
+    kinds: Vec<CalendarItemKind>,
+    filter: Option<ItemFilter>,
     page: Option<u32>,
     page_size: Option<u32>,
     inner: VdirItemList,
@@ -54,15 +64,27 @@ pub struct VdirCalendarItemList {
 impl VdirCalendarItemList {
     /// Builds the coroutine listing items of calendar `calendar_id`
     /// located at `path`, applying 1-indexed pagination on completion.
+    ///
+    /// When `kinds` is non-empty, only items of those kinds are kept;
+    /// when `time_range` is set, only items overlapping it (RFC 4791
+    /// section 9.9); when `filter` is set, only items matching it (RFC
+    /// 4791 section 9.7). The range and the filter need the `parser`
+    /// feature. Every filter runs before pagination.
     pub fn new(
         path: impl Into<VdirPath>,
         calendar_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
     ) -> Self {
         trace!("prepare vdir item list");
         Self {
             calendar_id: calendar_id.to_string(),
+            time_range: time_range.cloned(),
+            kinds: kinds.to_vec(),
+            filter: filter.cloned(),
             page,
             page_size,
             inner: VdirItemList::new(path, VdirItemListOptions::default()),
@@ -78,16 +100,44 @@ impl VdirCoroutine for VdirCalendarItemList {
         match self.inner.resume(arg) {
             VdirCoroutineState::Yielded(y) => VdirCoroutineState::Yielded(y),
             VdirCoroutineState::Complete(Ok(items)) => {
-                let mut items: Vec<CalendarItem> = items
+                let items = items
                     .into_iter()
                     .filter(|item| is_calendar_item(item.kind))
                     .filter_map(|item| item_from(item, &self.calendar_id))
                     .collect();
-                items.sort_by(|a, b| a.id.cmp(&b.id));
-                let items = paginate(items, self.page, self.page_size);
+                let items = select_items(
+                    items,
+                    self.page,
+                    self.page_size,
+                    self.time_range.as_ref(),
+                    &self.kinds,
+                    self.filter.as_ref(),
+                );
                 VdirCoroutineState::Complete(Ok(items))
             }
             VdirCoroutineState::Complete(Err(err)) => VdirCoroutineState::Complete(Err(err.into())),
         }
     }
 }
+
+/// Keeps the `items` of the requested kinds overlapping `time_range`
+/// and matching `filter`, sorts them by id, expands recurrences when
+/// asked to, then paginates.
+pub(crate) fn select_items(
+    items: Vec<CalendarItem>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    time_range: Option<&TimeRange>,
+    kinds: &[CalendarItemKind],
+    filter: Option<&ItemFilter>,
+) -> Vec<CalendarItem> {
+    let items = items
+        .into_iter()
+        .filter(|item| item.matches_kinds(kinds))
+        .collect();
+    let items = filter_props(items, filter);
+    let mut items = filter_time_range(items, time_range);
+    items.sort_by(|a, b| a.id.cmp(&b.id));
+    let items = expand_recurrences(items, time_range);
+    paginate(items, page, page_size)
+}
@@ -0,0 +1,86 @@
+//! Vdir item reference list coroutine wrapping
+//! [`io_vdir::item::list::VdirItemList`].
+//!
+//! The synthetic vdir ETag is a hash of the file contents, so the files
+//! are still read (in one batch); only their metadata is returned.
+//! io-vdir exposes no file metadata: the modification time is left
+//! unset here and filled in by the runners.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use io_calendar::{item::vdir::list_refs::VdirCalendarItemRefList, vdir::client::VdirClient};
+//!
+//! let client = VdirClient::new("/path/to/vdir");
+//! let path = client.inner.root().join("personal");
+//! let refs = client.run(VdirCalendarItemRefList::new(path, "personal"))?;
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_vdir::{
+    coroutine::*,
+    item::list::{VdirItemList, VdirItemListError, VdirItemListOptions},
+    path::VdirPath,
+};
+use log::trace;
+use thiserror::Error;
+
+use crate::{
+    item::CalendarItemRef,
+    vdir::convert::{is_calendar_item, item_from},
+};
+
+/// Errors produced by [`VdirCalendarItemRefList`].
+#[derive(Debug, Error)]
+pub enum VdirCalendarItemRefListError {
+    #[error(transparent)]
+    List(#[from] VdirItemListError),
+}
+
+/// I/O-free coroutine listing the id, ETag and size of every iCalendar
+/// item in a Vdir calendar.
+///
+/// On completion returns one [`CalendarItemRef`] per item, sorted by
+/// id.
+pub struct VdirCalendarItemRefList {
+    calendar_id: String,
+    inner: VdirItemList,
+}
+
+impl VdirCalendarItemRefList {
+    /// Builds the coroutine listing item references of calendar
+    /// `calendar_id` located at `path`.
+    pub fn new(path: impl Into<VdirPath>, calendar_id: &str) -> Self {
+        trace!("prepare vdir item ref list");
+        Self {
+            calendar_id: calendar_id.to_string(),
+            inner: VdirItemList::new(path, VdirItemListOptions::default()),
+        }
+    }
+}
+
+impl VdirCoroutine for VdirCalendarItemRefList {
+    type Yield = VdirYield;
+    type Return = Result<Vec<CalendarItemRef>, VdirCalendarItemRefListError>;
+
+    fn resume(&mut self, arg: Option<VdirReply>) -> VdirCoroutineState<Self::Yield, Self::Return> {
+        match self.inner.resume(arg) {
+            VdirCoroutineState::Yielded(y) => VdirCoroutineState::Yielded(y),
+            VdirCoroutineState::Complete(Ok(items)) => {
+                let mut refs: Vec<CalendarItemRef> = items
+                    .into_iter()
+                    .filter(|item| is_calendar_item(item.kind))
+                    .filter_map(|item| item_from(item, &self.calendar_id))
+                    .map(|item| CalendarItemRef::from(&item))
+                    .collect();
+                refs.sort_by(|a, b| a.id.cmp(&b.id));
+                VdirCoroutineState::Complete(Ok(refs))
+            }
+            VdirCoroutineState::Complete(Err(err)) => VdirCoroutineState::Complete(Err(err.into())),
+        }
+    }
+}
@@ -3,6 +3,12 @@
 
 pub mod create;
 pub mod delete;
+#[cfg(feature = "parser")]
+pub mod find;
 pub mod get;
 pub mod list;
+pub mod list_refs;
+pub mod multiget;
+#[cfg(feature = "parser")]
+pub mod search;
 pub mod update;
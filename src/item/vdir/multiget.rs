@@ -0,0 +1,122 @@
+//! Vdir item batch get coroutine, the vdir counterpart of the CalDAV
+//! `calendar-multiget` REPORT.
+//!
+//! io-vdir reads items one at a time, so this yields its own
+//! [`VdirYield::WantsFileExists`] then a single
+//! [`VdirYield::WantsFileRead`] naming every file, letting the runner
+//! read them in one batch (concurrently with
+//! [`VdirClientTokio`](crate::vdir::client_tokio::VdirClientTokio)).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use io_calendar::{item::vdir::multiget::VdirCalendarItemMultiget, vdir::client::VdirClient};
+//!
+//! let client = VdirClient::new("/path/to/vdir");
+//! let path = client.inner.root().join("personal");
+//! let items = client.run(VdirCalendarItemMultiget::new(path, "personal", &["a", "b"]))?;
+//! ```
+
+use alloc::{
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_vdir::{coroutine::*, path::VdirPath};
+use log::trace;
+use thiserror::Error;
+
+use crate::item::{CalendarItem, content_tag};
+
+/// Errors produced by [`VdirCalendarItemMultiget`].
+#[derive(Debug, Error)]
+pub enum VdirCalendarItemMultigetError {
+    #[error("Unexpected reply to a vdir item multiget")]
+    InvalidReply,
+}
+
+enum State {
+    Exists,
+    Read(Vec<(String, VdirPath)>),
+}
+
+/// I/O-free coroutine fetching several Vdir items by id with one
+/// batched file read.
+///
+/// On completion returns the found items in the requested order, with
+/// their synthetic ETags; ids without a file are left out and repeated
+/// ids are returned once.
+pub struct VdirCalendarItemMultiget {
+    calendar_id: String,
+    files: Vec<(String, VdirPath)>,
+    state: State,
+}
+
+impl VdirCalendarItemMultiget {
+    /// Builds the coroutine fetching items `item_ids` from calendar
+    /// `calendar_id` located at `path`.
+    pub fn new(path: impl Into<VdirPath>, calendar_id: &str, item_ids: &[&str]) -> Self {
+        trace!("prepare vdir item multiget");
+
+        let path = path.into();
+        let files = item_ids
+            .iter()
+            .map(|id| (id.to_string(), path.join(&format!("{id}.ics"))))
+            .collect();
+
+        Self {
+            calendar_id: calendar_id.to_string(),
+            files,
+            state: State::Exists,
+        }
+    }
+}
+
+impl VdirCoroutine for VdirCalendarItemMultiget {
+    type Yield = VdirYield;
+    type Return = Result<Vec<CalendarItem>, VdirCalendarItemMultigetError>;
+
+    fn resume(&mut self, arg: Option<VdirReply>) -> VdirCoroutineState<Self::Yield, Self::Return> {
+        match (&mut self.state, arg) {
+            (State::Exists, None) => {
+                let paths: BTreeSet<VdirPath> =
+                    self.files.iter().map(|(_, path)| path.clone()).collect();
+                VdirCoroutineState::Yielded(VdirYield::WantsFileExists(paths))
+            }
+            (State::Exists, Some(VdirReply::FileExists(exists))) => {
+                let files: Vec<(String, VdirPath)> = self
+                    .files
+                    .drain(..)
+                    .filter(|(_, path)| exists.get(path).copied().unwrap_or(false))
+                    .collect();
+
+                if files.is_empty() {
+                    return VdirCoroutineState::Complete(Ok(Vec::new()));
+                }
+
+                let paths = files.iter().map(|(_, path)| path.clone()).collect();
+                self.state = State::Read(files);
+                VdirCoroutineState::Yielded(VdirYield::WantsFileRead(paths))
+            }
+            (State::Read(files), Some(VdirReply::FileRead(mut contents))) => {
+                let items = files
+                    .drain(..)
+                    .filter_map(|(id, path)| {
+                        let contents = contents.remove(&path)?;
+                        Some(CalendarItem {
+                            id,
+                            calendar_id: self.calendar_id.clone(),
+                            etag: Some(content_tag(&contents)),
+                            contents,
+                        })
+                    })
+                    .collect();
+
+                VdirCoroutineState::Complete(Ok(items))
+            }
+            _ => VdirCoroutineState::Complete(Err(VdirCalendarItemMultigetError::InvalidReply)),
+        }
+    }
+}
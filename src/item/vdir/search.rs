@@ -0,0 +1,83 @@
+//! Vdir full-text item search, wrapping
+//! [`io_vdir::item::list::VdirItemList`].
+//!
+//! Vdir has no query language: every `.ics` file of the collection is
+//! read in one batch, parsed and scanned with Unicode case folding.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use io_calendar::{item::vdir::search::VdirCalendarItemSearch, vdir::client::VdirClient};
+//!
+//! let client = VdirClient::new("/path/to/vdir");
+//! let path = client.inner.root().join("personal");
+//! let hits = client.run(VdirCalendarItemSearch::new(path, "personal", "standup"))?;
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_vdir::{
+    coroutine::*,
+    item::list::{VdirItemList, VdirItemListError, VdirItemListOptions},
+    path::VdirPath,
+};
+use log::trace;
+use thiserror::Error;
+
+use crate::{
+    item::{SearchHit, search_items},
+    vdir::convert::{is_calendar_item, item_from},
+};
+
+/// Errors produced by [`VdirCalendarItemSearch`].
+#[derive(Debug, Error)]
+pub enum VdirCalendarItemSearchError {
+    #[error(transparent)]
+    List(#[from] VdirItemListError),
+}
+
+/// I/O-free coroutine searching the items of a Vdir calendar.
+///
+/// On completion returns the matching items with their synthetic ETag
+/// and matched field, sorted by id.
+pub struct VdirCalendarItemSearch {
+    calendar_id: String,
+    query: String,
+    inner: VdirItemList,
+}
+
+impl VdirCalendarItemSearch {
+    /// Builds the coroutine searching `query` in calendar `calendar_id`
+    /// located at `path`.
+    pub fn new(path: impl Into<VdirPath>, calendar_id: &str, query: &str) -> Self {
+        trace!("prepare vdir item search");
+        Self {
+            calendar_id: calendar_id.to_string(),
+            query: query.to_string(),
+            inner: VdirItemList::new(path, VdirItemListOptions::default()),
+        }
+    }
+}
+
+impl VdirCoroutine for VdirCalendarItemSearch {
+    type Yield = VdirYield;
+    type Return = Result<Vec<SearchHit>, VdirCalendarItemSearchError>;
+
+    fn resume(&mut self, arg: Option<VdirReply>) -> VdirCoroutineState<Self::Yield, Self::Return> {
+        match self.inner.resume(arg) {
+            VdirCoroutineState::Yielded(y) => VdirCoroutineState::Yielded(y),
+            VdirCoroutineState::Complete(Ok(items)) => {
+                let items = items
+                    .into_iter()
+                    .filter(|item| is_calendar_item(item.kind))
+                    .filter_map(|item| item_from(item, &self.calendar_id))
+                    .collect();
+                VdirCoroutineState::Complete(Ok(search_items(items, &self.query)))
+            }
+            VdirCoroutineState::Complete(Err(err)) => VdirCoroutineState::Complete(Err(err.into())),
+        }
+    }
+}
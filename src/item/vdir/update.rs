@@ -1,6 +1,9 @@
 //! Vdir item update coroutine wrapping
 //! [`io_vdir::item::store::VdirItemStore`] in overwrite mode.
 //!
+//! With an `if_match` entity tag, the item is read first and its
+//! synthetic ETag (a hash of the file contents) compared before writing.
+//!
 //! # Example
 //!
 //! ```rust,ignore
@@ -8,15 +11,19 @@
 //!
 //! let client = VdirClient::new("/path/to/vdir");
 //! let path = client.inner.root().join("personal");
-//! client.run(VdirCalendarItemUpdate::new(path, "event-1", ical_bytes)?)?;
+//! let receipt = client.run(VdirCalendarItemUpdate::new(path, "event-1", ical_bytes, Some(&etag))?)?;
 //! ```
 
-use alloc::{string::String, vec::Vec};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
 
 use io_vdir::{
     coroutine::*,
     item::{
         ItemKind,
+        get::{VdirItemGet, VdirItemGetError, VdirItemGetOptions},
         store::{VdirItemStore, VdirItemStoreError, VdirItemStoreOptions},
     },
     path::VdirPath,
@@ -24,27 +31,43 @@ use io_vdir::{
 use log::trace;
 use thiserror::Error;
 
+use crate::item::{CalendarItemReceipt, content_tag};
+
 /// Errors produced by [`VdirCalendarItemUpdate`].
 #[derive(Debug, Error)]
 pub enum VdirCalendarItemUpdateError {
     #[error(transparent)]
     Store(#[from] VdirItemStoreError),
+    #[error(transparent)]
+    Get(#[from] VdirItemGetError),
     #[error("Empty item body")]
     EmptyBody,
+    #[error("Item `{0}` does not match the expected entity tag")]
+    PreconditionFailed(String),
 }
 
 /// I/O-free coroutine overwriting an existing Vdir item's contents.
+///
+/// On completion returns the item id along with the synthetic ETag of
+/// the written contents.
 pub struct VdirCalendarItemUpdate {
+    item_id: String,
+    etag: String,
+    check: Option<(VdirItemGet, String)>,
     inner: VdirItemStore,
 }
 
 impl VdirCalendarItemUpdate {
     /// Builds the coroutine overwriting item `item_id` under the
     /// calendar at `path`, rejecting empty contents.
+    ///
+    /// When `if_match` is set, the write only happens if the item
+    /// exists and its current ETag equals it (`*` matches any).
     pub fn new(
         path: impl Into<VdirPath>,
         item_id: &str,
         contents: Vec<u8>,
+        if_match: Option<&str>,
     ) -> Result<Self, VdirCalendarItemUpdateError> {
         trace!("prepare vdir item update");
 
@@ -52,8 +75,17 @@ impl VdirCalendarItemUpdate {
             return Err(VdirCalendarItemUpdateError::EmptyBody);
         }
 
+        let path = path.into();
+        let check = if_match.map(|etag| {
+            let get = VdirItemGet::new(path.clone(), item_id, VdirItemGetOptions::default());
+            (get, etag.to_string())
+        });
+
         let id: String = item_id.into();
         Ok(Self {
+            item_id: id.clone(),
+            etag: content_tag(&contents),
+            check,
             inner: VdirItemStore::new(
                 path,
                 Some(id),
@@ -67,12 +99,36 @@ impl VdirCalendarItemUpdate {
 
 impl VdirCoroutine for VdirCalendarItemUpdate {
     type Yield = VdirYield;
-    type Return = Result<(), VdirCalendarItemUpdateError>;
+    type Return = Result<CalendarItemReceipt, VdirCalendarItemUpdateError>;
 
     fn resume(&mut self, arg: Option<VdirReply>) -> VdirCoroutineState<Self::Yield, Self::Return> {
+        let mut arg = arg;
+
+        if let Some((get, expected)) = &mut self.check {
+            match get.resume(arg.take()) {
+                VdirCoroutineState::Yielded(y) => return VdirCoroutineState::Yielded(y),
+                VdirCoroutineState::Complete(Ok(item)) => {
+                    if expected != "*" && content_tag(&item.contents) != *expected {
+                        let err =
+                            VdirCalendarItemUpdateError::PreconditionFailed(self.item_id.clone());
+                        return VdirCoroutineState::Complete(Err(err));
+                    }
+                    self.check = None;
+                }
+                VdirCoroutineState::Complete(Err(err)) => {
+                    return VdirCoroutineState::Complete(Err(err.into()));
+                }
+            }
+        }
+
         match self.inner.resume(arg) {
             VdirCoroutineState::Yielded(y) => VdirCoroutineState::Yielded(y),
-            VdirCoroutineState::Complete(Ok(_)) => VdirCoroutineState::Complete(Ok(())),
+            VdirCoroutineState::Complete(Ok(_)) => {
+                VdirCoroutineState::Complete(Ok(CalendarItemReceipt {
+                    id: self.item_id.clone(),
+                    etag: Some(self.etag.clone()),
+                }))
+            }
             VdirCoroutineState::Complete(Err(err)) => VdirCoroutineState::Complete(Err(err.into())),
         }
     }
@@ -1,29 +1,52 @@
-//! WebDAV item create coroutine wrapping
-//! [`io_webdav::rfc4791::item::create::CreateItem`].
+//! WebDAV item create coroutine sending a PUT through
+//! [`HttpExchange`](crate::webdav::http::HttpExchange).
+//!
+//! io-webdav's create request does not expose the response headers, so
+//! this sends its own PUT to learn the ETag of the new resource. The PUT
+//! carries `If-None-Match: *` (RFC 9110 section 13.1.2): an id collision
+//! or a retried request fails instead of overwriting an existing item.
 //!
 //! # Example
 //!
 //! ```rust,ignore
 //! // Driven through the shared-API method on the WebDAV client.
-//! let id = client.create_item("personal", ical_bytes)?;
+//! let receipt = client.create_item("personal", ical_bytes)?;
 //! ```
 
-use alloc::{string::String, vec::Vec};
-
-use io_webdav::{
-    coroutine::*,
-    rfc4791::item::create::CreateItem,
-    rfc4918::{WebdavAuth, send::SendError},
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
 };
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
 use log::trace;
+use thiserror::Error;
 use url::Url;
 
+use super::put::WebdavItemPut;
+use crate::{
+    item::CalendarItemReceipt,
+    webdav::{convert::item_path, http::HttpError},
+};
+
+/// Errors produced by [`WebdavCalendarItemCreate`].
+#[derive(Debug, Error)]
+pub enum WebdavCalendarItemCreateError {
+    #[error(transparent)]
+    Http(#[from] HttpError),
+    #[error("Item `{0}` already exists")]
+    AlreadyExists(String),
+}
+
 /// I/O-free coroutine creating a WebDAV item under an `id` minted by the
 /// caller.
 ///
-/// On completion returns the id the server confirmed.
+/// On completion returns the id along with the ETag the server
+/// reported for the new resource. A 412 answer to the `If-None-Match`
+/// precondition means a resource already has that id.
 pub struct WebdavCalendarItemCreate {
-    inner: CreateItem,
+    id: String,
+    inner: WebdavItemPut,
 }
 
 impl WebdavCalendarItemCreate {
@@ -39,21 +62,41 @@ impl WebdavCalendarItemCreate {
         contents: Vec<u8>,
     ) -> Self {
         trace!("prepare webdav item create");
+        let path = item_path(calendar_path, id);
         Self {
-            inner: CreateItem::new(base_url, auth, user_agent, calendar_path, id, contents),
+            id: id.to_string(),
+            inner: WebdavItemPut::new(
+                base_url,
+                auth,
+                user_agent,
+                &path,
+                contents,
+                Some(("If-None-Match", "*".to_string())),
+            ),
         }
     }
 }
 
 impl WebdavCoroutine for WebdavCalendarItemCreate {
     type Yield = WebdavYield;
-    type Return = Result<String, SendError>;
+    type Return = Result<CalendarItemReceipt, WebdavCalendarItemCreateError>;
 
     fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
         match self.inner.resume(arg) {
             WebdavCoroutineState::Yielded(y) => WebdavCoroutineState::Yielded(y),
-            WebdavCoroutineState::Complete(Ok(ok)) => WebdavCoroutineState::Complete(Ok(ok.id)),
-            WebdavCoroutineState::Complete(Err(err)) => WebdavCoroutineState::Complete(Err(err)),
+            WebdavCoroutineState::Complete(Ok(etag)) => {
+                WebdavCoroutineState::Complete(Ok(CalendarItemReceipt {
+                    id: self.id.clone(),
+                    etag,
+                }))
+            }
+            WebdavCoroutineState::Complete(Err(HttpError::Status { status: 412, .. })) => {
+                let err = WebdavCalendarItemCreateError::AlreadyExists(self.id.clone());
+                WebdavCoroutineState::Complete(Err(err))
+            }
+            WebdavCoroutineState::Complete(Err(err)) => {
+                WebdavCoroutineState::Complete(Err(err.into()))
+            }
         }
     }
 }
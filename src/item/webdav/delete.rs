@@ -1,51 +1,65 @@
-//! WebDAV item delete coroutine wrapping
-//! [`io_webdav::rfc4791::item::delete::DeleteItem`].
+//! WebDAV item delete coroutine sending a DELETE through
+//! [`HttpExchange`].
+//!
+//! io-webdav's delete request reports failures as an opaque error, so
+//! this sends its own (optionally `If-Match` gated) DELETE: a missing
+//! item or a stale entity tag surface as a 404 or 412
+//! [`HttpError::Status`].
 //!
 //! # Example
 //!
 //! ```rust,ignore
 //! // Driven through the shared-API method on the WebDAV client.
-//! client.delete_item("personal", "event-1")?;
+//! client.delete_item("personal", "event-1", None)?;
 //! ```
 
-use io_webdav::{
-    coroutine::*,
-    rfc4791::item::delete::DeleteItem,
-    rfc4918::{WebdavAuth, send::SendError},
-};
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
 use log::trace;
 use url::Url;
 
+use crate::webdav::{
+    convert::item_path,
+    http::{HttpError, HttpExchange, HttpRequest, quote_etag},
+};
+
 /// I/O-free coroutine deleting a single WebDAV item.
 pub struct WebdavCalendarItemDelete {
-    inner: DeleteItem,
+    inner: HttpExchange,
 }
 
 impl WebdavCalendarItemDelete {
     /// Builds the coroutine deleting item `item_id` from the collection
-    /// at `calendar_path`.
+    /// at `calendar_path`, gating on `if_match` when present.
     pub fn new(
         base_url: &Url,
         auth: &WebdavAuth,
         user_agent: &str,
         calendar_path: &str,
         item_id: &str,
+        if_match: Option<&str>,
     ) -> Self {
         trace!("prepare webdav item delete");
+        let path = item_path(calendar_path, item_id);
+        let mut request = HttpRequest::new("DELETE", base_url, auth, user_agent, &path);
+        if let Some(etag) = if_match {
+            request = request.header("If-Match", quote_etag(etag));
+        }
         Self {
-            inner: DeleteItem::new(base_url, auth, user_agent, calendar_path, item_id, None),
+            inner: HttpExchange::new(request),
         }
     }
 }
 
 impl WebdavCoroutine for WebdavCalendarItemDelete {
     type Yield = WebdavYield;
-    type Return = Result<(), SendError>;
+    type Return = Result<(), HttpError>;
 
     fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
         match self.inner.resume(arg) {
             WebdavCoroutineState::Yielded(y) => WebdavCoroutineState::Yielded(y),
-            WebdavCoroutineState::Complete(Ok(_)) => WebdavCoroutineState::Complete(Ok(())),
+            WebdavCoroutineState::Complete(Ok(response)) => {
+                WebdavCoroutineState::Complete(response.success().map(|_| ()))
+            }
             WebdavCoroutineState::Complete(Err(err)) => WebdavCoroutineState::Complete(Err(err)),
         }
     }
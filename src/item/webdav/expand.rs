@@ -0,0 +1,206 @@
+//! WebDAV item list coroutine asking the server to expand recurring
+//! items (RFC 4791 sections 9.6.5 and 9.6.6).
+//!
+//! io-webdav's `ListItems` only takes the `comp-filter`, so this sends
+//! its own `calendar-query` REPORT through [`HttpExchange`], with an
+//! `expand` or `limit-recurrence-set` element inside `calendar-data`.
+//! Kinds are queried one REPORT each, as in
+//! [`WebdavCalendarItemList`](super::list::WebdavCalendarItemList).
+//!
+//! With the `parser` feature, expanded resources are split into one
+//! [`CalendarItem`] per instance, matching what the vdir and memory
+//! backends return for the same [`TimeRange`].
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! // Driven through the shared-API method on the WebDAV client.
+//! let range = TimeRange::new(Some("20250101T000000Z"), Some("20250201T000000Z"))
+//!     .unwrap()
+//!     .expand();
+//! let occurrences = client.list_items("personal", None, None, Some(&range), &[], None)?;
+//! ```
+
+use alloc::{
+    collections::VecDeque,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::mem;
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
+use log::trace;
+use url::Url;
+
+use crate::{
+    item::{CalendarItem, CalendarItemKind, ItemFilter, RecurrenceExpansion, TimeRange, paginate},
+    webdav::{
+        convert::item_id_from_href,
+        http::{HttpError, HttpExchange, HttpRequest},
+        xml::Multistatus,
+    },
+};
+
+use super::list::comp_filters;
+
+/// Start sent for a range without one: CalDAV requires both bounds on
+/// `expand` and `limit-recurrence-set`.
+const EARLIEST: &str = "00010101T000000Z";
+
+/// End sent for a range without one.
+const LATEST: &str = "99991231T235959Z";
+
+/// I/O-free coroutine listing the items inside a WebDAV calendar
+/// collection with their recurrences shaped by the server.
+///
+/// On completion maps each response to a [`CalendarItem`], merges the
+/// per-kind queries, sorts by id, then applies 1-indexed pagination.
+pub struct WebdavCalendarItemExpand {
+    calendar_id: String,
+    expansion: RecurrenceExpansion,
+    page: Option<u32>,
+    page_size: Option<u32>,
+    items: Vec<CalendarItem>,
+    queue: VecDeque<HttpExchange>,
+    inner: HttpExchange,
+}
+
+impl WebdavCalendarItemExpand {
+    /// Builds the coroutine listing items of the collection at
+    /// `calendar_path` (the calendar `calendar_id`) overlapping
+    /// `time_range`, shaped by its [`RecurrenceExpansion`] (`expand`
+    /// when none is set), applying 1-indexed pagination on completion.
+    ///
+    /// When `kinds` is non-empty, only items of those kinds are listed;
+    /// when `filter` is set, only items matching it.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        base_url: &Url,
+        auth: &WebdavAuth,
+        user_agent: &str,
+        calendar_path: &str,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: &TimeRange,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Self {
+        trace!("prepare webdav item expand");
+
+        let expansion = time_range
+            .expansion()
+            .unwrap_or(RecurrenceExpansion::Expand);
+        let path = format!("{}/", calendar_path.trim_end_matches('/'));
+
+        let mut queue: VecDeque<HttpExchange> = comp_filters(Some(time_range), kinds, filter)
+            .iter()
+            .map(|filter| {
+                let request = HttpRequest::new("REPORT", base_url, auth, user_agent, &path)
+                    .header("Depth", "1")
+                    .xml(calendar_query(time_range, expansion, filter));
+                HttpExchange::new(request)
+            })
+            .collect();
+
+        // NOTE: comp_filters always returns at least one filter
+        let inner = queue.pop_front().unwrap();
+
+        Self {
+            calendar_id: calendar_id.to_string(),
+            expansion,
+            page,
+            page_size,
+            items: Vec::new(),
+            queue,
+            inner,
+        }
+    }
+}
+
+impl WebdavCoroutine for WebdavCalendarItemExpand {
+    type Yield = WebdavYield;
+    type Return = Result<Vec<CalendarItem>, HttpError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        let mut arg = arg;
+
+        loop {
+            let response = match self.inner.resume(arg.take()) {
+                WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+                WebdavCoroutineState::Complete(Ok(response)) => response,
+                WebdavCoroutineState::Complete(Err(err)) => {
+                    return WebdavCoroutineState::Complete(Err(err));
+                }
+            };
+
+            let response = match response.success() {
+                Ok(response) => response,
+                Err(err) => return WebdavCoroutineState::Complete(Err(err)),
+            };
+
+            let Some(multistatus) = Multistatus::parse(&response.body) else {
+                let err = HttpError::InvalidResponse("expected a multistatus body");
+                return WebdavCoroutineState::Complete(Err(err));
+            };
+
+            for response in multistatus.responses {
+                let Some(contents) = response.calendar_data() else {
+                    continue;
+                };
+
+                let item = CalendarItem {
+                    id: item_id_from_href(&response.href),
+                    calendar_id: self.calendar_id.clone(),
+                    etag: response.etag(),
+                    contents,
+                };
+
+                #[cfg(feature = "parser")]
+                if self.expansion == RecurrenceExpansion::Expand {
+                    self.items.extend(item.split_instances());
+                    continue;
+                }
+
+                self.items.push(item);
+            }
+
+            if let Some(next) = self.queue.pop_front() {
+                trace!("webdav item expand: running next kind query");
+                self.inner = next;
+                continue;
+            }
+
+            let mut items = mem::take(&mut self.items);
+            // NOTE: stable sort, so instances keep the server order
+            items.sort_by(|a, b| a.id.cmp(&b.id));
+            if self.expansion == RecurrenceExpansion::LimitRecurrenceSet {
+                items.dedup_by(|a, b| a.id == b.id);
+            }
+            let items = paginate(items, self.page, self.page_size);
+            return WebdavCoroutineState::Complete(Ok(items));
+        }
+    }
+}
+
+/// Builds a `calendar-query` REPORT body asking for `getetag` plus the
+/// `calendar-data` shaped by `expansion`, filtered by `filter`.
+fn calendar_query(range: &TimeRange, expansion: RecurrenceExpansion, filter: &str) -> String {
+    let element = match expansion {
+        RecurrenceExpansion::Expand => "expand",
+        RecurrenceExpansion::LimitRecurrenceSet => "limit-recurrence-set",
+    };
+    let start = range.start().unwrap_or(EARLIEST);
+    let end = range.end().unwrap_or(LATEST);
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <C:calendar-query xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+         <D:prop><D:getetag /><C:calendar-data>\
+         <C:{element} start=\"{start}\" end=\"{end}\" />\
+         </C:calendar-data></D:prop>\
+         <C:filter><C:comp-filter name=\"VCALENDAR\">{filter}</C:comp-filter></C:filter>\
+         </C:calendar-query>"
+    )
+}
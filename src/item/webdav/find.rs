@@ -0,0 +1,154 @@
+//! WebDAV item lookup by iCalendar UID, sending a `calendar-query`
+//! REPORT with a UID `prop-filter` (RFC 4791 section 7.8.6).
+//!
+//! `text-match` is a substring match, so the UID of every returned item
+//! is checked again here. A `comp-filter` only names one component, so
+//! kinds are queried one REPORT each, stopping at the first hit.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! // Driven through the shared-API method on the WebDAV client.
+//! let item = client.find_item_by_uid("personal", "4f1c@example.com")?;
+//! ```
+
+use alloc::{
+    collections::VecDeque,
+    format,
+    string::{String, ToString},
+};
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
+use log::trace;
+use url::Url;
+
+use crate::{
+    item::{CalendarItem, CalendarItemKind},
+    webdav::{
+        convert::item_id_from_href,
+        http::{HttpError, HttpExchange, HttpRequest},
+        xml::{Multistatus, escape},
+    },
+};
+
+/// I/O-free coroutine finding the item carrying a given UID inside a
+/// WebDAV calendar collection.
+///
+/// On completion returns the matching item with its ETag, the one with
+/// the smallest id when several match, or `None`.
+pub struct WebdavCalendarItemFind {
+    calendar_id: String,
+    uid: String,
+    queue: VecDeque<HttpExchange>,
+    inner: HttpExchange,
+}
+
+impl WebdavCalendarItemFind {
+    /// Builds the coroutine looking for `uid` in the collection at
+    /// `calendar_path` (the calendar `calendar_id`).
+    pub fn new(
+        base_url: &Url,
+        auth: &WebdavAuth,
+        user_agent: &str,
+        calendar_path: &str,
+        calendar_id: &str,
+        uid: &str,
+    ) -> Self {
+        trace!("prepare webdav item find");
+
+        let path = format!("{}/", calendar_path.trim_end_matches('/'));
+
+        let mut queue: VecDeque<HttpExchange> = CalendarItemKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let request = HttpRequest::new("REPORT", base_url, auth, user_agent, &path)
+                    .header("Depth", "1")
+                    .xml(calendar_query(kind, uid));
+                HttpExchange::new(request)
+            })
+            .collect();
+
+        // NOTE: CalendarItemKind::ALL is never empty
+        let inner = queue.pop_front().unwrap();
+
+        Self {
+            calendar_id: calendar_id.to_string(),
+            uid: uid.to_string(),
+            queue,
+            inner,
+        }
+    }
+}
+
+impl WebdavCoroutine for WebdavCalendarItemFind {
+    type Yield = WebdavYield;
+    type Return = Result<Option<CalendarItem>, HttpError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        let mut arg = arg;
+
+        loop {
+            let response = match self.inner.resume(arg.take()) {
+                WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+                WebdavCoroutineState::Complete(Ok(response)) => response,
+                WebdavCoroutineState::Complete(Err(err)) => {
+                    return WebdavCoroutineState::Complete(Err(err));
+                }
+            };
+
+            let response = match response.success() {
+                Ok(response) => response,
+                Err(err) => return WebdavCoroutineState::Complete(Err(err)),
+            };
+
+            let Some(multistatus) = Multistatus::parse(&response.body) else {
+                let err = HttpError::InvalidResponse("expected a multistatus body");
+                return WebdavCoroutineState::Complete(Err(err));
+            };
+
+            let found = multistatus
+                .responses
+                .into_iter()
+                .filter_map(|response| {
+                    Some(CalendarItem {
+                        id: item_id_from_href(&response.href),
+                        calendar_id: self.calendar_id.clone(),
+                        etag: response.etag(),
+                        contents: response.calendar_data()?,
+                    })
+                })
+                .filter(|item| item.uid().as_deref() == Some(self.uid.as_str()))
+                .min_by(|a, b| a.id.cmp(&b.id));
+
+            if found.is_some() {
+                return WebdavCoroutineState::Complete(Ok(found));
+            }
+
+            let Some(next) = self.queue.pop_front() else {
+                return WebdavCoroutineState::Complete(Ok(None));
+            };
+
+            trace!("webdav item find: running next kind query");
+            self.inner = next;
+        }
+    }
+}
+
+/// Builds a `calendar-query` REPORT body asking for `getetag` and
+/// `calendar-data` of the `kind` components whose UID contains `uid`.
+fn calendar_query(kind: CalendarItemKind, uid: &str) -> String {
+    let name = kind.component_name();
+    let uid = escape(uid);
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <C:calendar-query xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+         <D:prop><D:getetag /><C:calendar-data /></D:prop>\
+         <C:filter><C:comp-filter name=\"VCALENDAR\"><C:comp-filter name=\"{name}\">\
+         <C:prop-filter name=\"UID\">\
+         <C:text-match collation=\"i;octet\">{uid}</C:text-match>\
+         </C:prop-filter>\
+         </C:comp-filter></C:comp-filter></C:filter>\
+         </C:calendar-query>"
+    )
+}
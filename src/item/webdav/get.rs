@@ -1,5 +1,8 @@
-//! WebDAV item get coroutine wrapping
-//! [`io_webdav::rfc4791::item::read::ReadItem`].
+//! WebDAV item get coroutine sending a GET through [`HttpExchange`].
+//!
+//! io-webdav's read request reports failures as an opaque error, so
+//! this sends its own GET: a missing item surfaces as a 404
+//! [`HttpError::Status`].
 //!
 //! # Example
 //!
@@ -10,15 +13,17 @@
 
 use alloc::string::{String, ToString};
 
-use io_webdav::{
-    coroutine::*,
-    rfc4791::item::read::ReadItem,
-    rfc4918::{WebdavAuth, send::SendError},
-};
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
 use log::trace;
 use url::Url;
 
-use crate::{item::CalendarItem, webdav::convert::item_from_body};
+use crate::{
+    item::CalendarItem,
+    webdav::{
+        convert::item_path,
+        http::{HttpError, HttpExchange, HttpRequest},
+    },
+};
 
 /// I/O-free coroutine reading a single WebDAV item by id.
 ///
@@ -27,7 +32,7 @@ use crate::{item::CalendarItem, webdav::convert::item_from_body};
 pub struct WebdavCalendarItemGet {
     calendar_id: String,
     item_id: String,
-    inner: ReadItem,
+    inner: HttpExchange,
 }
 
 impl WebdavCalendarItemGet {
@@ -42,26 +47,42 @@ impl WebdavCalendarItemGet {
         item_id: &str,
     ) -> Self {
         trace!("prepare webdav item get");
+        let path = item_path(calendar_path, item_id);
+        let request = HttpRequest::new("GET", base_url, auth, user_agent, &path)
+            .header("Accept", "text/calendar");
         Self {
             calendar_id: calendar_id.to_string(),
             item_id: item_id.to_string(),
-            inner: ReadItem::new(base_url, auth, user_agent, calendar_path, item_id),
+            inner: HttpExchange::new(request),
         }
     }
 }
 
 impl WebdavCoroutine for WebdavCalendarItemGet {
     type Yield = WebdavYield;
-    type Return = Result<CalendarItem, SendError>;
+    type Return = Result<CalendarItem, HttpError>;
 
     fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
-        match self.inner.resume(arg) {
-            WebdavCoroutineState::Yielded(y) => WebdavCoroutineState::Yielded(y),
-            WebdavCoroutineState::Complete(Ok(body)) => {
-                let item = item_from_body(body, &self.calendar_id, &self.item_id);
-                WebdavCoroutineState::Complete(Ok(item))
+        let response = match self.inner.resume(arg) {
+            WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+            WebdavCoroutineState::Complete(Ok(response)) => response,
+            WebdavCoroutineState::Complete(Err(err)) => {
+                return WebdavCoroutineState::Complete(Err(err));
             }
-            WebdavCoroutineState::Complete(Err(err)) => WebdavCoroutineState::Complete(Err(err)),
-        }
+        };
+
+        let response = match response.success() {
+            Ok(response) => response,
+            Err(err) => return WebdavCoroutineState::Complete(Err(err)),
+        };
+
+        let item = CalendarItem {
+            id: self.item_id.clone(),
+            calendar_id: self.calendar_id.clone(),
+            etag: response.etag().filter(|etag| !etag.is_empty()),
+            contents: response.body,
+        };
+
+        WebdavCoroutineState::Complete(Ok(item))
     }
 }
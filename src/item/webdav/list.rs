@@ -1,23 +1,29 @@
 //! WebDAV item list coroutine wrapping
 //! [`io_webdav::rfc4791::item::list::ListItems`].
 //!
-//! Lists every item kind (the `comp_filter` is empty) unless a
-//! [`TimeRange`] is given, in which case the query is constrained to
-//! VEVENT components overlapping the range (the only kind a time-range
-//! filter applies to in the shared API).
+//! Lists every item kind (the `comp_filter` is empty) unless kinds, a
+//! [`TimeRange`] or an [`ItemFilter`] are given. CalDAV ANDs sibling
+//! comp-filters, so each requested kind is sent as its own REPORT (one
+//! `comp-filter` per query, carrying the time range and the
+//! `prop-filter`s when set) and the results are merged. A time range or
+//! a filter without kinds queries every kind, so VTODO and VJOURNAL are
+//! matched by the server following RFC 4791 sections 9.7 and 9.9 too.
 //!
 //! # Example
 //!
 //! ```rust,ignore
 //! // Driven through the shared-API method on the WebDAV client.
-//! let items = client.list_items("personal", None, None, None)?;
+//! let items = client.list_items("personal", None, None, None, &[CalendarItemKind::Todo], None)?;
 //! ```
 
 use alloc::{
+    collections::VecDeque,
     format,
     string::{String, ToString},
+    vec,
     vec::Vec,
 };
+use core::mem;
 
 use io_webdav::{
     coroutine::*,
@@ -28,19 +34,23 @@ use log::trace;
 use url::Url;
 
 use crate::{
-    item::{CalendarItem, TimeRange},
-    webdav::convert::{item_from_entry, paginate},
+    item::{
+        CalendarItem, CalendarItemKind, FilterTest, ItemFilter, TextMatch, TimeRange, paginate,
+    },
+    webdav::{convert::item_from_entry, xml::escape},
 };
 
-/// I/O-free coroutine listing every item inside a WebDAV calendar
+/// I/O-free coroutine listing the items inside a WebDAV calendar
 /// collection.
 ///
-/// On completion maps each wire entry to a [`CalendarItem`], sorts by
-/// id, then applies 1-indexed pagination.
+/// On completion maps each wire entry to a [`CalendarItem`], merges the
+/// per-kind queries, sorts by id, then applies 1-indexed pagination.
 pub struct WebdavCalendarItemList {
     calendar_id: String,
     page: Option<u32>,
     page_size: Option<u32>,
+    items: Vec<CalendarItem>,
+    queue: VecDeque<ListItems>,
     inner: ListItems,
 }
 
@@ -49,8 +59,10 @@ impl WebdavCalendarItemList {
     /// `calendar_path` (the calendar `calendar_id`), applying 1-indexed
     /// pagination on completion.
     ///
+    /// When `kinds` is non-empty, only items of those kinds are listed.
     /// When `time_range` is set, the server query is constrained to
-    /// VEVENT components overlapping the range.
+    /// components overlapping the range; when `filter` is set, to
+    /// components matching its property filters.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         base_url: &Url,
@@ -61,28 +73,26 @@ impl WebdavCalendarItemList {
         page: Option<u32>,
         page_size: Option<u32>,
         time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
     ) -> Self {
         trace!("prepare webdav item list");
 
-        let comp_filter = match time_range {
-            None => String::new(),
-            Some(range) => {
-                let mut attrs = String::new();
-                if let Some(start) = range.start() {
-                    attrs.push_str(&format!(" start=\"{start}\""));
-                }
-                if let Some(end) = range.end() {
-                    attrs.push_str(&format!(" end=\"{end}\""));
-                }
-                format!("<C:comp-filter name=\"VEVENT\"><C:time-range{attrs} /></C:comp-filter>")
-            }
-        };
+        let mut queue: VecDeque<ListItems> = comp_filters(time_range, kinds, filter)
+            .iter()
+            .map(|filter| ListItems::new(base_url, auth, user_agent, calendar_path, filter))
+            .collect();
+
+        // NOTE: comp_filters always returns at least one filter
+        let inner = queue.pop_front().unwrap();
 
         Self {
             calendar_id: calendar_id.to_string(),
             page,
             page_size,
-            inner: ListItems::new(base_url, auth, user_agent, calendar_path, &comp_filter),
+            items: Vec::new(),
+            queue,
+            inner,
         }
     }
 }
@@ -92,18 +102,123 @@ impl WebdavCoroutine for WebdavCalendarItemList {
     type Return = Result<Vec<CalendarItem>, SendError>;
 
     fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
-        match self.inner.resume(arg) {
-            WebdavCoroutineState::Yielded(y) => WebdavCoroutineState::Yielded(y),
-            WebdavCoroutineState::Complete(Ok(entries)) => {
-                let mut items: Vec<CalendarItem> = entries
-                    .into_iter()
-                    .map(|entry| item_from_entry(entry, &self.calendar_id))
-                    .collect();
-                items.sort_by(|a, b| a.id.cmp(&b.id));
-                let items = paginate(items, self.page, self.page_size);
-                WebdavCoroutineState::Complete(Ok(items))
+        let mut arg = arg;
+
+        loop {
+            match self.inner.resume(arg.take()) {
+                WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+                WebdavCoroutineState::Complete(Ok(entries)) => {
+                    self.items.extend(
+                        entries
+                            .into_iter()
+                            .map(|entry| item_from_entry(entry, &self.calendar_id)),
+                    );
+
+                    if let Some(next) = self.queue.pop_front() {
+                        trace!("webdav item list: running next kind query");
+                        self.inner = next;
+                        continue;
+                    }
+
+                    let mut items = mem::take(&mut self.items);
+                    items.sort_by(|a, b| a.id.cmp(&b.id));
+                    items.dedup_by(|a, b| a.id == b.id);
+                    let items = paginate(items, self.page, self.page_size);
+                    return WebdavCoroutineState::Complete(Ok(items));
+                }
+                WebdavCoroutineState::Complete(Err(err)) => {
+                    return WebdavCoroutineState::Complete(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Builds the inner `comp-filter` of each query: one per distinct kind,
+/// or a single empty filter when every item is wanted.
+pub(crate) fn comp_filters(
+    time_range: Option<&TimeRange>,
+    kinds: &[CalendarItemKind],
+    filter: Option<&ItemFilter>,
+) -> Vec<String> {
+    let time_range = match time_range {
+        None => String::new(),
+        Some(range) => {
+            let mut attrs = String::new();
+            if let Some(start) = range.start() {
+                attrs.push_str(&format!(" start=\"{start}\""));
+            }
+            if let Some(end) = range.end() {
+                attrs.push_str(&format!(" end=\"{end}\""));
             }
-            WebdavCoroutineState::Complete(Err(err)) => WebdavCoroutineState::Complete(Err(err)),
+            format!("<C:time-range{attrs} />")
         }
+    };
+
+    let kinds: Vec<CalendarItemKind> = CalendarItemKind::ALL
+        .into_iter()
+        .filter(|kind| kinds.is_empty() || kinds.contains(kind))
+        .collect();
+
+    let prop_filters = filter.map(prop_filters).unwrap_or_default();
+
+    if kinds.len() == CalendarItemKind::ALL.len()
+        && time_range.is_empty()
+        && prop_filters.is_empty()
+    {
+        return vec![String::new()];
     }
+
+    kinds
+        .into_iter()
+        .map(|kind| {
+            let name = kind.component_name();
+            format!("<C:comp-filter name=\"{name}\">{time_range}{prop_filters}</C:comp-filter>")
+        })
+        .collect()
+}
+
+/// Serializes the `prop-filter` elements of `filter` (RFC 4791 section
+/// 9.7.2).
+fn prop_filters(filter: &ItemFilter) -> String {
+    filter
+        .props
+        .iter()
+        .map(|prop| {
+            let name = escape(&prop.name.to_ascii_uppercase());
+            let test = match &prop.test {
+                FilterTest::NotDefined => {
+                    return format!(
+                        "<C:prop-filter name=\"{name}\"><C:is-not-defined /></C:prop-filter>"
+                    );
+                }
+                FilterTest::Defined => String::new(),
+                FilterTest::Text(text_match) => text_match_element(text_match),
+            };
+            let params: String = prop
+                .params
+                .iter()
+                .map(|param| {
+                    let test = match &param.test {
+                        FilterTest::NotDefined => "<C:is-not-defined />".to_string(),
+                        FilterTest::Defined => String::new(),
+                        FilterTest::Text(text_match) => text_match_element(text_match),
+                    };
+                    let name = escape(&param.name.to_ascii_uppercase());
+                    format!("<C:param-filter name=\"{name}\">{test}</C:param-filter>")
+                })
+                .collect();
+            format!("<C:prop-filter name=\"{name}\">{test}{params}</C:prop-filter>")
+        })
+        .collect()
+}
+
+/// Serializes a `text-match` element (RFC 4791 section 9.7.5).
+fn text_match_element(text_match: &TextMatch) -> String {
+    let collation = text_match.collation.as_str();
+    let negate = if text_match.negate { "yes" } else { "no" };
+    let text = escape(&text_match.text);
+    format!(
+        "<C:text-match collation=\"{collation}\" negate-condition=\"{negate}\">{text}</C:text-match>"
+    )
 }
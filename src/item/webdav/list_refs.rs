@@ -0,0 +1,114 @@
+//! WebDAV item reference list coroutine sending a Depth 1 PROPFIND
+//! through [`HttpExchange`].
+//!
+//! Only `getetag`, `getcontentlength`, `getlastmodified` and
+//! `resourcetype` are asked for, so no `calendar-data` is downloaded:
+//! enough to tell which items changed before fetching them.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! // Driven through the shared-API method on the WebDAV client.
+//! let refs = client.list_item_refs("personal")?;
+//! ```
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
+use log::trace;
+use url::Url;
+
+use crate::{
+    item::CalendarItemRef,
+    webdav::{
+        convert::item_id_from_href,
+        http::{HttpError, HttpExchange, HttpRequest},
+        xml::Multistatus,
+    },
+};
+
+/// Body of the PROPFIND request.
+const PROPFIND: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+    <D:propfind xmlns:D=\"DAV:\">\
+    <D:prop><D:resourcetype /><D:getetag /><D:getcontentlength /><D:getlastmodified /></D:prop>\
+    </D:propfind>";
+
+/// I/O-free coroutine listing the id, ETag, size and modification time
+/// of every member of a WebDAV calendar collection.
+///
+/// On completion returns one [`CalendarItemRef`] per non-collection
+/// member, sorted by id.
+pub struct WebdavCalendarItemRefList {
+    calendar_id: String,
+    inner: HttpExchange,
+}
+
+impl WebdavCalendarItemRefList {
+    /// Builds the coroutine listing the members of the collection at
+    /// `calendar_path` (the calendar `calendar_id`).
+    pub fn new(
+        base_url: &Url,
+        auth: &WebdavAuth,
+        user_agent: &str,
+        calendar_path: &str,
+        calendar_id: &str,
+    ) -> Self {
+        trace!("prepare webdav item ref list");
+
+        let path = format!("{}/", calendar_path.trim_end_matches('/'));
+        let request = HttpRequest::new("PROPFIND", base_url, auth, user_agent, &path)
+            .header("Depth", "1")
+            .xml(PROPFIND.to_string());
+
+        Self {
+            calendar_id: calendar_id.to_string(),
+            inner: HttpExchange::new(request),
+        }
+    }
+}
+
+impl WebdavCoroutine for WebdavCalendarItemRefList {
+    type Yield = WebdavYield;
+    type Return = Result<Vec<CalendarItemRef>, HttpError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        let response = match self.inner.resume(arg) {
+            WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+            WebdavCoroutineState::Complete(Ok(response)) => response,
+            WebdavCoroutineState::Complete(Err(err)) => {
+                return WebdavCoroutineState::Complete(Err(err));
+            }
+        };
+
+        let response = match response.success() {
+            Ok(response) => response,
+            Err(err) => return WebdavCoroutineState::Complete(Err(err)),
+        };
+
+        let Some(multistatus) = Multistatus::parse(&response.body) else {
+            let err = HttpError::InvalidResponse("expected a multistatus body");
+            return WebdavCoroutineState::Complete(Err(err));
+        };
+
+        // NOTE: the collection itself comes back as a collection member
+        let mut refs: Vec<CalendarItemRef> = multistatus
+            .responses
+            .into_iter()
+            .filter(|response| !response.is_collection())
+            .map(|response| CalendarItemRef {
+                id: item_id_from_href(&response.href),
+                calendar_id: self.calendar_id.clone(),
+                etag: response.etag(),
+                size: response.content_length(),
+                last_modified: response.last_modified(),
+            })
+            .collect();
+        refs.sort_by(|a, b| a.id.cmp(&b.id));
+
+        WebdavCoroutineState::Complete(Ok(refs))
+    }
+}
@@ -1,8 +1,20 @@
 //! WebDAV (CalDAV) coroutines mapping calendar item operations onto the
-//! io-webdav RFC 4791 coroutines.
+//! io-webdav RFC 4791 coroutines, or onto raw [`HttpExchange`]s where
+//! io-webdav has no matching request.
+//!
+//! [`HttpExchange`]: crate::webdav::http::HttpExchange
 
 pub mod create;
 pub mod delete;
+pub mod expand;
+#[cfg(feature = "parser")]
+pub mod find;
 pub mod get;
 pub mod list;
+pub mod list_refs;
+pub mod multiget;
+mod put;
+#[cfg(feature = "parser")]
+pub mod search;
+pub mod sync;
 pub mod update;
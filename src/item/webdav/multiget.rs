@@ -0,0 +1,153 @@
+//! WebDAV item batch get coroutine sending a `calendar-multiget`
+//! REPORT (RFC 4791 section 7.9).
+//!
+//! io-webdav has no multiget request, so this sends its own REPORT
+//! through [`HttpExchange`], naming every item by href: fetching many
+//! items costs one round trip instead of one GET per item.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! // Driven through the shared-API method on the WebDAV client.
+//! let items = client.get_items("personal", &["event-1", "event-2"])?;
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
+use log::trace;
+use url::Url;
+
+use crate::{
+    item::CalendarItem,
+    webdav::{
+        convert::{item_id_from_href, item_path},
+        http::{HttpError, HttpExchange, HttpRequest},
+        xml::{Multistatus, escape},
+    },
+};
+
+/// I/O-free coroutine reading several WebDAV items by id in one
+/// request.
+///
+/// On completion returns the found items in the requested order, with
+/// their ETags; ids the server reports as missing are left out and
+/// repeated ids are returned once.
+pub struct WebdavCalendarItemMultiget {
+    calendar_id: String,
+    item_ids: Vec<String>,
+    inner: HttpExchange,
+}
+
+impl WebdavCalendarItemMultiget {
+    /// Builds the coroutine reading items `item_ids` from the
+    /// collection at `calendar_path` (the calendar `calendar_id`).
+    pub fn new(
+        base_url: &Url,
+        auth: &WebdavAuth,
+        user_agent: &str,
+        calendar_path: &str,
+        calendar_id: &str,
+        item_ids: &[&str],
+    ) -> Self {
+        trace!("prepare webdav item multiget");
+
+        let path = format!("{}/", calendar_path.trim_end_matches('/'));
+        let request = HttpRequest::new("REPORT", base_url, auth, user_agent, &path)
+            .header("Depth", "1")
+            .xml(calendar_multiget(&path, item_ids));
+
+        Self {
+            calendar_id: calendar_id.to_string(),
+            item_ids: item_ids.iter().map(ToString::to_string).collect(),
+            inner: HttpExchange::new(request),
+        }
+    }
+}
+
+impl WebdavCoroutine for WebdavCalendarItemMultiget {
+    type Yield = WebdavYield;
+    type Return = Result<Vec<CalendarItem>, HttpError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        let response = match self.inner.resume(arg) {
+            WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+            WebdavCoroutineState::Complete(Ok(response)) => response,
+            WebdavCoroutineState::Complete(Err(err)) => {
+                return WebdavCoroutineState::Complete(Err(err));
+            }
+        };
+
+        let response = match response.success() {
+            Ok(response) => response,
+            Err(err) => return WebdavCoroutineState::Complete(Err(err)),
+        };
+
+        let Some(multistatus) = Multistatus::parse(&response.body) else {
+            let err = HttpError::InvalidResponse("expected a multistatus body");
+            return WebdavCoroutineState::Complete(Err(err));
+        };
+
+        let mut found = BTreeMap::new();
+
+        for response in multistatus.responses {
+            // NOTE: missing items come back as a 404 response
+            let Some(contents) = response.calendar_data() else {
+                continue;
+            };
+
+            let id = item_id_from_href(&response.href);
+            let item = CalendarItem {
+                id: id.clone(),
+                calendar_id: self.calendar_id.clone(),
+                etag: response.etag(),
+                contents,
+            };
+            found.insert(id, item);
+        }
+
+        let items = self
+            .item_ids
+            .iter()
+            .filter_map(|id| found.remove(id))
+            .collect();
+
+        WebdavCoroutineState::Complete(Ok(items))
+    }
+}
+
+/// Builds a `calendar-multiget` REPORT body asking for `getetag` and
+/// `calendar-data` of each item of the collection at `path`, the ids
+/// percent-encoded as path segments.
+fn calendar_multiget(path: &str, item_ids: &[&str]) -> String {
+    let hrefs: String = item_ids
+        .iter()
+        .map(|id| format!("<D:href>{}</D:href>", escape(&item_path(path, id))))
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <C:calendar-multiget xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+         <D:prop><D:getetag /><C:calendar-data /></D:prop>\
+         {hrefs}\
+         </C:calendar-multiget>"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_item_hrefs() {
+        let body = calendar_multiget("/dav/personal/", &["a b", "x&y", "event-1"]);
+        assert!(body.contains("<D:href>/dav/personal/a%20b.ics</D:href>"));
+        assert!(body.contains("<D:href>/dav/personal/x&amp;y.ics</D:href>"));
+        assert!(body.contains("<D:href>/dav/personal/event-1.ics</D:href>"));
+    }
+}
@@ -0,0 +1,101 @@
+//! PUT exchange shared by the WebDAV item create and update
+//! coroutines, learning the ETag of the written resource.
+//!
+//! The ETag is read from the PUT response header when the server sends
+//! one; otherwise a Depth 0 PROPFIND asks for `getetag`. A server that
+//! altered the stored contents may report no ETag at all (RFC 4791
+//! section 5.3.4), in which case none is returned.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
+use log::trace;
+use url::Url;
+
+use crate::webdav::{
+    http::{HttpError, HttpExchange, HttpRequest},
+    xml::Multistatus,
+};
+
+/// Body of the follow-up PROPFIND.
+const PROPFIND: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+    <D:propfind xmlns:D=\"DAV:\"><D:prop><D:getetag /></D:prop></D:propfind>";
+
+/// I/O-free coroutine writing one item resource, returning its ETag
+/// when the server reports one.
+pub(crate) struct WebdavItemPut {
+    propfind: Option<HttpRequest>,
+    inner: HttpExchange,
+}
+
+impl WebdavItemPut {
+    /// Builds the coroutine storing `contents` at the item resource
+    /// `path`, sending the `precondition` header (`If-Match` or
+    /// `If-None-Match`) when given.
+    pub fn new(
+        base_url: &Url,
+        auth: &WebdavAuth,
+        user_agent: &str,
+        path: &str,
+        contents: Vec<u8>,
+        precondition: Option<(&'static str, String)>,
+    ) -> Self {
+        let mut put = HttpRequest::new("PUT", base_url, auth, user_agent, path);
+        if let Some((name, value)) = precondition {
+            put = put.header(name, value);
+        }
+
+        let propfind = HttpRequest::new("PROPFIND", base_url, auth, user_agent, path)
+            .header("Depth", "0")
+            .xml(PROPFIND.to_string());
+
+        Self {
+            propfind: Some(propfind),
+            inner: HttpExchange::new(put.calendar(contents)),
+        }
+    }
+}
+
+impl WebdavCoroutine for WebdavItemPut {
+    type Yield = WebdavYield;
+    type Return = Result<Option<String>, HttpError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        let mut arg = arg;
+
+        loop {
+            let response = match self.inner.resume(arg.take()) {
+                WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+                WebdavCoroutineState::Complete(Ok(response)) => response,
+                WebdavCoroutineState::Complete(Err(err)) => {
+                    return WebdavCoroutineState::Complete(Err(err));
+                }
+            };
+
+            let Some(propfind) = self.propfind.take() else {
+                // NOTE: the write already succeeded, a failed lookup
+                // only means the ETag stays unknown
+                let etag = Some(response)
+                    .filter(|response| response.is_success())
+                    .and_then(|response| Multistatus::parse(&response.body))
+                    .and_then(|multistatus| multistatus.responses.first()?.etag());
+                return WebdavCoroutineState::Complete(Ok(etag));
+            };
+
+            let response = match response.success() {
+                Ok(response) => response,
+                Err(err) => return WebdavCoroutineState::Complete(Err(err)),
+            };
+
+            if let Some(etag) = response.etag().filter(|etag| !etag.is_empty()) {
+                return WebdavCoroutineState::Complete(Ok(Some(etag)));
+            }
+
+            trace!("no etag on put response, asking with propfind");
+            self.inner = HttpExchange::new(propfind);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! WebDAV full-text item search, sending `calendar-query` REPORTs with
+//! a `text-match` `prop-filter` per searched field (RFC 4791 section
+//! 9.7).
+//!
+//! CalDAV ANDs sibling filters and a `comp-filter` only names one
+//! component, so every (kind, field) pair is its own REPORT, plus one
+//! per kind matching the `CN` parameter of ATTENDEE. Results are merged
+//! by id and checked again here with Unicode case folding, which also
+//! yields the matched field.
+//!
+//! ASCII queries use the `i;ascii-casemap` collation every server
+//! supports. Other queries use `i;unicode-casemap`; when the server
+//! refuses it with a `supported-collation` precondition, the whole
+//! collection is fetched once and scanned instead.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! // Driven through the shared-API method on the WebDAV client.
+//! let hits = client.search_items("personal", "standup")?;
+//! ```
+
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::mem;
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
+use log::trace;
+use url::Url;
+
+use crate::{
+    item::{CalendarItem, CalendarItemKind, Collation, SearchField, SearchHit, search_items},
+    webdav::{
+        convert::item_id_from_href,
+        http::{HttpError, HttpExchange, HttpRequest},
+        xml::{Multistatus, escape},
+    },
+};
+
+/// I/O-free coroutine searching the items of a WebDAV calendar
+/// collection.
+///
+/// On completion returns the matching items with their ETag and
+/// matched field, sorted by id. A blank query completes at once with no
+/// hit.
+pub struct WebdavCalendarItemSearch {
+    calendar_id: String,
+    query: String,
+    request: HttpRequest,
+    fallback: bool,
+    items: BTreeMap<String, CalendarItem>,
+    queue: VecDeque<HttpExchange>,
+    inner: Option<HttpExchange>,
+}
+
+impl WebdavCalendarItemSearch {
+    /// Builds the coroutine searching `query` in the collection at
+    /// `calendar_path` (the calendar `calendar_id`).
+    pub fn new(
+        base_url: &Url,
+        auth: &WebdavAuth,
+        user_agent: &str,
+        calendar_path: &str,
+        calendar_id: &str,
+        query: &str,
+    ) -> Self {
+        trace!("prepare webdav item search");
+
+        let path = format!("{}/", calendar_path.trim_end_matches('/'));
+        let request =
+            HttpRequest::new("REPORT", base_url, auth, user_agent, &path).header("Depth", "1");
+
+        let query = query.trim();
+        let mut queue: VecDeque<HttpExchange> = if query.is_empty() {
+            VecDeque::new()
+        } else {
+            prop_filters(query)
+                .into_iter()
+                .flat_map(|filter| {
+                    CalendarItemKind::ALL.into_iter().map(move |kind| {
+                        let name = kind.component_name();
+                        calendar_query(&format!(
+                            "<C:comp-filter name=\"{name}\">{filter}</C:comp-filter>"
+                        ))
+                    })
+                })
+                .map(|body| HttpExchange::new(request.clone().xml(body)))
+                .collect()
+        };
+
+        let inner = queue.pop_front();
+
+        Self {
+            calendar_id: calendar_id.to_string(),
+            query: query.to_string(),
+            request,
+            fallback: false,
+            items: BTreeMap::new(),
+            queue,
+            inner,
+        }
+    }
+}
+
+impl WebdavCoroutine for WebdavCalendarItemSearch {
+    type Yield = WebdavYield;
+    type Return = Result<Vec<SearchHit>, HttpError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        let mut arg = arg;
+
+        loop {
+            let Some(inner) = self.inner.as_mut() else {
+                let items = mem::take(&mut self.items).into_values().collect();
+                return WebdavCoroutineState::Complete(Ok(search_items(items, &self.query)));
+            };
+
+            let response = match inner.resume(arg.take()) {
+                WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+                WebdavCoroutineState::Complete(Ok(response)) => response,
+                WebdavCoroutineState::Complete(Err(err)) => {
+                    return WebdavCoroutineState::Complete(Err(err));
+                }
+            };
+
+            let response = match response.success() {
+                Ok(response) => response,
+                Err(err) if !self.fallback && unsupported_collation(&err) => {
+                    trace!("webdav item search: collation refused, scanning the collection");
+                    let body = calendar_query("");
+                    self.fallback = true;
+                    self.items.clear();
+                    self.queue.clear();
+                    self.inner = Some(HttpExchange::new(self.request.clone().xml(body)));
+                    continue;
+                }
+                Err(err) => return WebdavCoroutineState::Complete(Err(err)),
+            };
+
+            let Some(multistatus) = Multistatus::parse(&response.body) else {
+                let err = HttpError::InvalidResponse("expected a multistatus body");
+                return WebdavCoroutineState::Complete(Err(err));
+            };
+
+            for response in multistatus.responses {
+                let Some(contents) = response.calendar_data() else {
+                    continue;
+                };
+                let id = item_id_from_href(&response.href);
+                let item = CalendarItem {
+                    id: id.clone(),
+                    calendar_id: self.calendar_id.clone(),
+                    etag: response.etag(),
+                    contents,
+                };
+                self.items.insert(id, item);
+            }
+
+            trace!("webdav item search: running next query");
+            self.inner = self.queue.pop_front();
+        }
+    }
+}
+
+/// Whether `err` is the server refusing the requested collation.
+fn unsupported_collation(err: &HttpError) -> bool {
+    match err {
+        HttpError::Status { condition, .. } => condition
+            .as_ref()
+            .is_some_and(|condition| condition.name == "supported-collation"),
+        _ => false,
+    }
+}
+
+/// Builds the `prop-filter` elements looking for `query`, one per
+/// searched field plus one on the `CN` parameter of ATTENDEE.
+fn prop_filters(query: &str) -> Vec<String> {
+    let collation = if query.is_ascii() {
+        Collation::AsciiCasemap
+    } else {
+        Collation::UnicodeCasemap
+    };
+    let text_match = format!(
+        "<C:text-match collation=\"{}\">{}</C:text-match>",
+        collation.as_str(),
+        escape(query),
+    );
+
+    let mut filters: Vec<String> = SearchField::ALL
+        .into_iter()
+        .map(|field| {
+            let name = field.property_name();
+            format!("<C:prop-filter name=\"{name}\">{text_match}</C:prop-filter>")
+        })
+        .collect();
+
+    filters.push(format!(
+        "<C:prop-filter name=\"ATTENDEE\"><C:param-filter name=\"CN\">{text_match}</C:param-filter></C:prop-filter>"
+    ));
+
+    filters
+}
+
+/// Builds a `calendar-query` REPORT body asking for `getetag` and
+/// `calendar-data` of the items matching `comp_filter`, nested in the
+/// `VCALENDAR` one. An empty `comp_filter` lists the whole collection.
+fn calendar_query(comp_filter: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <C:calendar-query xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+         <D:prop><D:getetag /><C:calendar-data /></D:prop>\
+         <C:filter><C:comp-filter name=\"VCALENDAR\">{comp_filter}</C:comp-filter></C:filter>\
+         </C:calendar-query>"
+    )
+}
@@ -1,26 +1,42 @@
-//! WebDAV item update coroutine wrapping
-//! [`io_webdav::rfc4791::item::update::UpdateItem`].
+//! WebDAV item update coroutine sending a PUT through
+//! [`HttpExchange`](crate::webdav::http::HttpExchange).
+//!
+//! io-webdav's update request does not expose the response headers, so
+//! this sends its own (optionally `If-Match` gated) PUT to learn the new
+//! ETag of the resource.
 //!
 //! # Example
 //!
 //! ```rust,ignore
 //! // Driven through the shared-API method on the WebDAV client.
-//! client.update_item("personal", "event-1", ical_bytes, None)?;
+//! let receipt = client.update_item("personal", "event-1", ical_bytes, None)?;
 //! ```
 
-use alloc::vec::Vec;
-
-use io_webdav::{
-    coroutine::*,
-    rfc4791::item::update::UpdateItem,
-    rfc4918::{WebdavAuth, send::SendError},
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
 };
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
 use log::trace;
 use url::Url;
 
+use super::put::WebdavItemPut;
+use crate::{
+    item::CalendarItemReceipt,
+    webdav::{
+        convert::item_path,
+        http::{HttpError, quote_etag},
+    },
+};
+
 /// I/O-free coroutine overwriting an existing WebDAV item.
+///
+/// On completion returns the item id along with the new ETag the
+/// server reported.
 pub struct WebdavCalendarItemUpdate {
-    inner: UpdateItem,
+    item_id: String,
+    inner: WebdavItemPut,
 }
 
 impl WebdavCalendarItemUpdate {
@@ -37,28 +53,28 @@ impl WebdavCalendarItemUpdate {
         if_match: Option<&str>,
     ) -> Self {
         trace!("prepare webdav item update");
+        let path = item_path(calendar_path, item_id);
+        let precondition = if_match.map(|etag| ("If-Match", quote_etag(etag)));
         Self {
-            inner: UpdateItem::new(
-                base_url,
-                auth,
-                user_agent,
-                calendar_path,
-                item_id,
-                contents,
-                if_match,
-            ),
+            item_id: item_id.to_string(),
+            inner: WebdavItemPut::new(base_url, auth, user_agent, &path, contents, precondition),
         }
     }
 }
 
 impl WebdavCoroutine for WebdavCalendarItemUpdate {
     type Yield = WebdavYield;
-    type Return = Result<(), SendError>;
+    type Return = Result<CalendarItemReceipt, HttpError>;
 
     fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
         match self.inner.resume(arg) {
             WebdavCoroutineState::Yielded(y) => WebdavCoroutineState::Yielded(y),
-            WebdavCoroutineState::Complete(Ok(_)) => WebdavCoroutineState::Complete(Ok(())),
+            WebdavCoroutineState::Complete(Ok(etag)) => {
+                WebdavCoroutineState::Complete(Ok(CalendarItemReceipt {
+                    id: self.item_id.clone(),
+                    etag,
+                }))
+            }
             WebdavCoroutineState::Complete(Err(err)) => WebdavCoroutineState::Complete(Err(err)),
         }
     }
@@ -6,11 +6,19 @@ extern crate alloc;
 #[cfg(feature = "client")]
 extern crate std;
 
+#[cfg(feature = "client")]
+pub mod backend;
 pub mod calendar;
 #[cfg(feature = "client")]
-#[cfg(any(feature = "vdir", feature = "webdav"))]
 pub mod client;
+#[cfg(feature = "tokio")]
+#[cfg(any(feature = "vdir", feature = "webdav", feature = "memory"))]
+pub mod client_tokio;
 pub mod item;
+#[cfg(feature = "memory")]
+pub mod memory;
+#[cfg(feature = "client")]
+pub mod sync;
 #[cfg(feature = "vdir")]
 pub mod vdir;
 #[cfg(feature = "webdav")]
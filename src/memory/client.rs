@@ -0,0 +1,582 @@
+//! In-memory calendar client.
+//!
+//! Keeps every [`Calendar`] and [`CalendarItem`] in ordered maps, so
+//! tests and previews can exercise the shared API without a vdir tree
+//! or a CalDAV server. Item ids are random RFC 4122 v4 ids, like the
+//! ones the vdir store mints. Entity tags and CTags are synthetic: a
+//! store-wide revision counter is bumped on every write and stamped on
+//! the written item and on its parent calendar.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use log::trace;
+use thiserror::Error;
+
+#[cfg(feature = "parser")]
+use crate::item::{SearchHit, UidCheck, UidConflict, check_uid, item_uid, search_items};
+use crate::{
+    calendar::{Calendar, CalendarDiff},
+    item::{
+        CalendarItem, CalendarItemKind, CalendarItemReceipt, CalendarItemRef, ItemFilter,
+        TimeRange, expand_recurrences, filter_props, filter_time_range, new_item_id, paginate,
+    },
+    memory::convert::revision_tag,
+};
+
+/// Errors surfaced by [`MemoryClient`].
+#[derive(Debug, Error)]
+pub enum MemoryClientError {
+    #[error("Failed to gather randomness: {0}")]
+    Random(getrandom::Error),
+    #[error("Invalid calendar `{0}`")]
+    InvalidCalendar(String),
+    #[error("Invalid item id `{0}`")]
+    InvalidItemId(String),
+    #[error("Calendar `{0}` not found")]
+    CalendarNotFound(String),
+    #[error("Calendar `{0}` already exists")]
+    CalendarAlreadyExists(String),
+    #[error("Item `{0}` not found")]
+    ItemNotFound(String),
+    #[error("Item `{0}` already exists")]
+    ItemAlreadyExists(String),
+    #[error("Item `{0}` does not match the expected entity tag")]
+    PreconditionFailed(String),
+    #[error("Item body is empty")]
+    EmptyItemBody,
+}
+
+/// A calendar plus the items it contains, keyed by item id.
+#[derive(Clone, Debug, Default)]
+struct MemoryCalendar {
+    calendar: Calendar,
+    items: BTreeMap<String, CalendarItem>,
+}
+
+/// In-memory calendar client.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryClient {
+    calendars: BTreeMap<String, MemoryCalendar>,
+    revision: u64,
+}
+
+impl MemoryClient {
+    /// Builds an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lists every calendar in the store, sorted by name.
+    pub fn list_calendars(&self) -> Result<Vec<Calendar>, MemoryClientError> {
+        trace!("list memory calendars");
+        let mut calendars: Vec<Calendar> = self
+            .calendars
+            .values()
+            .map(|entry| entry.calendar.clone())
+            .collect();
+        calendars.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(calendars)
+    }
+
+    /// Creates calendar `id` (display name `name`). Fails when the id
+    /// is already taken.
+    pub fn create_calendar(
+        &mut self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<(), MemoryClientError> {
+        self.validate_calendar(id)?;
+        let id = id.trim_matches('/');
+
+        if self.calendars.contains_key(id) {
+            return Err(MemoryClientError::CalendarAlreadyExists(id.to_string()));
+        }
+
+        let ctag = self.bump();
+        let calendar = Calendar {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            color: color.map(str::to_string),
+            ctag: Some(ctag),
+        };
+
+        trace!("create memory calendar {id}");
+        self.calendars.insert(
+            id.to_string(),
+            MemoryCalendar {
+                calendar,
+                items: BTreeMap::new(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Applies `patch` to calendar `id`. Fields left as `None` in
+    /// `patch` are preserved.
+    pub fn update_calendar(
+        &mut self,
+        id: &str,
+        patch: CalendarDiff,
+    ) -> Result<(), MemoryClientError> {
+        self.validate_calendar(id)?;
+        self.calendar(id)?;
+
+        let ctag = self.bump();
+        let entry = self.calendar_mut(id)?;
+
+        if let Some(name) = patch.name {
+            entry.calendar.name = name;
+        }
+        if let Some(description) = patch.description {
+            entry.calendar.description = description;
+        }
+        if let Some(color) = patch.color {
+            entry.calendar.color = color;
+        }
+        entry.calendar.ctag = Some(ctag);
+
+        Ok(())
+    }
+
+    /// Removes calendar `id` and every item it contains.
+    pub fn delete_calendar(&mut self, id: &str) -> Result<(), MemoryClientError> {
+        self.validate_calendar(id)?;
+        let id = id.trim_matches('/');
+
+        trace!("delete memory calendar {id}");
+        match self.calendars.remove(id) {
+            Some(_) => Ok(()),
+            None => Err(MemoryClientError::CalendarNotFound(id.to_string())),
+        }
+    }
+
+    /// Lists items inside `calendar_id` sorted by id, applying
+    /// 1-indexed pagination after the optional kind and time-range
+    /// filters.
+    pub fn list_items(
+        &self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<Vec<CalendarItem>, MemoryClientError> {
+        self.validate_calendar(calendar_id)?;
+        let entry = self.calendar(calendar_id)?;
+
+        // NOTE: the map is keyed by id, so values come out sorted.
+        let items = entry
+            .items
+            .values()
+            .filter(|item| item.matches_kinds(kinds))
+            .cloned()
+            .collect();
+        let items = filter_props(items, filter);
+        let items = filter_time_range(items, time_range);
+        let items = expand_recurrences(items, time_range);
+        Ok(paginate(items, page, page_size))
+    }
+
+    /// Fetches `item_id` from `calendar_id`.
+    pub fn get_item(
+        &self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<CalendarItem, MemoryClientError> {
+        self.validate_calendar(calendar_id)?;
+        self.validate_item(item_id)?;
+
+        self.calendar(calendar_id)?
+            .items
+            .get(item_id)
+            .cloned()
+            .ok_or_else(|| MemoryClientError::ItemNotFound(item_id.to_string()))
+    }
+
+    /// Fetches `item_ids` from `calendar_id`, in the requested order.
+    /// Unknown ids are left out.
+    pub fn get_items(
+        &self,
+        calendar_id: &str,
+        item_ids: &[&str],
+    ) -> Result<Vec<CalendarItem>, MemoryClientError> {
+        self.validate_calendar(calendar_id)?;
+        for item_id in item_ids {
+            self.validate_item(item_id)?;
+        }
+
+        let calendar = self.calendar(calendar_id)?;
+        let mut seen = BTreeSet::new();
+
+        Ok(item_ids
+            .iter()
+            .filter(|item_id| seen.insert(**item_id))
+            .filter_map(|item_id| calendar.items.get(*item_id).cloned())
+            .collect())
+    }
+
+    /// Finds the item of `calendar_id` carrying the iCalendar `uid`,
+    /// the one with the smallest id when several match.
+    #[cfg(feature = "parser")]
+    pub fn find_item_by_uid(
+        &self,
+        calendar_id: &str,
+        uid: &str,
+    ) -> Result<Option<CalendarItem>, MemoryClientError> {
+        self.validate_calendar(calendar_id)?;
+
+        // NOTE: the map is keyed by id, so the first match has the
+        // smallest id.
+        Ok(self
+            .calendar(calendar_id)?
+            .items
+            .values()
+            .find(|item| item.uid().as_deref() == Some(uid))
+            .cloned())
+    }
+
+    /// Searches `query` in the SUMMARY, DESCRIPTION, LOCATION, ATTENDEE
+    /// and CATEGORIES of the items of `calendar_id`. Returns the hits
+    /// sorted by id.
+    #[cfg(feature = "parser")]
+    pub fn search_items(
+        &self,
+        calendar_id: &str,
+        query: &str,
+    ) -> Result<Vec<SearchHit>, MemoryClientError> {
+        self.validate_calendar(calendar_id)?;
+        let items = self
+            .calendar(calendar_id)?
+            .items
+            .values()
+            .cloned()
+            .collect();
+        Ok(search_items(items, query))
+    }
+
+    /// Lists the id, synthetic ETag and size of every item of
+    /// `calendar_id`, sorted by id. Memory items carry no modification
+    /// time.
+    pub fn list_item_refs(
+        &self,
+        calendar_id: &str,
+    ) -> Result<Vec<CalendarItemRef>, MemoryClientError> {
+        self.validate_calendar(calendar_id)?;
+        let calendar = self.calendar(calendar_id)?;
+        Ok(calendar.items.values().map(CalendarItemRef::from).collect())
+    }
+
+    /// Stores a new item in `calendar_id` under its UID when safe (with
+    /// the `parser` feature), otherwise a freshly minted id. Returns
+    /// that id along with the item's new ETag; fails with
+    /// [`MemoryClientError::ItemAlreadyExists`] rather than overwrite
+    /// an existing item.
+    pub fn create_item(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, MemoryClientError> {
+        if contents.is_empty() {
+            return Err(MemoryClientError::EmptyItemBody);
+        }
+        self.validate_calendar(calendar_id)?;
+        let calendar = self.calendar(calendar_id)?;
+
+        let id = new_item_id(&contents).map_err(MemoryClientError::Random)?;
+        if calendar.items.contains_key(&id) {
+            return Err(MemoryClientError::ItemAlreadyExists(id));
+        }
+
+        self.write_item(calendar_id, &id, contents)
+    }
+
+    /// Stores a new item in `calendar_id` like
+    /// [`MemoryClient::create_item`], first looking for an item with
+    /// the same UID: `on_conflict` then either fails with
+    /// [`MemoryClientError::ItemAlreadyExists`], replaces that item or
+    /// stores the new one under a suffixed UID.
+    #[cfg(feature = "parser")]
+    pub fn create_item_with(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+        on_conflict: UidConflict,
+    ) -> Result<CalendarItemReceipt, MemoryClientError> {
+        let Some(uid) = item_uid(&contents) else {
+            return self.create_item(calendar_id, contents);
+        };
+        let items = self.list_items(calendar_id, None, None, None, &[], None)?;
+
+        match check_uid(items, &uid, contents, on_conflict) {
+            UidCheck::Create(contents) => self.create_item(calendar_id, contents),
+            UidCheck::Exists(id) => Err(MemoryClientError::ItemAlreadyExists(id)),
+            UidCheck::Replace(existing, contents) => {
+                let etag = existing.etag.as_deref();
+                self.update_item(calendar_id, &existing.id, contents, etag)
+            }
+        }
+    }
+
+    /// Overwrites `item_id` inside `calendar_id`. Returns the item's
+    /// new ETag; a missing item fails with
+    /// [`MemoryClientError::ItemNotFound`].
+    ///
+    /// When `if_match` is set the write only happens if the stored item
+    /// carries that entity tag; otherwise it fails with
+    /// [`MemoryClientError::PreconditionFailed`].
+    pub fn update_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, MemoryClientError> {
+        if contents.is_empty() {
+            return Err(MemoryClientError::EmptyItemBody);
+        }
+        self.validate_calendar(calendar_id)?;
+        self.validate_item(item_id)?;
+
+        let Some(current) = self.calendar(calendar_id)?.items.get(item_id) else {
+            return Err(MemoryClientError::ItemNotFound(item_id.to_string()));
+        };
+
+        if if_match.is_some_and(|expected| current.etag.as_deref() != Some(expected)) {
+            return Err(MemoryClientError::PreconditionFailed(item_id.to_string()));
+        }
+
+        self.write_item(calendar_id, item_id, contents)
+    }
+
+    /// Permanently deletes `item_id` from `calendar_id`.
+    ///
+    /// When `if_match` is set the item is only removed if it carries
+    /// that entity tag; otherwise it fails with
+    /// [`MemoryClientError::PreconditionFailed`].
+    pub fn delete_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), MemoryClientError> {
+        self.validate_calendar(calendar_id)?;
+        self.validate_item(item_id)?;
+
+        let Some(current) = self.calendar(calendar_id)?.items.get(item_id) else {
+            return Err(MemoryClientError::ItemNotFound(item_id.to_string()));
+        };
+
+        if if_match.is_some_and(|expected| current.etag.as_deref() != Some(expected)) {
+            return Err(MemoryClientError::PreconditionFailed(item_id.to_string()));
+        }
+
+        let ctag = self.bump();
+        let entry = self.calendar_mut(calendar_id)?;
+
+        trace!("delete memory item {item_id}");
+        entry.items.remove(item_id);
+        entry.calendar.ctag = Some(ctag);
+
+        Ok(())
+    }
+
+    /// Stores `contents` as `item_id`, stamping the item and its
+    /// calendar with a fresh revision tag.
+    fn write_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, MemoryClientError> {
+        let tag = self.bump();
+        let entry = self.calendar_mut(calendar_id)?;
+
+        trace!("write memory item {item_id} ({} bytes)", contents.len());
+        let item = CalendarItem {
+            id: item_id.to_string(),
+            calendar_id: entry.calendar.id.clone(),
+            etag: Some(tag.clone()),
+            contents,
+        };
+        entry.items.insert(item_id.to_string(), item);
+        entry.calendar.ctag = Some(tag.clone());
+
+        Ok(CalendarItemReceipt {
+            id: item_id.to_string(),
+            etag: Some(tag),
+        })
+    }
+
+    /// Bumps the store revision and returns it as a tag.
+    fn bump(&mut self) -> String {
+        self.revision += 1;
+        revision_tag(self.revision)
+    }
+
+    fn calendar(&self, id: &str) -> Result<&MemoryCalendar, MemoryClientError> {
+        let id = id.trim_matches('/');
+        self.calendars
+            .get(id)
+            .ok_or_else(|| MemoryClientError::CalendarNotFound(id.to_string()))
+    }
+
+    fn calendar_mut(&mut self, id: &str) -> Result<&mut MemoryCalendar, MemoryClientError> {
+        let id = id.trim_matches('/');
+        self.calendars
+            .get_mut(id)
+            .ok_or_else(|| MemoryClientError::CalendarNotFound(id.to_string()))
+    }
+
+    /// Rejects an empty calendar id (after trimming surrounding
+    /// slashes).
+    fn validate_calendar(&self, id: &str) -> Result<(), MemoryClientError> {
+        if id.trim_matches('/').is_empty() {
+            return Err(MemoryClientError::InvalidCalendar(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Rejects an empty item id.
+    fn validate_item(&self, id: &str) -> Result<(), MemoryClientError> {
+        if id.is_empty() {
+            return Err(MemoryClientError::InvalidItemId(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+
+    fn event(uid: &str) -> Vec<u8> {
+        format!("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:{uid}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n")
+            .into_bytes()
+    }
+
+    fn client() -> MemoryClient {
+        let mut client = MemoryClient::new();
+        client
+            .create_calendar("cal", "Calendar", None, None)
+            .unwrap();
+        client
+    }
+
+    #[test]
+    fn creates_gets_updates_and_deletes_items() {
+        let mut client = client();
+
+        let receipt = client.create_item("cal", event("a")).unwrap();
+        let item = client.get_item("cal", &receipt.id).unwrap();
+        assert_eq!(item.contents, event("a"));
+        assert_eq!(item.etag, receipt.etag);
+
+        client
+            .update_item("cal", &receipt.id, event("b"), None)
+            .unwrap();
+        let item = client.get_item("cal", &receipt.id).unwrap();
+        assert_eq!(item.contents, event("b"));
+
+        client.delete_item("cal", &receipt.id, None).unwrap();
+        assert!(matches!(
+            client.get_item("cal", &receipt.id),
+            Err(MemoryClientError::ItemNotFound(_))
+        ));
+        assert!(matches!(
+            client.delete_item("cal", &receipt.id, None),
+            Err(MemoryClientError::ItemNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn update_of_missing_item_is_not_found() {
+        let mut client = client();
+        assert!(matches!(
+            client.update_item("cal", "missing", event("a"), None),
+            Err(MemoryClientError::ItemNotFound(id)) if id == "missing"
+        ));
+        assert!(
+            client
+                .list_items("cal", None, None, None, &[], None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn writes_bump_item_and_calendar_tags() {
+        let mut client = client();
+        let ctag = client.list_calendars().unwrap()[0].ctag.clone();
+
+        let created = client.create_item("cal", event("a")).unwrap();
+        let updated = client
+            .update_item("cal", &created.id, event("b"), created.etag.as_deref())
+            .unwrap();
+        assert!(updated.etag.is_some());
+        assert_ne!(updated.etag, created.etag);
+
+        let calendar = client.list_calendars().unwrap().remove(0);
+        assert_ne!(calendar.ctag, ctag);
+        assert_eq!(calendar.ctag, updated.etag);
+    }
+
+    #[test]
+    fn rejects_stale_if_match() {
+        let mut client = client();
+        let created = client.create_item("cal", event("a")).unwrap();
+        client
+            .update_item("cal", &created.id, event("b"), None)
+            .unwrap();
+
+        let stale = created.etag.as_deref();
+        assert!(matches!(
+            client.update_item("cal", &created.id, event("c"), stale),
+            Err(MemoryClientError::PreconditionFailed(_))
+        ));
+        assert!(matches!(
+            client.delete_item("cal", &created.id, stale),
+            Err(MemoryClientError::PreconditionFailed(_))
+        ));
+        assert_eq!(
+            client.get_item("cal", &created.id).unwrap().contents,
+            event("b")
+        );
+    }
+
+    #[test]
+    fn paginates_listed_items() {
+        let mut client = client();
+        for uid in ["a", "b", "c"] {
+            client.create_item("cal", event(uid)).unwrap();
+        }
+
+        let all = client
+            .list_items("cal", None, None, None, &[], None)
+            .unwrap();
+        assert_eq!(all.len(), 3);
+
+        let page = client
+            .list_items("cal", Some(2), Some(2), None, &[], None)
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, all[2].id);
+
+        for (page, size) in [(Some(3), Some(2)), (Some(1), Some(0))] {
+            let items = client
+                .list_items("cal", page, size, None, &[], None)
+                .unwrap();
+            assert!(items.is_empty());
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Small helpers shared by the in-memory client, plus the `From` impl
+//! that wraps a [`MemoryClient`] into the unified client's memory
+//! variant.
+//!
+//! [`MemoryClient`]: crate::memory::client::MemoryClient
+
+use alloc::{format, string::String};
+
+#[cfg(feature = "client")]
+impl From<crate::memory::client::MemoryClient> for crate::client::CalendarClientStd {
+    fn from(client: crate::memory::client::MemoryClient) -> Self {
+        Self::Memory(client)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<crate::memory::client::MemoryClient> for crate::client_tokio::CalendarClientTokio {
+    fn from(client: crate::memory::client::MemoryClient) -> Self {
+        Self::Memory(client)
+    }
+}
+
+/// Formats a store revision as a synthetic entity tag (or CTag).
+///
+/// Every write bumps the store revision, so two writes never share a
+/// tag.
+pub(crate) fn revision_tag(revision: u64) -> String {
+    format!("{revision:016x}")
+}
@@ -0,0 +1,7 @@
+//! In-memory backend: the [`client`] keeping calendars and items in
+//! maps, plus the [`convert`] helpers it shares with the unified
+//! client. Meant for tests and previews; nothing is persisted.
+
+#[cfg(feature = "client")]
+pub mod client;
+pub mod convert;
@@ -0,0 +1,1125 @@
+//! Two-way sync between two unified clients, planned then applied step
+//! by step.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec::Vec,
+};
+use std::io;
+
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    calendar::{Calendar, CalendarDiff},
+    client::{CalendarClientStd, CalendarClientStdError},
+    item::{CalendarItem, content_tag},
+    sync::{
+        SyncAction, SyncConflict, SyncConflictKind, SyncConflictResolution, SyncPlan, SyncReport,
+        SyncSide, SyncStatus, SyncStatusEntry, SyncStatusItem, SyncStep,
+    },
+};
+
+/// Errors surfaced by [`CalendarSync`] and [`SyncStatus`].
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("Failed to sync side A: {0}")]
+    A(#[source] CalendarClientStdError),
+    #[error("Failed to sync side B: {0}")]
+    B(#[source] CalendarClientStdError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Invalid sync status at line {0}")]
+    InvalidStatus(usize),
+    #[error("Item `{0}` changed since the sync was planned")]
+    Stale(String),
+}
+
+impl SyncError {
+    fn side(side: SyncSide, err: CalendarClientStdError) -> Self {
+        match side {
+            SyncSide::A => Self::A(err),
+            SyncSide::B => Self::B(err),
+        }
+    }
+}
+
+/// Items of one calendar per side, keyed by id.
+type Listing = [BTreeMap<String, CalendarItem>; 2];
+
+/// Two backends kept in sync, like vdirsyncer pairs: typically a
+/// CalDAV account and its local vdir mirror, or two CalDAV accounts.
+///
+/// Each run lists both calendars, compares every item against the
+/// [`SyncStatus`] of the previous run and propagates creations,
+/// updates and deletions in both directions through the shared API.
+/// Items changed on both sides since the last run are settled by the
+/// [`SyncConflictResolution`] policy, which by default leaves them
+/// untouched and reports them as [`SyncConflict`]s.
+///
+/// Runs can be previewed: [`plan`](Self::plan) and
+/// [`plan_calendar`](Self::plan_calendar) only read both sides and
+/// return a [`SyncPlan`], which prints as a dry run and can be
+/// [`execute`](Self::execute)d or [`apply`](Self::apply)ed step by
+/// step later on.
+///
+/// ```rust,ignore
+/// use io_calendar::sync::{CalendarSync, SyncConflictResolution, SyncStatus};
+///
+/// let mut sync = CalendarSync::new(webdav.into(), vdir.into())
+///     .with_resolution(SyncConflictResolution::Newest);
+/// let mut status = SyncStatus::load("personal.status")?;
+/// let report = sync.sync_calendar("personal", "personal", &mut status);
+/// status.save("personal.status")?;
+/// let report = report?;
+/// ```
+#[derive(Debug)]
+pub struct CalendarSync {
+    pub a: CalendarClientStd,
+    pub b: CalendarClientStd,
+    pub resolution: SyncConflictResolution,
+}
+
+impl CalendarSync {
+    /// Pairs backend `a` with backend `b`, reporting conflicts.
+    pub fn new(a: CalendarClientStd, b: CalendarClientStd) -> Self {
+        Self {
+            a,
+            b,
+            resolution: SyncConflictResolution::default(),
+        }
+    }
+
+    /// Settles conflicts with `resolution` instead of reporting them.
+    pub fn with_resolution(mut self, resolution: SyncConflictResolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Syncs `calendar_a` of side A with `calendar_b` of side B,
+    /// updating `status` after every applied change.
+    ///
+    /// Changes are written with the ETag listed at the start of the run
+    /// as `if_match`, so an item modified concurrently fails the run
+    /// instead of being overwritten; `status` then still describes
+    /// what was applied and can be saved.
+    pub fn sync_calendar(
+        &mut self,
+        calendar_a: &str,
+        calendar_b: &str,
+        status: &mut SyncStatus,
+    ) -> Result<SyncReport, SyncError> {
+        let mut items = [
+            self.list(SyncSide::A, calendar_a)?,
+            self.list(SyncSide::B, calendar_b)?,
+        ];
+
+        let mut plan = SyncPlan::default();
+        self.plan_items(calendar_a, calendar_b, status, &items, &mut plan);
+
+        let mut report = SyncReport {
+            conflicts: plan.conflicts,
+            ..SyncReport::default()
+        };
+
+        for step in &plan.steps {
+            self.apply_step(step, status, &mut items, &mut report)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Plans the sync of every calendar of both sides, pairing
+    /// calendars by id, without writing anything.
+    ///
+    /// `statuses` holds the [`SyncStatus`] of each calendar synced
+    /// before, keyed by id. A calendar missing from one side is
+    /// deleted from the other when it has a status (it was synced,
+    /// then deleted), or created there with all its items otherwise.
+    /// Metadata differences are copied from side A, or from side B
+    /// under [`SyncConflictResolution::BWins`]; fields the source side
+    /// does not expose are left alone.
+    pub fn plan(&mut self, statuses: &BTreeMap<String, SyncStatus>) -> Result<SyncPlan, SyncError> {
+        let calendars = [
+            self.list_calendars(SyncSide::A)?,
+            self.list_calendars(SyncSide::B)?,
+        ];
+        let ids: BTreeSet<&String> = calendars.iter().flat_map(|c| c.keys()).collect();
+        let empty = SyncStatus::default();
+        let mut plan = SyncPlan::default();
+
+        for id in ids {
+            let status = statuses.get(id);
+
+            match (calendars[0].get(id), calendars[1].get(id)) {
+                (Some(a), Some(b)) => {
+                    if let Some((side, diff)) = self.calendar_diff(a, b) {
+                        let id = id.clone();
+                        plan.steps.push(SyncStep::UpdateCalendar { side, id, diff });
+                    }
+
+                    let items = [self.list(SyncSide::A, id)?, self.list(SyncSide::B, id)?];
+                    let status = status.unwrap_or(&empty);
+                    self.plan_items(id, id, status, &items, &mut plan);
+                }
+                (Some(_), None) | (None, Some(_)) if status.is_some() => {
+                    let side = match calendars[0].contains_key(id) {
+                        true => SyncSide::A,
+                        false => SyncSide::B,
+                    };
+                    let id = id.clone();
+                    plan.steps.push(SyncStep::DeleteCalendar { side, id });
+                }
+                (Some(calendar), None) | (None, Some(calendar)) => {
+                    let from = match calendars[0].contains_key(id) {
+                        true => SyncSide::A,
+                        false => SyncSide::B,
+                    };
+                    let calendar = calendar.clone();
+                    let side = from.other();
+                    plan.steps.push(SyncStep::CreateCalendar { side, calendar });
+
+                    let mut items = Listing::default();
+                    items[index(from)] = self.list(from, id)?;
+                    self.plan_items(id, id, &empty, &items, &mut plan);
+                }
+                (None, None) => {}
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Plans the sync of `calendar_a` of side A with `calendar_b` of
+    /// side B against `status`, without writing anything.
+    pub fn plan_calendar(
+        &mut self,
+        calendar_a: &str,
+        calendar_b: &str,
+        status: &SyncStatus,
+    ) -> Result<SyncPlan, SyncError> {
+        let items = [
+            self.list(SyncSide::A, calendar_a)?,
+            self.list(SyncSide::B, calendar_b)?,
+        ];
+
+        let mut plan = SyncPlan::default();
+        self.plan_items(calendar_a, calendar_b, status, &items, &mut plan);
+        Ok(plan)
+    }
+
+    /// Applies every step of `plan` made by [`plan`](Self::plan),
+    /// keeping `statuses` (keyed by calendar id) up to date after each
+    /// one.
+    pub fn execute(
+        &mut self,
+        plan: &SyncPlan,
+        statuses: &mut BTreeMap<String, SyncStatus>,
+    ) -> Result<SyncReport, SyncError> {
+        let mut report = SyncReport {
+            conflicts: plan.conflicts.clone(),
+            ..SyncReport::default()
+        };
+
+        for step in &plan.steps {
+            let id = match step {
+                SyncStep::CreateCalendar { calendar, .. } => &calendar.id,
+                SyncStep::UpdateCalendar { id, .. } | SyncStep::DeleteCalendar { id, .. } => id,
+                step => step.calendars().map(|(a, _)| a).unwrap_or_default(),
+            };
+            let status = statuses.entry(id.to_string()).or_default();
+            self.apply_step(step, status, &mut Listing::default(), &mut report)?;
+
+            if let SyncStep::DeleteCalendar { id, .. } = step {
+                statuses.remove(id);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Applies a single `step`, updating `status`, the status of the
+    /// step's calendar pair.
+    ///
+    /// The items of the step are read again and must still carry the
+    /// entity tags seen while planning, otherwise nothing is written
+    /// and [`SyncError::Stale`] is returned.
+    pub fn apply(
+        &mut self,
+        step: &SyncStep,
+        status: &mut SyncStatus,
+    ) -> Result<SyncReport, SyncError> {
+        let mut report = SyncReport::default();
+        self.apply_step(step, status, &mut Listing::default(), &mut report)?;
+        Ok(report)
+    }
+
+    /// Compares the items of a calendar pair against `status` and
+    /// appends the resulting steps and conflicts to `plan`.
+    fn plan_items(
+        &self,
+        calendar_a: &str,
+        calendar_b: &str,
+        status: &SyncStatus,
+        items: &Listing,
+        plan: &mut SyncPlan,
+    ) {
+        let calendars = [calendar_a, calendar_b];
+        let mut seen: [BTreeSet<&str>; 2] = Default::default();
+
+        for entry in &status.entries {
+            seen[0].insert(&entry.a.id);
+            seen[1].insert(&entry.b.id);
+
+            match (items[0].get(&entry.a.id), items[1].get(&entry.b.id)) {
+                (None, None) => plan.steps.push(SyncStep::ForgetItems {
+                    calendar_a: calendar_a.to_string(),
+                    calendar_b: calendar_b.to_string(),
+                    a: entry.a.id.clone(),
+                    b: entry.b.id.clone(),
+                }),
+                (Some(a), None) => plan_delete(SyncSide::A, &calendars, entry, a, plan),
+                (None, Some(b)) => plan_delete(SyncSide::B, &calendars, entry, b, plan),
+                (Some(a), Some(b)) => {
+                    let a_changed = tag(a) != entry.a.etag;
+                    let b_changed = tag(b) != entry.b.etag;
+
+                    let (from, source, target) = match (a_changed, b_changed) {
+                        (false, false) => continue,
+                        (true, false) => (SyncSide::A, a, b),
+                        (false, true) => (SyncSide::B, b, a),
+                        (true, true) => {
+                            self.plan_pair(&calendars, a, b, plan);
+                            continue;
+                        }
+                    };
+
+                    plan.steps.push(SyncStep::OverwriteItem {
+                        from,
+                        calendar_a: calendar_a.to_string(),
+                        calendar_b: calendar_b.to_string(),
+                        source: source.id.clone(),
+                        source_etag: tag(source),
+                        target: target.id.clone(),
+                        target_etag: tag(target),
+                    });
+                }
+            }
+        }
+
+        for (id, a) in &items[0] {
+            if seen[0].contains(id.as_str()) {
+                continue;
+            }
+
+            if let Some(b) = items[1].get(id).filter(|_| !seen[1].contains(id.as_str())) {
+                seen[1].insert(id);
+                self.plan_pair(&calendars, a, b, plan);
+                continue;
+            }
+
+            plan.steps.push(copy(SyncSide::A, &calendars, a));
+        }
+
+        for (id, b) in &items[1] {
+            if !seen[1].contains(id.as_str()) {
+                plan.steps.push(copy(SyncSide::B, &calendars, b));
+            }
+        }
+    }
+
+    /// Plans a pair of items that both diverged from the status (or
+    /// were never synced): paired when identical, resolved or reported
+    /// otherwise.
+    fn plan_pair(
+        &self,
+        calendars: &[&str; 2],
+        a: &CalendarItem,
+        b: &CalendarItem,
+        plan: &mut SyncPlan,
+    ) {
+        let calendar_a = calendars[0].to_string();
+        let calendar_b = calendars[1].to_string();
+
+        if a.contents == b.contents {
+            plan.steps.push(SyncStep::PairItems {
+                calendar_a,
+                calendar_b,
+                a: a.id.clone(),
+                a_etag: tag(a),
+                b: b.id.clone(),
+                b_etag: tag(b),
+            });
+        } else if let SyncConflictResolution::Report = self.resolution {
+            plan.conflicts.push(SyncConflict {
+                calendar_a,
+                calendar_b,
+                a: Some(a.id.clone()),
+                b: Some(b.id.clone()),
+                kind: SyncConflictKind::Modified,
+            });
+        } else {
+            plan.steps.push(SyncStep::ResolveItems {
+                calendar_a,
+                calendar_b,
+                a: a.id.clone(),
+                a_etag: tag(a),
+                b: b.id.clone(),
+                b_etag: tag(b),
+            });
+        }
+    }
+
+    /// Metadata changes bringing the calendar of one side in line with
+    /// the other, with the side to update.
+    fn calendar_diff(&self, a: &Calendar, b: &Calendar) -> Option<(SyncSide, CalendarDiff)> {
+        let from = match self.resolution {
+            SyncConflictResolution::BWins => SyncSide::B,
+            _ => SyncSide::A,
+        };
+        let (source, target) = from.pick((a, b), (b, a));
+
+        let diff = CalendarDiff {
+            name: Some(&source.name)
+                .filter(|name| **name != target.name)
+                .cloned(),
+            description: Some(&source.description)
+                .filter(|description| description.is_some() && **description != target.description)
+                .cloned(),
+            color: Some(&source.color)
+                .filter(|color| color.is_some() && **color != target.color)
+                .cloned(),
+        };
+
+        (diff != CalendarDiff::default()).then_some((from.other(), diff))
+    }
+
+    /// Applies `step`, reading its items from `cache` when listed
+    /// already, and records its outcome in `status` and `report`.
+    fn apply_step(
+        &mut self,
+        step: &SyncStep,
+        status: &mut SyncStatus,
+        cache: &mut Listing,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError> {
+        trace!("sync: {step}");
+
+        match step {
+            SyncStep::CreateCalendar { side, calendar } => {
+                let description = calendar.description.as_deref();
+                let color = calendar.color.as_deref();
+                self.client(*side)
+                    .create_calendar(&calendar.id, &calendar.name, description, color)
+                    .map_err(|err| SyncError::side(*side, err))?;
+
+                let (side, id) = (*side, calendar.id.clone());
+                report.applied.push(SyncAction::CreateCalendar { side, id });
+            }
+            SyncStep::UpdateCalendar { side, id, diff } => {
+                self.client(*side)
+                    .update_calendar(id, diff.clone())
+                    .map_err(|err| SyncError::side(*side, err))?;
+
+                let (side, id) = (*side, id.clone());
+                report.applied.push(SyncAction::UpdateCalendar { side, id });
+            }
+            SyncStep::DeleteCalendar { side, id } => {
+                self.client(*side)
+                    .delete_calendar(id)
+                    .map_err(|err| SyncError::side(*side, err))?;
+                status.entries.clear();
+
+                let (side, id) = (*side, id.clone());
+                report.applied.push(SyncAction::DeleteCalendar { side, id });
+            }
+            SyncStep::CopyItem {
+                from,
+                calendar_a,
+                calendar_b,
+                source,
+                etag,
+            } => {
+                let calendars = [calendar_a.as_str(), calendar_b.as_str()];
+                let to = from.other();
+                let item = self.fetch(*from, &calendars, source, etag, cache)?;
+                let target = self.write(to, &calendars, None, item.contents.clone(), None)?;
+
+                debug!("sync: created {} on side {to:?}", target.id);
+                report.applied.push(SyncAction::Create {
+                    from: *from,
+                    source: source.clone(),
+                    target: target.id.clone(),
+                });
+                upsert(
+                    status,
+                    SyncStatusEntry::new(*from, status_item(&item), target),
+                );
+            }
+            SyncStep::OverwriteItem {
+                from,
+                calendar_a,
+                calendar_b,
+                source,
+                source_etag,
+                target,
+                target_etag,
+            } => {
+                let calendars = [calendar_a.as_str(), calendar_b.as_str()];
+                let to = from.other();
+                let item = self.fetch(*from, &calendars, source, source_etag, cache)?;
+                let current = self.fetch(to, &calendars, target, target_etag, cache)?;
+                let if_match = current.etag.as_deref();
+                let written = self.write(
+                    to,
+                    &calendars,
+                    Some(target),
+                    item.contents.clone(),
+                    if_match,
+                )?;
+
+                debug!("sync: updated {target} on side {to:?}");
+                report.applied.push(SyncAction::Update {
+                    from: *from,
+                    source: source.clone(),
+                    target: target.clone(),
+                });
+                upsert(
+                    status,
+                    SyncStatusEntry::new(*from, status_item(&item), written),
+                );
+            }
+            SyncStep::DeleteItem {
+                side,
+                calendar_a,
+                calendar_b,
+                id,
+                etag,
+            } => {
+                let calendars = [calendar_a.as_str(), calendar_b.as_str()];
+                let item = self.fetch(*side, &calendars, id, etag, cache)?;
+                let calendar = side.pick(calendar_a, calendar_b);
+                self.client(*side)
+                    .delete_item(calendar, id, item.etag.as_deref())
+                    .map_err(|err| SyncError::side(*side, err))?;
+
+                debug!("sync: deleted {id} on side {side:?}");
+                report.applied.push(SyncAction::Delete {
+                    side: *side,
+                    id: id.clone(),
+                });
+                status.entries.retain(|entry| entry.side(*side).id != *id);
+            }
+            SyncStep::ResolveItems {
+                calendar_a,
+                calendar_b,
+                a,
+                a_etag,
+                b,
+                b_etag,
+            } => {
+                let calendars = [calendar_a.as_str(), calendar_b.as_str()];
+                let a = self.fetch(SyncSide::A, &calendars, a, a_etag, cache)?;
+                let b = self.fetch(SyncSide::B, &calendars, b, b_etag, cache)?;
+                self.resolve(&calendars, a, b, status, report)?;
+            }
+            SyncStep::PairItems {
+                a,
+                a_etag,
+                b,
+                b_etag,
+                ..
+            } => {
+                let a = SyncStatusItem {
+                    id: a.clone(),
+                    etag: a_etag.clone(),
+                };
+                let b = SyncStatusItem {
+                    id: b.clone(),
+                    etag: b_etag.clone(),
+                };
+                upsert(status, SyncStatusEntry { a, b });
+            }
+            SyncStep::ForgetItems { a, b, .. } => {
+                status
+                    .entries
+                    .retain(|entry| entry.a.id != *a || entry.b.id != *b);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Settles diverging items `a` and `b` with the
+    /// [`SyncConflictResolution`] policy, or reports them when it
+    /// declines.
+    fn resolve(
+        &mut self,
+        calendars: &[&str; 2],
+        a: CalendarItem,
+        b: CalendarItem,
+        status: &mut SyncStatus,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError> {
+        let Some(resolved) = self.resolution.resolve(&a, &b) else {
+            report.conflicts.push(SyncConflict {
+                calendar_a: calendars[0].to_string(),
+                calendar_b: calendars[1].to_string(),
+                a: Some(a.id),
+                b: Some(b.id),
+                kind: SyncConflictKind::Modified,
+            });
+            return Ok(());
+        };
+
+        let mut entry = SyncStatusEntry::new(SyncSide::A, status_item(&a), status_item(&b));
+
+        for (side, item) in [(SyncSide::A, a), (SyncSide::B, b)] {
+            if item.contents == resolved.contents {
+                continue;
+            }
+
+            let contents = resolved.contents.clone();
+            let if_match = item.etag.as_deref();
+            *entry.side_mut(side) =
+                self.write(side, calendars, Some(&item.id), contents, if_match)?;
+        }
+
+        debug!("sync: resolved {} / {}", entry.a.id, entry.b.id);
+        report.applied.push(SyncAction::Resolve {
+            a: entry.a.id.clone(),
+            b: entry.b.id.clone(),
+        });
+        upsert(status, entry);
+
+        if let Some(duplicate) = resolved.duplicate {
+            let a = self.write(SyncSide::A, calendars, None, duplicate.clone(), None)?;
+            let b = self.write(SyncSide::B, calendars, None, duplicate, None)?;
+
+            debug!("sync: duplicated as {} / {}", a.id, b.id);
+            report.applied.push(SyncAction::Duplicate {
+                a: a.id.clone(),
+                b: b.id.clone(),
+            });
+            status.entries.push(SyncStatusEntry { a, b });
+        }
+
+        Ok(())
+    }
+
+    /// Lists every calendar of `side`, keyed by id.
+    fn list_calendars(&mut self, side: SyncSide) -> Result<BTreeMap<String, Calendar>, SyncError> {
+        let calendars = self
+            .client(side)
+            .list_calendars()
+            .map_err(|err| SyncError::side(side, err))?;
+
+        Ok(calendars
+            .into_iter()
+            .map(|calendar| (calendar.id.clone(), calendar))
+            .collect())
+    }
+
+    /// Lists every item of `calendar` on `side`, keyed by id.
+    fn list(
+        &mut self,
+        side: SyncSide,
+        calendar: &str,
+    ) -> Result<BTreeMap<String, CalendarItem>, SyncError> {
+        trace!("sync: listing {calendar} on side {side:?}");
+
+        let items = self
+            .client(side)
+            .list_items(calendar, None, None, None, &[], None)
+            .map_err(|err| SyncError::side(side, err))?;
+
+        Ok(items
+            .into_iter()
+            .map(|item| (item.id.clone(), item))
+            .collect())
+    }
+
+    /// Item `id` of `side`, from `cache` when listed during this run,
+    /// checked against the tag seen while planning.
+    fn fetch(
+        &mut self,
+        side: SyncSide,
+        calendars: &[&str; 2],
+        id: &str,
+        etag: &str,
+        cache: &mut Listing,
+    ) -> Result<CalendarItem, SyncError> {
+        let item = match cache[index(side)].remove(id) {
+            Some(item) => item,
+            None => {
+                let calendar = side.pick(calendars[0], calendars[1]);
+                self.client(side)
+                    .get_item(calendar, id)
+                    .map_err(|err| SyncError::side(side, err))?
+            }
+        };
+
+        if tag(&item) != etag {
+            return Err(SyncError::Stale(id.to_string()));
+        }
+
+        Ok(item)
+    }
+
+    /// Creates (`id` is `None`) or overwrites an item on `side`, then
+    /// takes its entity tag from the write receipt. The item is only
+    /// read back when the backend reported no entity tag, as a server
+    /// altering the stored contents does.
+    fn write(
+        &mut self,
+        side: SyncSide,
+        calendars: &[&str; 2],
+        id: Option<&str>,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<SyncStatusItem, SyncError> {
+        let calendar = side.pick(calendars[0], calendars[1]);
+        let client = self.client(side);
+
+        let write = || -> Result<SyncStatusItem, CalendarClientStdError> {
+            let receipt = match id {
+                Some(id) => client.update_item(calendar, id, contents, if_match)?,
+                None => client.create_item(calendar, contents)?,
+            };
+
+            match receipt.etag {
+                Some(etag) => Ok(SyncStatusItem {
+                    id: receipt.id,
+                    etag,
+                }),
+                None => Ok(status_item(&client.get_item(calendar, &receipt.id)?)),
+            }
+        };
+
+        write().map_err(|err| SyncError::side(side, err))
+    }
+
+    fn client(&mut self, side: SyncSide) -> &mut CalendarClientStd {
+        side.pick(&mut self.a, &mut self.b)
+    }
+}
+
+/// Plans a pair whose item only remains on `side`: deleted when
+/// unchanged since the last run, reported otherwise.
+fn plan_delete(
+    side: SyncSide,
+    calendars: &[&str; 2],
+    entry: &SyncStatusEntry,
+    item: &CalendarItem,
+    plan: &mut SyncPlan,
+) {
+    let calendar_a = calendars[0].to_string();
+    let calendar_b = calendars[1].to_string();
+
+    if tag(item) != entry.side(side).etag {
+        plan.conflicts.push(SyncConflict {
+            calendar_a,
+            calendar_b,
+            a: side.pick(Some(item.id.clone()), None),
+            b: side.pick(None, Some(item.id.clone())),
+            kind: SyncConflictKind::Deleted(side.other()),
+        });
+        return;
+    }
+
+    plan.steps.push(SyncStep::DeleteItem {
+        side,
+        calendar_a,
+        calendar_b,
+        id: item.id.clone(),
+        etag: tag(item),
+    });
+}
+
+fn copy(from: SyncSide, calendars: &[&str; 2], item: &CalendarItem) -> SyncStep {
+    SyncStep::CopyItem {
+        from,
+        calendar_a: calendars[0].to_string(),
+        calendar_b: calendars[1].to_string(),
+        source: item.id.clone(),
+        etag: tag(item),
+    }
+}
+
+/// Replaces the entry sharing an item with `entry`, or appends it.
+fn upsert(status: &mut SyncStatus, entry: SyncStatusEntry) {
+    let existing = status
+        .entries
+        .iter_mut()
+        .find(|current| current.a.id == entry.a.id || current.b.id == entry.b.id);
+
+    match existing {
+        Some(current) => *current = entry,
+        None => status.entries.push(entry),
+    }
+}
+
+fn index(side: SyncSide) -> usize {
+    side.pick(0, 1)
+}
+
+/// Entity tag of `item`, or a hash of its contents when the backend
+/// has none.
+fn tag(item: &CalendarItem) -> String {
+    item.etag
+        .clone()
+        .unwrap_or_else(|| content_tag(&item.contents))
+}
+
+fn status_item(item: &CalendarItem) -> SyncStatusItem {
+    SyncStatusItem {
+        id: item.id.clone(),
+        etag: tag(item),
+    }
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use alloc::{boxed::Box, format, vec};
+
+    use super::*;
+    use crate::memory::client::MemoryClient;
+
+    /// An event of UID `1`, last modified on day `day` of 2025.
+    fn event(summary: &str, day: u8) -> Vec<u8> {
+        event_of("1", summary, day)
+    }
+
+    fn event_of(uid: &str, summary: &str, day: u8) -> Vec<u8> {
+        format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:{uid}\r\nSUMMARY:{summary}\r\n\
+             LAST-MODIFIED:202501{day:02}T000000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+        )
+        .into_bytes()
+    }
+
+    /// Two stores holding an empty `cal` calendar.
+    fn pair(resolution: SyncConflictResolution) -> CalendarSync {
+        let mut client = MemoryClient::new();
+        client
+            .create_calendar("cal", "Calendar", None, None)
+            .unwrap();
+
+        let a = CalendarClientStd::Memory(client.clone());
+        let b = CalendarClientStd::Memory(client);
+        CalendarSync::new(a, b).with_resolution(resolution)
+    }
+
+    /// A pair already holding one synced event, with the ids of both
+    /// copies.
+    fn synced(resolution: SyncConflictResolution) -> (CalendarSync, SyncStatus, [String; 2]) {
+        let mut sync = pair(resolution);
+        let mut status = SyncStatus::default();
+        create(&mut sync, SyncSide::A, event("one", 1));
+        run(&mut sync, &mut status);
+
+        let ids = [
+            status.entries[0].a.id.clone(),
+            status.entries[0].b.id.clone(),
+        ];
+        (sync, status, ids)
+    }
+
+    fn run(sync: &mut CalendarSync, status: &mut SyncStatus) -> SyncReport {
+        sync.sync_calendar("cal", "cal", status).unwrap()
+    }
+
+    fn create(sync: &mut CalendarSync, side: SyncSide, contents: Vec<u8>) -> String {
+        sync.client(side).create_item("cal", contents).unwrap().id
+    }
+
+    fn update(sync: &mut CalendarSync, side: SyncSide, id: &str, contents: Vec<u8>) {
+        let client = sync.client(side);
+        client.update_item("cal", id, contents, None).unwrap();
+    }
+
+    fn delete(sync: &mut CalendarSync, side: SyncSide, id: &str) {
+        sync.client(side).delete_item("cal", id, None).unwrap();
+    }
+
+    /// Sorted contents of every item of `side`.
+    fn contents(sync: &mut CalendarSync, side: SyncSide) -> Vec<Vec<u8>> {
+        let items = sync.list(side, "cal").unwrap();
+        let mut contents: Vec<_> = items.into_values().map(|item| item.contents).collect();
+        contents.sort();
+        contents
+    }
+
+    #[test]
+    fn copies_creations_both_ways() {
+        let mut sync = pair(SyncConflictResolution::default());
+        let mut status = SyncStatus::default();
+        let a = create(&mut sync, SyncSide::A, event_of("a", "a", 1));
+        let b = create(&mut sync, SyncSide::B, event_of("b", "b", 1));
+
+        let report = run(&mut sync, &mut status);
+        let sources: Vec<_> = report
+            .applied
+            .iter()
+            .map(|action| match action {
+                SyncAction::Create { from, source, .. } => (*from, source.as_str()),
+                action => panic!("unexpected {action:?}"),
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            [(SyncSide::A, a.as_str()), (SyncSide::B, b.as_str())]
+        );
+
+        let expected = vec![event_of("a", "a", 1), event_of("b", "b", 1)];
+        assert_eq!(contents(&mut sync, SyncSide::A), expected);
+        assert_eq!(contents(&mut sync, SyncSide::B), expected);
+        assert_eq!(status.entries.len(), 2);
+        assert_eq!(run(&mut sync, &mut status), SyncReport::default());
+    }
+
+    #[test]
+    fn propagates_updates_both_ways() {
+        let (mut sync, mut status, [a, b]) = synced(SyncConflictResolution::default());
+
+        for (side, id, summary) in [(SyncSide::A, &a, "two"), (SyncSide::B, &b, "three")] {
+            update(&mut sync, side, id, event(summary, 2));
+
+            let report = run(&mut sync, &mut status);
+            let (source, target) = side.pick((&a, &b), (&b, &a));
+            assert_eq!(
+                report.applied,
+                [SyncAction::Update {
+                    from: side,
+                    source: source.clone(),
+                    target: target.clone(),
+                }]
+            );
+            assert_eq!(contents(&mut sync, side.other()), [event(summary, 2)]);
+            assert_eq!(run(&mut sync, &mut status), SyncReport::default());
+        }
+    }
+
+    #[test]
+    fn propagates_deletions_both_ways() {
+        for side in [SyncSide::A, SyncSide::B] {
+            let (mut sync, mut status, ids) = synced(SyncConflictResolution::default());
+            delete(&mut sync, side, &ids[index(side)]);
+
+            let report = run(&mut sync, &mut status);
+            let other = side.other();
+            let id = ids[index(other)].clone();
+            assert_eq!(report.applied, [SyncAction::Delete { side: other, id }]);
+            assert!(contents(&mut sync, other).is_empty());
+            assert!(status.entries.is_empty());
+        }
+    }
+
+    #[test]
+    fn settles_both_modified_pairs_per_resolution() {
+        let (a, b) = (event("a", 2), event("b", 3));
+        let merged = event("merged", 4);
+        let merge = merged.clone();
+
+        for (resolution, expected) in [
+            (SyncConflictResolution::Report, None),
+            (SyncConflictResolution::AWins, Some(&a)),
+            (SyncConflictResolution::BWins, Some(&b)),
+            (SyncConflictResolution::Newest, Some(&b)),
+            (
+                SyncConflictResolution::Merge(Box::new(move |_, _| merge.clone())),
+                Some(&merged),
+            ),
+        ] {
+            let name = format!("{resolution:?}");
+            let (mut sync, mut status, ids) = synced(resolution);
+            update(&mut sync, SyncSide::A, &ids[0], a.clone());
+            update(&mut sync, SyncSide::B, &ids[1], b.clone());
+
+            let report = run(&mut sync, &mut status);
+
+            match expected {
+                None => {
+                    assert!(report.applied.is_empty(), "{name}");
+                    assert_eq!(report.conflicts.len(), 1, "{name}");
+                    assert_eq!(report.conflicts[0].kind, SyncConflictKind::Modified);
+                    assert_eq!(contents(&mut sync, SyncSide::A), vec![a.clone()]);
+                    assert_eq!(contents(&mut sync, SyncSide::B), vec![b.clone()]);
+                    assert_eq!(run(&mut sync, &mut status).conflicts.len(), 1, "{name}");
+                }
+                Some(expected) => {
+                    let (a, b) = (ids[0].clone(), ids[1].clone());
+                    assert_eq!(report.applied, [SyncAction::Resolve { a, b }], "{name}");
+                    assert!(report.conflicts.is_empty(), "{name}");
+                    assert_eq!(contents(&mut sync, SyncSide::A), vec![expected.clone()]);
+                    assert_eq!(contents(&mut sync, SyncSide::B), vec![expected.clone()]);
+                    assert_eq!(run(&mut sync, &mut status), SyncReport::default());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn newest_reports_ties() {
+        let (mut sync, mut status, ids) = synced(SyncConflictResolution::Newest);
+        update(&mut sync, SyncSide::A, &ids[0], event("a", 2));
+        update(&mut sync, SyncSide::B, &ids[1], event("b", 2));
+
+        let report = run(&mut sync, &mut status);
+        assert!(report.applied.is_empty());
+        assert_eq!(report.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn keep_both_duplicates_the_b_version() {
+        let (mut sync, mut status, ids) = synced(SyncConflictResolution::KeepBoth);
+        update(&mut sync, SyncSide::A, &ids[0], event("a", 2));
+        update(&mut sync, SyncSide::B, &ids[1], event("b", 2));
+
+        let report = run(&mut sync, &mut status);
+        assert!(matches!(
+            report.applied.as_slice(),
+            [SyncAction::Resolve { .. }, SyncAction::Duplicate { .. }]
+        ));
+
+        let items = contents(&mut sync, SyncSide::A);
+        assert_eq!(items, contents(&mut sync, SyncSide::B));
+        assert_eq!(items.len(), 2);
+        assert!(items.contains(&event("a", 2)));
+        assert_eq!(status.entries.len(), 2);
+        assert_eq!(run(&mut sync, &mut status), SyncReport::default());
+    }
+
+    #[test]
+    fn reports_deleted_and_modified_pairs() {
+        for side in [SyncSide::A, SyncSide::B] {
+            let (mut sync, mut status, ids) = synced(SyncConflictResolution::AWins);
+            let other = side.other();
+            delete(&mut sync, side, &ids[index(side)]);
+            update(&mut sync, other, &ids[index(other)], event("two", 2));
+
+            let report = run(&mut sync, &mut status);
+            let id = Some(ids[index(other)].clone());
+            assert!(report.applied.is_empty());
+            assert_eq!(
+                report.conflicts,
+                [SyncConflict {
+                    calendar_a: "cal".to_string(),
+                    calendar_b: "cal".to_string(),
+                    a: side.pick(None, id.clone()),
+                    b: side.pick(id, None),
+                    kind: SyncConflictKind::Deleted(side),
+                }]
+            );
+            assert_eq!(contents(&mut sync, other), [event("two", 2)]);
+            assert_eq!(status.entries.len(), 1);
+        }
+    }
+
+    #[test]
+    fn resumes_from_a_saved_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cal.status");
+        let (mut sync, status, ids) = synced(SyncConflictResolution::default());
+        status.save(&path).unwrap();
+
+        let mut status = SyncStatus::load(&path).unwrap();
+        assert_eq!(run(&mut sync, &mut status), SyncReport::default());
+
+        delete(&mut sync, SyncSide::A, &ids[0]);
+        run(&mut sync, &mut status);
+        status.save(&path).unwrap();
+        assert_eq!(SyncStatus::load(&path).unwrap(), SyncStatus::default());
+    }
+
+    #[test]
+    fn plans_without_writing_then_applies_steps() {
+        let (mut sync, mut status, [a, b]) = synced(SyncConflictResolution::default());
+        update(&mut sync, SyncSide::A, &a, event("two", 2));
+
+        let plan = sync.plan_calendar("cal", "cal", &status).unwrap();
+        assert!(plan.conflicts.is_empty());
+        assert!(matches!(
+            plan.steps.as_slice(),
+            [SyncStep::OverwriteItem { from: SyncSide::A, source, target, .. }]
+                if *source == a && *target == b
+        ));
+        assert_eq!(contents(&mut sync, SyncSide::B), [event("one", 1)]);
+
+        let report = sync.apply(&plan.steps[0], &mut status).unwrap();
+        assert_eq!(report.applied.len(), 1);
+        assert_eq!(contents(&mut sync, SyncSide::B), [event("two", 2)]);
+        assert!(
+            sync.plan_calendar("cal", "cal", &status)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn refuses_stale_steps() {
+        let mut sync = pair(SyncConflictResolution::default());
+        let mut status = SyncStatus::default();
+        let id = create(&mut sync, SyncSide::A, event("one", 1));
+
+        let plan = sync.plan_calendar("cal", "cal", &status).unwrap();
+        update(&mut sync, SyncSide::A, &id, event("two", 2));
+
+        let err = sync.apply(&plan.steps[0], &mut status).unwrap_err();
+        assert!(matches!(err, SyncError::Stale(stale) if stale == id));
+        assert!(contents(&mut sync, SyncSide::B).is_empty());
+        assert!(status.entries.is_empty());
+    }
+
+    #[test]
+    fn plans_and_executes_every_calendar() {
+        let mut a = MemoryClient::new();
+        a.create_calendar("cal", "Calendar", None, Some("#ff0000"))
+            .unwrap();
+        a.create_calendar("new", "New", None, None).unwrap();
+        a.create_item("new", event("one", 1)).unwrap();
+
+        let mut b = MemoryClient::new();
+        b.create_calendar("cal", "Old", None, None).unwrap();
+        b.create_calendar("gone", "Gone", None, None).unwrap();
+
+        let a = CalendarClientStd::Memory(a);
+        let b = CalendarClientStd::Memory(b);
+        let mut sync = CalendarSync::new(a, b);
+        let mut statuses = BTreeMap::from([("gone".to_string(), SyncStatus::default())]);
+
+        let plan = sync.plan(&statuses).unwrap();
+        let steps: Vec<_> = plan.steps.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            steps,
+            [
+                "update calendar cal on B: name=Calendar color=#ff0000",
+                "delete calendar gone on B",
+                "create calendar new on B",
+                &format!("copy item new/{} from A to new on B", items(&plan)[0]),
+            ]
+        );
+
+        sync.execute(&plan, &mut statuses).unwrap();
+        let calendars = sync.list_calendars(SyncSide::B).unwrap();
+        assert_eq!(calendars.keys().collect::<Vec<_>>(), ["cal", "new"]);
+        assert_eq!(calendars["cal"].name, "Calendar");
+        assert_eq!(calendars["cal"].color.as_deref(), Some("#ff0000"));
+        assert_eq!(sync.list(SyncSide::B, "new").unwrap().len(), 1);
+        assert_eq!(statuses.keys().collect::<Vec<_>>(), ["cal", "new"]);
+        assert_eq!(statuses["new"].entries.len(), 1);
+        assert!(sync.plan(&statuses).unwrap().is_empty());
+    }
+
+    /// Source ids of the item copies of `plan`.
+    fn items(plan: &SyncPlan) -> Vec<&str> {
+        plan.steps
+            .iter()
+            .filter_map(|step| match step {
+                SyncStep::CopyItem { source, .. } => Some(source.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,27 @@
+//! Two-way sync between two backends: the [`CalendarSync`] engine,
+//! its persistent [`SyncStatus`], its [`SyncConflictResolution`]
+//! policies, the [`SyncPlan`] of a dry run and the [`SyncReport`] of
+//! each run.
+//!
+//! The engine only goes through the shared API of
+//! [`CalendarClientStd`](crate::client::CalendarClientStd) (`list_items`,
+//! `get_item`, `create_item`, `update_item`, `delete_item`), so any two
+//! backends pair up: vdir and WebDAV, two WebDAV accounts, or a custom
+//! [`CalendarBackend`](crate::backend::CalendarBackend).
+
+mod engine;
+mod plan;
+mod resolution;
+mod status;
+mod types;
+
+#[doc(inline)]
+pub use engine::*;
+#[doc(inline)]
+pub use plan::*;
+#[doc(inline)]
+pub use resolution::*;
+#[doc(inline)]
+pub use status::*;
+#[doc(inline)]
+pub use types::*;
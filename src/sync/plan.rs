@@ -0,0 +1,325 @@
+//! Serializable sync plans: what a [`CalendarSync`](super::CalendarSync)
+//! run would change, computed without writing anything, printable as a
+//! dry run and executable step by step.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use crate::{
+    calendar::{Calendar, CalendarDiff},
+    sync::{SyncConflict, SyncConflictKind, SyncSide},
+};
+
+/// One change of a [`SyncPlan`].
+///
+/// Item steps carry the entity tags seen while planning: applying a
+/// step whose items changed since then fails with
+/// [`SyncError::Stale`](super::SyncError::Stale) instead of writing
+/// over the newer version.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SyncStep {
+    /// Create `calendar` on `side`, copying the metadata of the other
+    /// side.
+    CreateCalendar { side: SyncSide, calendar: Calendar },
+    /// Apply `diff` to calendar `id` on `side`.
+    UpdateCalendar {
+        side: SyncSide,
+        id: String,
+        diff: CalendarDiff,
+    },
+    /// Delete calendar `id` from `side`, it being gone from the other
+    /// side since the last run.
+    DeleteCalendar { side: SyncSide, id: String },
+    /// Copy item `source` of side `from` to the other side.
+    CopyItem {
+        from: SyncSide,
+        calendar_a: String,
+        calendar_b: String,
+        source: String,
+        etag: String,
+    },
+    /// Overwrite item `target` with item `source` of side `from`.
+    OverwriteItem {
+        from: SyncSide,
+        calendar_a: String,
+        calendar_b: String,
+        source: String,
+        source_etag: String,
+        target: String,
+        target_etag: String,
+    },
+    /// Delete item `id` from `side`, its counterpart being gone.
+    DeleteItem {
+        side: SyncSide,
+        calendar_a: String,
+        calendar_b: String,
+        id: String,
+        etag: String,
+    },
+    /// Settle diverging items `a` and `b` with the
+    /// [`SyncConflictResolution`](super::SyncConflictResolution).
+    ResolveItems {
+        calendar_a: String,
+        calendar_b: String,
+        a: String,
+        a_etag: String,
+        b: String,
+        b_etag: String,
+    },
+    /// Record items `a` and `b` as in sync (identical contents); only
+    /// the status changes.
+    PairItems {
+        calendar_a: String,
+        calendar_b: String,
+        a: String,
+        a_etag: String,
+        b: String,
+        b_etag: String,
+    },
+    /// Drop the pair `a` / `b`, gone from both sides; only the status
+    /// changes.
+    ForgetItems {
+        calendar_a: String,
+        calendar_b: String,
+        a: String,
+        b: String,
+    },
+}
+
+impl SyncStep {
+    /// The calendar pair of an item step, `None` for calendar steps.
+    pub fn calendars(&self) -> Option<(&str, &str)> {
+        match self {
+            Self::CreateCalendar { .. }
+            | Self::UpdateCalendar { .. }
+            | Self::DeleteCalendar { .. } => None,
+            Self::CopyItem {
+                calendar_a,
+                calendar_b,
+                ..
+            }
+            | Self::OverwriteItem {
+                calendar_a,
+                calendar_b,
+                ..
+            }
+            | Self::DeleteItem {
+                calendar_a,
+                calendar_b,
+                ..
+            }
+            | Self::ResolveItems {
+                calendar_a,
+                calendar_b,
+                ..
+            }
+            | Self::PairItems {
+                calendar_a,
+                calendar_b,
+                ..
+            }
+            | Self::ForgetItems {
+                calendar_a,
+                calendar_b,
+                ..
+            } => Some((calendar_a, calendar_b)),
+        }
+    }
+}
+
+impl fmt::Display for SyncStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateCalendar { side, calendar } => {
+                write!(f, "create calendar {} on {side:?}", calendar.id)
+            }
+            Self::UpdateCalendar { side, id, diff } => {
+                write!(f, "update calendar {id} on {side:?}:")?;
+                if let Some(name) = &diff.name {
+                    write!(f, " name={name}")?;
+                }
+                if let Some(description) = &diff.description {
+                    write!(f, " description={}", description.as_deref().unwrap_or("-"))?;
+                }
+                if let Some(color) = &diff.color {
+                    write!(f, " color={}", color.as_deref().unwrap_or("-"))?;
+                }
+                Ok(())
+            }
+            Self::DeleteCalendar { side, id } => write!(f, "delete calendar {id} on {side:?}"),
+            Self::CopyItem {
+                from,
+                calendar_a,
+                calendar_b,
+                source,
+                ..
+            } => {
+                let (src, dst) = from.pick((calendar_a, calendar_b), (calendar_b, calendar_a));
+                let to = from.other();
+                write!(
+                    f,
+                    "copy item {src}/{source} from {from:?} to {dst} on {to:?}"
+                )
+            }
+            Self::OverwriteItem {
+                from,
+                calendar_a,
+                calendar_b,
+                source,
+                target,
+                ..
+            } => {
+                let (src, dst) = from.pick((calendar_a, calendar_b), (calendar_b, calendar_a));
+                let to = from.other();
+                write!(
+                    f,
+                    "overwrite item {dst}/{target} on {to:?} with {src}/{source}"
+                )
+            }
+            Self::DeleteItem {
+                side,
+                calendar_a,
+                calendar_b,
+                id,
+                ..
+            } => {
+                let calendar = side.pick(calendar_a, calendar_b);
+                write!(f, "delete item {calendar}/{id} on {side:?}")
+            }
+            Self::ResolveItems {
+                calendar_a,
+                calendar_b,
+                a,
+                b,
+                ..
+            } => write!(
+                f,
+                "resolve items {calendar_a}/{a} on A and {calendar_b}/{b} on B"
+            ),
+            Self::PairItems {
+                calendar_a,
+                calendar_b,
+                a,
+                b,
+                ..
+            } => write!(
+                f,
+                "pair items {calendar_a}/{a} on A and {calendar_b}/{b} on B"
+            ),
+            Self::ForgetItems {
+                calendar_a,
+                calendar_b,
+                a,
+                b,
+            } => write!(
+                f,
+                "forget items {calendar_a}/{a} on A and {calendar_b}/{b} on B"
+            ),
+        }
+    }
+}
+
+/// Every step a sync would apply, in order, plus the conflicts it
+/// would leave to the caller.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct SyncPlan {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub steps: Vec<SyncStep>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub conflicts: Vec<SyncConflict>,
+}
+
+impl SyncPlan {
+    /// Whether the sync would change nothing.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// Dry-run output: one step per line, then one conflict per line.
+impl fmt::Display for SyncPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+
+        for conflict in &self.conflicts {
+            let a = conflict.a.as_deref().unwrap_or("-");
+            let b = conflict.b.as_deref().unwrap_or("-");
+            let (calendar_a, calendar_b) = (&conflict.calendar_a, &conflict.calendar_b);
+            write!(
+                f,
+                "conflict on {calendar_a}/{a} on A and {calendar_b}/{b} on B: "
+            )?;
+
+            match conflict.kind {
+                SyncConflictKind::Modified => writeln!(f, "changed on both sides")?,
+                SyncConflictKind::Deleted(side) => {
+                    writeln!(f, "deleted on {side:?}, changed on the other")?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+
+    #[test]
+    fn prints_dry_runs() {
+        let (calendar_a, calendar_b) = ("work".to_string(), "job".to_string());
+        let plan = SyncPlan {
+            steps: vec![
+                SyncStep::UpdateCalendar {
+                    side: SyncSide::B,
+                    id: "job".to_string(),
+                    diff: CalendarDiff {
+                        name: Some("Work".to_string()),
+                        description: Some(None),
+                        color: None,
+                    },
+                },
+                SyncStep::CopyItem {
+                    from: SyncSide::B,
+                    calendar_a: calendar_a.clone(),
+                    calendar_b: calendar_b.clone(),
+                    source: "1".to_string(),
+                    etag: "e".to_string(),
+                },
+                SyncStep::DeleteItem {
+                    side: SyncSide::A,
+                    calendar_a: calendar_a.clone(),
+                    calendar_b: calendar_b.clone(),
+                    id: "2".to_string(),
+                    etag: "e".to_string(),
+                },
+            ],
+            conflicts: vec![SyncConflict {
+                calendar_a,
+                calendar_b,
+                a: None,
+                b: Some("3".to_string()),
+                kind: SyncConflictKind::Deleted(SyncSide::A),
+            }],
+        };
+
+        assert!(!plan.is_empty());
+        assert!(SyncPlan::default().is_empty());
+        assert_eq!(
+            plan.to_string(),
+            "update calendar job on B: name=Work description=-\n\
+             copy item job/1 from B to work on A\n\
+             delete item work/2 on A\n\
+             conflict on work/- on A and job/3 on B: deleted on A, changed on the other\n"
+        );
+    }
+}
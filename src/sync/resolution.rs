@@ -0,0 +1,277 @@
+//! Policies applied to items changed on both sides of a sync.
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{cmp::Ordering, fmt};
+
+use crate::item::{CalendarItem, content_tag};
+
+/// Callback merging the side A and side B versions of an item into
+/// the bytes written to both sides.
+pub type SyncMerge = Box<dyn FnMut(&CalendarItem, &CalendarItem) -> Vec<u8>>;
+
+/// How [`CalendarSync`](super::CalendarSync) settles a pair of items
+/// that both changed since the last run (or two unknown items sharing
+/// an id with different contents).
+///
+/// Items changed on one side and deleted on the other are always
+/// reported, whatever the policy.
+#[derive(Default)]
+pub enum SyncConflictResolution {
+    /// Leave both items untouched and report the conflict.
+    #[default]
+    Report,
+    /// Overwrite side B with the side A version.
+    AWins,
+    /// Overwrite side A with the side B version.
+    BWins,
+    /// Keep the version with the latest `LAST-MODIFIED`, then the
+    /// highest `SEQUENCE`; report the conflict on a tie.
+    Newest,
+    /// Keep the side A version in the pair and copy the side B version
+    /// to both sides as a new item, under a new `UID`.
+    KeepBoth,
+    /// Write the bytes returned by the callback to both sides.
+    Merge(SyncMerge),
+}
+
+impl fmt::Debug for SyncConflictResolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Report => f.write_str("Report"),
+            Self::AWins => f.write_str("AWins"),
+            Self::BWins => f.write_str("BWins"),
+            Self::Newest => f.write_str("Newest"),
+            Self::KeepBoth => f.write_str("KeepBoth"),
+            Self::Merge(_) => f.write_str("Merge(..)"),
+        }
+    }
+}
+
+/// Outcome of a [`SyncConflictResolution`] on one pair.
+pub(crate) struct Resolved {
+    /// Bytes both items of the pair must hold.
+    pub contents: Vec<u8>,
+    /// Bytes of a new item to create on both sides.
+    pub duplicate: Option<Vec<u8>>,
+}
+
+impl SyncConflictResolution {
+    /// Settles the conflict between `a` and `b`, or `None` to report
+    /// it.
+    pub(crate) fn resolve(&mut self, a: &CalendarItem, b: &CalendarItem) -> Option<Resolved> {
+        let contents = match self {
+            Self::Report => return None,
+            Self::AWins => a.contents.clone(),
+            Self::BWins => b.contents.clone(),
+            Self::Newest => match revision(&a.contents).cmp(&revision(&b.contents)) {
+                Ordering::Greater => a.contents.clone(),
+                Ordering::Less => b.contents.clone(),
+                Ordering::Equal => return None,
+            },
+            Self::KeepBoth => {
+                return Some(Resolved {
+                    contents: a.contents.clone(),
+                    duplicate: Some(with_new_uid(&b.contents)),
+                });
+            }
+            Self::Merge(merge) => merge(a, b),
+        };
+
+        Some(Resolved {
+            contents,
+            duplicate: None,
+        })
+    }
+}
+
+/// Unfolded content lines of an iCalendar object (RFC 5545 section
+/// 3.1).
+fn lines(contents: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(contents);
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Splits a content line into its upper-cased name and its value.
+fn property(line: &str) -> Option<(String, &str)> {
+    let (head, value) = line.split_once(':')?;
+    let name = head.split(';').next()?.to_ascii_uppercase();
+    Some((name, value))
+}
+
+/// Latest `LAST-MODIFIED` and highest `SEQUENCE` of the item's
+/// components, time zones excluded. `LAST-MODIFIED` is always UTC, so
+/// its basic ISO 8601 form compares as text.
+fn revision(contents: &[u8]) -> (Option<String>, u32) {
+    let mut last_modified: Option<String> = None;
+    let mut sequence = 0;
+    let mut depth = 0usize;
+
+    for line in lines(contents) {
+        let Some((name, value)) = property(&line) else {
+            continue;
+        };
+        let value = value.trim();
+
+        match name.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VTIMEZONE") || depth > 0 => depth += 1,
+            "END" if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            "LAST-MODIFIED" if last_modified.as_deref().is_none_or(|last| value > last) => {
+                last_modified = Some(value.to_string());
+            }
+            "SEQUENCE" => sequence = sequence.max(value.parse().unwrap_or(0)),
+            _ => {}
+        }
+    }
+
+    (last_modified, sequence)
+}
+
+/// Copy of `contents` whose `UID`s are suffixed with a hash of the
+/// original bytes, so the copy is stable across runs and distinct
+/// from its source.
+fn with_new_uid(contents: &[u8]) -> Vec<u8> {
+    let suffix = content_tag(contents);
+    let mut out = String::new();
+
+    for line in lines(contents) {
+        if line.is_empty() {
+            continue;
+        }
+
+        match property(&line) {
+            Some((name, value)) if name == "UID" => {
+                let head = &line[..line.len() - value.len()];
+                out.push_str(&format!("{head}{}-{suffix}\r\n", value.trim()));
+            }
+            _ => {
+                out.push_str(&line);
+                out.push_str("\r\n");
+            }
+        }
+    }
+
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::fixture;
+
+    fn newest(a: &CalendarItem, b: &CalendarItem) -> Option<Vec<u8>> {
+        let resolved = SyncConflictResolution::Newest.resolve(a, b)?;
+        assert!(resolved.duplicate.is_none());
+        Some(resolved.contents)
+    }
+
+    #[test]
+    fn newest_compares_last_modified_then_sequence() {
+        let old = "LAST-MODIFIED:20250101T000000Z";
+        let new = "LAST-MODIFIED:20250102T000000Z";
+
+        for (a, b, winner) in [
+            (&[old][..], &[new][..], Some('b')),
+            (&[new, "SEQUENCE:0"], &[old, "SEQUENCE:5"], Some('a')),
+            (&["SEQUENCE:2"], &["SEQUENCE:1"], Some('a')),
+            (&[old, "SEQUENCE:1"], &[old, "SEQUENCE:2"], Some('b')),
+            (&[old], &[old], None),
+            (&[], &[], None),
+        ] {
+            let (a, b) = (fixture::item("1", a), fixture::item("1", b));
+            let expected = winner.map(|winner| match winner {
+                'a' => a.contents.clone(),
+                _ => b.contents.clone(),
+            });
+            assert_eq!(newest(&a, &b), expected, "{a:?} / {b:?}");
+        }
+    }
+
+    #[test]
+    fn newest_ignores_time_zones_and_unfolds_lines() {
+        let a = fixture::item(
+            "1",
+            &[
+                "BEGIN:VTIMEZONE",
+                "LAST-MODIFIED:20300101T000000Z",
+                "END:VTIMEZONE",
+                "BEGIN:VEVENT",
+                "LAST-MODIFIED:20250101T000000Z",
+                "END:VEVENT",
+            ],
+        );
+        let b = fixture::item(
+            "1",
+            &[
+                "BEGIN:VEVENT",
+                "LAST-MODIFIED:2025010",
+                " 2T000000Z",
+                "END:VEVENT",
+            ],
+        );
+
+        assert_eq!(newest(&a, &b), Some(b.contents.clone()));
+    }
+
+    #[test]
+    fn keep_both_suffixes_uids_of_the_b_version() {
+        let a = fixture::item("1", &["BEGIN:VEVENT", "UID:1", "SUMMARY:a", "END:VEVENT"]);
+        let b = fixture::item("1", &["BEGIN:VEVENT", "UID:1", "SUMMARY:b", "END:VEVENT"]);
+
+        let resolved = SyncConflictResolution::KeepBoth.resolve(&a, &b).unwrap();
+        assert_eq!(resolved.contents, a.contents);
+
+        let duplicate = resolved.duplicate.unwrap();
+        let suffix = content_tag(&b.contents);
+        let expected = fixture::item(
+            "1",
+            &[
+                "BEGIN:VEVENT",
+                &format!("UID:1-{suffix}"),
+                "SUMMARY:b",
+                "END:VEVENT",
+            ],
+        );
+        assert_eq!(duplicate, expected.contents);
+        assert_eq!(with_new_uid(&b.contents), duplicate);
+    }
+
+    #[test]
+    fn fixed_policies_pick_a_side_or_the_merge() {
+        let (a, b) = (
+            fixture::item("1", &["SUMMARY:a"]),
+            fixture::item("1", &["SUMMARY:b"]),
+        );
+        let merge: SyncMerge = Box::new(|a, b| [&a.contents[..], &b.contents[..]].concat());
+
+        assert!(SyncConflictResolution::Report.resolve(&a, &b).is_none());
+
+        for (mut resolution, expected) in [
+            (SyncConflictResolution::AWins, a.contents.clone()),
+            (SyncConflictResolution::BWins, b.contents.clone()),
+            (
+                SyncConflictResolution::Merge(merge),
+                [a.contents.clone(), b.contents.clone()].concat(),
+            ),
+        ] {
+            let resolved = resolution.resolve(&a, &b).unwrap();
+            assert_eq!(resolved.contents, expected, "{resolution:?}");
+            assert!(resolved.duplicate.is_none());
+        }
+    }
+}
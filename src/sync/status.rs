@@ -0,0 +1,258 @@
+//! Persistent state of a two-way sync: which item of side A is paired
+//! with which item of side B, and the entity tags both had when they
+//! were last in sync.
+//!
+//! The status file is plain UTF-8 text: a header line, then one line
+//! per pair holding the A id, A ETag, B id and B ETag separated by
+//! tabs. Tabs, newlines and backslashes inside values are escaped.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use std::{fs, io, path::Path};
+
+use crate::sync::{SyncError, SyncSide};
+
+/// First line of every status file.
+const HEADER: &str = "# io-calendar sync status v1";
+
+/// One side of a [`SyncStatusEntry`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct SyncStatusItem {
+    /// Item id on that side.
+    pub id: String,
+    /// Entity tag the item had after the last sync. Items of backends
+    /// without entity tags are tagged with a hash of their contents.
+    pub etag: String,
+}
+
+/// A pair of items known to be in sync.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct SyncStatusEntry {
+    pub a: SyncStatusItem,
+    pub b: SyncStatusItem,
+}
+
+impl SyncStatusEntry {
+    /// Builds the entry pairing `source` on side `from` with `target`
+    /// on the other side.
+    pub fn new(from: SyncSide, source: SyncStatusItem, target: SyncStatusItem) -> Self {
+        match from {
+            SyncSide::A => Self {
+                a: source,
+                b: target,
+            },
+            SyncSide::B => Self {
+                a: target,
+                b: source,
+            },
+        }
+    }
+
+    /// The item of `side`.
+    pub fn side(&self, side: SyncSide) -> &SyncStatusItem {
+        side.pick(&self.a, &self.b)
+    }
+
+    /// The item of `side`, mutably.
+    pub fn side_mut(&mut self, side: SyncSide) -> &mut SyncStatusItem {
+        side.pick(&mut self.a, &mut self.b)
+    }
+}
+
+/// Every pair of items of one synced calendar pair.
+///
+/// [`CalendarSync`](super::CalendarSync) updates it after each applied
+/// change, so it stays accurate even
+/// when a run fails halfway and can be saved either way.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct SyncStatus {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub entries: Vec<SyncStatusEntry>,
+}
+
+impl SyncStatus {
+    /// Reads the status file at `path`; a missing file gives an empty
+    /// status (first sync).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SyncError> {
+        match fs::read(path) {
+            Ok(bytes) => Self::parse(&bytes),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the status file at `path`, through a temporary file
+    /// renamed over it so a crash never leaves a truncated status.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SyncError> {
+        let path = path.as_ref();
+        let mut tmp = path.to_path_buf().into_os_string();
+        tmp.push(".tmp");
+
+        fs::write(&tmp, self.to_bytes())?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Parses the contents of a status file.
+    pub fn parse(bytes: &[u8]) -> Result<Self, SyncError> {
+        let text = core::str::from_utf8(bytes).map_err(|_| SyncError::InvalidStatus(1))?;
+        let mut lines = text.lines().enumerate();
+
+        match lines.next() {
+            Some((_, HEADER)) | None => (),
+            Some(_) => return Err(SyncError::InvalidStatus(1)),
+        }
+
+        let mut entries = Vec::new();
+
+        for (index, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<String> = line.split('\t').map(unescape).collect();
+            let [a_id, a_etag, b_id, b_etag] =
+                <[String; 4]>::try_from(fields).map_err(|_| SyncError::InvalidStatus(index + 1))?;
+
+            entries.push(SyncStatusEntry {
+                a: SyncStatusItem {
+                    id: a_id,
+                    etag: a_etag,
+                },
+                b: SyncStatusItem {
+                    id: b_id,
+                    etag: b_etag,
+                },
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Serializes the status into the file format read by
+    /// [`parse`](Self::parse).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut text = HEADER.to_string();
+        text.push('\n');
+
+        for entry in &self.entries {
+            let fields = [&entry.a.id, &entry.a.etag, &entry.b.id, &entry.b.etag];
+            let fields: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+            text.push_str(&fields.join("\t"));
+            text.push('\n');
+        }
+
+        text.into_bytes()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, vec};
+
+    use super::*;
+
+    fn status() -> SyncStatus {
+        let item = |id: &str, etag: &str| SyncStatusItem {
+            id: id.to_string(),
+            etag: etag.to_string(),
+        };
+
+        SyncStatus {
+            entries: vec![
+                SyncStatusEntry {
+                    a: item("a1", "\"1\""),
+                    b: item("b1", "W/\"2\""),
+                },
+                SyncStatusEntry {
+                    a: item("tab\there", "new\nline"),
+                    b: item("back\\slash", "cr\rlf"),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips_escaped_values() {
+        let status = status();
+        let bytes = status.to_bytes();
+
+        assert_eq!(bytes.iter().filter(|byte| **byte == b'\n').count(), 3);
+        assert_eq!(SyncStatus::parse(&bytes).unwrap(), status);
+    }
+
+    #[test]
+    fn saves_and_loads_status_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cal.status");
+
+        assert_eq!(SyncStatus::load(&path).unwrap(), SyncStatus::default());
+
+        let status = status();
+        status.save(&path).unwrap();
+        assert_eq!(SyncStatus::load(&path).unwrap(), status);
+        assert!(!dir.path().join("cal.status.tmp").exists());
+    }
+
+    #[test]
+    fn rejects_invalid_status_files() {
+        let header = format!("{HEADER}\n");
+
+        for (text, line) in [
+            (String::from("# other\n"), 1),
+            (format!("{header}a\t1\tb\n"), 2),
+            (format!("{header}a\t1\tb\t2\n\na\t1\tb\t2\textra\n"), 4),
+        ] {
+            assert!(
+                matches!(
+                    SyncStatus::parse(text.as_bytes()),
+                    Err(SyncError::InvalidStatus(n)) if n == line
+                ),
+                "{text:?}"
+            );
+        }
+
+        assert!(matches!(
+            SyncStatus::parse(&[0xff]),
+            Err(SyncError::InvalidStatus(1))
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+//! Sides, actions, conflicts and reports of a two-way sync.
+
+use alloc::{string::String, vec::Vec};
+
+/// One of the two backends paired by a
+/// [`CalendarSync`](super::CalendarSync).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SyncSide {
+    A,
+    B,
+}
+
+impl SyncSide {
+    /// The opposite side.
+    pub fn other(self) -> Self {
+        match self {
+            Self::A => Self::B,
+            Self::B => Self::A,
+        }
+    }
+
+    /// Picks `a` or `b` according to this side.
+    pub(crate) fn pick<T>(self, a: T, b: T) -> T {
+        match self {
+            Self::A => a,
+            Self::B => b,
+        }
+    }
+}
+
+/// A change applied by a sync.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SyncAction {
+    /// Calendar `id` was created on `side`.
+    CreateCalendar { side: SyncSide, id: String },
+    /// The metadata of calendar `id` was updated on `side`.
+    UpdateCalendar { side: SyncSide, id: String },
+    /// Calendar `id` was deleted from `side`.
+    DeleteCalendar { side: SyncSide, id: String },
+    /// Item `source` of side `from` was copied to the other side as
+    /// `target`.
+    Create {
+        from: SyncSide,
+        source: String,
+        target: String,
+    },
+    /// Item `source` of side `from` overwrote its counterpart `target`.
+    Update {
+        from: SyncSide,
+        source: String,
+        target: String,
+    },
+    /// Item `id` was deleted from `side`, its counterpart being gone.
+    Delete { side: SyncSide, id: String },
+    /// Conflicting items `a` and `b` were settled by the
+    /// [`SyncConflictResolution`](super::SyncConflictResolution).
+    Resolve { a: String, b: String },
+    /// A conflicting version was kept as a new pair of items `a` and
+    /// `b`.
+    Duplicate { a: String, b: String },
+}
+
+/// Why a pair of items could not be synced automatically.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SyncConflictKind {
+    /// Both items changed since the last sync, or two unknown items
+    /// share an id with different contents.
+    Modified,
+    /// The item was deleted from this side and changed on the other.
+    Deleted(SyncSide),
+}
+
+/// A pair of items left untouched because both sides diverged.
+///
+/// The status entry of the pair is kept as it was, so the conflict is
+/// reported again until one side is fixed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct SyncConflict {
+    /// Calendar id on side A.
+    pub calendar_a: String,
+    /// Calendar id on side B.
+    pub calendar_b: String,
+    /// Item id on side A, when it still exists there.
+    pub a: Option<String>,
+    /// Item id on side B, when it still exists there.
+    pub b: Option<String>,
+    pub kind: SyncConflictKind,
+}
+
+/// Outcome of a [`CalendarSync`](super::CalendarSync) run.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct SyncReport {
+    /// Changes applied, in order.
+    pub applied: Vec<SyncAction>,
+    /// Pairs left for the caller to reconcile.
+    pub conflicts: Vec<SyncConflict>,
+}
@@ -8,17 +8,29 @@
 
 use alloc::{
     collections::{BTreeMap, BTreeSet},
+    format,
     string::{String, ToString},
     vec,
     vec::Vec,
 };
-use std::{fs, io};
+use std::{fs, io, time::UNIX_EPOCH};
 
 use getrandom::fill;
 use io_vdir::{client::VdirClient as InnerVdirClient, coroutine::*, path::VdirPath};
 use log::trace;
 use thiserror::Error;
 
+#[cfg(feature = "parser")]
+use crate::item::{
+    SearchHit, UidCheck, UidConflict, check_uid, item_uid,
+    vdir::{
+        find::{VdirCalendarItemFind, VdirCalendarItemFindError},
+        list::select_items,
+        search::{VdirCalendarItemSearch, VdirCalendarItemSearchError},
+    },
+};
+#[cfg(feature = "parser")]
+use crate::vdir::index::{VdirIndex, VdirIndexRefresh, VdirIndexRefreshError};
 use crate::{
     calendar::{
         Calendar, CalendarDiff,
@@ -30,23 +42,27 @@ use crate::{
         },
     },
     item::{
-        CalendarItem, TimeRange,
+        CalendarItem, CalendarItemKind, CalendarItemReceipt, CalendarItemRef, ItemFilter,
+        TimeRange,
         vdir::{
             create::{VdirCalendarItemCreate, VdirCalendarItemCreateError},
             delete::{VdirCalendarItemDelete, VdirCalendarItemDeleteError},
             get::{VdirCalendarItemGet, VdirCalendarItemGetError},
             list::{VdirCalendarItemList, VdirCalendarItemListError},
+            list_refs::{VdirCalendarItemRefList, VdirCalendarItemRefListError},
+            multiget::{VdirCalendarItemMultiget, VdirCalendarItemMultigetError},
             update::{VdirCalendarItemUpdate, VdirCalendarItemUpdateError},
         },
     },
-    vdir::convert::calendar_path,
+    vdir::convert::{VdirItemStat, calendar_path, collection_tag, item_id_from_file_name},
 };
 
 /// Errors surfaced by [`VdirClient`] while running a coroutine.
 ///
 /// One variant per shared-API Vdir coroutine, plus filesystem and
 /// randomness failures from the run loop and the domain validation
-/// failures from the client methods.
+/// failures from the client methods. A create refused because the item
+/// file exists is lifted to [`VdirClientError::AlreadyExists`].
 #[derive(Debug, Error)]
 pub enum VdirClientError {
     #[error(transparent)]
@@ -63,6 +79,8 @@ pub enum VdirClientError {
     CalendarNotFound(String),
     #[error("Item body is empty")]
     EmptyItemBody,
+    #[error("Item `{0}` already exists")]
+    AlreadyExists(String),
 
     #[error(transparent)]
     CalendarCreate(#[from] VdirCalendarCreateError),
@@ -74,7 +92,7 @@ pub enum VdirClientError {
     CalendarUpdate(#[from] VdirCalendarUpdateError),
 
     #[error(transparent)]
-    ItemCreate(#[from] VdirCalendarItemCreateError),
+    ItemCreate(VdirCalendarItemCreateError),
     #[error(transparent)]
     ItemDelete(#[from] VdirCalendarItemDeleteError),
     #[error(transparent)]
@@ -82,13 +100,50 @@ pub enum VdirClientError {
     #[error(transparent)]
     ItemList(#[from] VdirCalendarItemListError),
     #[error(transparent)]
+    ItemRefList(#[from] VdirCalendarItemRefListError),
+    #[error(transparent)]
+    ItemMultiget(#[from] VdirCalendarItemMultigetError),
+    #[cfg(feature = "parser")]
+    #[error(transparent)]
+    ItemFind(#[from] VdirCalendarItemFindError),
+    #[cfg(feature = "parser")]
+    #[error(transparent)]
+    ItemSearch(#[from] VdirCalendarItemSearchError),
+    #[error(transparent)]
     ItemUpdate(#[from] VdirCalendarItemUpdateError),
+
+    #[cfg(feature = "parser")]
+    #[error(transparent)]
+    IndexRefresh(VdirIndexRefreshError),
+}
+
+impl From<VdirCalendarItemCreateError> for VdirClientError {
+    fn from(err: VdirCalendarItemCreateError) -> Self {
+        match err {
+            VdirCalendarItemCreateError::AlreadyExists(id) => Self::AlreadyExists(id),
+            err => Self::ItemCreate(err),
+        }
+    }
+}
+
+#[cfg(feature = "parser")]
+impl From<VdirIndexRefreshError> for VdirClientError {
+    fn from(err: VdirIndexRefreshError) -> Self {
+        match err {
+            VdirIndexRefreshError::CalendarNotFound(id) => Self::CalendarNotFound(id),
+            err => Self::IndexRefresh(err),
+        }
+    }
 }
 
 /// Std-blocking Vdir calendar client built on a filesystem root.
 #[derive(Debug)]
 pub struct VdirClient {
     pub inner: InnerVdirClient,
+
+    /// Location of the persistent [`VdirIndex`], when enabled.
+    #[cfg(feature = "parser")]
+    index: Option<VdirPath>,
 }
 
 impl VdirClient {
@@ -96,9 +151,25 @@ impl VdirClient {
     pub fn new(root: impl Into<VdirPath>) -> Self {
         Self {
             inner: InnerVdirClient::new(root),
+            #[cfg(feature = "parser")]
+            index: None,
         }
     }
 
+    /// Enables the persistent [`VdirIndex`] stored at `path`, either
+    /// alongside the vdir (`<root>/`[`VdirIndex::FILE_NAME`]) or in a
+    /// cache directory.
+    ///
+    /// [`list_items`](Self::list_items) and
+    /// [`find_item_by_uid`](Self::find_item_by_uid) then refresh the
+    /// index entries of their calendar and only read the files that
+    /// can match their time range, kinds or UID.
+    #[cfg(feature = "parser")]
+    pub fn with_index(mut self, path: impl Into<VdirPath>) -> Self {
+        self.index = Some(path.into());
+        self
+    }
+
     /// Pumps any standard-shape Vdir coroutine (`Yield = VdirYield`,
     /// `Return = Result<T, E>`) against the local filesystem until it
     /// terminates.
@@ -218,8 +289,18 @@ impl VdirClient {
     }
 
     /// Lists every calendar under the configured root, sorted by name.
+    ///
+    /// Each CTag is derived from the names, modification times and
+    /// sizes of the calendar's item files, none of which is read.
     pub fn list_calendars(&self) -> Result<Vec<Calendar>, VdirClientError> {
-        self.run(VdirCalendarList::new(self.inner.root().clone()))
+        let mut calendars = self.run(VdirCalendarList::new(self.inner.root().clone()))?;
+
+        for calendar in &mut calendars {
+            let path = calendar_path(self.inner.root(), &calendar.id);
+            calendar.ctag = Some(collection_tag(&stat_items(&path)?));
+        }
+
+        Ok(calendars)
     }
 
     /// Creates calendar `id` (display name `name`) under the root.
@@ -282,25 +363,42 @@ impl VdirClient {
     /// Lists items inside `calendar_id`, applying 1-indexed pagination.
     ///
     /// When `time_range` is set, the fetched items are filtered
-    /// client-side, keeping only VEVENTs whose start date falls in the
-    /// range (the filesystem backend has no server-side query). Needs
-    /// the `parser` feature; without it the range is ignored.
+    /// client-side following RFC 4791 section 9.9 for every component
+    /// kind (the filesystem backend has no server-side query). Needs
+    /// the `parser` feature; without it the range is ignored. When
+    /// `kinds` is non-empty, only items of those kinds are kept. A
+    /// [`RecurrenceExpansion`](crate::item::RecurrenceExpansion) on the
+    /// range is applied client-side too, before pagination.
     pub fn list_items(
         &self,
         calendar_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
         time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
     ) -> Result<Vec<CalendarItem>, VdirClientError> {
         self.validate_calendar(calendar_id)?;
         let path = calendar_path(self.inner.root(), calendar_id);
-        let items = self.run(VdirCalendarItemList::new(
+
+        #[cfg(feature = "parser")]
+        if let Some(index) = self.refresh_index(calendar_id, &path)? {
+            let ids = index.select(calendar_id, time_range, kinds);
+            let items = self.run(VdirCalendarItemMultiget::new(path, calendar_id, &ids))?;
+            return Ok(select_items(
+                items, page, page_size, time_range, kinds, filter,
+            ));
+        }
+
+        self.run(VdirCalendarItemList::new(
             path,
             calendar_id,
             page,
             page_size,
-        ))?;
-        Ok(filter_time_range(items, time_range))
+            time_range,
+            kinds,
+            filter,
+        ))
     }
 
     /// Fetches `item_id` from `calendar_id`.
@@ -315,13 +413,102 @@ impl VdirClient {
         self.run(VdirCalendarItemGet::new(path, calendar_id, item_id))
     }
 
+    /// Fetches `item_ids` from `calendar_id` with one batched file
+    /// read, in the requested order. Ids without a file are left out.
+    pub fn get_items(
+        &self,
+        calendar_id: &str,
+        item_ids: &[&str],
+    ) -> Result<Vec<CalendarItem>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        for item_id in item_ids {
+            self.validate_item(item_id)?;
+        }
+
+        if item_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let path = calendar_path(self.inner.root(), calendar_id);
+        self.run(VdirCalendarItemMultiget::new(path, calendar_id, item_ids))
+    }
+
+    /// Finds the item of `calendar_id` carrying the iCalendar `uid`,
+    /// reading and parsing every file of the calendar, or only the
+    /// files the index names when enabled. Returns the one with the
+    /// smallest id when several match.
+    #[cfg(feature = "parser")]
+    pub fn find_item_by_uid(
+        &self,
+        calendar_id: &str,
+        uid: &str,
+    ) -> Result<Option<CalendarItem>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+
+        if let Some(index) = self.refresh_index(calendar_id, &path)? {
+            let ids = index.find_uid(calendar_id, uid);
+            let items = self.run(VdirCalendarItemMultiget::new(path, calendar_id, &ids))?;
+            return Ok(items
+                .into_iter()
+                .find(|item| item.uid().as_deref() == Some(uid)));
+        }
+
+        self.run(VdirCalendarItemFind::new(path, calendar_id, uid))
+    }
+
+    /// Searches `query` in the SUMMARY, DESCRIPTION, LOCATION, ATTENDEE
+    /// and CATEGORIES of the items of `calendar_id`, reading and
+    /// parsing every file of the calendar. Returns the hits sorted by
+    /// id.
+    #[cfg(feature = "parser")]
+    pub fn search_items(
+        &self,
+        calendar_id: &str,
+        query: &str,
+    ) -> Result<Vec<SearchHit>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+        self.run(VdirCalendarItemSearch::new(path, calendar_id, query))
+    }
+
+    /// Lists the id, synthetic ETag, size and modification time of
+    /// every item of `calendar_id`, sorted by id, without returning
+    /// their contents.
+    ///
+    /// The files are still read, since the synthetic ETag hashes their
+    /// contents; the modification time comes from the file metadata.
+    pub fn list_item_refs(
+        &self,
+        calendar_id: &str,
+    ) -> Result<Vec<CalendarItemRef>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+        let mut refs = self.run(VdirCalendarItemRefList::new(path.clone(), calendar_id))?;
+
+        for item in &mut refs {
+            let file = path.join(&format!("{}.ics", item.id));
+            item.last_modified = fs::metadata(file.as_str())
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|elapsed| elapsed.as_secs());
+        }
+
+        Ok(refs)
+    }
+
     /// Appends a new iCalendar item to `calendar_id`. Returns its
-    /// assigned id.
+    /// assigned id along with the synthetic ETag of the contents.
+    ///
+    /// With the `parser` feature the id is the item's UID when it makes
+    /// a safe file stem. Fails with [`VdirClientError::AlreadyExists`]
+    /// rather than overwrite an existing file.
     pub fn create_item(
         &self,
         calendar_id: &str,
         contents: Vec<u8>,
-    ) -> Result<String, VdirClientError> {
+    ) -> Result<CalendarItemReceipt, VdirClientError> {
         if contents.is_empty() {
             return Err(VdirClientError::EmptyItemBody);
         }
@@ -330,30 +517,73 @@ impl VdirClient {
         self.run(VdirCalendarItemCreate::new(path, contents)?)
     }
 
-    /// Overwrites `item_id` inside `calendar_id`. `if_match` is ignored:
-    /// vdir has no entity-tag concept.
+    /// Appends a new iCalendar item to `calendar_id` like
+    /// [`create_item`](Self::create_item), first scanning the calendar
+    /// for an item with the same UID: `on_conflict` then either fails
+    /// with [`VdirClientError::AlreadyExists`], replaces that item
+    /// (gated on its synthetic ETag) or stores the new one under a
+    /// suffixed UID.
+    #[cfg(feature = "parser")]
+    pub fn create_item_with(
+        &self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+        on_conflict: UidConflict,
+    ) -> Result<CalendarItemReceipt, VdirClientError> {
+        let Some(uid) = item_uid(&contents) else {
+            return self.create_item(calendar_id, contents);
+        };
+        let items = self.list_items(calendar_id, None, None, None, &[], None)?;
+
+        match check_uid(items, &uid, contents, on_conflict) {
+            UidCheck::Create(contents) => self.create_item(calendar_id, contents),
+            UidCheck::Exists(id) => Err(VdirClientError::AlreadyExists(id)),
+            UidCheck::Replace(existing, contents) => {
+                let etag = existing.etag.as_deref();
+                self.update_item(calendar_id, &existing.id, contents, etag)
+            }
+        }
+    }
+
+    /// Overwrites `item_id` inside `calendar_id`, returning the
+    /// synthetic ETag of the new contents.
+    ///
+    /// When `if_match` is set, the write only happens if the file still
+    /// carries that synthetic ETag; otherwise it fails with
+    /// [`VdirCalendarItemUpdateError::PreconditionFailed`].
     pub fn update_item(
         &self,
         calendar_id: &str,
         item_id: &str,
         contents: Vec<u8>,
-        _if_match: Option<&str>,
-    ) -> Result<(), VdirClientError> {
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, VdirClientError> {
         if contents.is_empty() {
             return Err(VdirClientError::EmptyItemBody);
         }
         self.validate_calendar(calendar_id)?;
         self.validate_item(item_id)?;
         let path = calendar_path(self.inner.root(), calendar_id);
-        self.run(VdirCalendarItemUpdate::new(path, item_id, contents)?)
+        self.run(VdirCalendarItemUpdate::new(
+            path, item_id, contents, if_match,
+        )?)
     }
 
     /// Permanently deletes `item_id` from `calendar_id`.
-    pub fn delete_item(&self, calendar_id: &str, item_id: &str) -> Result<(), VdirClientError> {
+    ///
+    /// When `if_match` is set, the file is only removed if it still
+    /// carries that synthetic ETag; otherwise it fails with
+    /// [`VdirCalendarItemDeleteError::PreconditionFailed`].
+    pub fn delete_item(
+        &self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), VdirClientError> {
         self.validate_calendar(calendar_id)?;
         self.validate_item(item_id)?;
         let path = calendar_path(self.inner.root(), calendar_id);
-        self.run(VdirCalendarItemDelete::new(path, item_id))
+        self.run(VdirCalendarItemDelete::new(path, item_id, if_match))
     }
 
     /// Rejects an empty calendar id (after trimming surrounding
@@ -365,6 +595,29 @@ impl VdirClient {
         Ok(())
     }
 
+    /// Refreshes the entries of `calendar_id`, stored at `path`, in
+    /// the index when enabled. A missing calendar directory is reported
+    /// as [`VdirClientError::CalendarNotFound`].
+    #[cfg(feature = "parser")]
+    fn refresh_index(
+        &self,
+        calendar_id: &str,
+        path: &VdirPath,
+    ) -> Result<Option<VdirIndex>, VdirClientError> {
+        let Some(location) = &self.index else {
+            return Ok(None);
+        };
+
+        let stats = match stat_items(path) {
+            Ok(stats) => Some(stats),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        let refresh = VdirIndexRefresh::new(location.clone(), calendar_id, path.clone(), stats);
+        self.run(refresh).map(Some)
+    }
+
     /// Rejects an empty item id.
     fn validate_item(&self, id: &str) -> Result<(), VdirClientError> {
         if id.is_empty() {
@@ -374,6 +627,41 @@ impl VdirClient {
     }
 }
 
+/// Gathers the [`VdirItemStat`] of every item file (`{id}.ics`) of the
+/// calendar directory `path`, by item id.
+fn stat_items(path: &VdirPath) -> io::Result<BTreeMap<String, VdirItemStat>> {
+    let mut stats = BTreeMap::new();
+
+    for entry in fs::read_dir(path.as_str())? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(id) = name.to_str().and_then(item_id_from_file_name) else {
+            continue;
+        };
+
+        let metadata = fs::metadata(entry.path())?;
+        if metadata.is_file() {
+            stats.insert(id.to_string(), item_stat(&metadata)?);
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Builds the [`VdirItemStat`] of a file from its metadata.
+pub(crate) fn item_stat(metadata: &fs::Metadata) -> io::Result<VdirItemStat> {
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+
+    Ok(VdirItemStat {
+        modified,
+        size: metadata.len(),
+    })
+}
+
 /// Normalizes a host [`std::path::PathBuf`] into a `/`-separated
 /// [`VdirPath`].
 fn normalize_path(path: std::path::PathBuf) -> VdirPath {
@@ -382,85 +670,3 @@ fn normalize_path(path: std::path::PathBuf) -> VdirPath {
     let s = s.replace('\\', "/");
     VdirPath::new(s)
 }
-
-/// Keeps only the items matching `time_range`, when set: VEVENTs whose
-/// `DTSTART` date is within `[start, end)` at day precision.
-#[cfg(feature = "parser")]
-fn filter_time_range(
-    items: Vec<CalendarItem>,
-    time_range: Option<&TimeRange>,
-) -> Vec<CalendarItem> {
-    let Some(range) = time_range else {
-        return items;
-    };
-
-    items
-        .into_iter()
-        .filter(|item| event_in_range(item, range))
-        .collect()
-}
-
-/// Without the `parser` feature the items cannot be inspected, so the
-/// range is ignored and every fetched item is returned.
-#[cfg(not(feature = "parser"))]
-fn filter_time_range(
-    items: Vec<CalendarItem>,
-    time_range: Option<&TimeRange>,
-) -> Vec<CalendarItem> {
-    if time_range.is_some() {
-        trace!("vdir time-range filter ignored: parser feature is disabled");
-    }
-    items
-}
-
-/// Whether `item`'s first VEVENT carries a `DTSTART` date inside
-/// `range` (inclusive lower bound, exclusive upper bound, day
-/// precision). Items without a parseable VEVENT start are dropped.
-#[cfg(feature = "parser")]
-fn event_in_range(item: &CalendarItem, range: &TimeRange) -> bool {
-    use alloc::format;
-
-    use calcard::icalendar::{ICalendarComponentType, ICalendarProperty, ICalendarValue};
-
-    let Some(ical) = item.as_ical() else {
-        return false;
-    };
-
-    let Some(vevent) = ical
-        .components
-        .iter()
-        .find(|component| component.component_type == ICalendarComponentType::VEvent)
-    else {
-        return false;
-    };
-
-    let Some(property) = vevent.property(&ICalendarProperty::Dtstart) else {
-        return false;
-    };
-
-    let date = property.values.iter().find_map(|value| match value {
-        ICalendarValue::PartialDateTime(pdt) => match (pdt.year, pdt.month, pdt.day) {
-            (Some(year), Some(month), Some(day)) => Some(format!("{year:04}{month:02}{day:02}")),
-            _ => None,
-        },
-        _ => None,
-    });
-
-    let Some(date) = date else {
-        return false;
-    };
-
-    if let Some(start) = range.start() {
-        if date.as_str() < &start[..8] {
-            return false;
-        }
-    }
-
-    if let Some(end) = range.end() {
-        if date.as_str() >= &end[..8] {
-            return false;
-        }
-    }
-
-    true
-}
@@ -0,0 +1,622 @@
+//! Async (tokio) Vdir calendar client.
+//!
+//! Pumps the same io-calendar Vdir coroutines as
+//! [`VdirClient`](crate::vdir::client::VdirClient), answering every
+//! [`VdirYield`] with `tokio::fs` instead of blocking `std::fs` calls.
+//! Batched [`VdirYield::WantsFileRead`] paths are read concurrently, at
+//! most [`VdirClientTokio::MAX_CONCURRENT_READS`] at a time, so listing
+//! a calendar with thousands of `.ics` files neither stalls the
+//! executor nor exhausts file descriptors. With the `parser` feature,
+//! the client can keep the same persistent
+//! [`VdirIndex`](crate::vdir::index::VdirIndex) as the std one.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use std::{io, time::UNIX_EPOCH};
+
+use getrandom::fill;
+use io_vdir::{client::VdirClient as InnerVdirClient, coroutine::*, path::VdirPath};
+use log::trace;
+use tokio::{fs, task::JoinSet};
+
+#[cfg(feature = "parser")]
+use crate::item::{
+    SearchHit, UidCheck, UidConflict, check_uid, item_uid,
+    vdir::{find::VdirCalendarItemFind, list::select_items, search::VdirCalendarItemSearch},
+};
+#[cfg(feature = "parser")]
+use crate::vdir::index::{VdirIndex, VdirIndexRefresh};
+use crate::{
+    calendar::{
+        Calendar, CalendarDiff,
+        vdir::{
+            create::VdirCalendarCreate, delete::VdirCalendarDelete, list::VdirCalendarList,
+            update::VdirCalendarUpdate,
+        },
+    },
+    item::{
+        CalendarItem, CalendarItemKind, CalendarItemReceipt, CalendarItemRef, ItemFilter,
+        TimeRange,
+        vdir::{
+            create::VdirCalendarItemCreate, delete::VdirCalendarItemDelete,
+            get::VdirCalendarItemGet, list::VdirCalendarItemList,
+            list_refs::VdirCalendarItemRefList, multiget::VdirCalendarItemMultiget,
+            update::VdirCalendarItemUpdate,
+        },
+    },
+    vdir::{
+        client::{VdirClientError, item_stat},
+        convert::{VdirItemStat, calendar_path, collection_tag, item_id_from_file_name},
+    },
+};
+
+/// Async Vdir calendar client built on a filesystem root.
+///
+/// Shares [`VdirClientError`] with the std client.
+#[derive(Debug)]
+pub struct VdirClientTokio {
+    pub inner: InnerVdirClient,
+
+    /// Location of the persistent [`VdirIndex`], when enabled.
+    #[cfg(feature = "parser")]
+    index: Option<VdirPath>,
+}
+
+impl VdirClientTokio {
+    /// Most files read at once while answering a batched
+    /// [`VdirYield::WantsFileRead`].
+    pub const MAX_CONCURRENT_READS: usize = 64;
+
+    /// Builds a client rooted at `root`.
+    pub fn new(root: impl Into<VdirPath>) -> Self {
+        Self {
+            inner: InnerVdirClient::new(root),
+            #[cfg(feature = "parser")]
+            index: None,
+        }
+    }
+
+    /// Enables the persistent [`VdirIndex`] stored at `path`, like
+    /// [`VdirClient::with_index`](crate::vdir::client::VdirClient::with_index).
+    #[cfg(feature = "parser")]
+    pub fn with_index(mut self, path: impl Into<VdirPath>) -> Self {
+        self.index = Some(path.into());
+        self
+    }
+
+    /// Pumps any standard-shape Vdir coroutine (`Yield = VdirYield`,
+    /// `Return = Result<T, E>`) against the local filesystem until it
+    /// terminates.
+    pub async fn run<C, T, E>(&self, mut coroutine: C) -> Result<T, VdirClientError>
+    where
+        C: VdirCoroutine<Yield = VdirYield, Return = Result<T, E>>,
+        VdirClientError: From<E>,
+    {
+        let mut arg: Option<VdirReply> = None;
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                VdirCoroutineState::Complete(Ok(out)) => return Ok(out),
+                VdirCoroutineState::Complete(Err(err)) => return Err(err.into()),
+                VdirCoroutineState::Yielded(VdirYield::WantsRandom { len }) => {
+                    let mut bytes = vec![0u8; len];
+                    fill(&mut bytes).map_err(VdirClientError::Random)?;
+                    arg = Some(VdirReply::Random(bytes));
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsFileExists(paths)) => {
+                    let mut out = BTreeMap::new();
+                    for path in paths {
+                        let exists = fs::metadata(path.as_str())
+                            .await
+                            .map(|m| m.is_file())
+                            .unwrap_or(false);
+                        trace!("file_exists {path}: {exists}");
+                        out.insert(path, exists);
+                    }
+                    arg = Some(VdirReply::FileExists(out));
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsDirExists(paths)) => {
+                    let mut out = BTreeMap::new();
+                    for path in paths {
+                        let exists = fs::metadata(path.as_str())
+                            .await
+                            .map(|m| m.is_dir())
+                            .unwrap_or(false);
+                        trace!("dir_exists {path}: {exists}");
+                        out.insert(path, exists);
+                    }
+                    arg = Some(VdirReply::DirExists(out));
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsDirRead(paths)) => {
+                    let mut entries = BTreeMap::new();
+                    for path in paths {
+                        trace!("read_dir {path}");
+                        let mut names = BTreeSet::new();
+                        match fs::read_dir(path.as_str()).await {
+                            Ok(mut iter) => {
+                                while let Some(entry) = iter.next_entry().await? {
+                                    names.insert(normalize_path(entry.path()));
+                                }
+                            }
+                            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                            Err(err) => return Err(err.into()),
+                        }
+                        entries.insert(path, names);
+                    }
+                    arg = Some(VdirReply::DirRead(entries));
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsFileRead(paths)) => {
+                    let mut reads = JoinSet::new();
+                    let mut contents = BTreeMap::new();
+
+                    for path in paths {
+                        if reads.len() >= Self::MAX_CONCURRENT_READS {
+                            if let Some(read) = reads.join_next().await {
+                                let (path, bytes) = read.map_err(io::Error::other)?;
+                                contents.insert(path, bytes?);
+                            }
+                        }
+
+                        trace!("read_file {path}");
+                        reads.spawn(async move {
+                            let bytes = fs::read(path.as_str()).await;
+                            (path, bytes)
+                        });
+                    }
+
+                    while let Some(read) = reads.join_next().await {
+                        let (path, bytes) = read.map_err(io::Error::other)?;
+                        contents.insert(path, bytes?);
+                    }
+                    arg = Some(VdirReply::FileRead(contents));
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsFileCreate(files)) => {
+                    for (path, bytes) in files {
+                        trace!("write {path} ({} bytes)", bytes.len());
+                        if let Some(parent) = std::path::Path::new(path.as_str()).parent() {
+                            fs::create_dir_all(parent).await?;
+                        }
+                        fs::write(path.as_str(), &bytes).await?;
+                    }
+                    arg = Some(VdirReply::FileCreate);
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsDirCreate(paths)) => {
+                    for path in paths {
+                        trace!("create_dir_all {path}");
+                        fs::create_dir_all(path.as_str()).await?;
+                    }
+                    arg = Some(VdirReply::DirCreate);
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsDirRemove(paths)) => {
+                    for path in paths {
+                        trace!("remove_dir_all {path}");
+                        fs::remove_dir_all(path.as_str()).await?;
+                    }
+                    arg = Some(VdirReply::DirRemove);
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsFileRemove(paths)) => {
+                    for path in paths {
+                        trace!("remove_file {path}");
+                        fs::remove_file(path.as_str()).await?;
+                    }
+                    arg = Some(VdirReply::FileRemove);
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsRename(pairs)) => {
+                    for (from, to) in pairs {
+                        trace!("rename {from} -> {to}");
+                        fs::rename(from.as_str(), to.as_str()).await?;
+                    }
+                    arg = Some(VdirReply::Rename);
+                }
+                VdirCoroutineState::Yielded(VdirYield::WantsCopy(pairs)) => {
+                    for (from, to) in pairs {
+                        trace!("copy {from} -> {to}");
+                        fs::copy(from.as_str(), to.as_str()).await?;
+                    }
+                    arg = Some(VdirReply::Copy);
+                }
+            }
+        }
+    }
+
+    /// Lists every calendar under the configured root, sorted by name.
+    ///
+    /// Each CTag is derived from the names, modification times and
+    /// sizes of the calendar's item files, none of which is read.
+    pub async fn list_calendars(&self) -> Result<Vec<Calendar>, VdirClientError> {
+        let mut calendars = self
+            .run(VdirCalendarList::new(self.inner.root().clone()))
+            .await?;
+
+        for calendar in &mut calendars {
+            let path = calendar_path(self.inner.root(), &calendar.id);
+            calendar.ctag = Some(collection_tag(&stat_items(&path).await?));
+        }
+
+        Ok(calendars)
+    }
+
+    /// Creates calendar `id` (display name `name`) under the root.
+    pub async fn create_calendar(
+        &self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<(), VdirClientError> {
+        self.validate_calendar(id)?;
+        self.run(VdirCalendarCreate::new(
+            self.inner.root(),
+            id,
+            name,
+            description,
+            color,
+        )?)
+        .await
+    }
+
+    /// Applies `patch` to calendar `id`, merging it against the current
+    /// calendar metadata. The calendars are listed without their CTags,
+    /// so no item file is stat'ed.
+    pub async fn update_calendar(
+        &self,
+        id: &str,
+        patch: CalendarDiff,
+    ) -> Result<(), VdirClientError> {
+        self.validate_calendar(id)?;
+
+        let current = self
+            .run(VdirCalendarList::new(self.inner.root().clone()))
+            .await?
+            .into_iter()
+            .find(|c| c.id == id)
+            .ok_or_else(|| VdirClientError::CalendarNotFound(id.to_string()))?;
+
+        let name = match patch.name {
+            Some(name) => name,
+            None => current.name,
+        };
+        let description = match patch.description {
+            Some(description) => description,
+            None => current.description,
+        };
+        let color = match patch.color {
+            Some(color) => color,
+            None => current.color,
+        };
+
+        self.run(VdirCalendarUpdate::new(
+            self.inner.root(),
+            id,
+            name,
+            description,
+            color,
+        ))
+        .await
+    }
+
+    /// Recursively removes calendar `id`.
+    pub async fn delete_calendar(&self, id: &str) -> Result<(), VdirClientError> {
+        self.validate_calendar(id)?;
+        self.run(VdirCalendarDelete::new(self.inner.root(), id))
+            .await
+    }
+
+    /// Lists items inside `calendar_id`, applying 1-indexed pagination.
+    ///
+    /// When `time_range` is set, the fetched items are filtered
+    /// client-side following RFC 4791 section 9.9 for every component
+    /// kind (the filesystem backend has no server-side query). Needs
+    /// the `parser` feature; without it the range is ignored. When
+    /// `kinds` is non-empty, only items of those kinds are kept. A
+    /// [`RecurrenceExpansion`](crate::item::RecurrenceExpansion) on the
+    /// range is applied client-side too, before pagination.
+    pub async fn list_items(
+        &self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<Vec<CalendarItem>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+
+        #[cfg(feature = "parser")]
+        if let Some(index) = self.refresh_index(calendar_id, &path).await? {
+            let ids = index.select(calendar_id, time_range, kinds);
+            let items = self
+                .run(VdirCalendarItemMultiget::new(path, calendar_id, &ids))
+                .await?;
+            return Ok(select_items(
+                items, page, page_size, time_range, kinds, filter,
+            ));
+        }
+
+        self.run(VdirCalendarItemList::new(
+            path,
+            calendar_id,
+            page,
+            page_size,
+            time_range,
+            kinds,
+            filter,
+        ))
+        .await
+    }
+
+    /// Fetches `item_id` from `calendar_id`.
+    pub async fn get_item(
+        &self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<CalendarItem, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        self.validate_item(item_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+        self.run(VdirCalendarItemGet::new(path, calendar_id, item_id))
+            .await
+    }
+
+    /// Fetches `item_ids` from `calendar_id` with one batched file
+    /// read, in the requested order. Ids without a file are left out.
+    pub async fn get_items(
+        &self,
+        calendar_id: &str,
+        item_ids: &[&str],
+    ) -> Result<Vec<CalendarItem>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        for item_id in item_ids {
+            self.validate_item(item_id)?;
+        }
+
+        if item_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let path = calendar_path(self.inner.root(), calendar_id);
+        self.run(VdirCalendarItemMultiget::new(path, calendar_id, item_ids))
+            .await
+    }
+
+    /// Finds the item of `calendar_id` carrying the iCalendar `uid`,
+    /// reading and parsing every file of the calendar, or only the
+    /// files the index names when enabled. Returns the one with the
+    /// smallest id when several match.
+    #[cfg(feature = "parser")]
+    pub async fn find_item_by_uid(
+        &self,
+        calendar_id: &str,
+        uid: &str,
+    ) -> Result<Option<CalendarItem>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+
+        if let Some(index) = self.refresh_index(calendar_id, &path).await? {
+            let ids = index.find_uid(calendar_id, uid);
+            let items = self
+                .run(VdirCalendarItemMultiget::new(path, calendar_id, &ids))
+                .await?;
+            return Ok(items
+                .into_iter()
+                .find(|item| item.uid().as_deref() == Some(uid)));
+        }
+
+        self.run(VdirCalendarItemFind::new(path, calendar_id, uid))
+            .await
+    }
+
+    /// Searches `query` in the SUMMARY, DESCRIPTION, LOCATION, ATTENDEE
+    /// and CATEGORIES of the items of `calendar_id`, reading and
+    /// parsing every file of the calendar. Returns the hits sorted by
+    /// id.
+    #[cfg(feature = "parser")]
+    pub async fn search_items(
+        &self,
+        calendar_id: &str,
+        query: &str,
+    ) -> Result<Vec<SearchHit>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+        self.run(VdirCalendarItemSearch::new(path, calendar_id, query))
+            .await
+    }
+
+    /// Lists the id, synthetic ETag, size and modification time of
+    /// every item of `calendar_id`, sorted by id, without returning
+    /// their contents.
+    ///
+    /// The files are still read, since the synthetic ETag hashes their
+    /// contents; the modification time comes from the file metadata.
+    pub async fn list_item_refs(
+        &self,
+        calendar_id: &str,
+    ) -> Result<Vec<CalendarItemRef>, VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+        let mut refs = self
+            .run(VdirCalendarItemRefList::new(path.clone(), calendar_id))
+            .await?;
+
+        for item in &mut refs {
+            let file = path.join(&format!("{}.ics", item.id));
+            item.last_modified = fs::metadata(file.as_str())
+                .await
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|elapsed| elapsed.as_secs());
+        }
+
+        Ok(refs)
+    }
+
+    /// Appends a new iCalendar item to `calendar_id`. Returns its
+    /// assigned id along with the synthetic ETag of the contents.
+    ///
+    /// With the `parser` feature the id is the item's UID when it makes
+    /// a safe file stem. Fails with [`VdirClientError::AlreadyExists`]
+    /// rather than overwrite an existing file.
+    pub async fn create_item(
+        &self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, VdirClientError> {
+        if contents.is_empty() {
+            return Err(VdirClientError::EmptyItemBody);
+        }
+        self.validate_calendar(calendar_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+        self.run(VdirCalendarItemCreate::new(path, contents)?).await
+    }
+
+    /// Appends a new iCalendar item to `calendar_id` like
+    /// [`create_item`](Self::create_item), first scanning the calendar
+    /// for an item with the same UID: `on_conflict` then either fails
+    /// with [`VdirClientError::AlreadyExists`], replaces that item
+    /// (gated on its synthetic ETag) or stores the new one under a
+    /// suffixed UID.
+    #[cfg(feature = "parser")]
+    pub async fn create_item_with(
+        &self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+        on_conflict: UidConflict,
+    ) -> Result<CalendarItemReceipt, VdirClientError> {
+        let Some(uid) = item_uid(&contents) else {
+            return self.create_item(calendar_id, contents).await;
+        };
+        let items = self
+            .list_items(calendar_id, None, None, None, &[], None)
+            .await?;
+
+        match check_uid(items, &uid, contents, on_conflict) {
+            UidCheck::Create(contents) => self.create_item(calendar_id, contents).await,
+            UidCheck::Exists(id) => Err(VdirClientError::AlreadyExists(id)),
+            UidCheck::Replace(existing, contents) => {
+                let etag = existing.etag.as_deref();
+                self.update_item(calendar_id, &existing.id, contents, etag)
+                    .await
+            }
+        }
+    }
+
+    /// Overwrites `item_id` inside `calendar_id`, returning the
+    /// synthetic ETag of the new contents.
+    ///
+    /// When `if_match` is set, the write only happens if the file still
+    /// carries that synthetic ETag; otherwise it fails with
+    /// [`VdirCalendarItemUpdateError::PreconditionFailed`].
+    pub async fn update_item(
+        &self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, VdirClientError> {
+        if contents.is_empty() {
+            return Err(VdirClientError::EmptyItemBody);
+        }
+        self.validate_calendar(calendar_id)?;
+        self.validate_item(item_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+        self.run(VdirCalendarItemUpdate::new(
+            path, item_id, contents, if_match,
+        )?)
+        .await
+    }
+
+    /// Permanently deletes `item_id` from `calendar_id`.
+    ///
+    /// When `if_match` is set, the file is only removed if it still
+    /// carries that synthetic ETag; otherwise it fails with
+    /// [`VdirCalendarItemDeleteError::PreconditionFailed`].
+    pub async fn delete_item(
+        &self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), VdirClientError> {
+        self.validate_calendar(calendar_id)?;
+        self.validate_item(item_id)?;
+        let path = calendar_path(self.inner.root(), calendar_id);
+        self.run(VdirCalendarItemDelete::new(path, item_id, if_match))
+            .await
+    }
+
+    /// Rejects an empty calendar id (after trimming surrounding
+    /// slashes).
+    fn validate_calendar(&self, id: &str) -> Result<(), VdirClientError> {
+        if id.trim_matches('/').is_empty() {
+            return Err(VdirClientError::InvalidCalendar(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Refreshes the entries of `calendar_id`, stored at `path`, in
+    /// the index when enabled. A missing calendar directory is reported
+    /// as [`VdirClientError::CalendarNotFound`].
+    #[cfg(feature = "parser")]
+    async fn refresh_index(
+        &self,
+        calendar_id: &str,
+        path: &VdirPath,
+    ) -> Result<Option<VdirIndex>, VdirClientError> {
+        let Some(location) = &self.index else {
+            return Ok(None);
+        };
+
+        let stats = match stat_items(path).await {
+            Ok(stats) => Some(stats),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+
+        let refresh = VdirIndexRefresh::new(location.clone(), calendar_id, path.clone(), stats);
+        self.run(refresh).await.map(Some)
+    }
+
+    /// Rejects an empty item id.
+    fn validate_item(&self, id: &str) -> Result<(), VdirClientError> {
+        if id.is_empty() {
+            return Err(VdirClientError::InvalidItemId(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Gathers the [`VdirItemStat`] of every item file (`{id}.ics`) of the
+/// calendar directory `path`, by item id.
+async fn stat_items(path: &VdirPath) -> io::Result<BTreeMap<String, VdirItemStat>> {
+    let mut stats = BTreeMap::new();
+    let mut entries = fs::read_dir(path.as_str()).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let Some(id) = name.to_str().and_then(item_id_from_file_name) else {
+            continue;
+        };
+
+        let metadata = fs::metadata(entry.path()).await?;
+        if metadata.is_file() {
+            stats.insert(id.to_string(), item_stat(&metadata)?);
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Normalizes a host [`std::path::PathBuf`] into a `/`-separated
+/// [`VdirPath`].
+fn normalize_path(path: std::path::PathBuf) -> VdirPath {
+    let s = path.to_string_lossy().into_owned();
+    #[cfg(windows)]
+    let s = s.replace('\\', "/");
+    VdirPath::new(s)
+}
@@ -2,7 +2,12 @@
 //! [`Calendar`] / [`CalendarItem`] types, plus small list
 //! helpers shared by the vdir coroutines.
 
-use alloc::{string::ToString, vec::Vec};
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 
 use io_vdir::{
     collection::Collection,
@@ -10,7 +15,10 @@ use io_vdir::{
     path::VdirPath,
 };
 
-use crate::{calendar::Calendar, item::CalendarItem};
+use crate::{
+    calendar::Calendar,
+    item::{CalendarItem, content_tag, digest},
+};
 
 #[cfg(feature = "client")]
 impl From<crate::vdir::client::VdirClient> for crate::client::CalendarClientStd {
@@ -19,6 +27,13 @@ impl From<crate::vdir::client::VdirClient> for crate::client::CalendarClientStd
     }
 }
 
+#[cfg(feature = "tokio")]
+impl From<crate::vdir::client_tokio::VdirClientTokio> for crate::client_tokio::CalendarClientTokio {
+    fn from(client: crate::vdir::client_tokio::VdirClientTokio) -> Self {
+        Self::Vdir(client)
+    }
+}
+
 impl From<Collection> for Calendar {
     fn from(collection: Collection) -> Self {
         let id = collection.id().to_string();
@@ -45,18 +60,49 @@ pub(crate) fn calendar_path(root: &VdirPath, calendar_id: &str) -> VdirPath {
 
 /// Maps a vdir [`VdirItem`] to a shared [`CalendarItem`], pinning it to
 /// `calendar_id`. Returns `None` when the item path has no usable file
-/// stem; ETag is `None` because vdir has no entity-tag concept.
+/// stem. The ETag is a hash of the file contents.
 pub(crate) fn item_from(item: VdirItem, calendar_id: &str) -> Option<CalendarItem> {
     let id = item.id()?.to_string();
 
     Some(CalendarItem {
         id,
         calendar_id: calendar_id.to_string(),
-        etag: None,
+        etag: Some(content_tag(&item.contents)),
         contents: item.contents,
     })
 }
 
+/// Modification time and size of a vdir item file. io-vdir exposes no
+/// file metadata, so the runners gather them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VdirItemStat {
+    /// Modification time, in nanoseconds since the Unix epoch.
+    pub modified: u128,
+
+    /// Size, in bytes.
+    pub size: u64,
+}
+
+/// Returns the id of the calendar item stored in the file named
+/// `name`: its stem when the name ends with exactly `.ics`, the
+/// extension the item coroutines write.
+pub(crate) fn item_id_from_file_name(name: &str) -> Option<&str> {
+    name.strip_suffix(".ics").filter(|id| !id.is_empty())
+}
+
+/// Synthetic CTag of a vdir collection: the hash of the id,
+/// modification time and size of every calendar item file, in id
+/// order. Any item added, removed or rewritten gives a new tag, and no
+/// file has to be read.
+pub(crate) fn collection_tag(stats: &BTreeMap<String, VdirItemStat>) -> String {
+    let lines: Vec<String> = stats
+        .iter()
+        .map(|(id, stat)| format!("{id}/{}/{}\n", stat.modified, stat.size))
+        .collect();
+
+    digest(lines.iter().map(|line| line.as_bytes()))
+}
+
 /// Returns `true` when the vdir item kind is an iCalendar object
 /// (`.ics`); used to filter vCard items out of the shared calendar
 /// API.
@@ -64,24 +110,45 @@ pub(crate) fn is_calendar_item(kind: VdirItemKind) -> bool {
     matches!(kind, VdirItemKind::Ical)
 }
 
-/// 1-indexed pagination on an in-memory list. `page_size = None`
-/// returns the full slice; `page_size = 0` or a page past the end
-/// returns an empty vector.
-pub(crate) fn paginate<T>(items: Vec<T>, page: Option<u32>, page_size: Option<u32>) -> Vec<T> {
-    let Some(size) = page_size else {
-        return items;
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if size == 0 {
-        return Vec::new();
+    fn stats(entries: &[(&str, u128, u64)]) -> BTreeMap<String, VdirItemStat> {
+        entries
+            .iter()
+            .map(|(id, modified, size)| {
+                let stat = VdirItemStat {
+                    modified: *modified,
+                    size: *size,
+                };
+                (id.to_string(), stat)
+            })
+            .collect()
     }
 
-    let page = page.unwrap_or(1).max(1);
-    let skip = ((page - 1) as usize).saturating_mul(size as usize);
+    #[test]
+    fn collection_tag_follows_item_files() {
+        let tag = collection_tag(&stats(&[("a", 1, 10), ("b", 2, 20)]));
+        assert_eq!(tag, collection_tag(&stats(&[("b", 2, 20), ("a", 1, 10)])));
 
-    if skip >= items.len() {
-        return Vec::new();
+        for changed in [
+            stats(&[("a", 1, 10)]),
+            stats(&[("a", 1, 10), ("b", 3, 20)]),
+            stats(&[("a", 1, 10), ("b", 2, 21)]),
+            stats(&[("a", 1, 10), ("c", 2, 20)]),
+        ] {
+            assert_ne!(tag, collection_tag(&changed));
+        }
     }
 
-    items.into_iter().skip(skip).take(size as usize).collect()
+    #[test]
+    fn item_id_needs_exact_ics_extension() {
+        assert_eq!(item_id_from_file_name("event-1.ics"), Some("event-1"));
+        assert_eq!(item_id_from_file_name("a.b.ics"), Some("a.b"));
+        assert_eq!(item_id_from_file_name("event-1.ICS"), None);
+        assert_eq!(item_id_from_file_name("event-1.ics.tmp"), None);
+        assert_eq!(item_id_from_file_name(".ics"), None);
+        assert_eq!(item_id_from_file_name("event-1.vcf"), None);
+    }
 }
@@ -0,0 +1,706 @@
+//! Persistent on-disk index of vdir calendars, used by
+//! [`VdirClient`](crate::vdir::client::VdirClient) and its tokio
+//! counterpart once enabled with
+//! [`with_index`](crate::vdir::client::VdirClient::with_index).
+//!
+//! The index records, per `.ics` file, its modification time and size
+//! plus what the time-range, kind and UID queries need: the UID, the
+//! item kind, the UTC `DTSTART` / `DTEND` of the main component and
+//! bounds covering every recurrence. Each query first refreshes the
+//! entries of its calendar: only files whose modification time or size
+//! changed are read and parsed again, entries of removed files are
+//! dropped. The query is then answered from the index, so files that
+//! cannot match are neither read nor parsed.
+//!
+//! Time bounds are conservative: the files they select still go
+//! through the exact RFC 4791 section 9.9 check before being returned.
+//!
+//! The index is a versioned, tab-separated text file, written to a
+//! uniquely named temporary file then renamed into place. It can live
+//! alongside the vdir (see [`VdirIndex::FILE_NAME`]) or in a cache
+//! directory; an unreadable or outdated file is rebuilt from scratch.
+//!
+//! Loading, refreshing and saving the index is the I/O-free
+//! [`VdirIndexRefresh`] coroutine. io-vdir exposes no file metadata, so
+//! the runner hands it the [`VdirItemStat`] of the calendar's item
+//! files.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::mem;
+
+use io_vdir::{coroutine::*, path::VdirPath};
+use log::{debug, trace};
+use thiserror::Error;
+
+use crate::{
+    item::{CalendarItem, CalendarItemKind, TimeRange, item_span, item_uid, span_overlaps},
+    vdir::convert::VdirItemStat,
+};
+
+/// First line of an index file, bumped whenever the format changes.
+const HEADER: &str = "io-calendar vdir index 1";
+
+/// Persistent index of the items of one or more vdir calendars.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VdirIndex {
+    /// Entries by calendar id, then by item id.
+    calendars: BTreeMap<String, BTreeMap<String, VdirIndexEntry>>,
+}
+
+/// What the index knows about one `.ics` file.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VdirIndexEntry {
+    /// Modification time of the file, in nanoseconds since the Unix
+    /// epoch.
+    pub modified: u128,
+
+    /// Size of the file, in bytes.
+    pub size: u64,
+
+    /// UID of the main component.
+    pub uid: Option<String>,
+
+    /// Kind of the item.
+    pub kind: Option<CalendarItemKind>,
+
+    /// UTC `DTSTART` of the main component, in seconds since the Unix
+    /// epoch.
+    pub start: Option<i64>,
+
+    /// UTC `DTEND` of the main component (or `DUE`, or `DTSTART` plus
+    /// `DURATION`), in seconds since the Unix epoch.
+    pub end: Option<i64>,
+
+    /// Inclusive UTC bounds, in seconds since the Unix epoch, of every
+    /// instant a time range must reach to match the item, recurrences
+    /// included. `i64::MIN` and `i64::MAX` stand for open ends; `None`
+    /// means no time range matches the item.
+    pub bounds: Option<(i64, i64)>,
+}
+
+impl VdirIndexEntry {
+    /// Builds the entry of a file of `size` bytes modified at
+    /// `modified`, parsing its `contents`.
+    pub fn new(modified: u128, size: u64, contents: Vec<u8>) -> Self {
+        let item = CalendarItem {
+            contents,
+            ..Default::default()
+        };
+        let span = item_span(&item);
+
+        Self {
+            modified,
+            size,
+            uid: item_uid(&item.contents),
+            kind: item.kind(),
+            start: span.start,
+            end: span.end,
+            bounds: span.bounds,
+        }
+    }
+
+    /// Whether the item may match `time_range` and `kinds` (an empty
+    /// slice matching every kind). The kind check is exact, the range
+    /// check conservative.
+    pub fn may_match(&self, time_range: Option<&TimeRange>, kinds: &[CalendarItemKind]) -> bool {
+        let kind = kinds.is_empty() || self.kind.is_some_and(|kind| kinds.contains(&kind));
+
+        let range = match time_range {
+            None => true,
+            Some(time_range) => self
+                .bounds
+                .is_some_and(|bounds| span_overlaps(bounds, time_range)),
+        };
+
+        kind && range
+    }
+
+    /// Serializes the entry as the tab-separated fields following the
+    /// calendar and item ids.
+    fn to_fields(&self) -> String {
+        let number = |n: Option<i64>| n.map(|n| n.to_string()).unwrap_or_default();
+        let (lower, upper) = match self.bounds {
+            Some((lower, upper)) => (Some(lower), Some(upper)),
+            None => (None, None),
+        };
+
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.modified,
+            self.size,
+            escape(self.uid.as_deref().unwrap_or_default()),
+            self.kind
+                .map(|kind| kind.component_name())
+                .unwrap_or_default(),
+            number(self.start),
+            number(self.end),
+            number(lower),
+            number(upper),
+        )
+    }
+
+    /// Parses the fields written by [`Self::to_fields`].
+    fn from_fields(fields: &[&str]) -> Option<Self> {
+        let [modified, size, uid, kind, start, end, lower, upper] = fields else {
+            return None;
+        };
+
+        let number = |field: &str| -> Option<Option<i64>> {
+            if field.is_empty() {
+                Some(None)
+            } else {
+                field.parse().ok().map(Some)
+            }
+        };
+
+        let uid = unescape(uid);
+        let kind = match *kind {
+            "" => None,
+            name => Some(CalendarItemKind::from_component_name(name)?),
+        };
+        let bounds = match (number(lower)?, number(upper)?) {
+            (Some(lower), Some(upper)) => Some((lower, upper)),
+            _ => None,
+        };
+
+        Some(Self {
+            modified: modified.parse().ok()?,
+            size: size.parse().ok()?,
+            uid: (!uid.is_empty()).then_some(uid),
+            kind,
+            start: number(start)?,
+            end: number(end)?,
+            bounds,
+        })
+    }
+}
+
+impl VdirIndex {
+    /// File name of an index stored alongside the vdir, at its root.
+    pub const FILE_NAME: &str = ".io-calendar-index";
+
+    /// Builds an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entries of `calendar_id`, by item id.
+    pub fn entries(&self, calendar_id: &str) -> impl Iterator<Item = (&str, &VdirIndexEntry)> {
+        self.calendars
+            .get(calendar_id)
+            .into_iter()
+            .flatten()
+            .map(|(id, entry)| (id.as_str(), entry))
+    }
+
+    /// Ids, sorted, of the items of `calendar_id` that may match
+    /// `time_range` and `kinds`, see [`VdirIndexEntry::may_match`].
+    pub fn select(
+        &self,
+        calendar_id: &str,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+    ) -> Vec<&str> {
+        self.entries(calendar_id)
+            .filter(|(_, entry)| entry.may_match(time_range, kinds))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Ids, sorted, of the items of `calendar_id` carrying `uid`.
+    pub fn find_uid(&self, calendar_id: &str, uid: &str) -> Vec<&str> {
+        self.entries(calendar_id)
+            .filter(|(_, entry)| entry.uid.as_deref() == Some(uid))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = String::from(HEADER);
+        out.push('\n');
+
+        for (calendar_id, calendar) in &self.calendars {
+            for (id, entry) in calendar {
+                let calendar_id = escape(calendar_id);
+                let id = escape(id);
+                let fields = entry.to_fields();
+                out.push_str(&format!("{calendar_id}\t{id}\t{fields}\n"));
+            }
+        }
+
+        out.into_bytes()
+    }
+
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let text = core::str::from_utf8(bytes).ok()?;
+        let mut lines = text.lines();
+
+        if lines.next()? != HEADER {
+            return None;
+        }
+
+        let mut index = Self::new();
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [calendar_id, id, fields @ ..] = fields.as_slice() else {
+                return None;
+            };
+            let entry = VdirIndexEntry::from_fields(fields)?;
+
+            index
+                .calendars
+                .entry(unescape(calendar_id))
+                .or_default()
+                .insert(unescape(id), entry);
+        }
+
+        Some(index)
+    }
+}
+
+/// Errors produced by [`VdirIndexRefresh`].
+#[derive(Debug, Error)]
+pub enum VdirIndexRefreshError {
+    #[error("Calendar `{0}` not found")]
+    CalendarNotFound(String),
+    #[error("Unexpected reply to a vdir index refresh")]
+    InvalidReply,
+}
+
+enum State {
+    Exists,
+    Load,
+    Read(BTreeMap<VdirPath, (String, VdirItemStat)>),
+    Random,
+    Write(VdirPath),
+    Rename,
+}
+
+/// I/O-free coroutine loading the [`VdirIndex`] stored at a location,
+/// bringing the entries of one calendar up to date and saving it back
+/// when they changed.
+///
+/// Files that are new or whose modification time or size changed are
+/// read and parsed, entries of removed files are dropped. The index is
+/// saved through a temporary file named after the runner's randomness,
+/// then renamed into place, so concurrent writers never share a
+/// temporary file and readers never see a partial index.
+///
+/// On completion returns the refreshed index. When the calendar
+/// directory is missing, the calendar is dropped from the index and the
+/// coroutine fails with [`VdirIndexRefreshError::CalendarNotFound`].
+pub struct VdirIndexRefresh {
+    location: VdirPath,
+    calendar_id: String,
+    path: VdirPath,
+    stats: Option<BTreeMap<String, VdirItemStat>>,
+    index: VdirIndex,
+    changed: bool,
+    state: State,
+}
+
+impl VdirIndexRefresh {
+    /// Builds the coroutine refreshing, in the index stored at
+    /// `location`, the entries of calendar `calendar_id` located at
+    /// `path`. `stats` holds the [`VdirItemStat`] of every item file of
+    /// the calendar by item id, or `None` when its directory does not
+    /// exist.
+    pub fn new(
+        location: impl Into<VdirPath>,
+        calendar_id: &str,
+        path: impl Into<VdirPath>,
+        stats: Option<BTreeMap<String, VdirItemStat>>,
+    ) -> Self {
+        trace!("prepare vdir index refresh of {calendar_id}");
+        Self {
+            location: location.into(),
+            calendar_id: calendar_id.to_string(),
+            path: path.into(),
+            stats,
+            index: VdirIndex::new(),
+            changed: false,
+            state: State::Exists,
+        }
+    }
+
+    /// Compares the loaded entries of the calendar against the item
+    /// files, then reads the new and changed ones.
+    fn refresh(
+        &mut self,
+    ) -> VdirCoroutineState<VdirYield, Result<VdirIndex, VdirIndexRefreshError>> {
+        let previous = self
+            .index
+            .calendars
+            .remove(&self.calendar_id)
+            .unwrap_or_default();
+
+        let Some(stats) = self.stats.take() else {
+            self.changed = !previous.is_empty();
+            return self.save();
+        };
+
+        let mut calendar = BTreeMap::new();
+        let mut files = BTreeMap::new();
+
+        for (id, stat) in stats {
+            match previous.get(&id) {
+                Some(entry) if entry.modified == stat.modified && entry.size == stat.size => {
+                    calendar.insert(id, entry.clone());
+                }
+                _ => {
+                    trace!("index vdir item {}/{id}", self.calendar_id);
+                    let file = self.path.join(&format!("{id}.ics"));
+                    files.insert(file, (id, stat));
+                }
+            }
+        }
+
+        self.changed = !files.is_empty() || calendar.len() != previous.len();
+        self.index
+            .calendars
+            .insert(self.calendar_id.clone(), calendar);
+
+        if files.is_empty() {
+            return self.save();
+        }
+
+        let paths = files.keys().cloned().collect();
+        self.state = State::Read(files);
+        VdirCoroutineState::Yielded(VdirYield::WantsFileRead(paths))
+    }
+
+    /// Saves the index when it changed, otherwise completes.
+    fn save(&mut self) -> VdirCoroutineState<VdirYield, Result<VdirIndex, VdirIndexRefreshError>> {
+        if !self.changed {
+            return self.complete();
+        }
+
+        self.state = State::Random;
+        VdirCoroutineState::Yielded(VdirYield::WantsRandom { len: 8 })
+    }
+
+    fn complete(
+        &mut self,
+    ) -> VdirCoroutineState<VdirYield, Result<VdirIndex, VdirIndexRefreshError>> {
+        if self.index.calendars.contains_key(&self.calendar_id) {
+            VdirCoroutineState::Complete(Ok(mem::take(&mut self.index)))
+        } else {
+            let id = self.calendar_id.clone();
+            VdirCoroutineState::Complete(Err(VdirIndexRefreshError::CalendarNotFound(id)))
+        }
+    }
+}
+
+impl VdirCoroutine for VdirIndexRefresh {
+    type Yield = VdirYield;
+    type Return = Result<VdirIndex, VdirIndexRefreshError>;
+
+    fn resume(&mut self, arg: Option<VdirReply>) -> VdirCoroutineState<Self::Yield, Self::Return> {
+        match (&mut self.state, arg) {
+            (State::Exists, None) => {
+                let paths = BTreeSet::from([self.location.clone()]);
+                VdirCoroutineState::Yielded(VdirYield::WantsFileExists(paths))
+            }
+            (State::Exists, Some(VdirReply::FileExists(exists))) => {
+                if !exists.get(&self.location).copied().unwrap_or(false) {
+                    return self.refresh();
+                }
+
+                self.state = State::Load;
+                let paths = BTreeSet::from([self.location.clone()]);
+                VdirCoroutineState::Yielded(VdirYield::WantsFileRead(paths))
+            }
+            (State::Load, Some(VdirReply::FileRead(mut contents))) => {
+                let bytes = contents.remove(&self.location).unwrap_or_default();
+                self.index = VdirIndex::parse(&bytes).unwrap_or_else(|| {
+                    debug!("discard unreadable vdir index {}", self.location);
+                    VdirIndex::new()
+                });
+                self.refresh()
+            }
+            (State::Read(files), Some(VdirReply::FileRead(mut contents))) => {
+                let calendar = self
+                    .index
+                    .calendars
+                    .entry(self.calendar_id.clone())
+                    .or_default();
+
+                // NOTE: a file removed since it was listed is left out,
+                // as if it had been removed before.
+                for (file, (id, stat)) in mem::take(files) {
+                    if let Some(contents) = contents.remove(&file) {
+                        let entry = VdirIndexEntry::new(stat.modified, stat.size, contents);
+                        calendar.insert(id, entry);
+                    }
+                }
+
+                self.save()
+            }
+            (State::Random, Some(VdirReply::Random(bytes))) => {
+                let mut tmp = format!("{}.", self.location);
+                for byte in bytes {
+                    tmp.push_str(&format!("{byte:02x}"));
+                }
+                tmp.push_str(".tmp");
+
+                let tmp = VdirPath::new(tmp);
+                let files = [(tmp.clone(), self.index.to_bytes())];
+                self.state = State::Write(tmp);
+                VdirCoroutineState::Yielded(VdirYield::WantsFileCreate(files.into_iter().collect()))
+            }
+            (State::Write(tmp), Some(VdirReply::FileCreate)) => {
+                let pairs = [(tmp.clone(), self.location.clone())];
+                self.state = State::Rename;
+                VdirCoroutineState::Yielded(VdirYield::WantsRename(pairs.into_iter().collect()))
+            }
+            (State::Rename, Some(VdirReply::Rename)) => self.complete(),
+            _ => VdirCoroutineState::Complete(Err(VdirIndexRefreshError::InvalidReply)),
+        }
+    }
+}
+
+/// Escapes backslashes, tabs and line breaks of an index field.
+fn escape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+
+    for c in field.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Reverts [`escape`].
+fn unescape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    /// In-memory filesystem answering the requests of a
+    /// [`VdirIndexRefresh`].
+    #[derive(Default)]
+    struct Files {
+        files: BTreeMap<VdirPath, Vec<u8>>,
+        reads: Vec<VdirPath>,
+        renames: Vec<(VdirPath, VdirPath)>,
+    }
+
+    impl Files {
+        fn run(
+            &mut self,
+            mut refresh: VdirIndexRefresh,
+        ) -> Result<VdirIndex, VdirIndexRefreshError> {
+            let mut arg = None;
+
+            loop {
+                let reply = match refresh.resume(arg.take()) {
+                    VdirCoroutineState::Complete(out) => return out,
+                    VdirCoroutineState::Yielded(VdirYield::WantsFileExists(paths)) => {
+                        let exists = paths
+                            .into_iter()
+                            .map(|path| {
+                                let exists = self.files.contains_key(&path);
+                                (path, exists)
+                            })
+                            .collect();
+                        VdirReply::FileExists(exists)
+                    }
+                    VdirCoroutineState::Yielded(VdirYield::WantsFileRead(paths)) => {
+                        self.reads.extend(paths.iter().cloned());
+                        let contents = paths
+                            .into_iter()
+                            .filter_map(|path| {
+                                let bytes = self.files.get(&path)?.clone();
+                                Some((path, bytes))
+                            })
+                            .collect();
+                        VdirReply::FileRead(contents)
+                    }
+                    VdirCoroutineState::Yielded(VdirYield::WantsRandom { len }) => {
+                        VdirReply::Random(vec![0xab; len])
+                    }
+                    VdirCoroutineState::Yielded(VdirYield::WantsFileCreate(files)) => {
+                        for (path, bytes) in files {
+                            self.files.insert(path, bytes);
+                        }
+                        VdirReply::FileCreate
+                    }
+                    VdirCoroutineState::Yielded(VdirYield::WantsRename(pairs)) => {
+                        for (from, to) in pairs {
+                            let bytes = self.files.remove(&from).unwrap();
+                            self.files.insert(to.clone(), bytes);
+                            self.renames.push((from, to));
+                        }
+                        VdirReply::Rename
+                    }
+                    _ => panic!("unexpected vdir request"),
+                };
+
+                arg = Some(reply);
+            }
+        }
+
+        /// Stores item `id` in the calendar and returns its stat.
+        fn store(&mut self, id: &str, uid: &str, modified: u128) -> (String, VdirItemStat) {
+            let contents = format!(
+                "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:{uid}\r\nDTSTART:20250101T100000Z\r\n\
+                 END:VEVENT\r\nEND:VCALENDAR\r\n"
+            )
+            .into_bytes();
+            let stat = VdirItemStat {
+                modified,
+                size: contents.len() as u64,
+            };
+
+            self.files
+                .insert(dir().join(&format!("{id}.ics")), contents);
+            (id.to_string(), stat)
+        }
+
+        fn refresh(
+            &mut self,
+            stats: Option<&[&(String, VdirItemStat)]>,
+        ) -> Result<VdirIndex, VdirIndexRefreshError> {
+            self.reads.clear();
+            let stats = stats.map(|stats| stats.iter().map(|stat| (*stat).clone()).collect());
+            self.run(VdirIndexRefresh::new(location(), "cal", dir(), stats))
+        }
+    }
+
+    fn location() -> VdirPath {
+        VdirPath::new(String::from("/vdir/.io-calendar-index"))
+    }
+
+    fn dir() -> VdirPath {
+        VdirPath::new(String::from("/vdir/cal"))
+    }
+
+    fn ids(index: &VdirIndex) -> Vec<&str> {
+        index.entries("cal").map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn refreshes_only_stale_entries() {
+        let mut files = Files::default();
+        let a = files.store("a", "uid-a", 1);
+        let b = files.store("b", "uid-b", 1);
+
+        let index = files.refresh(Some(&[&a, &b])).unwrap();
+        assert_eq!(ids(&index), ["a", "b"]);
+        assert_eq!(files.reads.len(), 2);
+        assert_eq!(files.renames.len(), 1);
+
+        let b = files.store("b", "uid-b2", 2);
+        let index = files.refresh(Some(&[&a, &b])).unwrap();
+        assert_eq!(files.reads, [location(), dir().join("b.ics")]);
+        assert_eq!(index.find_uid("cal", "uid-b2"), ["b"]);
+        assert!(index.find_uid("cal", "uid-b").is_empty());
+        assert_eq!(files.renames.len(), 2);
+
+        let index = files.refresh(Some(&[&a, &b])).unwrap();
+        assert_eq!(files.reads, [location()]);
+        assert_eq!(ids(&index), ["a", "b"]);
+        assert_eq!(files.renames.len(), 2, "unchanged index is not saved");
+    }
+
+    #[test]
+    fn saves_through_unique_temporary_file() {
+        let mut files = Files::default();
+        let a = files.store("a", "uid-a", 1);
+        files.refresh(Some(&[&a])).unwrap();
+
+        let tmp = VdirPath::new(format!("{}.abababababababab.tmp", location()));
+        assert_eq!(files.renames, [(tmp.clone(), location())]);
+        assert!(!files.files.contains_key(&tmp));
+        assert!(files.files.contains_key(&location()));
+    }
+
+    #[test]
+    fn rescans_corrupt_or_outdated_index() {
+        let mut files = Files::default();
+        let a = files.store("a", "uid-a", 1);
+        files.refresh(Some(&[&a])).unwrap();
+
+        let saved = String::from_utf8(files.files[&location()].clone()).unwrap();
+        let outdated = saved.replacen(HEADER, "io-calendar vdir index 0", 1);
+        let malformed = format!("{HEADER}\ncal\ta\tnot-a-number\n");
+
+        for bytes in [
+            outdated.into_bytes(),
+            malformed.into_bytes(),
+            b"\xff\xfe".to_vec(),
+            Vec::new(),
+        ] {
+            files.files.insert(location(), bytes);
+            let index = files.refresh(Some(&[&a])).unwrap();
+            assert_eq!(files.reads, [location(), dir().join("a.ics")]);
+            assert_eq!(index.find_uid("cal", "uid-a"), ["a"]);
+        }
+    }
+
+    #[test]
+    fn detects_renamed_and_deleted_files() {
+        let mut files = Files::default();
+        let a = files.store("a", "uid-a", 1);
+        let b = files.store("b", "uid-b", 1);
+        files.refresh(Some(&[&a, &b])).unwrap();
+
+        let c = files.store("c", "uid-b", 1);
+        let index = files.refresh(Some(&[&a, &c])).unwrap();
+        assert_eq!(files.reads, [location(), dir().join("c.ics")]);
+        assert_eq!(ids(&index), ["a", "c"]);
+        assert_eq!(index.find_uid("cal", "uid-b"), ["c"]);
+
+        let index = files.refresh(Some(&[&c])).unwrap();
+        assert_eq!(files.reads, [location()]);
+        assert_eq!(ids(&index), ["c"]);
+        assert_eq!(files.renames.len(), 3);
+    }
+
+    #[test]
+    fn drops_missing_calendar() {
+        let mut files = Files::default();
+        let a = files.store("a", "uid-a", 1);
+        files.refresh(Some(&[&a])).unwrap();
+
+        let err = files.refresh(None).unwrap_err();
+        assert!(matches!(err, VdirIndexRefreshError::CalendarNotFound(id) if id == "cal"));
+
+        let saved = String::from_utf8(files.files[&location()].clone()).unwrap();
+        assert_eq!(saved, format!("{HEADER}\n"));
+    }
+}
@@ -1,6 +1,13 @@
-//! Vdir backend: the std-blocking [`client`] plus the [`convert`]
-//! helpers shared by the vdir collection and item coroutines.
+//! Vdir backend: the std-blocking [`client`], its async `client_tokio`
+//! counterpart (`tokio` feature), plus the [`convert`] helpers shared
+//! by the vdir collection and item coroutines. With the `parser`
+//! feature, both clients can keep a persistent [`index`] of their
+//! calendars.
 
 #[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "tokio")]
+pub mod client_tokio;
 pub mod convert;
+#[cfg(all(feature = "client", feature = "parser"))]
+pub mod index;
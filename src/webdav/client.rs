@@ -17,28 +17,23 @@ use std::io::{Read, Write};
 use io_webdav::{
     client::WebdavClientStd as InnerWebdavClientStd,
     coroutine::{WebdavCoroutine, WebdavCoroutineState, WebdavYield},
-    rfc4791::calendar::Calendar as WireCalendar,
     rfc4918::send::SendError,
 };
 use thiserror::Error;
+use url::Url;
 
+#[cfg(feature = "parser")]
+use crate::item::{SearchHit, UidCheck, UidConflict, check_uid, item_uid};
 use crate::{
-    calendar::{
-        Calendar, CalendarDiff,
-        webdav::{
-            create::WebdavCalendarCreate, delete::WebdavCalendarDelete, list::WebdavCalendarList,
-            update::WebdavCalendarUpdate,
-        },
-    },
+    calendar::{Calendar, CalendarDiff},
     item::{
-        CalendarItem, TimeRange,
-        webdav::{
-            create::WebdavCalendarItemCreate, delete::WebdavCalendarItemDelete,
-            get::WebdavCalendarItemGet, list::WebdavCalendarItemList,
-            update::WebdavCalendarItemUpdate,
-        },
+        CalendarItem, CalendarItemKind, CalendarItemReceipt, CalendarItemRef, CalendarItemSync,
+        ItemFilter, TimeRange, webdav::create::WebdavCalendarItemCreateError,
+    },
+    webdav::{
+        http::{DavCondition, HttpError},
+        session::{WebdavSession, validate_calendar},
     },
-    webdav::convert::{calendar_path, fresh_item_id},
 };
 
 /// Socket read-buffer size for the WebDAV run loop.
@@ -48,6 +43,11 @@ const READ_BUFFER_SIZE: usize = 16 * 1024;
 ///
 /// Flattens the inner client error (discovery plus per-request I/O) and
 /// adds the domain validation failures from the client methods.
+///
+/// Failed [`HttpError::Status`] replies are sorted into typed variants
+/// (carrying the request path): 404, 412, 403 and 507 statuses, and
+/// the condition of a `DAV:error` body such as `no-uid-conflict`.
+/// Other statuses stay under [`WebdavClientError::Http`].
 #[derive(Debug, Error)]
 pub enum WebdavClientError {
     #[error(transparent)]
@@ -55,6 +55,8 @@ pub enum WebdavClientError {
     #[error(transparent)]
     Send(#[from] SendError),
     #[error(transparent)]
+    Http(HttpError),
+    #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error("Invalid calendar `{0}`")]
     InvalidCalendar(String),
@@ -64,10 +66,65 @@ pub enum WebdavClientError {
     CalendarNotFound(String),
     #[error("Item body is empty")]
     EmptyItemBody,
+    #[error("Item `{0}` already exists")]
+    AlreadyExists(String),
+    #[error("Resource `{0}` not found")]
+    NotFound(String),
+    #[error("Resource `{0}` does not match the expected entity tag")]
+    PreconditionFailed(String),
+    #[error("Access to `{0}` is forbidden")]
+    Forbidden(String),
+    #[error("Insufficient storage to write `{0}`")]
+    InsufficientStorage(String),
+    #[error("Condition `{}` failed for `{path}` (HTTP {status})", condition.name)]
+    Condition {
+        path: String,
+        status: u16,
+        condition: DavCondition,
+    },
     #[error("Failed to {0}")]
     OperationFailed(&'static str),
 }
 
+impl From<HttpError> for WebdavClientError {
+    fn from(err: HttpError) -> Self {
+        let HttpError::Status {
+            status,
+            path,
+            condition,
+        } = err
+        else {
+            return Self::Http(err);
+        };
+
+        match (status, condition) {
+            (412, _) => Self::PreconditionFailed(path),
+            (status, Some(condition)) => Self::Condition {
+                path,
+                status,
+                condition,
+            },
+            (404, None) => Self::NotFound(path),
+            (403, None) => Self::Forbidden(path),
+            (507, None) => Self::InsufficientStorage(path),
+            (status, condition) => Self::Http(HttpError::Status {
+                status,
+                path,
+                condition,
+            }),
+        }
+    }
+}
+
+impl From<WebdavCalendarItemCreateError> for WebdavClientError {
+    fn from(err: WebdavCalendarItemCreateError) -> Self {
+        match err {
+            WebdavCalendarItemCreateError::Http(err) => err.into(),
+            WebdavCalendarItemCreateError::AlreadyExists(id) => Self::AlreadyExists(id),
+        }
+    }
+}
+
 /// Std-blocking WebDAV (CalDAV) calendar client built on a connected
 /// stream.
 #[derive(Debug)]
@@ -116,14 +173,7 @@ impl WebdavClientStd {
     /// name.
     pub fn list_calendars(&mut self) -> Result<Vec<Calendar>, WebdavClientError> {
         let home = self.inner.calendar_home_set()?;
-        let home_path = home.path().to_string();
-
-        let coroutine = WebdavCalendarList::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &home_path,
-        );
+        let coroutine = self.session(&home).list_calendars();
         self.run(coroutine)
     }
 
@@ -135,27 +185,10 @@ impl WebdavClientStd {
         description: Option<&str>,
         color: Option<&str>,
     ) -> Result<(), WebdavClientError> {
-        self.validate_calendar(id)?;
-
         let home = self.inner.calendar_home_set()?;
-        let home_path = home.path().to_string();
-
-        let wire = WireCalendar {
-            id: id.to_string(),
-            display_name: Some(name.to_string()),
-            description: description.map(str::to_string),
-            color: color.map(str::to_string),
-            ctag: None,
-            tz: None,
-        };
-
-        let coroutine = WebdavCalendarCreate::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &home_path,
-            &wire,
-        );
+        let coroutine = self
+            .session(&home)
+            .create_calendar(id, name, description, color)?;
         self.run(coroutine)?;
         Ok(())
     }
@@ -167,7 +200,7 @@ impl WebdavClientStd {
         id: &str,
         patch: CalendarDiff,
     ) -> Result<(), WebdavClientError> {
-        self.validate_calendar(id)?;
+        validate_calendar(id)?;
 
         let current = self
             .list_calendars()?
@@ -175,208 +208,234 @@ impl WebdavClientStd {
             .find(|c| c.id == id)
             .ok_or_else(|| WebdavClientError::CalendarNotFound(id.to_string()))?;
 
-        let display_name = match patch.name {
-            Some(name) => Some(name),
-            None => Some(current.name),
-        };
-        let description = match patch.description {
-            Some(description) => description,
-            None => current.description,
-        };
-        let color = match patch.color {
-            Some(color) => color,
-            None => current.color,
-        };
-
         let home = self.inner.calendar_home_set()?;
-        let home_path = home.path().to_string();
-
-        let wire = WireCalendar {
-            id: id.to_string(),
-            display_name,
-            description,
-            color,
-            ctag: None,
-            tz: None,
-        };
-
-        let coroutine = WebdavCalendarUpdate::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &home_path,
-            &wire,
-        );
+        let coroutine = self.session(&home).update_calendar(current, patch);
         self.run(coroutine)?;
         Ok(())
     }
 
     /// Deletes calendar `id`.
     pub fn delete_calendar(&mut self, id: &str) -> Result<(), WebdavClientError> {
-        self.validate_calendar(id)?;
-
         let home = self.inner.calendar_home_set()?;
-        let home_path = home.path().to_string();
-
-        let coroutine = WebdavCalendarDelete::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &home_path,
-            id,
-        );
+        let coroutine = self.session(&home).delete_calendar(id)?;
         self.run(coroutine)?;
         Ok(())
     }
 
     /// Lists items inside `calendar_id`, applying 1-indexed pagination.
     ///
-    /// When `time_range` is set, the server query is constrained to
-    /// VEVENT components overlapping the range.
+    /// When `kinds` is non-empty, one `comp-filter` query per kind is
+    /// sent and the results merged. When `time_range` is set, the
+    /// server query is constrained to components of every requested
+    /// kind (every kind when `kinds` is empty) overlapping the range.
+    /// When the range also carries a
+    /// [`RecurrenceExpansion`](crate::item::RecurrenceExpansion), the
+    /// server shapes recurring items through `expand` or
+    /// `limit-recurrence-set`.
     pub fn list_items(
         &mut self,
         calendar_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
         time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
     ) -> Result<Vec<CalendarItem>, WebdavClientError> {
-        self.validate_calendar(calendar_id)?;
-
         let home = self.inner.calendar_home_set()?;
-        let path = calendar_path(&home, calendar_id);
-
-        let coroutine = WebdavCalendarItemList::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &path,
+        let coroutine = self.session(&home).list_items(
             calendar_id,
             page,
             page_size,
             time_range,
-        );
+            kinds,
+            filter,
+        )?;
         self.run(coroutine)
     }
 
     /// Fetches `item_id` from `calendar_id`.
+    ///
+    /// A missing item fails with [`WebdavClientError::NotFound`].
     pub fn get_item(
         &mut self,
         calendar_id: &str,
         item_id: &str,
     ) -> Result<CalendarItem, WebdavClientError> {
-        self.validate_calendar(calendar_id)?;
-        self.validate_item(item_id)?;
+        let home = self.inner.calendar_home_set()?;
+        let coroutine = self.session(&home).get_item(calendar_id, item_id)?;
+        self.run(coroutine)
+    }
 
+    /// Fetches `item_ids` from `calendar_id` with a single
+    /// `calendar-multiget` REPORT, in the requested order. Ids the
+    /// server does not know are left out.
+    pub fn get_items(
+        &mut self,
+        calendar_id: &str,
+        item_ids: &[&str],
+    ) -> Result<Vec<CalendarItem>, WebdavClientError> {
         let home = self.inner.calendar_home_set()?;
-        let path = calendar_path(&home, calendar_id);
+        match self.session(&home).get_items(calendar_id, item_ids)? {
+            Some(coroutine) => self.run(coroutine),
+            None => Ok(Vec::new()),
+        }
+    }
 
-        let coroutine = WebdavCalendarItemGet::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &path,
-            calendar_id,
-            item_id,
-        );
+    /// Finds the item of `calendar_id` carrying the iCalendar `uid`
+    /// with a UID `prop-filter` `calendar-query` REPORT, one per item
+    /// kind. Returns the one with the smallest id when several match.
+    #[cfg(feature = "parser")]
+    pub fn find_item_by_uid(
+        &mut self,
+        calendar_id: &str,
+        uid: &str,
+    ) -> Result<Option<CalendarItem>, WebdavClientError> {
+        let home = self.inner.calendar_home_set()?;
+        let coroutine = self.session(&home).find_item_by_uid(calendar_id, uid)?;
+        self.run(coroutine)
+    }
+
+    /// Searches `query` in the SUMMARY, DESCRIPTION, LOCATION, ATTENDEE
+    /// and CATEGORIES of the items of `calendar_id` with `text-match`
+    /// `calendar-query` REPORTs, one per item kind and field. Returns
+    /// the hits sorted by id.
+    #[cfg(feature = "parser")]
+    pub fn search_items(
+        &mut self,
+        calendar_id: &str,
+        query: &str,
+    ) -> Result<Vec<SearchHit>, WebdavClientError> {
+        let home = self.inner.calendar_home_set()?;
+        let coroutine = self.session(&home).search_items(calendar_id, query)?;
+        self.run(coroutine)
+    }
+
+    /// Lists the id, ETag, size and modification time of every item of
+    /// `calendar_id` with a Depth 1 PROPFIND, sorted by id, without
+    /// downloading their contents.
+    pub fn list_item_refs(
+        &mut self,
+        calendar_id: &str,
+    ) -> Result<Vec<CalendarItemRef>, WebdavClientError> {
+        let home = self.inner.calendar_home_set()?;
+        let coroutine = self.session(&home).list_item_refs(calendar_id)?;
         self.run(coroutine)
     }
 
     /// Appends a new item to `calendar_id`. Returns the id the server
-    /// confirmed.
+    /// confirmed along with the ETag it reported (from the response
+    /// header, otherwise a follow-up PROPFIND).
+    ///
+    /// With the `parser` feature the resource is named after the item's
+    /// UID when that makes a safe URL segment. The write is sent with
+    /// `If-None-Match: *`, so it fails with
+    /// [`WebdavClientError::AlreadyExists`] rather than overwrite an
+    /// existing resource.
     pub fn create_item(
         &mut self,
         calendar_id: &str,
         contents: Vec<u8>,
-    ) -> Result<String, WebdavClientError> {
-        if contents.is_empty() {
-            return Err(WebdavClientError::EmptyItemBody);
-        }
-        self.validate_calendar(calendar_id)?;
-
-        let id = fresh_item_id()?;
-
+    ) -> Result<CalendarItemReceipt, WebdavClientError> {
         let home = self.inner.calendar_home_set()?;
-        let path = calendar_path(&home, calendar_id);
-
-        let coroutine = WebdavCalendarItemCreate::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &path,
-            &id,
-            contents,
-        );
+        let coroutine = self.session(&home).create_item(calendar_id, contents)?;
         self.run(coroutine)
     }
 
+    /// Appends a new item to `calendar_id` like
+    /// [`create_item`](Self::create_item), first scanning the calendar
+    /// for an item with the same UID: `on_conflict` then either fails
+    /// with [`WebdavClientError::AlreadyExists`], replaces that item
+    /// (gated on its ETag) or stores the new one under a suffixed UID.
+    #[cfg(feature = "parser")]
+    pub fn create_item_with(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+        on_conflict: UidConflict,
+    ) -> Result<CalendarItemReceipt, WebdavClientError> {
+        let Some(uid) = item_uid(&contents) else {
+            return self.create_item(calendar_id, contents);
+        };
+        let items = self.list_items(calendar_id, None, None, None, &[], None)?;
+
+        match check_uid(items, &uid, contents, on_conflict) {
+            UidCheck::Create(contents) => self.create_item(calendar_id, contents),
+            UidCheck::Exists(id) => Err(WebdavClientError::AlreadyExists(id)),
+            UidCheck::Replace(existing, contents) => {
+                let etag = existing.etag.as_deref();
+                self.update_item(calendar_id, &existing.id, contents, etag)
+            }
+        }
+    }
+
     /// Overwrites `item_id` inside `calendar_id`, gating on `if_match`
-    /// when present.
+    /// when present. Returns the new ETag the server reported (from the
+    /// response header, otherwise a follow-up PROPFIND).
+    ///
+    /// A stale `if_match` fails with
+    /// [`WebdavClientError::PreconditionFailed`].
     pub fn update_item(
         &mut self,
         calendar_id: &str,
         item_id: &str,
         contents: Vec<u8>,
         if_match: Option<&str>,
-    ) -> Result<(), WebdavClientError> {
-        if contents.is_empty() {
-            return Err(WebdavClientError::EmptyItemBody);
-        }
-        self.validate_calendar(calendar_id)?;
-        self.validate_item(item_id)?;
-
+    ) -> Result<CalendarItemReceipt, WebdavClientError> {
         let home = self.inner.calendar_home_set()?;
-        let path = calendar_path(&home, calendar_id);
-
-        let coroutine = WebdavCalendarItemUpdate::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &path,
-            item_id,
-            contents,
-            if_match,
-        );
-        self.run(coroutine)?;
-        Ok(())
+        let coroutine =
+            self.session(&home)
+                .update_item(calendar_id, item_id, contents, if_match)?;
+        self.run(coroutine)
     }
 
-    /// Permanently deletes `item_id` from `calendar_id`.
+    /// Permanently deletes `item_id` from `calendar_id`, gating on
+    /// `if_match` when present.
+    ///
+    /// A stale `if_match` fails with
+    /// [`WebdavClientError::PreconditionFailed`], a missing item with
+    /// [`WebdavClientError::NotFound`].
     pub fn delete_item(
         &mut self,
         calendar_id: &str,
         item_id: &str,
+        if_match: Option<&str>,
     ) -> Result<(), WebdavClientError> {
-        self.validate_calendar(calendar_id)?;
-        self.validate_item(item_id)?;
-
         let home = self.inner.calendar_home_set()?;
-        let path = calendar_path(&home, calendar_id);
-
-        let coroutine = WebdavCalendarItemDelete::new(
-            &self.inner.base_url,
-            self.inner.auth(),
-            &self.inner.user_agent,
-            &path,
-            item_id,
-        );
+        let coroutine = self
+            .session(&home)
+            .delete_item(calendar_id, item_id, if_match)?;
         self.run(coroutine)?;
         Ok(())
     }
 
-    /// Rejects an empty calendar id (after trimming surrounding
-    /// slashes).
-    fn validate_calendar(&self, id: &str) -> Result<(), WebdavClientError> {
-        if id.trim_matches('/').is_empty() {
-            return Err(WebdavClientError::InvalidCalendar(id.to_string()));
-        }
-        Ok(())
+    /// Collects the changes of `calendar_id` since `sync_token` through
+    /// an RFC 6578 `sync-collection` REPORT.
+    ///
+    /// Without a token, or when the server rejects it, every item is
+    /// fetched and diffed by ETag against `known` (`(id, etag)` pairs
+    /// the caller already holds); the result is then flagged
+    /// [`full`](CalendarItemSync::full).
+    pub fn sync_items(
+        &mut self,
+        calendar_id: &str,
+        sync_token: Option<&str>,
+        known: &[(&str, &str)],
+    ) -> Result<CalendarItemSync, WebdavClientError> {
+        let home = self.inner.calendar_home_set()?;
+        let coroutine = self
+            .session(&home)
+            .sync_items(calendar_id, sync_token, known)?;
+        self.run(coroutine)
     }
 
-    /// Rejects an empty item id.
-    fn validate_item(&self, id: &str) -> Result<(), WebdavClientError> {
-        if id.is_empty() {
-            return Err(WebdavClientError::InvalidItemId(id.to_string()));
+    /// Borrows the connection settings of the inner client, with the
+    /// discovered `home`, to build coroutines.
+    fn session<'a>(&'a self, home: &'a Url) -> WebdavSession<'a> {
+        WebdavSession {
+            base_url: &self.inner.base_url,
+            auth: self.inner.auth(),
+            user_agent: &self.inner.user_agent,
+            home,
         }
-        Ok(())
     }
 }
@@ -0,0 +1,415 @@
+//! Async (tokio) WebDAV (CalDAV) calendar client.
+//!
+//! Runs the same io-calendar WebDAV coroutines as
+//! [`WebdavClientStd`](crate::webdav::client::WebdavClientStd), built
+//! by the same shared request code, but over any tokio [`AsyncRead`] +
+//! [`AsyncWrite`] stream, so async services do not have to wrap every
+//! call in `spawn_blocking`.
+//!
+//! The CalDAV home-set is discovered on the first call (see
+//! [`WebdavHomeSetDiscovery`]) and cached; a known home-set can be set
+//! up front with [`WebdavClientTokio::with_home_set`].
+
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Debug;
+
+use io_webdav::{
+    coroutine::{WebdavCoroutine, WebdavCoroutineState, WebdavYield},
+    rfc4918::WebdavAuth,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use url::Url;
+
+#[cfg(feature = "parser")]
+use crate::item::{SearchHit, UidCheck, UidConflict, check_uid, item_uid};
+use crate::{
+    calendar::{Calendar, CalendarDiff},
+    item::{
+        CalendarItem, CalendarItemKind, CalendarItemReceipt, CalendarItemRef, CalendarItemSync,
+        ItemFilter, TimeRange,
+    },
+    webdav::{
+        client::WebdavClientError,
+        discover::WebdavHomeSetDiscovery,
+        session::{WebdavSession, validate_calendar},
+    },
+};
+
+/// Socket read-buffer size for the WebDAV run loop. The buffer lives
+/// on the heap so it does not bloat the futures built on top of it.
+const READ_BUFFER_SIZE: usize = 16 * 1024;
+
+/// Object-safe bound on the stream of a [`WebdavClientTokio`], so the
+/// unified async client can hold any stream behind a box.
+pub trait WebdavStreamTokio: AsyncRead + AsyncWrite + Unpin + Send + Debug {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + Debug> WebdavStreamTokio for T {}
+
+/// Async WebDAV (CalDAV) calendar client built on a connected tokio
+/// stream.
+///
+/// Shares [`WebdavClientError`] with the std client.
+#[derive(Debug)]
+pub struct WebdavClientTokio<S> {
+    /// Connected (and, for HTTPS, already TLS-wrapped) stream.
+    pub stream: S,
+    /// Server base URL; request paths are resolved against it.
+    pub base_url: Url,
+    /// Credentials sent with every request.
+    pub auth: WebdavAuth,
+    /// `User-Agent` header sent with every request.
+    pub user_agent: String,
+    /// CalDAV calendar home-set collection URL, once discovered.
+    pub home_set: Option<Url>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> WebdavClientTokio<S> {
+    /// Builds a client over an already-connected `stream`. The
+    /// calendar home-set is discovered on the first call.
+    pub fn new(stream: S, base_url: Url, auth: WebdavAuth, user_agent: impl ToString) -> Self {
+        Self {
+            stream,
+            base_url,
+            auth,
+            user_agent: user_agent.to_string(),
+            home_set: None,
+        }
+    }
+
+    /// Addresses calendars under `home_set`, skipping discovery.
+    pub fn with_home_set(mut self, home_set: Url) -> Self {
+        self.home_set = Some(home_set);
+        self
+    }
+
+    /// Boxes the stream behind [`WebdavStreamTokio`], erasing its type.
+    pub fn boxed(self) -> WebdavClientTokio<Box<dyn WebdavStreamTokio>>
+    where
+        S: WebdavStreamTokio + 'static,
+    {
+        WebdavClientTokio {
+            stream: Box::new(self.stream),
+            base_url: self.base_url,
+            auth: self.auth,
+            user_agent: self.user_agent,
+            home_set: self.home_set,
+        }
+    }
+
+    /// Returns the CalDAV calendar home-set, running discovery on the
+    /// first call.
+    pub async fn calendar_home_set(&mut self) -> Result<Url, WebdavClientError> {
+        if let Some(home) = &self.home_set {
+            return Ok(home.clone());
+        }
+
+        let coroutine = WebdavHomeSetDiscovery::new(&self.base_url, &self.auth, &self.user_agent);
+        let home = self.run(coroutine).await?;
+        self.home_set = Some(home.clone());
+        Ok(home)
+    }
+
+    /// Pumps any standard-shape WebDAV coroutine (`Yield =
+    /// WebdavYield`) against the stream until it terminates.
+    pub async fn run<C, T, E>(&mut self, mut coroutine: C) -> Result<T, WebdavClientError>
+    where
+        C: WebdavCoroutine<Yield = WebdavYield, Return = Result<T, E>>,
+        WebdavClientError: From<E>,
+    {
+        let mut buf = vec![0u8; READ_BUFFER_SIZE];
+        let mut arg: Option<&[u8]> = None;
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                WebdavCoroutineState::Complete(Ok(out)) => return Ok(out),
+                WebdavCoroutineState::Complete(Err(err)) => return Err(err.into()),
+                WebdavCoroutineState::Yielded(WebdavYield::WantsRead) => {
+                    let n = self.stream.read(&mut buf).await?;
+                    arg = Some(&buf[..n]);
+                }
+                WebdavCoroutineState::Yielded(WebdavYield::WantsWrite(bytes)) => {
+                    self.stream.write_all(&bytes).await?;
+                }
+            }
+        }
+    }
+
+    /// Lists every calendar under the discovered home-set, sorted by
+    /// name.
+    pub async fn list_calendars(&mut self) -> Result<Vec<Calendar>, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).list_calendars();
+        self.run(coroutine).await
+    }
+
+    /// Creates calendar `id` (display name `name`) under the home-set.
+    pub async fn create_calendar(
+        &mut self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<(), WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self
+            .session(&home)
+            .create_calendar(id, name, description, color)?;
+        self.run(coroutine).await?;
+        Ok(())
+    }
+
+    /// Applies `patch` to calendar `id`, merging it against the current
+    /// calendar metadata.
+    pub async fn update_calendar(
+        &mut self,
+        id: &str,
+        patch: CalendarDiff,
+    ) -> Result<(), WebdavClientError> {
+        validate_calendar(id)?;
+
+        let current = self
+            .list_calendars()
+            .await?
+            .into_iter()
+            .find(|c| c.id == id)
+            .ok_or_else(|| WebdavClientError::CalendarNotFound(id.to_string()))?;
+
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).update_calendar(current, patch);
+        self.run(coroutine).await?;
+        Ok(())
+    }
+
+    /// Deletes calendar `id`.
+    pub async fn delete_calendar(&mut self, id: &str) -> Result<(), WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).delete_calendar(id)?;
+        self.run(coroutine).await?;
+        Ok(())
+    }
+
+    /// Lists items inside `calendar_id`, applying 1-indexed pagination.
+    ///
+    /// When `kinds` is non-empty, one `comp-filter` query per kind is
+    /// sent and the results merged. When `time_range` is set, the
+    /// server query is constrained to components of every requested
+    /// kind (every kind when `kinds` is empty) overlapping the range.
+    /// When the range also carries a
+    /// [`RecurrenceExpansion`](crate::item::RecurrenceExpansion), the
+    /// server shapes recurring items through `expand` or
+    /// `limit-recurrence-set`.
+    pub async fn list_items(
+        &mut self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<Vec<CalendarItem>, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).list_items(
+            calendar_id,
+            page,
+            page_size,
+            time_range,
+            kinds,
+            filter,
+        )?;
+        self.run(coroutine).await
+    }
+
+    /// Fetches `item_id` from `calendar_id`.
+    ///
+    /// A missing item fails with [`WebdavClientError::NotFound`].
+    pub async fn get_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<CalendarItem, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).get_item(calendar_id, item_id)?;
+        self.run(coroutine).await
+    }
+
+    /// Fetches `item_ids` from `calendar_id` with a single
+    /// `calendar-multiget` REPORT, in the requested order. Ids the
+    /// server does not know are left out.
+    pub async fn get_items(
+        &mut self,
+        calendar_id: &str,
+        item_ids: &[&str],
+    ) -> Result<Vec<CalendarItem>, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        match self.session(&home).get_items(calendar_id, item_ids)? {
+            Some(coroutine) => self.run(coroutine).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Finds the item of `calendar_id` carrying the iCalendar `uid`
+    /// with a UID `prop-filter` `calendar-query` REPORT, one per item
+    /// kind. Returns the one with the smallest id when several match.
+    #[cfg(feature = "parser")]
+    pub async fn find_item_by_uid(
+        &mut self,
+        calendar_id: &str,
+        uid: &str,
+    ) -> Result<Option<CalendarItem>, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).find_item_by_uid(calendar_id, uid)?;
+        self.run(coroutine).await
+    }
+
+    /// Searches `query` in the SUMMARY, DESCRIPTION, LOCATION, ATTENDEE
+    /// and CATEGORIES of the items of `calendar_id` with `text-match`
+    /// `calendar-query` REPORTs, one per item kind and field. Returns
+    /// the hits sorted by id.
+    #[cfg(feature = "parser")]
+    pub async fn search_items(
+        &mut self,
+        calendar_id: &str,
+        query: &str,
+    ) -> Result<Vec<SearchHit>, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).search_items(calendar_id, query)?;
+        self.run(coroutine).await
+    }
+
+    /// Lists the id, ETag, size and modification time of every item of
+    /// `calendar_id` with a Depth 1 PROPFIND, sorted by id, without
+    /// downloading their contents.
+    pub async fn list_item_refs(
+        &mut self,
+        calendar_id: &str,
+    ) -> Result<Vec<CalendarItemRef>, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).list_item_refs(calendar_id)?;
+        self.run(coroutine).await
+    }
+
+    /// Appends a new item to `calendar_id`. Returns the id the server
+    /// confirmed along with the ETag it reported (from the response
+    /// header, otherwise a follow-up PROPFIND).
+    ///
+    /// With the `parser` feature the resource is named after the item's
+    /// UID when that makes a safe URL segment. The write is sent with
+    /// `If-None-Match: *`, so it fails with
+    /// [`WebdavClientError::AlreadyExists`] rather than overwrite an
+    /// existing resource.
+    pub async fn create_item(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<CalendarItemReceipt, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self.session(&home).create_item(calendar_id, contents)?;
+        self.run(coroutine).await
+    }
+
+    /// Appends a new item to `calendar_id` like
+    /// [`create_item`](Self::create_item), first scanning the calendar
+    /// for an item with the same UID: `on_conflict` then either fails
+    /// with [`WebdavClientError::AlreadyExists`], replaces that item
+    /// (gated on its ETag) or stores the new one under a suffixed UID.
+    #[cfg(feature = "parser")]
+    pub async fn create_item_with(
+        &mut self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+        on_conflict: UidConflict,
+    ) -> Result<CalendarItemReceipt, WebdavClientError> {
+        let Some(uid) = item_uid(&contents) else {
+            return self.create_item(calendar_id, contents).await;
+        };
+        let items = self
+            .list_items(calendar_id, None, None, None, &[], None)
+            .await?;
+
+        match check_uid(items, &uid, contents, on_conflict) {
+            UidCheck::Create(contents) => self.create_item(calendar_id, contents).await,
+            UidCheck::Exists(id) => Err(WebdavClientError::AlreadyExists(id)),
+            UidCheck::Replace(existing, contents) => {
+                let etag = existing.etag.as_deref();
+                self.update_item(calendar_id, &existing.id, contents, etag)
+                    .await
+            }
+        }
+    }
+
+    /// Overwrites `item_id` inside `calendar_id`, gating on `if_match`
+    /// when present. Returns the new ETag the server reported (from the
+    /// response header, otherwise a follow-up PROPFIND).
+    ///
+    /// A stale `if_match` fails with
+    /// [`WebdavClientError::PreconditionFailed`].
+    pub async fn update_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<CalendarItemReceipt, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine =
+            self.session(&home)
+                .update_item(calendar_id, item_id, contents, if_match)?;
+        self.run(coroutine).await
+    }
+
+    /// Permanently deletes `item_id` from `calendar_id`, gating on
+    /// `if_match` when present.
+    ///
+    /// A stale `if_match` fails with
+    /// [`WebdavClientError::PreconditionFailed`], a missing item with
+    /// [`WebdavClientError::NotFound`].
+    pub async fn delete_item(
+        &mut self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<(), WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self
+            .session(&home)
+            .delete_item(calendar_id, item_id, if_match)?;
+        self.run(coroutine).await?;
+        Ok(())
+    }
+
+    /// Collects the changes of `calendar_id` since `sync_token` through
+    /// an RFC 6578 `sync-collection` REPORT.
+    ///
+    /// Without a token, or when the server rejects it, every item is
+    /// fetched and diffed by ETag against `known` (`(id, etag)` pairs
+    /// the caller already holds); the result is then flagged
+    /// [`full`](CalendarItemSync::full).
+    pub async fn sync_items(
+        &mut self,
+        calendar_id: &str,
+        sync_token: Option<&str>,
+        known: &[(&str, &str)],
+    ) -> Result<CalendarItemSync, WebdavClientError> {
+        let home = self.calendar_home_set().await?;
+        let coroutine = self
+            .session(&home)
+            .sync_items(calendar_id, sync_token, known)?;
+        self.run(coroutine).await
+    }
+
+    /// Borrows the connection settings, with the discovered `home`, to
+    /// build coroutines.
+    fn session<'a>(&'a self, home: &'a Url) -> WebdavSession<'a> {
+        WebdavSession {
+            base_url: &self.base_url,
+            auth: &self.auth,
+            user_agent: &self.user_agent,
+            home,
+        }
+    }
+}
@@ -6,19 +6,16 @@
 //!
 //! [`WebdavClientStd`]: crate::webdav::client::WebdavClientStd
 
-use alloc::{string::ToString, vec::Vec};
-
-#[cfg(feature = "client")]
-use alloc::{format, string::String};
-
-use io_webdav::rfc4791::{
-    calendar::Calendar as WireCalendar,
-    item::{ItemBody, ItemEntry},
+use alloc::{
+    format,
+    string::{String, ToString},
 };
+
+use io_webdav::rfc4791::{calendar::Calendar as WireCalendar, item::ItemEntry};
 #[cfg(feature = "client")]
 use url::Url;
 
-use crate::{calendar::Calendar, item::CalendarItem};
+use crate::{calendar::Calendar, item::CalendarItem, webdav::xml::percent_decode};
 
 #[cfg(feature = "client")]
 impl From<crate::webdav::client::WebdavClientStd> for crate::client::CalendarClientStd {
@@ -27,9 +24,22 @@ impl From<crate::webdav::client::WebdavClientStd> for crate::client::CalendarCli
     }
 }
 
+#[cfg(feature = "tokio")]
+impl<S> From<crate::webdav::client_tokio::WebdavClientTokio<S>>
+    for crate::client_tokio::CalendarClientTokio
+where
+    S: crate::webdav::client_tokio::WebdavStreamTokio + 'static,
+{
+    fn from(client: crate::webdav::client_tokio::WebdavClientTokio<S>) -> Self {
+        Self::Webdav(alloc::boxed::Box::new(client.boxed()))
+    }
+}
+
+/// The wire id is the collection's URL segment, percent-decoded here
+/// once; paths built from it encode it back through [`encode_segment`].
 impl From<WireCalendar> for Calendar {
     fn from(wire: WireCalendar) -> Self {
-        let id = wire.id;
+        let id = percent_decode(&wire.id);
         let name = wire.display_name.clone().unwrap_or_else(|| id.clone());
 
         Calendar {
@@ -43,89 +53,155 @@ impl From<WireCalendar> for Calendar {
 }
 
 /// Maps a WebDAV [`ItemEntry`] to a shared [`CalendarItem`], pinning it
-/// to `calendar_id`.
+/// to `calendar_id`. The entry id is the raw URL segment, decoded here
+/// once.
 pub(crate) fn item_from_entry(entry: ItemEntry, calendar_id: &str) -> CalendarItem {
     CalendarItem {
-        id: entry.id,
+        id: percent_decode(&entry.id),
         calendar_id: calendar_id.to_string(),
         etag: entry.etag,
         contents: entry.data,
     }
 }
 
-/// Maps a WebDAV [`ItemBody`] to a shared [`CalendarItem`]. The body
-/// carries no id, so the requested `item_id` is used.
-pub(crate) fn item_from_body(body: ItemBody, calendar_id: &str, item_id: &str) -> CalendarItem {
-    CalendarItem {
-        id: item_id.to_string(),
-        calendar_id: calendar_id.to_string(),
-        etag: body.etag,
-        contents: body.data,
-    }
+/// Item id of the resource at `href`: its last path segment without
+/// the `.ics` extension.
+pub(crate) fn item_id_from_href(href: &str) -> String {
+    let segment = href
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(href);
+    segment.strip_suffix(".ics").unwrap_or(segment).to_string()
+}
+
+/// Path of item `item_id` inside the collection at `calendar_path`,
+/// the id percent-encoded as a single path segment.
+pub(crate) fn item_path(calendar_path: &str, item_id: &str) -> String {
+    let id = encode_segment(item_id);
+    format!("{}/{id}.ics", calendar_path.trim_end_matches('/'))
 }
 
 /// Builds the collection path of `calendar_id` under the home-set URL
-/// (trim the home-set trailing slash and the id's surrounding slashes).
+/// (trim the home-set trailing slash and the id's surrounding slashes),
+/// the id percent-encoded as a single path segment.
 #[cfg(feature = "client")]
 pub(crate) fn calendar_path(home: &Url, calendar_id: &str) -> String {
     let base = home.path().trim_end_matches('/');
-    let id = calendar_id.trim_matches('/');
+    let id = calendar_segment(calendar_id);
     format!("{base}/{id}")
 }
 
-/// 1-indexed pagination on an in-memory list. `page_size = None`
-/// returns the full slice; `page_size = 0` or a page past the end
-/// returns an empty vector.
-pub(crate) fn paginate<T>(items: Vec<T>, page: Option<u32>, page_size: Option<u32>) -> Vec<T> {
-    let Some(size) = page_size else {
-        return items;
-    };
+/// URL segment of `calendar_id`: its surrounding slashes trimmed, then
+/// percent-encoded.
+#[cfg(feature = "client")]
+pub(crate) fn calendar_segment(calendar_id: &str) -> String {
+    encode_segment(calendar_id.trim_matches('/'))
+}
 
-    if size == 0 {
-        return Vec::new();
+/// Percent-encodes `segment` so it stays one path segment (RFC 3986
+/// section 3.3): every byte but the unreserved characters and the
+/// sub-delimiters, `:` and `@` is escaped, `/`, `?`, `#`, `%`, spaces
+/// and control characters included.
+pub(crate) fn encode_segment(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+
+    for byte in segment.bytes() {
+        let keep = byte.is_ascii_alphanumeric()
+            || matches!(
+                byte,
+                b'-' | b'.'
+                    | b'_'
+                    | b'~'
+                    | b'!'
+                    | b'$'
+                    | b'&'
+                    | b'\''
+                    | b'('
+                    | b')'
+                    | b'*'
+                    | b'+'
+                    | b','
+                    | b';'
+                    | b'='
+                    | b':'
+                    | b'@'
+            );
+
+        if keep {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
     }
 
-    let page = page.unwrap_or(1).max(1);
-    let skip = ((page - 1) as usize).saturating_mul(size as usize);
+    out
+}
 
-    if skip >= items.len() {
-        return Vec::new();
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn encodes_item_path_segment() {
+        assert_eq!(
+            item_path("/dav/personal/", "a b/c?d#e%f"),
+            "/dav/personal/a%20b%2Fc%3Fd%23e%25f.ics"
+        );
+        assert_eq!(
+            item_path("/dav/personal", "uid-1@example.com"),
+            "/dav/personal/uid-1@example.com.ics"
+        );
     }
 
-    items.into_iter().skip(skip).take(size as usize).collect()
-}
+    #[cfg(feature = "client")]
+    #[test]
+    fn encodes_calendar_path_segment() {
+        let home = Url::parse("https://dav.example.com/dav/calendars/alice/").unwrap();
+        assert_eq!(
+            calendar_path(&home, "/work & play/"),
+            "/dav/calendars/alice/work%20&%20play"
+        );
+        assert_eq!(
+            calendar_path(&home, "x\r\ny"),
+            "/dav/calendars/alice/x%0D%0Ay"
+        );
+        assert_eq!(calendar_segment("/a b/"), "a%20b");
+    }
 
-/// Generates a fresh RFC 4122 v4 item id from the system entropy
-/// source.
-///
-/// CalDAV requires the caller to supply the resource name; the
-/// iCalendar UID parsing path is gated behind the optional parser
-/// feature upstream, so on the bare client API we always synthesize the
-/// id.
-#[cfg(feature = "client")]
-pub(crate) fn fresh_item_id() -> Result<String, crate::webdav::client::WebdavClientError> {
-    use crate::webdav::client::WebdavClientError;
-
-    let mut bytes = [0u8; 16];
-    getrandom::fill(&mut bytes)
-        .map_err(|_| WebdavClientError::OperationFailed("gather randomness"))?;
-
-    // NOTE: RFC 4122 4.4 stamps version 4 and variant 10xx.
-    bytes[6] = (bytes[6] & 0x0f) | 0x40;
-    bytes[8] = (bytes[8] & 0x3f) | 0x80;
-
-    const HEX: &[u8; 16] = b"0123456789abcdef";
-    let mut out = [0u8; 36];
-    let mut cursor = 0;
-    for (i, byte) in bytes.iter().enumerate() {
-        if matches!(i, 4 | 6 | 8 | 10) {
-            out[cursor] = b'-';
-            cursor += 1;
-        }
-        out[cursor] = HEX[(byte >> 4) as usize];
-        out[cursor + 1] = HEX[(byte & 0x0f) as usize];
-        cursor += 2;
+    #[test]
+    fn item_id_round_trips_through_href() {
+        assert_eq!(item_id_from_href("/dav/personal/event-1.ics"), "event-1");
+        assert_eq!(item_id_from_href("/dav/personal/event-1.ics/"), "event-1");
+
+        // hrefs are percent-decoded once when the multistatus is read
+        let path = item_path("/dav/personal", "a b");
+        assert_eq!(path, "/dav/personal/a%20b.ics");
+        assert_eq!(item_id_from_href(&percent_decode(&path)), "a b");
     }
 
-    Ok(String::from_utf8(out.to_vec()).expect("ASCII hex is always valid UTF-8"))
+    #[test]
+    fn decodes_entry_id_once() {
+        let entry = ItemEntry {
+            id: "a%20b".into(),
+            etag: None,
+            data: b"BEGIN:VCALENDAR".to_vec(),
+        };
+        let item = item_from_entry(entry, "personal");
+        assert_eq!(item.id, "a b");
+        assert_eq!(
+            item_path("/dav/personal", &item.id),
+            "/dav/personal/a%20b.ics"
+        );
+
+        // an escaped percent sign is not decoded twice
+        let entry = ItemEntry {
+            id: "100%2525".into(),
+            etag: None,
+            data: Vec::new(),
+        };
+        assert_eq!(item_from_entry(entry, "personal").id, "100%25");
+    }
 }
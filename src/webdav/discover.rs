@@ -0,0 +1,286 @@
+//! CalDAV calendar home-set discovery (RFC 4791 section 6.2.1, RFC
+//! 5397), sending Depth 0 PROPFINDs through [`HttpExchange`].
+//!
+//! The std client delegates discovery to its inner io-webdav client;
+//! this coroutine gives the async client the same behaviour without a
+//! blocking stream. The base URL is asked for both
+//! `current-user-principal` and `calendar-home-set`: a home-set found
+//! there is used as is, otherwise the principal is asked for its own.
+//! Redirects (such as the `/.well-known/caldav` one) are followed.
+//!
+//! Every request goes to the host of the base URL, so a redirect or an
+//! href leaving its origin fails with [`HttpError::CrossOrigin`]
+//! instead of being sent there.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let home = client.run(WebdavHomeSetDiscovery::new(&base_url, &auth, "io-calendar")).await?;
+//! ```
+
+use alloc::string::{String, ToString};
+
+use io_webdav::{coroutine::*, rfc4918::WebdavAuth};
+use log::trace;
+use url::{Position, Url};
+
+use crate::webdav::{
+    http::{HttpError, HttpExchange, HttpRequest},
+    xml::Multistatus,
+};
+
+/// Body of the PROPFIND requests.
+const PROPFIND: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+    <D:propfind xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+    <D:prop><D:current-user-principal /><C:calendar-home-set /></D:prop>\
+    </D:propfind>";
+
+/// Most redirects followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// I/O-free coroutine discovering the CalDAV calendar home-set.
+///
+/// On completion returns the home-set URL, resolved against the base
+/// URL.
+pub struct WebdavHomeSetDiscovery {
+    base_url: Url,
+    auth: WebdavAuth,
+    user_agent: String,
+    url: Url,
+    principal: bool,
+    redirects: usize,
+    inner: HttpExchange,
+}
+
+impl WebdavHomeSetDiscovery {
+    /// Builds the coroutine starting discovery at `base_url`.
+    pub fn new(base_url: &Url, auth: &WebdavAuth, user_agent: &str) -> Self {
+        trace!("prepare webdav home-set discovery");
+        Self {
+            base_url: base_url.clone(),
+            auth: auth.clone(),
+            user_agent: user_agent.to_string(),
+            url: base_url.clone(),
+            principal: false,
+            redirects: 0,
+            inner: propfind(base_url, auth, user_agent, base_url),
+        }
+    }
+
+    /// Sends the next PROPFIND to `url`.
+    fn send(&mut self, url: Url) {
+        self.inner = propfind(&self.base_url, &self.auth, &self.user_agent, &url);
+        self.url = url;
+    }
+
+    /// Resolves `reference` against the URL last asked, or `None` when
+    /// it is not a valid URL reference. URLs outside the origin of the
+    /// base URL are refused.
+    fn resolve(&self, reference: &str) -> Option<Result<Url, HttpError>> {
+        let url = self.url.join(reference).ok()?;
+
+        if url.origin() == self.base_url.origin() {
+            Some(Ok(url))
+        } else {
+            Some(Err(HttpError::CrossOrigin(url.to_string())))
+        }
+    }
+}
+
+impl WebdavCoroutine for WebdavHomeSetDiscovery {
+    type Yield = WebdavYield;
+    type Return = Result<Url, HttpError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        let mut arg = arg;
+
+        loop {
+            let response = match self.inner.resume(arg.take()) {
+                WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+                WebdavCoroutineState::Complete(Ok(response)) => response,
+                WebdavCoroutineState::Complete(Err(err)) => {
+                    return WebdavCoroutineState::Complete(Err(err));
+                }
+            };
+
+            if (300..400).contains(&response.status) && self.redirects < MAX_REDIRECTS {
+                match response.header("Location").and_then(|l| self.resolve(l)) {
+                    Some(Ok(location)) => {
+                        trace!("webdav home-set discovery: follow redirect to {location}");
+                        self.redirects += 1;
+                        self.send(location);
+                        continue;
+                    }
+                    Some(Err(err)) => return WebdavCoroutineState::Complete(Err(err)),
+                    None => {}
+                }
+            }
+
+            let response = match response.success() {
+                Ok(response) => response,
+                Err(err) => return WebdavCoroutineState::Complete(Err(err)),
+            };
+
+            let Some(multistatus) = Multistatus::parse(&response.body) else {
+                let err = HttpError::InvalidResponse("expected a multistatus body");
+                return WebdavCoroutineState::Complete(Err(err));
+            };
+
+            let href = |name: &str| {
+                multistatus
+                    .responses
+                    .iter()
+                    .filter_map(|response| response.prop(name))
+                    .find_map(|prop| prop.child("href"))
+                    .and_then(|href| self.resolve(href.text.trim()))
+            };
+
+            match href("calendar-home-set") {
+                Some(Ok(home)) => {
+                    trace!("webdav home-set discovery: found {home}");
+                    return WebdavCoroutineState::Complete(Ok(home));
+                }
+                Some(Err(err)) => return WebdavCoroutineState::Complete(Err(err)),
+                None => {}
+            }
+
+            match href("current-user-principal") {
+                Some(Ok(principal)) if !self.principal => {
+                    trace!("webdav home-set discovery: ask principal {principal}");
+                    self.principal = true;
+                    self.send(principal);
+                }
+                Some(Err(err)) => return WebdavCoroutineState::Complete(Err(err)),
+                _ => {
+                    let err = HttpError::InvalidResponse("no calendar home-set found");
+                    return WebdavCoroutineState::Complete(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Builds the Depth 0 PROPFIND to the path and query of `url`.
+fn propfind(base_url: &Url, auth: &WebdavAuth, user_agent: &str, url: &Url) -> HttpExchange {
+    let target = &url[Position::BeforePath..Position::AfterQuery];
+    let request = HttpRequest::new("PROPFIND", base_url, auth, user_agent, target)
+        .header("Depth", "0")
+        .xml(PROPFIND.to_string());
+    HttpExchange::new(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, vec::Vec};
+
+    use super::*;
+
+    fn multistatus(prop: &str) -> Vec<u8> {
+        let body = format!(
+            "<?xml version=\"1.0\"?>\
+             <D:multistatus xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+             <D:response><D:href>/</D:href><D:propstat><D:prop>{prop}</D:prop>\
+             <D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\
+             </D:multistatus>"
+        );
+        let mut reply = format!(
+            "HTTP/1.1 207 Multi-Status\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        reply.extend(body.into_bytes());
+        reply
+    }
+
+    /// Runs discovery against `replies`, returning the result and the
+    /// request lines sent.
+    fn discover(replies: &[Vec<u8>]) -> (Result<Url, HttpError>, Vec<String>) {
+        let base_url = Url::parse("https://dav.example.com/").unwrap();
+        let mut coroutine = WebdavHomeSetDiscovery::new(&base_url, &WebdavAuth::None, "test");
+        let mut replies = replies.iter();
+        let mut requests = Vec::new();
+        let mut arg = None;
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                WebdavCoroutineState::Complete(out) => return (out, requests),
+                WebdavCoroutineState::Yielded(WebdavYield::WantsWrite(bytes)) => {
+                    let request = String::from_utf8(bytes).unwrap();
+                    requests.push(request.lines().next().unwrap().to_string());
+                }
+                WebdavCoroutineState::Yielded(WebdavYield::WantsRead) => {
+                    arg = Some(replies.next().map(Vec::as_slice).unwrap_or_default());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn uses_home_set_of_base_url() {
+        let (home, requests) = discover(&[multistatus(
+            "<C:calendar-home-set><D:href>/dav/calendars/alice/</D:href></C:calendar-home-set>",
+        )]);
+
+        assert_eq!(
+            home.unwrap().as_str(),
+            "https://dav.example.com/dav/calendars/alice/"
+        );
+        assert_eq!(requests, ["PROPFIND / HTTP/1.1"]);
+    }
+
+    fn redirect(location: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: {location}\r\nContent-Length: 0\r\n\r\n"
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn follows_redirect_then_principal() {
+        let (home, requests) = discover(&[
+            redirect("/dav/?user=alice"),
+            multistatus(
+                "<D:current-user-principal><D:href>/dav/principals/alice/</D:href></D:current-user-principal>",
+            ),
+            multistatus(
+                "<C:calendar-home-set><D:href>https://dav.example.com/cal/alice/</D:href></C:calendar-home-set>",
+            ),
+        ]);
+
+        assert_eq!(home.unwrap().as_str(), "https://dav.example.com/cal/alice/");
+        assert_eq!(
+            requests,
+            [
+                "PROPFIND / HTTP/1.1",
+                "PROPFIND /dav/?user=alice HTTP/1.1",
+                "PROPFIND /dav/principals/alice/ HTTP/1.1",
+            ]
+        );
+    }
+
+    #[test]
+    fn refuses_cross_origin_urls() {
+        let home_set = |href: &str| {
+            multistatus(&format!(
+                "<C:calendar-home-set><D:href>{href}</D:href></C:calendar-home-set>"
+            ))
+        };
+
+        for replies in [
+            [redirect("https://evil.example.com/dav/")],
+            [redirect("http://dav.example.com/dav/")],
+            [home_set("https://cal.example.com/alice/")],
+            [home_set("https://dav.example.com:8443/alice/")],
+        ] {
+            let (home, requests) = discover(&replies);
+            assert!(matches!(home, Err(HttpError::CrossOrigin(_))), "{home:?}");
+            assert_eq!(requests, ["PROPFIND / HTTP/1.1"]);
+        }
+    }
+
+    #[test]
+    fn fails_without_home_set() {
+        let (home, _) = discover(&[multistatus("")]);
+        assert!(matches!(home, Err(HttpError::InvalidResponse(_))));
+    }
+}
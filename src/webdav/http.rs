@@ -0,0 +1,667 @@
+//! Minimal HTTP/1.1 exchange for the CalDAV requests io-webdav has no
+//! coroutine for: REPORT bodies carrying `calendar-data` options,
+//! `sync-collection`, `calendar-multiget`, PROPFIND and conditional
+//! writes.
+//!
+//! [`HttpExchange`] writes one request and reads back one response on
+//! the stream shared with the io-webdav coroutines. The response body
+//! is always read in full (`Content-Length`, chunked or until EOF), so
+//! the connection stays usable for the next request.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let request = HttpRequest::new("REPORT", &base_url, &auth, "io-calendar", "/dav/personal/")
+//!     .header("Depth", "1")
+//!     .xml(body);
+//! let response = client.run(HttpExchange::new(request))?;
+//! ```
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str::from_utf8;
+
+use io_webdav::{
+    coroutine::{WebdavCoroutine, WebdavCoroutineState, WebdavYield},
+    rfc4918::WebdavAuth,
+};
+use log::trace;
+use thiserror::Error;
+use url::Url;
+
+use crate::webdav::xml::{Element, percent_decode};
+
+/// Errors surfaced by [`HttpExchange`].
+#[derive(Debug, Error)]
+pub enum HttpError {
+    #[error("Invalid HTTP response: {0}")]
+    InvalidResponse(&'static str),
+    #[error("Connection closed before the HTTP response was complete")]
+    UnexpectedEof,
+    #[error("Invalid HTTP request: `{0}` holds a control character")]
+    InvalidRequest(&'static str),
+    #[error("Refusing to follow `{0}` outside the origin of the base URL")]
+    CrossOrigin(String),
+    #[error("Unexpected HTTP status {status} for `{path}`")]
+    Status {
+        status: u16,
+        path: String,
+        condition: Option<DavCondition>,
+    },
+}
+
+/// A precondition or postcondition element reported in a `DAV:error`
+/// response body (RFC 4918 section 16), such as the CalDAV
+/// `no-uid-conflict` or `valid-calendar-data` (RFC 4791 section
+/// 5.3.2.1).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DavCondition {
+    /// Local name of the condition element.
+    pub name: String,
+
+    /// Href the condition points at, when the server reports one (the
+    /// resource already holding the UID for `no-uid-conflict`).
+    pub href: Option<String>,
+}
+
+impl DavCondition {
+    /// Reads the first condition of a `DAV:error` body.
+    fn parse(body: &[u8]) -> Option<Self> {
+        let root = Element::parse(body)?;
+
+        if root.name != "error" {
+            return None;
+        }
+
+        let condition = root.children.first()?;
+        let href = condition
+            .child("href")
+            .map(|href| percent_decode(href.text.trim()));
+
+        Some(Self {
+            name: condition.name.clone(),
+            href,
+        })
+    }
+}
+
+/// An HTTP/1.1 request addressed to a path of the WebDAV server.
+#[derive(Clone, Debug)]
+pub struct HttpRequest {
+    method: &'static str,
+    path: String,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// Builds a `method` request to `path` on the `base_url` host,
+    /// carrying the `Host`, `User-Agent` and `Authorization` headers.
+    pub fn new(
+        method: &'static str,
+        base_url: &Url,
+        auth: &WebdavAuth,
+        user_agent: &str,
+        path: &str,
+    ) -> Self {
+        let mut host = base_url.host_str().unwrap_or_default().to_string();
+        if let Some(port) = base_url.port() {
+            host.push_str(&format!(":{port}"));
+        }
+
+        let mut request = Self {
+            method,
+            path: path.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        };
+
+        request = request.header("Host", host);
+        request = request.header("User-Agent", user_agent);
+
+        match auth {
+            WebdavAuth::None => request,
+            WebdavAuth::Basic { username, password } => {
+                let credentials = base64(format!("{username}:{password}").as_bytes());
+                request.header("Authorization", format!("Basic {credentials}"))
+            }
+            WebdavAuth::Bearer { token } => {
+                request.header("Authorization", format!("Bearer {token}"))
+            }
+        }
+    }
+
+    /// Adds a header. A value holding a control character (CR and LF
+    /// included) makes the exchange fail with
+    /// [`HttpError::InvalidRequest`] before anything is sent.
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Sets an XML body.
+    pub fn xml(self, body: String) -> Self {
+        self.body("application/xml; charset=utf-8", body.into_bytes())
+    }
+
+    /// Sets an iCalendar body.
+    pub fn calendar(self, body: Vec<u8>) -> Self {
+        self.body("text/calendar; charset=utf-8", body)
+    }
+
+    fn body(mut self, content_type: &str, body: Vec<u8>) -> Self {
+        self = self.header("Content-Type", content_type);
+        self.body = body;
+        self
+    }
+
+    /// Name of the first request part (`path` or a header name) holding
+    /// a control character, which would let it inject headers or a
+    /// whole request.
+    fn invalid_part(&self) -> Option<&'static str> {
+        let invalid = |value: &str| value.chars().any(|c| c.is_control() && c != '\t');
+
+        if invalid(&self.path) || self.path.contains(' ') {
+            return Some("path");
+        }
+
+        self.headers
+            .iter()
+            .find(|(_, value)| invalid(value))
+            .map(|(name, _)| *name)
+    }
+
+    /// Serializes the request line, headers and body.
+    fn into_bytes(self) -> Vec<u8> {
+        let mut head = format!("{} {} HTTP/1.1\r\n", self.method, self.path);
+
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+
+        if !self.body.is_empty() || !matches!(self.method, "GET" | "HEAD" | "DELETE") {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+
+        head.push_str("\r\n");
+
+        let mut bytes = head.into_bytes();
+        bytes.extend(self.body);
+        bytes
+    }
+}
+
+/// A fully read HTTP/1.1 response.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HttpResponse {
+    /// Path of the request this response answers.
+    pub path: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// First value of header `name`, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the status is 2xx.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// `ETag` header, verbatim.
+    pub fn etag(&self) -> Option<String> {
+        self.header("ETag").map(parse_etag)
+    }
+
+    /// Fails with [`HttpError::Status`] unless the status is 2xx,
+    /// carrying the condition of a `DAV:error` body when there is one.
+    pub fn success(self) -> Result<Self, HttpError> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(HttpError::Status {
+                status: self.status,
+                condition: DavCondition::parse(&self.body),
+                path: self.path,
+            })
+        }
+    }
+}
+
+/// I/O-free coroutine sending one [`HttpRequest`] and reading its
+/// [`HttpResponse`].
+///
+/// Interim `1xx` responses are skipped. Non-2xx statuses are returned
+/// as responses, not errors: callers decide what they mean.
+pub struct HttpExchange {
+    path: String,
+    invalid: Option<&'static str>,
+    request: Option<Vec<u8>>,
+    head: bool,
+    buffer: Vec<u8>,
+}
+
+impl HttpExchange {
+    /// Builds the coroutine sending `request`.
+    pub fn new(request: HttpRequest) -> Self {
+        trace!("prepare http {} {}", request.method, request.path);
+        Self {
+            path: request.path.clone(),
+            invalid: request.invalid_part(),
+            head: request.method == "HEAD",
+            request: Some(request.into_bytes()),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl WebdavCoroutine for HttpExchange {
+    type Yield = WebdavYield;
+    type Return = Result<HttpResponse, HttpError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        if let Some(part) = self.invalid {
+            return WebdavCoroutineState::Complete(Err(HttpError::InvalidRequest(part)));
+        }
+
+        if let Some(bytes) = self.request.take() {
+            return WebdavCoroutineState::Yielded(WebdavYield::WantsWrite(bytes));
+        }
+
+        let eof = match arg {
+            Some([]) => true,
+            Some(bytes) => {
+                self.buffer.extend_from_slice(bytes);
+                false
+            }
+            None => false,
+        };
+
+        match parse_response(&self.buffer, self.head, eof) {
+            Ok(Some(response)) => WebdavCoroutineState::Complete(Ok(HttpResponse {
+                path: self.path.clone(),
+                ..response
+            })),
+            Ok(None) if eof => WebdavCoroutineState::Complete(Err(HttpError::UnexpectedEof)),
+            Ok(None) => WebdavCoroutineState::Yielded(WebdavYield::WantsRead),
+            Err(err) => WebdavCoroutineState::Complete(Err(err)),
+        }
+    }
+}
+
+/// Parses the final response out of `buffer`, or `None` while more
+/// bytes are needed.
+fn parse_response(
+    mut buffer: &[u8],
+    head_request: bool,
+    eof: bool,
+) -> Result<Option<HttpResponse>, HttpError> {
+    loop {
+        let Some(end) = find(buffer, b"\r\n\r\n") else {
+            return Ok(None);
+        };
+
+        let head = from_utf8(&buffer[..end])
+            .map_err(|_| HttpError::InvalidResponse("head is not UTF-8"))?;
+        let rest = &buffer[end + 4..];
+
+        let mut lines = head.split("\r\n");
+        let status = lines
+            .next()
+            .and_then(|line| line.split(' ').nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or(HttpError::InvalidResponse("status line"))?;
+
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        if (100..200).contains(&status) {
+            buffer = rest;
+            continue;
+        }
+
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+
+        let body = if head_request || status == 204 || status == 304 {
+            Some(Vec::new())
+        } else if header("Transfer-Encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"))
+        {
+            dechunk(rest)?
+        } else if let Some(length) = header("Content-Length") {
+            let length: usize = length
+                .parse()
+                .map_err(|_| HttpError::InvalidResponse("Content-Length"))?;
+            (rest.len() >= length).then(|| rest[..length].to_vec())
+        } else if eof {
+            Some(rest.to_vec())
+        } else {
+            None
+        };
+
+        return Ok(body.map(|body| HttpResponse {
+            path: String::new(),
+            status,
+            headers,
+            body,
+        }));
+    }
+}
+
+/// Decodes a chunked body, or `None` while the last chunk is missing.
+fn dechunk(mut bytes: &[u8]) -> Result<Option<Vec<u8>>, HttpError> {
+    let mut body = Vec::new();
+
+    loop {
+        let Some(end) = find(bytes, b"\r\n") else {
+            return Ok(None);
+        };
+
+        let size = from_utf8(&bytes[..end])
+            .ok()
+            .map(|line| line.split(';').next().unwrap_or_default().trim())
+            .and_then(|size| usize::from_str_radix(size, 16).ok())
+            .ok_or(HttpError::InvalidResponse("chunk size"))?;
+        bytes = &bytes[end + 2..];
+
+        if size == 0 {
+            // trailers end with an empty line
+            let complete = bytes.starts_with(b"\r\n") || find(bytes, b"\r\n\r\n").is_some();
+            return Ok(complete.then_some(body));
+        }
+
+        if bytes.len() < size + 2 {
+            return Ok(None);
+        }
+
+        body.extend_from_slice(&bytes[..size]);
+        bytes = &bytes[size + 2..];
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Entity tag as the server sent it, quotes and weak marker included
+/// (`"abc"`, `W/"abc"`), so it can be sent back verbatim.
+pub(crate) fn parse_etag(etag: &str) -> String {
+    etag.trim().to_string()
+}
+
+/// Entity tag for `If-Match`: quoted tags, weak ones included, and `*`
+/// are kept as is, bare tags are quoted (`abc` gives `"abc"`).
+pub(crate) fn quote_etag(etag: &str) -> String {
+    let etag = etag.trim();
+    let opaque = etag.strip_prefix("W/").unwrap_or(etag);
+    let quoted = opaque.len() > 1 && opaque.starts_with('"') && opaque.ends_with('"');
+
+    if quoted || etag == "*" {
+        etag.to_string()
+    } else {
+        format!("\"{etag}\"")
+    }
+}
+
+/// Parses an IMF-fixdate (RFC 9110 section 5.6.7, as in
+/// `Sun, 06 Nov 1994 08:49:37 GMT`) into seconds since the Unix epoch.
+/// The obsolete RFC 850 and asctime forms are not accepted.
+pub(crate) fn parse_http_date(date: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let (_, date) = date.trim().split_once(", ")?;
+    let mut parts = date.split(' ');
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u64 + 1;
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':');
+    let hour: u64 = time.next()?.parse().ok()?;
+    let minute: u64 = time.next()?.parse().ok()?;
+    let second: u64 = time.next()?.parse().ok()?;
+
+    if parts.next()? != "GMT" || year < 1970 || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // days from civil (Howard Hinnant), with March-based years
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y % 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+/// Standard base64 with padding (RFC 4648 section 4), for the `Basic`
+/// credentials.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = match *chunk {
+            [a, b, c] => u32::from(a) << 16 | u32::from(b) << 8 | u32::from(c),
+            [a, b] => u32::from(a) << 16 | u32::from(b) << 8,
+            [a] => u32::from(a) << 16,
+            _ => unreachable!(),
+        };
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn request(path: &str) -> HttpRequest {
+        let base_url = Url::parse("https://dav.example.com:8443/").unwrap();
+        let auth = WebdavAuth::Basic {
+            username: "alice".into(),
+            password: "secret".into(),
+        };
+        HttpRequest::new("GET", &base_url, &auth, "io-calendar", path)
+    }
+
+    fn exchange(request: HttpRequest, replies: &[&[u8]]) -> Result<HttpResponse, HttpError> {
+        let mut exchange = HttpExchange::new(request);
+        let mut replies = replies.iter();
+        let mut arg = None;
+
+        loop {
+            match exchange.resume(arg.take()) {
+                WebdavCoroutineState::Complete(out) => return out,
+                WebdavCoroutineState::Yielded(WebdavYield::WantsWrite(_)) => {}
+                WebdavCoroutineState::Yielded(WebdavYield::WantsRead) => {
+                    arg = Some(*replies.next().unwrap_or(&&b""[..]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn serializes_request() {
+        let bytes = request("/dav/personal/event.ics")
+            .header("If-Match", quote_etag("abc"))
+            .into_bytes();
+
+        assert_eq!(
+            from_utf8(&bytes).unwrap(),
+            "GET /dav/personal/event.ics HTTP/1.1\r\n\
+             Host: dav.example.com:8443\r\n\
+             User-Agent: io-calendar\r\n\
+             Authorization: Basic YWxpY2U6c2VjcmV0\r\n\
+             If-Match: \"abc\"\r\n\
+             \r\n"
+        );
+    }
+
+    #[test]
+    fn rejects_header_injection() {
+        let request = request("/dav/personal/event.ics")
+            .header("If-Match", quote_etag("abc\r\nX-Injected: 1"));
+
+        assert!(matches!(
+            exchange(request, &[]),
+            Err(HttpError::InvalidRequest("If-Match"))
+        ));
+    }
+
+    #[test]
+    fn rejects_request_line_injection() {
+        let request = request("/dav/a HTTP/1.1\r\nHost: evil\r\n\r\nGET /b");
+
+        assert!(matches!(
+            exchange(request, &[]),
+            Err(HttpError::InvalidRequest("path"))
+        ));
+    }
+
+    #[test]
+    fn reads_content_length_body_across_reads() {
+        let response = exchange(
+            request("/a.ics"),
+            &[
+                b"HTTP/1.1 200 OK\r\nETag: W/\"1\"\r\nContent-Length: 5\r\n\r\nBE",
+                b"GIN",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.etag().as_deref(), Some("W/\"1\""));
+        assert_eq!(response.body, b"BEGIN");
+        assert_eq!(response.path, "/a.ics");
+    }
+
+    #[test]
+    fn reads_chunked_body_after_interim_response() {
+        let response = exchange(
+            request("/a.ics"),
+            &[b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 207 Multi-Status\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2;ext\r\nde\r\n0\r\n\r\n"],
+        )
+        .unwrap();
+
+        assert_eq!(response.status, 207);
+        assert_eq!(response.body, b"abcde");
+    }
+
+    #[test]
+    fn reads_body_until_eof() {
+        let response = exchange(request("/a.ics"), &[b"HTTP/1.1 200 OK\r\n\r\nall", b""]).unwrap();
+        assert_eq!(response.body, b"all");
+    }
+
+    #[test]
+    fn fails_on_truncated_response() {
+        let err = exchange(
+            request("/a.ics"),
+            &[b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort", b""],
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, HttpError::UnexpectedEof));
+    }
+
+    #[test]
+    fn reports_dav_condition() {
+        let body = b"<?xml version=\"1.0\"?>\
+            <D:error xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+            <C:no-uid-conflict><D:href>/dav/personal/other%20one.ics</D:href></C:no-uid-conflict>\
+            </D:error>";
+        let mut reply = vec![];
+        reply.extend_from_slice(
+            format!(
+                "HTTP/1.1 403 Forbidden\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            )
+            .as_bytes(),
+        );
+        reply.extend_from_slice(body);
+
+        let err = exchange(request("/a.ics"), &[&reply])
+            .unwrap()
+            .success()
+            .unwrap_err();
+
+        let HttpError::Status {
+            status,
+            path,
+            condition: Some(condition),
+        } = err
+        else {
+            panic!("expected a status error with a condition");
+        };
+        assert_eq!(status, 403);
+        assert_eq!(path, "/a.ics");
+        assert_eq!(condition.name, "no-uid-conflict");
+        assert_eq!(
+            condition.href.as_deref(),
+            Some("/dav/personal/other one.ics")
+        );
+    }
+
+    #[test]
+    fn keeps_etags_verbatim() {
+        for (etag, quoted) in [
+            ("abc", "\"abc\""),
+            ("\"abc\"", "\"abc\""),
+            ("W/\"abc\"", "W/\"abc\""),
+            (" \"abc\" ", "\"abc\""),
+            ("*", "*"),
+        ] {
+            assert_eq!(quote_etag(etag), quoted, "{etag}");
+        }
+
+        assert_eq!(parse_etag(" W/\"abc\"\t"), "W/\"abc\"");
+        assert_eq!(quote_etag(&parse_etag("W/\"abc\"")), "W/\"abc\"");
+    }
+
+    #[test]
+    fn parses_http_date() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784_111_777)
+        );
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+    }
+}
@@ -1,7 +1,18 @@
-//! WebDAV (CalDAV) backend: the std-blocking [`client`] plus the
+//! WebDAV (CalDAV) backend: the std-blocking [`client`], its async
+//! `client_tokio` counterpart (`tokio` feature), plus the
 //! [`convert`] helpers shared by the WebDAV collection and item
-//! coroutines.
+//! coroutines, the raw [`http`] exchange used for the requests
+//! io-webdav does not cover, and the home-set discovery of the async
+//! client.
 
 #[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "tokio")]
+pub mod client_tokio;
 pub mod convert;
+#[cfg(feature = "tokio")]
+pub mod discover;
+pub mod http;
+#[cfg(feature = "client")]
+pub(crate) mod session;
+pub(crate) mod xml;
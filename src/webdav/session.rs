@@ -0,0 +1,390 @@
+//! Request building shared by the std and tokio WebDAV clients.
+//!
+//! A [`WebdavSession`] borrows the server base URL, credentials,
+//! `User-Agent` and discovered calendar home-set of a client, validates
+//! the arguments of a shared-API call and builds the matching
+//! coroutine. The clients only resolve the home-set and run what comes
+//! out, blocking or async.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use io_webdav::{coroutine::*, rfc4791::calendar::Calendar as WireCalendar, rfc4918::WebdavAuth};
+use url::Url;
+
+#[cfg(feature = "parser")]
+use crate::item::webdav::{find::WebdavCalendarItemFind, search::WebdavCalendarItemSearch};
+use crate::{
+    calendar::{
+        Calendar, CalendarDiff,
+        webdav::{
+            create::WebdavCalendarCreate, delete::WebdavCalendarDelete, list::WebdavCalendarList,
+            update::WebdavCalendarUpdate,
+        },
+    },
+    item::{
+        CalendarItem, CalendarItemKind, ItemFilter, TimeRange, new_item_id,
+        webdav::{
+            create::WebdavCalendarItemCreate, delete::WebdavCalendarItemDelete,
+            expand::WebdavCalendarItemExpand, get::WebdavCalendarItemGet,
+            list::WebdavCalendarItemList, list_refs::WebdavCalendarItemRefList,
+            multiget::WebdavCalendarItemMultiget, sync::WebdavCalendarItemSync,
+            update::WebdavCalendarItemUpdate,
+        },
+    },
+    webdav::{
+        client::WebdavClientError,
+        convert::{calendar_path, calendar_segment},
+    },
+};
+
+/// Borrowed connection settings of a WebDAV client, building the
+/// coroutine of each shared-API call.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WebdavSession<'a> {
+    pub base_url: &'a Url,
+    pub auth: &'a WebdavAuth,
+    pub user_agent: &'a str,
+    pub home: &'a Url,
+}
+
+impl WebdavSession<'_> {
+    pub fn list_calendars(&self) -> WebdavCalendarList {
+        WebdavCalendarList::new(self.base_url, self.auth, self.user_agent, self.home.path())
+    }
+
+    pub fn create_calendar(
+        &self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        color: Option<&str>,
+    ) -> Result<WebdavCalendarCreate, WebdavClientError> {
+        validate_calendar(id)?;
+
+        // io-webdav joins the id to the home-set path as is
+        let wire = WireCalendar {
+            id: calendar_segment(id),
+            display_name: Some(name.to_string()),
+            description: description.map(str::to_string),
+            color: color.map(str::to_string),
+            ctag: None,
+            tz: None,
+        };
+
+        Ok(WebdavCalendarCreate::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            self.home.path(),
+            &wire,
+        ))
+    }
+
+    /// Builds the update of calendar `current`, merging `patch` against
+    /// its current metadata.
+    pub fn update_calendar(&self, current: Calendar, patch: CalendarDiff) -> WebdavCalendarUpdate {
+        let wire = WireCalendar {
+            id: calendar_segment(&current.id),
+            display_name: Some(patch.name.unwrap_or(current.name)),
+            description: patch.description.unwrap_or(current.description),
+            color: patch.color.unwrap_or(current.color),
+            ctag: None,
+            tz: None,
+        };
+
+        WebdavCalendarUpdate::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            self.home.path(),
+            &wire,
+        )
+    }
+
+    pub fn delete_calendar(&self, id: &str) -> Result<WebdavCalendarDelete, WebdavClientError> {
+        validate_calendar(id)?;
+
+        Ok(WebdavCalendarDelete::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            self.home.path(),
+            &calendar_segment(id),
+        ))
+    }
+
+    pub fn list_items(
+        &self,
+        calendar_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+        time_range: Option<&TimeRange>,
+        kinds: &[CalendarItemKind],
+        filter: Option<&ItemFilter>,
+    ) -> Result<WebdavItemListing, WebdavClientError> {
+        let path = self.calendar_path(calendar_id)?;
+
+        if let Some(range) = time_range.filter(|range| range.expansion().is_some()) {
+            return Ok(WebdavItemListing::Expand(WebdavCalendarItemExpand::new(
+                self.base_url,
+                self.auth,
+                self.user_agent,
+                &path,
+                calendar_id,
+                page,
+                page_size,
+                range,
+                kinds,
+                filter,
+            )));
+        }
+
+        Ok(WebdavItemListing::List(WebdavCalendarItemList::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            calendar_id,
+            page,
+            page_size,
+            time_range,
+            kinds,
+            filter,
+        )))
+    }
+
+    pub fn get_item(
+        &self,
+        calendar_id: &str,
+        item_id: &str,
+    ) -> Result<WebdavCalendarItemGet, WebdavClientError> {
+        let path = self.calendar_path(calendar_id)?;
+        validate_item(item_id)?;
+
+        Ok(WebdavCalendarItemGet::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            calendar_id,
+            item_id,
+        ))
+    }
+
+    /// Builds the `calendar-multiget` of `item_ids`, or `None` when
+    /// there is nothing to fetch.
+    pub fn get_items(
+        &self,
+        calendar_id: &str,
+        item_ids: &[&str],
+    ) -> Result<Option<WebdavCalendarItemMultiget>, WebdavClientError> {
+        let path = self.calendar_path(calendar_id)?;
+        for item_id in item_ids {
+            validate_item(item_id)?;
+        }
+
+        if item_ids.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(WebdavCalendarItemMultiget::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            calendar_id,
+            item_ids,
+        )))
+    }
+
+    #[cfg(feature = "parser")]
+    pub fn find_item_by_uid(
+        &self,
+        calendar_id: &str,
+        uid: &str,
+    ) -> Result<WebdavCalendarItemFind, WebdavClientError> {
+        let path = self.calendar_path(calendar_id)?;
+
+        Ok(WebdavCalendarItemFind::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            calendar_id,
+            uid,
+        ))
+    }
+
+    #[cfg(feature = "parser")]
+    pub fn search_items(
+        &self,
+        calendar_id: &str,
+        query: &str,
+    ) -> Result<WebdavCalendarItemSearch, WebdavClientError> {
+        let path = self.calendar_path(calendar_id)?;
+
+        Ok(WebdavCalendarItemSearch::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            calendar_id,
+            query,
+        ))
+    }
+
+    pub fn list_item_refs(
+        &self,
+        calendar_id: &str,
+    ) -> Result<WebdavCalendarItemRefList, WebdavClientError> {
+        let path = self.calendar_path(calendar_id)?;
+
+        Ok(WebdavCalendarItemRefList::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            calendar_id,
+        ))
+    }
+
+    /// Builds the creation of `contents`, naming the new resource with
+    /// [`new_item_id`].
+    pub fn create_item(
+        &self,
+        calendar_id: &str,
+        contents: Vec<u8>,
+    ) -> Result<WebdavCalendarItemCreate, WebdavClientError> {
+        if contents.is_empty() {
+            return Err(WebdavClientError::EmptyItemBody);
+        }
+        let path = self.calendar_path(calendar_id)?;
+
+        let id = new_item_id(&contents)
+            .map_err(|_| WebdavClientError::OperationFailed("gather randomness"))?;
+
+        Ok(WebdavCalendarItemCreate::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            &id,
+            contents,
+        ))
+    }
+
+    pub fn update_item(
+        &self,
+        calendar_id: &str,
+        item_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<WebdavCalendarItemUpdate, WebdavClientError> {
+        if contents.is_empty() {
+            return Err(WebdavClientError::EmptyItemBody);
+        }
+        let path = self.calendar_path(calendar_id)?;
+        validate_item(item_id)?;
+
+        Ok(WebdavCalendarItemUpdate::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            item_id,
+            contents,
+            if_match,
+        ))
+    }
+
+    pub fn delete_item(
+        &self,
+        calendar_id: &str,
+        item_id: &str,
+        if_match: Option<&str>,
+    ) -> Result<WebdavCalendarItemDelete, WebdavClientError> {
+        let path = self.calendar_path(calendar_id)?;
+        validate_item(item_id)?;
+
+        Ok(WebdavCalendarItemDelete::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            item_id,
+            if_match,
+        ))
+    }
+
+    pub fn sync_items(
+        &self,
+        calendar_id: &str,
+        sync_token: Option<&str>,
+        known: &[(&str, &str)],
+    ) -> Result<WebdavCalendarItemSync, WebdavClientError> {
+        let path = self.calendar_path(calendar_id)?;
+
+        Ok(WebdavCalendarItemSync::new(
+            self.base_url,
+            self.auth,
+            self.user_agent,
+            &path,
+            calendar_id,
+            sync_token,
+            known,
+        ))
+    }
+
+    /// Validates `calendar_id` and builds its collection path under the
+    /// home-set.
+    fn calendar_path(&self, calendar_id: &str) -> Result<String, WebdavClientError> {
+        validate_calendar(calendar_id)?;
+        Ok(calendar_path(self.home, calendar_id))
+    }
+}
+
+/// Item listing coroutine: a plain `calendar-query`, or one asking the
+/// server to expand recurrences.
+pub(crate) enum WebdavItemListing {
+    List(WebdavCalendarItemList),
+    Expand(WebdavCalendarItemExpand),
+}
+
+impl WebdavCoroutine for WebdavItemListing {
+    type Yield = WebdavYield;
+    type Return = Result<Vec<CalendarItem>, WebdavClientError>;
+
+    fn resume(&mut self, arg: Option<&[u8]>) -> WebdavCoroutineState<Self::Yield, Self::Return> {
+        let out = match self {
+            Self::List(coroutine) => match coroutine.resume(arg) {
+                WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+                WebdavCoroutineState::Complete(out) => out.map_err(WebdavClientError::from),
+            },
+            Self::Expand(coroutine) => match coroutine.resume(arg) {
+                WebdavCoroutineState::Yielded(y) => return WebdavCoroutineState::Yielded(y),
+                WebdavCoroutineState::Complete(out) => out.map_err(WebdavClientError::from),
+            },
+        };
+
+        WebdavCoroutineState::Complete(out)
+    }
+}
+
+/// Rejects an empty calendar id (after trimming surrounding slashes).
+pub(crate) fn validate_calendar(id: &str) -> Result<(), WebdavClientError> {
+    if id.trim_matches('/').is_empty() {
+        return Err(WebdavClientError::InvalidCalendar(id.to_string()));
+    }
+    Ok(())
+}
+
+/// Rejects an empty item id.
+fn validate_item(id: &str) -> Result<(), WebdavClientError> {
+    if id.is_empty() {
+        return Err(WebdavClientError::InvalidItemId(id.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,459 @@
+//! Just enough XML to read WebDAV `multistatus` bodies (RFC 4918
+//! section 14.16) and to escape values sent in request bodies.
+//!
+//! Elements are matched by local name: namespace prefixes vary between
+//! servers and every element read here is unambiguous within the `DAV:`
+//! and CalDAV namespaces. Attributes, comments, processing instructions
+//! and doctypes are skipped.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str::from_utf8;
+
+/// Nesting depth past which a document is rejected.
+const MAX_DEPTH: usize = 64;
+
+/// An XML element: local name, child elements and concatenated text.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Element {
+    pub name: String,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    /// Parses the root element of a document.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader {
+            input: from_utf8(bytes).ok()?,
+        };
+        reader.skip_misc();
+        reader.element(0)
+    }
+
+    /// First child named `name`.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Every child named `name`.
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+struct Reader<'a> {
+    input: &'a str,
+}
+
+impl Reader<'_> {
+    /// Skips whitespace, the XML declaration, comments and doctypes.
+    fn skip_misc(&mut self) {
+        loop {
+            self.input = self.input.trim_start();
+            if self.input.starts_with("<?") {
+                self.skip_past("?>");
+            } else if self.input.starts_with("<!--") {
+                self.skip_past("-->");
+            } else if self.input.starts_with("<!") && !self.input.starts_with("<![CDATA[") {
+                self.skip_past(">");
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn skip_past(&mut self, needle: &str) -> Option<&str> {
+        let end = self.input.find(needle)?;
+        let skipped = &self.input[..end];
+        self.input = &self.input[end + needle.len()..];
+        Some(skipped)
+    }
+
+    fn element(&mut self, depth: usize) -> Option<Element> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        self.input = self.input.strip_prefix('<')?;
+
+        let end = self
+            .input
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')?;
+        let name = local_name(&self.input[..end]).to_string();
+        self.input = &self.input[end..];
+
+        // attributes, with quoted values possibly holding `>`
+        let mut quote = None;
+        let close = self.input.char_indices().find(|(_, c)| {
+            match quote {
+                Some(q) if *c == q => quote = None,
+                Some(_) => {}
+                None if *c == '"' || *c == '\'' => quote = Some(*c),
+                None => return *c == '>',
+            }
+            false
+        });
+        let (close, _) = close?;
+        let empty = self.input[..close].ends_with('/');
+        self.input = &self.input[close + 1..];
+
+        let mut element = Element {
+            name,
+            ..Element::default()
+        };
+
+        if empty {
+            return Some(element);
+        }
+
+        loop {
+            if self.input.starts_with("</") {
+                self.skip_past(">")?;
+                return Some(element);
+            } else if self.input.starts_with("<![CDATA[") {
+                self.input = &self.input["<![CDATA[".len()..];
+                let text = self.skip_past("]]>")?;
+                element.text.push_str(text);
+            } else if self.input.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.input.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.input.starts_with('<') {
+                element.children.push(self.element(depth + 1)?);
+            } else if self.input.is_empty() {
+                return None;
+            } else {
+                let end = self.input.find('<').unwrap_or(self.input.len());
+                element.text.push_str(&unescape(&self.input[..end]));
+                self.input = &self.input[end..];
+            }
+        }
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+/// Resolves the predefined entities and character references.
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+
+        let entity = &rest[1..end];
+        let resolved = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+
+        match resolved {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Escapes `text` for use in element content and attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// A parsed `multistatus` body.
+#[derive(Debug, Default)]
+pub(crate) struct Multistatus {
+    pub responses: Vec<Response>,
+    /// RFC 6578 `sync-token`, on `sync-collection` replies.
+    pub sync_token: Option<String>,
+}
+
+impl Multistatus {
+    /// Parses a `multistatus` body; `None` when the root element is
+    /// something else.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let root = Element::parse(bytes)?;
+
+        if root.name != "multistatus" {
+            return None;
+        }
+
+        let responses = root
+            .children("response")
+            .filter_map(|response| {
+                let href = percent_decode(response.child("href")?.text.trim());
+                let status = response.child("status").and_then(status_code);
+                let mut props = Vec::new();
+
+                for propstat in response.children("propstat") {
+                    let ok = propstat
+                        .child("status")
+                        .and_then(status_code)
+                        .is_none_or(|code| (200..300).contains(&code));
+
+                    if let (true, Some(prop)) = (ok, propstat.child("prop")) {
+                        props.extend(prop.children.iter().cloned());
+                    }
+                }
+
+                Some(Response {
+                    href,
+                    status,
+                    props,
+                })
+            })
+            .collect();
+
+        let sync_token = root
+            .child("sync-token")
+            .map(|token| token.text.trim().to_string());
+
+        Some(Self {
+            responses,
+            sync_token,
+        })
+    }
+}
+
+/// One `response` of a [`Multistatus`]: its (percent-decoded) href,
+/// the response-level status when any, and the properties of its 2xx
+/// `propstat` blocks.
+#[derive(Debug, Default)]
+pub(crate) struct Response {
+    pub href: String,
+    pub status: Option<u16>,
+    pub props: Vec<Element>,
+}
+
+impl Response {
+    /// Property named `name`, when returned with a 2xx status.
+    pub fn prop(&self, name: &str) -> Option<&Element> {
+        self.props.iter().find(|prop| prop.name == name)
+    }
+
+    /// `getetag`, verbatim.
+    pub fn etag(&self) -> Option<String> {
+        self.prop("getetag")
+            .map(|etag| crate::webdav::http::parse_etag(&etag.text))
+            .filter(|etag| !etag.is_empty())
+    }
+
+    /// `calendar-data` bytes.
+    pub fn calendar_data(&self) -> Option<Vec<u8>> {
+        self.prop("calendar-data")
+            .map(|data| data.text.trim().as_bytes().to_vec())
+            .filter(|data| !data.is_empty())
+    }
+
+    /// Whether `resourcetype` marks a collection.
+    pub fn is_collection(&self) -> bool {
+        self.prop("resourcetype")
+            .is_some_and(|kind| kind.child("collection").is_some())
+    }
+
+    /// `getcontentlength` in bytes.
+    pub fn content_length(&self) -> Option<u64> {
+        self.prop("getcontentlength")?.text.trim().parse().ok()
+    }
+
+    /// `getlastmodified` in seconds since the Unix epoch.
+    pub fn last_modified(&self) -> Option<u64> {
+        crate::webdav::http::parse_http_date(&self.prop("getlastmodified")?.text)
+    }
+}
+
+/// Code of a `HTTP/1.1 200 OK` status element.
+fn status_code(status: &Element) -> Option<u16> {
+    status.text.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Decodes `%XX` escapes; invalid escapes are kept verbatim.
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(out).unwrap_or_else(|_| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::String};
+
+    use super::*;
+
+    #[test]
+    fn matches_elements_by_local_name() {
+        let xml = br#"<?xml version="1.0" encoding="utf-8"?>
+            <!-- any prefix, or none, maps to the same element -->
+            <d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+              <d:response>
+                <href xmlns="DAV:">/dav/a.ics</href>
+                <d:propstat>
+                  <d:prop><cal:calendar-data/><x:getetag xmlns:x="DAV:">"1"</x:getetag></d:prop>
+                  <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+              </d:response>
+            </d:multistatus>"#;
+
+        let root = Element::parse(xml).unwrap();
+        assert_eq!(root.name, "multistatus");
+
+        let response = root.child("response").unwrap();
+        assert_eq!(response.child("href").unwrap().text, "/dav/a.ics");
+
+        let prop = response.child("propstat").unwrap().child("prop").unwrap();
+        let names: Vec<_> = prop.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["calendar-data", "getetag"]);
+    }
+
+    #[test]
+    fn reads_cdata_verbatim() {
+        let xml = b"<C:calendar-data xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+            <![CDATA[BEGIN:VCALENDAR\r\nSUMMARY:<b>&amp; more</b>\r\nEND:VCALENDAR]]>\
+            </C:calendar-data>";
+
+        let element = Element::parse(xml).unwrap();
+        assert_eq!(
+            element.text,
+            "BEGIN:VCALENDAR\r\nSUMMARY:<b>&amp; more</b>\r\nEND:VCALENDAR"
+        );
+    }
+
+    #[test]
+    fn resolves_entities() {
+        let xml = b"<a>&lt;&gt;&amp;&quot;&apos; &#65;&#x42;&#X43; &unknown; &amp</a>";
+        let element = Element::parse(xml).unwrap();
+        assert_eq!(element.text, "<>&\"' ABC &unknown; &amp");
+
+        let text = "<\"a\" & 'b'>";
+        let xml = format!("<a>{}</a>", escape(text));
+        assert_eq!(Element::parse(xml.as_bytes()).unwrap().text, text);
+    }
+
+    #[test]
+    fn decodes_multistatus_hrefs() {
+        let xml = b"<D:multistatus xmlns:D=\"DAV:\">\
+            <D:response><D:href>/dav/personal/a%20b.ics</D:href>\
+            <D:status>HTTP/1.1 404 Not Found</D:status></D:response>\
+            <D:response><D:href>/dav/caf%C3%A9/100%25.ics</D:href>\
+            <D:propstat><D:prop><D:getetag>W/\"x\"</D:getetag></D:prop>\
+            <D:status>HTTP/1.1 200 OK</D:status></D:propstat>\
+            <D:propstat><D:prop><D:displayname/></D:prop>\
+            <D:status>HTTP/1.1 404 Not Found</D:status></D:propstat></D:response>\
+            <D:response><D:href>/dav/bad%zz%4.ics</D:href></D:response>\
+            <D:sync-token>token-2</D:sync-token>\
+            </D:multistatus>";
+
+        let multistatus = Multistatus::parse(xml).unwrap();
+        let hrefs: Vec<_> = multistatus
+            .responses
+            .iter()
+            .map(|r| r.href.as_str())
+            .collect();
+        assert_eq!(
+            hrefs,
+            [
+                "/dav/personal/a b.ics",
+                "/dav/café/100%.ics",
+                "/dav/bad%zz%4.ics"
+            ]
+        );
+
+        assert_eq!(multistatus.responses[0].status, Some(404));
+        assert_eq!(multistatus.responses[1].etag().as_deref(), Some("W/\"x\""));
+        // properties of non-2xx propstats are dropped
+        assert!(multistatus.responses[1].prop("displayname").is_none());
+        assert_eq!(multistatus.sync_token.as_deref(), Some("token-2"));
+    }
+
+    #[test]
+    fn rejects_malformed_multistatus() {
+        let table: &[&[u8]] = &[
+            b"",
+            b"not xml",
+            b"<D:multistatus xmlns:D=\"DAV:\"><D:response>",
+            b"<D:multistatus xmlns:D=\"DAV:\"><![CDATA[unterminated</D:multistatus>",
+            b"<D:error xmlns:D=\"DAV:\"><D:response/></D:error>",
+            b"\xff\xfe<multistatus/>",
+        ];
+
+        for xml in table {
+            assert!(
+                Multistatus::parse(xml).is_none(),
+                "{}",
+                String::from_utf8_lossy(xml)
+            );
+        }
+
+        let deep = format!(
+            "{}{}",
+            "<a>".repeat(MAX_DEPTH + 2),
+            "</a>".repeat(MAX_DEPTH + 2)
+        );
+        assert!(Element::parse(deep.as_bytes()).is_none());
+
+        // a response without href is skipped, not fatal
+        let xml =
+            b"<multistatus><response><status>HTTP/1.1 200 OK</status></response></multistatus>";
+        assert!(Multistatus::parse(xml).unwrap().responses.is_empty());
+    }
+}